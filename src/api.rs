@@ -0,0 +1,874 @@
+//! Public API surface and breaking-change detection.
+//!
+//! [`Metadata::api_surface`] collects the types and members that code outside
+//! the assembly can use: public types, nested types as visible as their
+//! enclosing types, public and protected members, accessors' properties and
+//! events, and explicit implementations of visible interfaces. Protected
+//! members of sealed types are excluded, since nothing can derive from them.
+//!
+//! [`ApiSurface::breaking_changes`] compares two surfaces and reports the
+//! changes that break code compiled against the older one, such as removed
+//! members, changed signatures, reduced visibility, newly sealed or abstract
+//! types and members, and changed enum values or generic constraints.
+//! Members are matched by their ILAsm-style signatures, as in
+//! [`Metadata::diff`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::diff::EntityKind;
+use crate::metadata::Metadata;
+use crate::resolve::{attribute_class, resolve_type_def};
+use crate::tables::{
+    CodedIndex, MEMBER_ACCESS_MASK, MEMBER_FAM_OR_ASSEM, MEMBER_FAMILY, MEMBER_PRIVATE,
+    MEMBER_PUBLIC, METHOD_ABSTRACT, METHOD_FINAL, METHOD_VIRTUAL, TYPE_ABSTRACT, TYPE_INTERFACE,
+    TYPE_NESTED_FAM_OR_ASSEM, TYPE_NESTED_FAMILY, TYPE_NESTED_PUBLIC, TYPE_PUBLIC, TYPE_SEALED,
+    TYPE_VISIBILITY_MASK, TableId,
+};
+
+/// How far outside the assembly a type or member can be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Visibility {
+    /// Not usable outside the assembly. Never part of an [`ApiSurface`];
+    /// used for entities a newer version hid.
+    Hidden,
+    /// Usable from derived types (`protected` or `protected internal`).
+    Protected,
+    /// Usable everywhere.
+    Public,
+}
+
+impl Visibility {
+    /// Get the lowercase name used in messages.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Hidden => "hidden",
+            Self::Protected => "protected",
+            Self::Public => "public",
+        }
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An externally visible type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiType {
+    /// Full name, with nested types joined by `+`.
+    pub name: String,
+    /// Effective visibility, taking enclosing types into account.
+    pub visibility: Visibility,
+    /// TypeAttributes flags.
+    pub flags: u32,
+    /// Base type, if any.
+    pub base_type: Option<String>,
+    /// Implemented interfaces, sorted.
+    pub interfaces: Vec<String>,
+    /// Variance and constraints of each generic parameter, without names.
+    pub generic_constraints: Vec<String>,
+    /// Visible members, in declaration order.
+    pub members: Vec<ApiMember>,
+}
+
+impl ApiType {
+    /// Check if this is an interface.
+    #[must_use]
+    pub fn is_interface(&self) -> bool {
+        self.flags & TYPE_INTERFACE != 0
+    }
+
+    /// Check if the type is abstract (including interfaces and static
+    /// classes).
+    #[must_use]
+    pub fn is_abstract(&self) -> bool {
+        self.flags & TYPE_ABSTRACT != 0
+    }
+
+    /// Check if the type is sealed (including value types and static
+    /// classes).
+    #[must_use]
+    pub fn is_sealed(&self) -> bool {
+        self.flags & TYPE_SEALED != 0
+    }
+
+    /// Check if this is an enum.
+    #[must_use]
+    pub fn is_enum(&self) -> bool {
+        self.base_type.as_deref() == Some("System.Enum")
+    }
+}
+
+/// An externally visible field, method, property or event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiMember {
+    /// Field, method, property or event.
+    pub kind: EntityKind,
+    /// Member name.
+    pub name: String,
+    /// ILAsm-style signature, including the name.
+    pub signature: String,
+    /// Visibility within its type.
+    pub visibility: Visibility,
+    /// Field, method, property or event flags.
+    pub flags: u16,
+    /// Whether this is a private method implementing an interface method.
+    pub explicit_implementation: bool,
+    /// Whether the member (or an accessor) is abstract.
+    pub is_abstract: bool,
+    /// Whether the member (or an accessor) is virtual and not final.
+    pub is_overridable: bool,
+    /// Constant value of a literal field.
+    pub value: Option<String>,
+    /// Variance and constraints of each generic parameter of a method,
+    /// without names.
+    pub generic_constraints: Vec<String>,
+}
+
+/// The externally visible types and members of an assembly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiSurface {
+    /// Visible types, sorted by name.
+    pub types: Vec<ApiType>,
+}
+
+impl ApiSurface {
+    /// Find a type by full name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ApiType> {
+        self.types
+            .binary_search_by(|ty| ty.name.as_str().cmp(name))
+            .ok()
+            .map(|i| &self.types[i])
+    }
+
+    /// Find the changes from this surface to a newer one that break code
+    /// compiled against this one.
+    ///
+    /// Types and members missing from `newer` are reported as removed; use
+    /// [`Metadata::breaking_changes`] to tell them apart from entities whose
+    /// visibility was reduced.
+    #[must_use]
+    pub fn breaking_changes(&self, newer: &ApiSurface) -> Vec<BreakingChange> {
+        let mut changes = Vec::new();
+        for old in &self.types {
+            match newer.get(&old.name) {
+                Some(new) => compare_types(old, new, &mut changes),
+                None => changes.push(BreakingChange::new(
+                    BreakingChangeKind::TypeRemoved,
+                    &old.name,
+                    "type was removed".to_string(),
+                )),
+            }
+        }
+        changes
+    }
+}
+
+/// A kind of binary-breaking change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BreakingChangeKind {
+    /// A visible type was removed.
+    TypeRemoved,
+    /// A visible member was removed or renamed.
+    MemberRemoved,
+    /// A visible member's signature changed.
+    SignatureChanged,
+    /// A type or member became less visible.
+    VisibilityReduced,
+    /// A type became sealed.
+    TypeSealed,
+    /// A type became abstract.
+    TypeAbstract,
+    /// An overridable member became non-virtual or final.
+    MemberSealed,
+    /// A member became abstract.
+    MemberAbstract,
+    /// An abstract member was added to an unsealed type.
+    AbstractMemberAdded,
+    /// An enum member's value changed.
+    EnumValueChanged,
+    /// The variance or constraints of a generic parameter changed.
+    GenericConstraintsChanged,
+}
+
+impl BreakingChangeKind {
+    /// Get the kebab-case name of the rule.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::TypeRemoved => "type-removed",
+            Self::MemberRemoved => "member-removed",
+            Self::SignatureChanged => "signature-changed",
+            Self::VisibilityReduced => "visibility-reduced",
+            Self::TypeSealed => "type-sealed",
+            Self::TypeAbstract => "type-abstract",
+            Self::MemberSealed => "member-sealed",
+            Self::MemberAbstract => "member-abstract",
+            Self::AbstractMemberAdded => "abstract-member-added",
+            Self::EnumValueChanged => "enum-value-changed",
+            Self::GenericConstraintsChanged => "generic-constraints-changed",
+        }
+    }
+}
+
+impl fmt::Display for BreakingChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A binary-breaking change between two versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakingChange {
+    /// The kind of change.
+    pub kind: BreakingChangeKind,
+    /// The type's full name, or `Type::signature` for a member.
+    pub name: String,
+    /// What changed.
+    pub message: String,
+}
+
+impl BreakingChange {
+    fn new(kind: BreakingChangeKind, name: &str, message: String) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            message,
+        }
+    }
+}
+
+/// Formats as `[kind] name: message`.
+impl fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.kind, self.name, self.message)
+    }
+}
+
+/// Compare a type present in both versions.
+fn compare_types(old: &ApiType, new: &ApiType, changes: &mut Vec<BreakingChange>) {
+    use BreakingChangeKind as Kind;
+    let name = old.name.as_str();
+    if new.visibility < old.visibility {
+        changes.push(BreakingChange::new(
+            Kind::VisibilityReduced,
+            name,
+            format!(
+                "type visibility reduced from {} to {}",
+                old.visibility, new.visibility
+            ),
+        ));
+        if new.visibility == Visibility::Hidden {
+            return;
+        }
+    }
+    if !old.is_sealed() && new.is_sealed() {
+        changes.push(BreakingChange::new(
+            Kind::TypeSealed,
+            name,
+            "type became sealed".to_string(),
+        ));
+    }
+    if !old.is_abstract() && new.is_abstract() {
+        changes.push(BreakingChange::new(
+            Kind::TypeAbstract,
+            name,
+            "type became abstract".to_string(),
+        ));
+    }
+    if old.generic_constraints != new.generic_constraints {
+        changes.push(BreakingChange::new(
+            Kind::GenericConstraintsChanged,
+            name,
+            format!(
+                "generic parameters changed from <{}> to <{}>",
+                old.generic_constraints.join(", "),
+                new.generic_constraints.join(", ")
+            ),
+        ));
+    }
+
+    let mut by_signature: HashMap<(EntityKind, &str), &ApiMember> = HashMap::new();
+    for member in &new.members {
+        by_signature.insert((member.kind, &member.signature), member);
+    }
+    for member in &old.members {
+        let full_name = format!("{name}::{}", member.signature);
+        let Some(new_member) = by_signature.get(&(member.kind, member.signature.as_str())) else {
+            let overloads: Vec<&str> = new
+                .members
+                .iter()
+                .filter(|m| m.kind == member.kind && m.name == member.name)
+                .filter(|m| m.visibility != Visibility::Hidden)
+                .map(|m| m.signature.as_str())
+                .collect();
+            changes.push(if overloads.is_empty() {
+                BreakingChange::new(
+                    Kind::MemberRemoved,
+                    &full_name,
+                    format!("{} was removed", member.kind),
+                )
+            } else {
+                BreakingChange::new(
+                    Kind::SignatureChanged,
+                    &full_name,
+                    format!("signature changed to {}", overloads.join(" or ")),
+                )
+            });
+            continue;
+        };
+        if new_member.visibility < member.visibility {
+            changes.push(BreakingChange::new(
+                Kind::VisibilityReduced,
+                &full_name,
+                format!(
+                    "{} visibility reduced from {} to {}",
+                    member.kind, member.visibility, new_member.visibility
+                ),
+            ));
+            if new_member.visibility == Visibility::Hidden {
+                continue;
+            }
+        }
+        if !member.is_abstract && new_member.is_abstract {
+            changes.push(BreakingChange::new(
+                Kind::MemberAbstract,
+                &full_name,
+                format!("{} became abstract", member.kind),
+            ));
+        }
+        if member.is_overridable && !new_member.is_overridable && !new.is_sealed() {
+            changes.push(BreakingChange::new(
+                Kind::MemberSealed,
+                &full_name,
+                format!("{} can no longer be overridden", member.kind),
+            ));
+        }
+        if old.is_enum() && member.value != new_member.value {
+            changes.push(BreakingChange::new(
+                Kind::EnumValueChanged,
+                &full_name,
+                format!(
+                    "value changed from {} to {}",
+                    member.value.as_deref().unwrap_or("none"),
+                    new_member.value.as_deref().unwrap_or("none")
+                ),
+            ));
+        }
+        if member.generic_constraints != new_member.generic_constraints {
+            changes.push(BreakingChange::new(
+                Kind::GenericConstraintsChanged,
+                &full_name,
+                format!(
+                    "generic parameters changed from <{}> to <{}>",
+                    member.generic_constraints.join(", "),
+                    new_member.generic_constraints.join(", ")
+                ),
+            ));
+        }
+    }
+
+    if !new.is_sealed() {
+        let old_signatures: HashSet<(EntityKind, &str)> = old
+            .members
+            .iter()
+            .map(|member| (member.kind, member.signature.as_str()))
+            .collect();
+        for member in &new.members {
+            if member.is_abstract
+                && member.visibility != Visibility::Hidden
+                && !old_signatures.contains(&(member.kind, member.signature.as_str()))
+            {
+                changes.push(BreakingChange::new(
+                    Kind::AbstractMemberAdded,
+                    &format!("{name}::{}", member.signature),
+                    format!("abstract {} was added", member.kind),
+                ));
+            }
+        }
+    }
+}
+
+/// Get the visibility a type's own flags grant.
+fn type_visibility(flags: u32) -> Visibility {
+    match flags & TYPE_VISIBILITY_MASK {
+        TYPE_PUBLIC | TYPE_NESTED_PUBLIC => Visibility::Public,
+        TYPE_NESTED_FAMILY | TYPE_NESTED_FAM_OR_ASSEM => Visibility::Protected,
+        _ => Visibility::Hidden,
+    }
+}
+
+/// Get the visibility a field's or method's access flags grant.
+fn member_visibility(flags: u16) -> Visibility {
+    match flags & MEMBER_ACCESS_MASK {
+        MEMBER_PUBLIC => Visibility::Public,
+        MEMBER_FAMILY | MEMBER_FAM_OR_ASSEM => Visibility::Protected,
+        _ => Visibility::Hidden,
+    }
+}
+
+impl Metadata {
+    /// Collect the types and members visible outside the assembly.
+    #[must_use]
+    pub fn api_surface(&self) -> ApiSurface {
+        self.collect_api(false)
+    }
+
+    /// Find the changes from this assembly to a newer version that break
+    /// code compiled against this one.
+    ///
+    /// Like [`ApiSurface::breaking_changes`], but types and members the
+    /// newer version still declares with less visibility are reported as
+    /// visibility reductions rather than removals.
+    #[must_use]
+    pub fn breaking_changes(&self, newer: &Metadata) -> Vec<BreakingChange> {
+        self.api_surface()
+            .breaking_changes(&newer.collect_api(true))
+    }
+
+    /// Collect types and members, with or without hidden ones.
+    fn collect_api(&self, include_hidden: bool) -> ApiSurface {
+        let mut visibilities: Vec<Option<Visibility>> = vec![None; self.type_defs.len()];
+        let mut types = BTreeMap::new();
+        for rid in 1..=self.type_defs.len() as u32 {
+            let visibility = self.effective_type_visibility(rid, &mut visibilities, 0);
+            if visibility == Visibility::Hidden && !include_hidden {
+                continue;
+            }
+            let ty = self.api_type(rid, visibility, include_hidden, &mut visibilities);
+            types.insert(ty.name.clone(), ty);
+        }
+        ApiSurface {
+            types: types.into_values().collect(),
+        }
+    }
+
+    /// Get the visibility of a type, limited by its enclosing types.
+    fn effective_type_visibility(
+        &self,
+        rid: u32,
+        cache: &mut [Option<Visibility>],
+        depth: usize,
+    ) -> Visibility {
+        let index = rid as usize - 1;
+        if let Some(visibility) = cache[index] {
+            return visibility;
+        }
+        let flags = self.type_defs[index].flags;
+        let mut visibility = type_visibility(flags);
+        // Guard against nesting cycles in malformed metadata
+        let enclosing = self
+            .get_enclosing_type(rid)
+            .filter(|_| depth < self.type_defs.len())
+            .and_then(|enclosing| Some((enclosing, self.get_type_def(enclosing)?.flags)));
+        match enclosing {
+            Some((enclosing, enclosing_flags)) => {
+                let outer = self.effective_type_visibility(enclosing, cache, depth + 1);
+                if visibility == Visibility::Protected && enclosing_flags & TYPE_SEALED != 0 {
+                    visibility = Visibility::Hidden;
+                }
+                visibility = visibility.min(outer);
+            }
+            None if flags & TYPE_VISIBILITY_MASK != TYPE_PUBLIC => visibility = Visibility::Hidden,
+            None => {}
+        }
+        cache[index] = Some(visibility);
+        visibility
+    }
+
+    /// Get the visibility of the interface declaring a method that an
+    /// explicit implementation overrides. Types outside this module are
+    /// taken to be public.
+    fn declaring_type_visibility(
+        &self,
+        declaration: CodedIndex,
+        cache: &mut [Option<Visibility>],
+    ) -> Visibility {
+        match attribute_class(self, declaration)
+            .and_then(|class| resolve_type_def(self, class, None))
+        {
+            Some((metadata, rid)) if std::ptr::eq(metadata, self) => {
+                self.effective_type_visibility(rid, cache, 0)
+            }
+            _ => Visibility::Public,
+        }
+    }
+
+    fn api_type(
+        &self,
+        rid: u32,
+        visibility: Visibility,
+        include_hidden: bool,
+        cache: &mut [Option<Visibility>],
+    ) -> ApiType {
+        let row = &self.type_defs[rid as usize - 1];
+        let sealed = row.flags & TYPE_SEALED != 0;
+        let owner = CodedIndex {
+            table: Some(TableId::TypeDef),
+            row: rid,
+        };
+        let mut interfaces: Vec<String> = self
+            .interface_impls
+            .iter()
+            .filter(|imp| imp.class == rid)
+            .map(|imp| self.type_index_text(imp.interface, false))
+            .collect();
+        interfaces.sort();
+
+        // Private methods implementing interface methods are as visible as
+        // the most visible interface they implement
+        let mut explicit: HashMap<u32, Visibility> = HashMap::new();
+        for imp in &self.method_impls {
+            if imp.class != rid || imp.method_body.table != Some(TableId::MethodDef) {
+                continue;
+            }
+            let visibility = self.declaring_type_visibility(imp.method_declaration, cache);
+            let entry = explicit
+                .entry(imp.method_body.row)
+                .or_insert(Visibility::Hidden);
+            *entry = (*entry).max(visibility);
+        }
+        let method_visibility = |method: u32, flags: u16| {
+            if flags & MEMBER_ACCESS_MASK == MEMBER_PRIVATE {
+                if let Some(&visibility) = explicit.get(&method) {
+                    return (visibility, true);
+                }
+            }
+            match member_visibility(flags) {
+                Visibility::Protected if sealed => (Visibility::Hidden, false),
+                visibility => (visibility, false),
+            }
+        };
+
+        let mut members = Vec::new();
+        for (field, field_row) in self.get_type_fields(rid) {
+            let visibility = match member_visibility(field_row.flags) {
+                Visibility::Protected if sealed => Visibility::Hidden,
+                visibility => visibility,
+            };
+            let value = self.field_value_text(field);
+            members.push(ApiMember {
+                kind: EntityKind::Field,
+                name: self.strings.get(field_row.name).unwrap_or("").to_string(),
+                signature: self.field_text(field_row, false),
+                visibility,
+                flags: field_row.flags,
+                explicit_implementation: false,
+                is_abstract: false,
+                is_overridable: false,
+                value: (!value.is_empty()).then_some(value),
+                generic_constraints: Vec::new(),
+            });
+        }
+
+        let mut accessors: HashMap<(Option<TableId>, u32), Vec<u32>> = HashMap::new();
+        for semantics in &self.method_semantics {
+            accessors
+                .entry((semantics.association.table, semantics.association.row))
+                .or_default()
+                .push(semantics.method);
+        }
+        for (method, method_row) in self.get_type_methods(rid) {
+            let (visibility, explicit_implementation) = method_visibility(method, method_row.flags);
+            let generic_constraints = self
+                .generic_params_of(CodedIndex {
+                    table: Some(TableId::MethodDef),
+                    row: method,
+                })
+                .into_iter()
+                .map(|(param, _)| {
+                    self.generic_param_constraints_text(param, false)
+                        .trim_end()
+                        .to_string()
+                })
+                .collect();
+            members.push(ApiMember {
+                kind: EntityKind::Method,
+                name: self.strings.get(method_row.name).unwrap_or("").to_string(),
+                signature: self.method_text(method_row, false),
+                visibility,
+                flags: method_row.flags,
+                explicit_implementation,
+                is_abstract: method_row.flags & METHOD_ABSTRACT != 0,
+                is_overridable: method_row.flags & (METHOD_VIRTUAL | METHOD_FINAL)
+                    == METHOD_VIRTUAL,
+                value: None,
+                generic_constraints,
+            });
+        }
+
+        // Properties and events are as visible as their most visible accessor
+        let accessor_member = |kind, table, row: u32, name: u32, flags, signature| {
+            let mut member = ApiMember {
+                kind,
+                name: self.strings.get(name).unwrap_or("").to_string(),
+                signature,
+                visibility: Visibility::Hidden,
+                flags,
+                explicit_implementation: false,
+                is_abstract: false,
+                is_overridable: false,
+                value: None,
+                generic_constraints: Vec::new(),
+            };
+            for &method in accessors.get(&(Some(table), row)).into_iter().flatten() {
+                let Some(method_row) = self.method_defs.get((method as usize).wrapping_sub(1))
+                else {
+                    continue;
+                };
+                let (visibility, explicit) = method_visibility(method, method_row.flags);
+                if visibility > member.visibility {
+                    member.visibility = visibility;
+                    member.explicit_implementation = explicit;
+                }
+                member.is_abstract |= method_row.flags & METHOD_ABSTRACT != 0;
+                member.is_overridable |=
+                    method_row.flags & (METHOD_VIRTUAL | METHOD_FINAL) == METHOD_VIRTUAL;
+            }
+            member
+        };
+        for (property, property_row) in self.get_type_properties(rid) {
+            members.push(accessor_member(
+                EntityKind::Property,
+                TableId::Property,
+                property,
+                property_row.name,
+                property_row.flags,
+                self.property_text(property_row, false),
+            ));
+        }
+        for (event, event_row) in self.get_type_events(rid) {
+            members.push(accessor_member(
+                EntityKind::Event,
+                TableId::Event,
+                event,
+                event_row.name,
+                event_row.event_flags,
+                self.event_text(event_row, false),
+            ));
+        }
+        if !include_hidden {
+            members.retain(|member| member.visibility != Visibility::Hidden);
+        }
+
+        ApiType {
+            name: self.type_def_full_name(rid),
+            visibility,
+            flags: row.flags,
+            base_type: (!row.extends.is_null()).then(|| self.type_index_text(row.extends, false)),
+            interfaces,
+            generic_constraints: self
+                .generic_params_of(owner)
+                .into_iter()
+                .map(|(param, _)| {
+                    self.generic_param_constraints_text(param, false)
+                        .trim_end()
+                        .to_string()
+                })
+                .collect(),
+            members,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ilasm::assemble;
+
+    const V1: &str = r#"
+        .assembly extern mscorlib {}
+        .assembly Lib {}
+        .namespace Lib {
+            .class public Shape extends [mscorlib]System.Object {
+                .method public newslot virtual instance float64 Area() { ret }
+                .method public instance void Move(int32 x) { ret }
+                .method public instance void Old() { ret }
+                .method family instance void Helper() { ret }
+                .method assembly instance void Internal() { ret }
+                .method public instance int32 get_Sides() { ret }
+                .property instance int32 Sides() { .get instance int32 Lib.Shape::get_Sides() }
+            }
+            .class public sealed Closed extends [mscorlib]System.Object {
+                .method family instance void NotVisible() { ret }
+                .class nested family Inner extends [mscorlib]System.Object {}
+            }
+            .class public sealed Color extends [mscorlib]System.Enum {
+                .field public specialname rtspecialname int32 value__
+                .field public static literal valuetype Lib.Color Red = int32(1)
+            }
+            .class public Box`1<T> extends [mscorlib]System.Object {}
+            .class public Disposer extends [mscorlib]System.Object
+                implements [mscorlib]System.IDisposable {
+                .method private final virtual newslot instance void System.IDisposable.Dispose() {
+                    .override [mscorlib]System.IDisposable::Dispose
+                    ret
+                }
+            }
+            .class private Secret extends [mscorlib]System.Object {
+                .class nested public Leak extends [mscorlib]System.Object {}
+            }
+        }
+    "#;
+
+    const V2: &str = r#"
+        .assembly extern mscorlib {}
+        .assembly Lib {}
+        .namespace Lib {
+            .class public abstract Shape extends [mscorlib]System.Object {
+                .method public instance float64 Area() { ret }
+                .method public instance void Move(int64 x) { ret }
+                .method family instance void Helper() { ret }
+                .method public newslot abstract virtual instance void Draw() {}
+                .method public instance int32 get_Sides() { ret }
+                .property instance int32 Sides() { .get instance int32 Lib.Shape::get_Sides() }
+            }
+            .class public sealed Closed extends [mscorlib]System.Object {
+                .method family instance void NotVisible() { ret }
+                .class nested family Inner extends [mscorlib]System.Object {}
+            }
+            .class public sealed Color extends [mscorlib]System.Enum {
+                .field public specialname rtspecialname int32 value__
+                .field public static literal valuetype Lib.Color Red = int32(2)
+            }
+            .class public Box`1<class T> extends [mscorlib]System.Object {}
+            .class private Disposer extends [mscorlib]System.Object
+                implements [mscorlib]System.IDisposable {
+                .method private final virtual newslot instance void System.IDisposable.Dispose() {
+                    .override [mscorlib]System.IDisposable::Dispose
+                    ret
+                }
+            }
+        }
+    "#;
+
+    #[test]
+    fn test_api_surface() {
+        let surface = assemble(V1).unwrap().metadata.api_surface();
+        let names: Vec<&str> = surface.types.iter().map(|ty| ty.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Lib.Box`1",
+                "Lib.Closed",
+                "Lib.Color",
+                "Lib.Disposer",
+                "Lib.Shape"
+            ]
+        );
+
+        let shape = surface.get("Lib.Shape").unwrap();
+        let members: Vec<(&str, Visibility)> = shape
+            .members
+            .iter()
+            .map(|m| (m.signature.as_str(), m.visibility))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ("instance float64 Area()", Visibility::Public),
+                ("instance void Move(int32)", Visibility::Public),
+                ("instance void Old()", Visibility::Public),
+                ("instance void Helper()", Visibility::Protected),
+                ("instance int32 get_Sides()", Visibility::Public),
+                ("instance int32 Sides()", Visibility::Public),
+            ]
+        );
+        assert!(shape.members[0].is_overridable);
+
+        // Protected members of sealed types cannot be reached
+        assert!(surface.get("Lib.Closed").unwrap().members.is_empty());
+        let disposer = surface.get("Lib.Disposer").unwrap();
+        assert!(disposer.members[0].explicit_implementation);
+        let color = surface.get("Lib.Color").unwrap();
+        assert!(color.is_enum());
+        assert_eq!(color.members[1].value.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_explicit_implementation_visibility() {
+        let md = assemble(
+            r#"
+            .assembly extern mscorlib { .ver 4:0:0:0 }
+            .assembly Lib {}
+            .class interface private abstract Lib.IHidden {
+                .method public abstract virtual newslot instance void Run() {}
+            }
+            .class interface public abstract Lib.IShown {
+                .method public abstract virtual newslot instance void Show() {}
+            }
+            .class public Lib.Worker extends [mscorlib]System.Object
+                implements Lib.IHidden, Lib.IShown {
+                .method private final virtual newslot instance void Lib.IHidden.Run() {
+                    .override Lib.IHidden::Run
+                    ret
+                }
+                .method private final virtual newslot instance void Lib.IShown.Show() {
+                    .override Lib.IShown::Show
+                    ret
+                }
+            }
+            "#,
+        )
+        .unwrap()
+        .metadata;
+        let surface = md.api_surface();
+        let worker = surface.get("Lib.Worker").unwrap();
+        let members: Vec<&str> = worker.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(members, ["Lib.IShown.Show"]);
+        assert!(worker.members[0].explicit_implementation);
+
+        // The hidden implementation is still collected as hidden
+        let all = md.collect_api(true);
+        let run = all
+            .get("Lib.Worker")
+            .unwrap()
+            .members
+            .iter()
+            .find(|m| m.name == "Lib.IHidden.Run")
+            .unwrap();
+        assert_eq!(run.visibility, Visibility::Hidden);
+        assert!(run.explicit_implementation);
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let v1 = assemble(V1).unwrap().metadata;
+        let v2 = assemble(V2).unwrap().metadata;
+        assert!(v1.breaking_changes(&v1).is_empty());
+
+        let changes: Vec<String> = v1
+            .breaking_changes(&v2)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                "[generic-constraints-changed] Lib.Box`1: generic parameters changed from <> to <class>",
+                "[enum-value-changed] Lib.Color::valuetype Lib.Color Red: value changed from 1 to 2",
+                "[visibility-reduced] Lib.Disposer: type visibility reduced from public to hidden",
+                "[type-abstract] Lib.Shape: type became abstract",
+                "[member-sealed] Lib.Shape::instance float64 Area(): method can no longer be overridden",
+                "[signature-changed] Lib.Shape::instance void Move(int32): signature changed to instance void Move(int64)",
+                "[member-removed] Lib.Shape::instance void Old(): method was removed",
+                "[abstract-member-added] Lib.Shape::instance void Draw(): abstract method was added",
+            ]
+        );
+
+        // Comparing surfaces alone cannot tell a hidden type from a removed one
+        let changes = v1.api_surface().breaking_changes(&v2.api_surface());
+        assert!(
+            changes
+                .iter()
+                .any(|change| change.kind == BreakingChangeKind::TypeRemoved
+                    && change.name == "Lib.Disposer")
+        );
+    }
+}
@@ -0,0 +1,854 @@
+//! Multi-assembly workspaces.
+//!
+//! [`Metadata`] describes a single module and stops at TypeRef and AssemblyRef
+//! boundaries. An [`AssemblySet`] holds many parsed assemblies, loads missing
+//! references on demand through a [`Resolver`], and resolves TypeRefs to the
+//! TypeDef that defines them, following ExportedType forwarders.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::identity::{AssemblyIdentity, IdentityComparison};
+use crate::image::metadata_bytes;
+use crate::metadata::{AssemblyRefInfo, Metadata, ResolvedType};
+use crate::resolve::{AssemblyResolver, resolve_type_def};
+use crate::tables::{CodedIndex, TableId};
+use crate::type_name::TypeName;
+
+/// Locates and loads referenced assemblies.
+pub trait Resolver {
+    /// Load the assembly named by a reference, or `Ok(None)` if it cannot be
+    /// found.
+    fn resolve(&self, reference: &AssemblyRefInfo) -> Result<Option<Metadata>>;
+}
+
+/// Parse metadata from either a PE image or raw metadata bytes.
+fn load(data: &[u8]) -> Result<Metadata> {
+    Metadata::parse(metadata_bytes(data)?)
+}
+
+/// Probes directories for `<name>.dll` and `<name>.exe`.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryResolver {
+    directories: Vec<PathBuf>,
+}
+
+impl DirectoryResolver {
+    /// Create a resolver probing the given directories in order.
+    pub fn new<P: Into<PathBuf>>(directories: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            directories: directories.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Add a directory to probe after the existing ones.
+    pub fn add_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.directories.push(directory.into());
+    }
+
+    /// Get the probed directories.
+    #[must_use]
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+}
+
+impl Resolver for DirectoryResolver {
+    fn resolve(&self, reference: &AssemblyRefInfo) -> Result<Option<Metadata>> {
+        for directory in &self.directories {
+            for extension in ["dll", "exe"] {
+                let path = directory.join(format!("{}.{extension}", reference.name));
+                if path.is_file() {
+                    return load(&std::fs::read(path)?).map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Resolves against a targeting pack laid out as `<pack>/<version>/ref/<tfm>/`,
+/// e.g. `dotnet/packs/Microsoft.NETCore.App.Ref/8.0.0/ref/net8.0`.
+#[derive(Debug, Clone)]
+pub struct ReferencePackResolver {
+    inner: DirectoryResolver,
+}
+
+impl ReferencePackResolver {
+    /// Create a resolver for a reference pack.
+    ///
+    /// `version` and `framework` select the pack version and target framework
+    /// directories; when `None`, the highest available one is used.
+    pub fn new(
+        pack: impl AsRef<Path>,
+        version: Option<&str>,
+        framework: Option<&str>,
+    ) -> Result<Self> {
+        let pack = pack.as_ref();
+        let version_dir = match version {
+            Some(version) => pack.join(version),
+            None => highest_subdirectory(pack, |name| Some(name))?,
+        };
+        let ref_dir = version_dir.join("ref");
+        let framework_dir = match framework {
+            Some(framework) => ref_dir.join(framework),
+            None => highest_subdirectory(&ref_dir, |name| name.strip_prefix("net"))?,
+        };
+        if !framework_dir.is_dir() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "reference pack directory not found: {}",
+                    framework_dir.display()
+                ),
+            )));
+        }
+        Ok(Self {
+            inner: DirectoryResolver::new([framework_dir]),
+        })
+    }
+
+    /// Get the directory holding the reference assemblies.
+    #[must_use]
+    pub fn directory(&self) -> &Path {
+        &self.inner.directories[0]
+    }
+}
+
+impl Resolver for ReferencePackResolver {
+    fn resolve(&self, reference: &AssemblyRefInfo) -> Result<Option<Metadata>> {
+        self.inner.resolve(reference)
+    }
+}
+
+/// Get the subdirectory with the highest dotted version, after stripping a
+/// prefix from its name with `version_of`.
+fn highest_subdirectory(
+    directory: &Path,
+    version_of: impl Fn(&str) -> Option<&str>,
+) -> Result<PathBuf> {
+    let mut best: Option<(Vec<u64>, PathBuf)> = None;
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        let Some(version) = name.to_str().and_then(&version_of) else {
+            continue;
+        };
+        // Numeric components only; prerelease suffixes are ignored
+        let key: Vec<u64> = version
+            .split(['.', '-'])
+            .map_while(|part| part.parse().ok())
+            .collect();
+        if key.is_empty() {
+            continue;
+        }
+        if best.as_ref().is_none_or(|(best_key, _)| key > *best_key) {
+            best = Some((key, entry.path()));
+        }
+    }
+    best.map(|(_, path)| path).ok_or_else(|| {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no versioned directory in {}", directory.display()),
+        ))
+    })
+}
+
+/// Resolves from assemblies held in memory, keyed by simple name.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    assemblies: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    /// Create an empty resolver.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an assembly as a PE image or raw metadata bytes.
+    pub fn insert(&mut self, name: &str, data: Vec<u8>) {
+        self.assemblies.insert(name.to_ascii_lowercase(), data);
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn resolve(&self, reference: &AssemblyRefInfo) -> Result<Option<Metadata>> {
+        self.assemblies
+            .get(&reference.name.to_ascii_lowercase())
+            .map(|data| load(data))
+            .transpose()
+    }
+}
+
+/// Identifies an assembly within an [`AssemblySet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssemblyId(usize);
+
+impl AssemblyId {
+    /// Get the position of the assembly in load order.
+    #[must_use]
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A TypeDef in a specific assembly of an [`AssemblySet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TypeHandle {
+    /// The defining assembly.
+    pub assembly: AssemblyId,
+    /// 1-based TypeDef row.
+    pub type_def: u32,
+}
+
+/// A set of loaded assemblies keyed by simple name (case-insensitive).
+#[derive(Default)]
+pub struct AssemblySet {
+    assemblies: Vec<Metadata>,
+    names: HashMap<String, AssemblyId>,
+    resolver: Option<Box<dyn Resolver>>,
+    /// Names the resolver could not find.
+    missing: HashSet<String>,
+    /// Resolved type references, keyed by assembly, table and row.
+    types: HashMap<(AssemblyId, Option<TableId>, u32), Option<TypeHandle>>,
+}
+
+impl AssemblySet {
+    /// Create an empty set without a resolver.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty set that loads missing references through `resolver`.
+    #[must_use]
+    pub fn with_resolver(resolver: impl Resolver + 'static) -> Self {
+        Self {
+            resolver: Some(Box::new(resolver)),
+            ..Self::default()
+        }
+    }
+
+    /// Add an assembly.
+    ///
+    /// If an assembly with the same name is already in the set, it is kept
+    /// and its id returned.
+    pub fn add(&mut self, metadata: Metadata) -> AssemblyId {
+        let name = assembly_name(&metadata);
+        if let Some(&id) = self.names.get(&name) {
+            return id;
+        }
+        let id = AssemblyId(self.assemblies.len());
+        self.assemblies.push(metadata);
+        self.names.insert(name, id);
+        // Previously unresolvable references may now resolve
+        self.types.retain(|_, resolved| resolved.is_some());
+        id
+    }
+
+    /// Get an assembly by id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id belongs to another set.
+    #[must_use]
+    pub fn get(&self, id: AssemblyId) -> &Metadata {
+        &self.assemblies[id.0]
+    }
+
+    /// Find a loaded assembly by simple name.
+    #[must_use]
+    pub fn find(&self, name: &str) -> Option<AssemblyId> {
+        self.names.get(&name.to_ascii_lowercase()).copied()
+    }
+
+    /// Find a loaded assembly whose identity satisfies `identity`.
+    #[must_use]
+    pub fn find_identity(
+        &self,
+        identity: &AssemblyIdentity,
+        comparison: IdentityComparison,
+    ) -> Option<AssemblyId> {
+        let id = self.find(&identity.name)?;
+        let candidate = self.identity(id)?;
+        identity.matches(&candidate, comparison).then_some(id)
+    }
+
+    /// Get the identity of a loaded assembly (None for netmodules).
+    #[must_use]
+    pub fn identity(&self, id: AssemblyId) -> Option<AssemblyIdentity> {
+        self.get(id).assembly_identity()
+    }
+
+    /// Get the number of loaded assemblies.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.assemblies.len()
+    }
+
+    /// Check whether the set is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.assemblies.is_empty()
+    }
+
+    /// Iterate over loaded assemblies in load order.
+    pub fn iter(&self) -> impl Iterator<Item = (AssemblyId, &Metadata)> {
+        self.assemblies
+            .iter()
+            .enumerate()
+            .map(|(index, metadata)| (AssemblyId(index), metadata))
+    }
+
+    /// Get the assembly named by a reference, loading it through the resolver
+    /// if needed. Failed lookups are remembered.
+    pub fn load(&mut self, reference: &AssemblyRefInfo) -> Result<Option<AssemblyId>> {
+        if let Some(id) = self.find(&reference.name) {
+            return Ok(Some(id));
+        }
+        let key = reference.name.to_ascii_lowercase();
+        if self.missing.contains(&key) {
+            return Ok(None);
+        }
+        let Some(resolver) = &self.resolver else {
+            return Ok(None);
+        };
+        match resolver.resolve(reference)? {
+            Some(metadata) => {
+                let id = self.add(metadata);
+                // Keep the requested name as an alias in case the file differs
+                self.names.entry(key).or_insert(id);
+                Ok(Some(id))
+            }
+            None => {
+                self.missing.insert(key);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Load every assembly transitively referenced by `id`.
+    ///
+    /// References the resolver cannot find are skipped.
+    pub fn load_dependencies(&mut self, id: AssemblyId) -> Result<()> {
+        let mut pending = vec![id];
+        let mut visited = HashSet::from([id]);
+        while let Some(id) = pending.pop() {
+            for reference in self.get(id).assembly_refs() {
+                if let Some(dependency) = self.load(&reference)? {
+                    if visited.insert(dependency) {
+                        pending.push(dependency);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve a TypeRef (1-based index) of an assembly to its definition.
+    pub fn resolve_type_ref(
+        &mut self,
+        assembly: AssemblyId,
+        type_ref: u32,
+    ) -> Result<Option<TypeHandle>> {
+        self.resolve_type(
+            assembly,
+            CodedIndex {
+                table: Some(TableId::TypeRef),
+                row: type_ref,
+            },
+        )
+    }
+
+    /// Resolve an ExportedType (1-based index) of an assembly to the TypeDef
+    /// it ultimately forwards to, following chains across facades.
+    pub fn resolve_exported_type(
+        &mut self,
+        assembly: AssemblyId,
+        exported_type: u32,
+    ) -> Result<Option<TypeHandle>> {
+        self.resolve_type(
+            assembly,
+            CodedIndex {
+                table: Some(TableId::ExportedType),
+                row: exported_type,
+            },
+        )
+    }
+
+    /// Resolve a type returned by [`Metadata::resolve_type`] on an assembly
+    /// of this set to its definition.
+    pub fn resolve(
+        &mut self,
+        assembly: AssemblyId,
+        resolved: &ResolvedType,
+    ) -> Result<Option<TypeHandle>> {
+        let index = match *resolved {
+            ResolvedType::TypeDef { index, .. } => CodedIndex {
+                table: Some(TableId::TypeDef),
+                row: index,
+            },
+            ResolvedType::TypeRef { index, .. } => CodedIndex {
+                table: Some(TableId::TypeRef),
+                row: index,
+            },
+            ResolvedType::TypeSpec { index, .. } => CodedIndex {
+                table: Some(TableId::TypeSpec),
+                row: index,
+            },
+        };
+        self.resolve_type(assembly, index)
+    }
+
+    /// Resolve a reflection type name to its definition, ignoring generic
+    /// arguments and modifiers.
+    ///
+    /// Assembly-qualified names are looked up in the named assembly; others
+    /// in `context` and then in its core library, as `Type.GetType` does.
+    pub fn resolve_type_name(
+        &mut self,
+        context: AssemblyId,
+        name: &TypeName,
+    ) -> Result<Option<TypeHandle>> {
+        let scopes = match &name.assembly {
+            Some(assembly) => vec![Some(assembly.clone())],
+            None => vec![None, self.get(context).corlib_identity()],
+        };
+        for scope in scopes {
+            let id = match scope {
+                None => Some(context),
+                Some(assembly) => self.load(&assembly.to_assembly_ref())?,
+            };
+            let Some(id) = id else {
+                continue;
+            };
+            let (namespace, top) = name.top_level();
+            let metadata = self.get(id);
+            let handle = if let Some(type_def) = metadata.find_type_def(namespace, top, None) {
+                Some(TypeHandle {
+                    assembly: id,
+                    type_def,
+                })
+            } else if let Some(exported) = metadata.find_exported_type(namespace, top) {
+                self.resolve_exported_type(id, exported.index)?
+            } else {
+                None
+            };
+            let handle = name.nested.iter().try_fold(handle, |handle, nested| {
+                handle.and_then(|handle| {
+                    let type_def = self.get(handle.assembly).find_type_def(
+                        "",
+                        nested,
+                        Some(handle.type_def),
+                    )?;
+                    Some(Some(TypeHandle {
+                        assembly: handle.assembly,
+                        type_def,
+                    }))
+                })
+            });
+            if let Some(Some(handle)) = handle {
+                return Ok(Some(handle));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Resolve a TypeDefOrRef or ExportedType reference of an assembly to its
+    /// definition, loading referenced assemblies as needed. TypeSpecs resolve
+    /// to their generic type definition.
+    pub fn resolve_type(
+        &mut self,
+        assembly: AssemblyId,
+        index: CodedIndex,
+    ) -> Result<Option<TypeHandle>> {
+        let key = (assembly, index.table, index.row);
+        if let Some(&resolved) = self.types.get(&key) {
+            return Ok(resolved);
+        }
+
+        loop {
+            let recorder = Recorder {
+                set: self,
+                missing: RefCell::new(Vec::new()),
+            };
+            let resolved = resolve_type_def(self.get(assembly), index, Some(&recorder)).map(
+                |(metadata, type_def)| TypeHandle {
+                    assembly: self.id_of(metadata),
+                    type_def,
+                },
+            );
+            let missing = recorder.missing.into_inner();
+
+            if resolved.is_none() {
+                // Load whatever the lookup stopped at and try again
+                let mut loaded = false;
+                for reference in &missing {
+                    loaded |= self.load(reference)?.is_some();
+                }
+                if loaded {
+                    continue;
+                }
+            }
+            self.types.insert(key, resolved);
+            return Ok(resolved);
+        }
+    }
+
+    fn id_of(&self, metadata: &Metadata) -> AssemblyId {
+        let index = self
+            .assemblies
+            .iter()
+            .position(|candidate| std::ptr::eq(candidate, metadata))
+            .expect("resolved metadata belongs to the set");
+        AssemblyId(index)
+    }
+}
+
+/// Finds already-loaded assemblies, so member and type resolution on a
+/// [`Metadata`] can reach across the set.
+impl AssemblyResolver for AssemblySet {
+    fn resolve_assembly(&self, reference: &AssemblyRefInfo) -> Option<&Metadata> {
+        self.find(&reference.name).map(|id| self.get(id))
+    }
+}
+
+impl fmt::Debug for AssemblySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssemblySet")
+            .field(
+                "assemblies",
+                &self
+                    .assemblies
+                    .iter()
+                    .map(assembly_name)
+                    .collect::<Vec<_>>(),
+            )
+            .field("has_resolver", &self.resolver.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Records references that are not loaded yet.
+struct Recorder<'a> {
+    set: &'a AssemblySet,
+    missing: RefCell<Vec<AssemblyRefInfo>>,
+}
+
+impl AssemblyResolver for Recorder<'_> {
+    fn resolve_assembly(&self, reference: &AssemblyRefInfo) -> Option<&Metadata> {
+        let resolved = self.set.resolve_assembly(reference);
+        if resolved.is_none() {
+            self.missing.borrow_mut().push(reference.clone());
+        }
+        resolved
+    }
+}
+
+/// Lowercase simple name of an assembly, or of the module for netmodules.
+fn assembly_name(metadata: &Metadata) -> String {
+    let name = match metadata.assembly() {
+        Some(assembly) => assembly.name,
+        None => metadata
+            .modules
+            .first()
+            .and_then(|module| metadata.strings.get(module.name).ok())
+            .unwrap_or_default()
+            .to_string(),
+    };
+    name.to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::tests::TempDir;
+    use crate::ilasm::assemble;
+    use crate::image::test_image;
+    use crate::resolve::MemberDefinition;
+    use crate::tables::{ExportedTypeRow, TypeRefRow};
+
+    fn module(source: &str) -> Metadata {
+        assemble(source).unwrap().metadata
+    }
+
+    fn core() -> Metadata {
+        module(
+            r#"
+            .assembly Core {}
+            .namespace System {
+                .class public Object {}
+                .class public String extends System.Object {
+                    .method public instance int32 get_Length() { ldc.i4.0 ret }
+                }
+            }
+            "#,
+        )
+    }
+
+    /// A facade forwarding `System.String` to Core.
+    fn facade() -> Metadata {
+        module(
+            r#"
+            .assembly extern Core {}
+            .assembly Facade {}
+            .class extern forwarder System.String { .assembly extern Core }
+            "#,
+        )
+    }
+
+    fn app() -> Metadata {
+        module(
+            r#"
+            .assembly extern Facade {}
+            .assembly App {}
+            .class public Program extends [Facade]System.String {
+                .method public static int32 Len(class [Facade]System.String s) {
+                    ldarg s
+                    call instance int32 [Facade]System.String::get_Length()
+                    ret
+                }
+            }
+            "#,
+        )
+    }
+
+    fn type_ref_named(md: &Metadata, name: &str) -> u32 {
+        let position = md
+            .type_refs
+            .iter()
+            .position(|row: &TypeRefRow| md.strings.get(row.type_name).unwrap() == name)
+            .unwrap();
+        position as u32 + 1
+    }
+
+    #[test]
+    fn test_resolve_through_forwarder() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("Core", core().write());
+        resolver.insert("Facade", test_image(&facade().write(), 0));
+
+        let mut set = AssemblySet::with_resolver(resolver);
+        let app = set.add(app());
+        let string = type_ref_named(set.get(app), "String");
+
+        let handle = set.resolve_type_ref(app, string).unwrap().unwrap();
+        let core = set.find("core").unwrap();
+        assert_eq!(handle.assembly, core);
+        assert_eq!(
+            set.get(core).find_type_def("System", "String", None),
+            Some(handle.type_def)
+        );
+        assert_eq!(set.len(), 3);
+
+        // Cached, and member resolution reaches across the set
+        assert_eq!(set.resolve_type_ref(app, string).unwrap(), Some(handle));
+        let resolved = set.get(app).resolve_member_ref_with(1, &set).unwrap();
+        assert!(matches!(resolved.member, MemberDefinition::Method(_)));
+        assert!(std::ptr::eq(resolved.metadata, set.get(core)));
+    }
+
+    #[test]
+    fn test_missing_reference() {
+        let mut set = AssemblySet::with_resolver(MemoryResolver::new());
+        let app = set.add(app());
+        let string = type_ref_named(set.get(app), "String");
+        assert_eq!(set.resolve_type_ref(app, string).unwrap(), None);
+
+        // Adding the assemblies later makes the reference resolvable
+        set.add(facade());
+        set.add(core());
+        assert!(set.resolve_type_ref(app, string).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_forwarder_chain() {
+        let core = module(
+            r#"
+            .assembly Core {}
+            .class public System.Object {}
+            .class public System.String extends System.Object {
+                .class nested public Enumerator extends System.Object {}
+            }
+            "#,
+        );
+        let runtime = module(
+            r#"
+            .assembly System.Runtime {}
+            .class extern forwarder System.String { .assembly extern Core }
+            "#,
+        );
+        let netstandard = module(
+            r#"
+            .assembly netstandard {}
+            .class extern forwarder System.String { .assembly extern System.Runtime }
+            "#,
+        );
+        let app = module(
+            r#"
+            .assembly extern netstandard {}
+            .assembly App {}
+            .class public P extends [netstandard]System.String {
+                .field public class [netstandard]System.String/Enumerator e
+            }
+            "#,
+        );
+
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("Core", core.write());
+        resolver.insert("System.Runtime", runtime.write());
+        resolver.insert("netstandard", netstandard.write());
+        let mut set = AssemblySet::with_resolver(resolver);
+        let app = set.add(app);
+
+        // Through ResolvedType, across both facades
+        let (program, _) = set.get(app).find_type("P", None).unwrap();
+        let base = set.get(app).get_base_type(program).unwrap();
+        assert!(base.is_type_ref());
+        let string = set.resolve(app, &base).unwrap().unwrap();
+        let core = set.find("Core").unwrap();
+        assert_eq!(string.assembly, core);
+
+        // Nested types follow their forwarded enclosing type
+        let enumerator = type_ref_named(set.get(app), "Enumerator");
+        let handle = set.resolve_type_ref(app, enumerator).unwrap().unwrap();
+        assert_eq!(handle.assembly, core);
+        assert_eq!(
+            set.get(core).get_enclosing_type(handle.type_def),
+            Some(string.type_def)
+        );
+
+        // The forwarder itself resolves to the final definition
+        let netstandard = set.find("netstandard").unwrap();
+        assert!(set.get(netstandard).exported_types()[0].is_forwarder);
+        assert_eq!(
+            set.resolve_exported_type(netstandard, 1).unwrap(),
+            Some(string)
+        );
+    }
+
+    #[test]
+    fn test_forwarder_cycle() {
+        let first = module(
+            r#"
+            .assembly First {}
+            .class extern forwarder System.String { .assembly extern Second }
+            "#,
+        );
+        let mut second = module(
+            r#"
+            .assembly Second {}
+            .class extern forwarder System.String { .assembly extern First }
+            "#,
+        );
+        // Nested rows that enclose each other
+        for (name, enclosing) in [("A", 3), ("B", 2)] {
+            let type_name = second.strings.add(name);
+            second.exported_types.push(ExportedTypeRow {
+                flags: 0x0000_0002,
+                type_def_id: 0,
+                type_name,
+                type_namespace: 0,
+                implementation: CodedIndex {
+                    table: Some(TableId::ExportedType),
+                    row: enclosing,
+                },
+            });
+        }
+
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("First", first.write());
+        let mut set = AssemblySet::with_resolver(resolver);
+        let second = set.add(second);
+        assert_eq!(set.resolve_exported_type(second, 1).unwrap(), None);
+        assert!(set.find("First").is_some());
+        assert_eq!(set.resolve_exported_type(second, 2).unwrap(), None);
+        assert_eq!(set.resolve_exported_type(second, 3).unwrap(), None);
+    }
+
+    #[test]
+    fn test_resolve_type_name() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("Core", core().write());
+        resolver.insert("Facade", facade().write());
+        let mut set = AssemblySet::with_resolver(resolver);
+        let app = set.add(app());
+
+        let name = TypeName::parse("System.String[], Facade, Version=0.0.0.0").unwrap();
+        let handle = set.resolve_type_name(app, &name).unwrap().unwrap();
+        let core = set.find("Core").unwrap();
+        assert_eq!(handle.assembly, core);
+        assert_eq!(
+            set.get(core).find_type_def("System", "String", None),
+            Some(handle.type_def)
+        );
+
+        let program = TypeName::parse("Program").unwrap();
+        let handle = set.resolve_type_name(app, &program).unwrap().unwrap();
+        assert_eq!(handle.assembly, app);
+        let missing = TypeName::parse("System.String, Missing").unwrap();
+        assert_eq!(set.resolve_type_name(app, &missing).unwrap(), None);
+        // Unqualified names only search the context and its core library
+        let string = TypeName::parse("System.String").unwrap();
+        assert_eq!(set.resolve_type_name(app, &string).unwrap(), None);
+        assert!(set.resolve_type_name(core, &string).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_load_dependencies() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("Core", core().write());
+        resolver.insert("Facade", facade().write());
+        let mut set = AssemblySet::with_resolver(resolver);
+        let app = set.add(app());
+        set.load_dependencies(app).unwrap();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.add(core()), set.find("Core").unwrap());
+
+        let reference = set.get(app).assembly_refs()[0].identity();
+        assert_eq!(
+            set.find_identity(&reference, IdentityComparison::Unification),
+            set.find(&reference.name)
+        );
+        let mut newer = reference;
+        newer.version = Some((99, 0, 0, 0));
+        assert_eq!(set.find_identity(&newer, IdentityComparison::Exact), None);
+    }
+
+    #[test]
+    fn test_directory_resolvers() {
+        let root = TempDir::new("resolver");
+        let pack = root.path().join("Microsoft.NETCore.App.Ref");
+        for dir in [
+            "8.0.0/ref/net8.0",
+            "10.0.1/ref/net9.0",
+            "10.0.1/ref/net10.0",
+        ] {
+            std::fs::create_dir_all(pack.join(dir)).unwrap();
+        }
+        let latest = pack.join("10.0.1/ref/net10.0");
+        std::fs::write(latest.join("Core.dll"), test_image(&core().write(), 0)).unwrap();
+
+        let resolver = ReferencePackResolver::new(&pack, None, None).unwrap();
+        assert_eq!(resolver.directory(), latest);
+        let pinned = ReferencePackResolver::new(&pack, Some("8.0.0"), Some("net8.0")).unwrap();
+        assert_eq!(pinned.directory(), pack.join("8.0.0/ref/net8.0"));
+        assert!(ReferencePackResolver::new(&pack, Some("7.0.0"), None).is_err());
+
+        let mut set = AssemblySet::with_resolver(DirectoryResolver::new([latest]));
+        let app = set.add(module(
+            ".assembly extern Core {} .assembly App {} .class public P extends [Core]System.Object {}",
+        ));
+        let object = type_ref_named(set.get(app), "Object");
+        let handle = set.resolve_type_ref(app, object).unwrap().unwrap();
+        assert_eq!(handle.assembly, set.find("Core").unwrap());
+    }
+}
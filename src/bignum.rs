@@ -0,0 +1,367 @@
+//! Arbitrary-precision unsigned integers for RSA.
+//!
+//! Just enough arithmetic for strong-name signatures: conversions to and from
+//! byte strings, addition, subtraction, multiplication, remainder, and
+//! modular exponentiation using Montgomery multiplication for odd moduli.
+//! Nothing here is constant-time.
+
+use std::cmp::Ordering;
+
+/// An unsigned integer stored as little-endian 32-bit limbs without leading
+/// zero limbs.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    /// Create a value from a small integer.
+    pub(crate) fn from_u32(value: u32) -> Self {
+        Self::from_limbs(vec![value])
+    }
+
+    fn from_limbs(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        Self { limbs }
+    }
+
+    /// Create a value from big-endian bytes.
+    pub(crate) fn from_be_bytes(bytes: &[u8]) -> Self {
+        let limbs = bytes
+            .rchunks(4)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0u32, |limb, &byte| (limb << 8) | u32::from(byte))
+            })
+            .collect();
+        Self::from_limbs(limbs)
+    }
+
+    /// Create a value from little-endian bytes.
+    pub(crate) fn from_le_bytes(bytes: &[u8]) -> Self {
+        let reversed: Vec<u8> = bytes.iter().rev().copied().collect();
+        Self::from_be_bytes(&reversed)
+    }
+
+    /// Get the value as big-endian bytes, left-padded with zeros to `len`.
+    /// Values wider than `len` keep their low-order bytes.
+    pub(crate) fn to_be_bytes(&self, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        for (i, byte) in bytes.iter_mut().rev().enumerate() {
+            if let Some(limb) = self.limbs.get(i / 4) {
+                *byte = (limb >> (8 * (i % 4))) as u8;
+            }
+        }
+        bytes
+    }
+
+    /// Get the value as little-endian bytes, padded with zeros to `len`.
+    pub(crate) fn to_le_bytes(&self, len: usize) -> Vec<u8> {
+        let mut bytes = self.to_be_bytes(len);
+        bytes.reverse();
+        bytes
+    }
+
+    /// Check whether the value is zero.
+    pub(crate) fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Get the number of significant bits.
+    pub(crate) fn bits(&self) -> usize {
+        self.limbs.last().map_or(0, |top| {
+            self.limbs.len() * 32 - top.leading_zeros() as usize
+        })
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.limbs
+            .get(index / 32)
+            .is_some_and(|limb| limb >> (index % 32) & 1 == 1)
+    }
+
+    /// Compute `self + other`.
+    pub(crate) fn add(&self, other: &Self) -> Self {
+        let len = self.limbs.len().max(other.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for i in 0..len {
+            let sum = u64::from(limb(&self.limbs, i)) + u64::from(limb(&other.limbs, i)) + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        Self::from_limbs(limbs)
+    }
+
+    /// Compute `self - other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` is greater than `self`.
+    pub(crate) fn sub(&self, other: &Self) -> Self {
+        assert!(*self >= *other, "BigUint subtraction underflow");
+        let mut limbs = self.limbs.clone();
+        sub_in_place(&mut limbs, &other.limbs);
+        Self::from_limbs(limbs)
+    }
+
+    /// Compute `self * other`.
+    pub(crate) fn mul(&self, other: &Self) -> Self {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = u64::from(a) * u64::from(b) + u64::from(limbs[i + j]) + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        Self::from_limbs(limbs)
+    }
+
+    /// Compute `self mod modulus` by shift-and-subtract.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modulus` is zero.
+    pub(crate) fn rem(&self, modulus: &Self) -> Self {
+        assert!(!modulus.is_zero(), "BigUint remainder by zero");
+        if self < modulus {
+            return self.clone();
+        }
+        let mut remainder = Self::default();
+        for index in (0..self.bits()).rev() {
+            remainder.shl1();
+            if self.bit(index) {
+                match remainder.limbs.first_mut() {
+                    Some(low) => *low |= 1,
+                    None => remainder.limbs.push(1),
+                }
+            }
+            if remainder >= *modulus {
+                sub_in_place(&mut remainder.limbs, &modulus.limbs);
+                remainder = Self::from_limbs(remainder.limbs);
+            }
+        }
+        remainder
+    }
+
+    fn shl1(&mut self) {
+        let mut carry = 0;
+        for limb in &mut self.limbs {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            self.limbs.push(carry);
+        }
+    }
+
+    /// Compute `self ^ exponent mod modulus`.
+    ///
+    /// Uses Montgomery multiplication, so the modulus must be odd, as RSA
+    /// moduli and primes are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modulus` is even.
+    pub(crate) fn mod_pow(&self, exponent: &Self, modulus: &Self) -> Self {
+        let montgomery = Montgomery::new(modulus);
+        let base = montgomery.to_montgomery(&self.rem(modulus));
+        let mut result = montgomery.one.clone();
+        for index in (0..exponent.bits()).rev() {
+            result = montgomery.mul(&result, &result);
+            if exponent.bit(index) {
+                result = montgomery.mul(&result, &base);
+            }
+        }
+        let mut one = vec![0u32; montgomery.modulus.len()];
+        one[0] = 1;
+        Self::from_limbs(montgomery.mul(&result, &one))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+fn limb(limbs: &[u32], index: usize) -> u32 {
+    limbs.get(index).copied().unwrap_or(0)
+}
+
+/// Subtract `b` from `a` in place; `a` must be at least `b`.
+fn sub_in_place(a: &mut [u32], b: &[u32]) {
+    let mut borrow = 0i64;
+    for (i, value) in a.iter_mut().enumerate() {
+        let difference = i64::from(*value) - i64::from(limb(b, i)) - borrow;
+        *value = difference as u32;
+        borrow = i64::from(difference < 0);
+    }
+}
+
+/// Montgomery arithmetic modulo an odd number with `R = 2^(32 * limbs)`.
+struct Montgomery {
+    modulus: Vec<u32>,
+    /// `-modulus^-1 mod 2^32`.
+    inverse: u32,
+    /// `R^2 mod modulus`.
+    r_squared: Vec<u32>,
+    /// `R mod modulus`, i.e. 1 in Montgomery form.
+    one: Vec<u32>,
+}
+
+impl Montgomery {
+    fn new(modulus: &BigUint) -> Self {
+        assert!(modulus.bit(0), "Montgomery modulus must be odd");
+        let len = modulus.limbs.len();
+
+        // Newton iteration doubles the correct low bits each step
+        let low = modulus.limbs[0];
+        let mut inverse = 1u32;
+        for _ in 0..5 {
+            inverse = inverse.wrapping_mul(2u32.wrapping_sub(low.wrapping_mul(inverse)));
+        }
+
+        let mut r_squared = vec![0u32; 2 * len + 1];
+        r_squared[2 * len] = 1;
+        let r_squared = BigUint::from_limbs(r_squared).rem(modulus);
+
+        let mut montgomery = Self {
+            modulus: modulus.limbs.clone(),
+            inverse: inverse.wrapping_neg(),
+            r_squared: pad(&r_squared.limbs, len),
+            one: Vec::new(),
+        };
+        let mut one = vec![0u32; len];
+        one[0] = 1;
+        montgomery.one = montgomery.mul(&one, &montgomery.r_squared);
+        montgomery
+    }
+
+    fn to_montgomery(&self, value: &BigUint) -> Vec<u32> {
+        self.mul(&pad(&value.limbs, self.modulus.len()), &self.r_squared)
+    }
+
+    /// Compute `a * b * R^-1 mod modulus` (CIOS method).
+    fn mul(&self, a: &[u32], b: &[u32]) -> Vec<u32> {
+        let n = &self.modulus;
+        let len = n.len();
+        let mut t = vec![0u32; len + 2];
+        for &b_i in b {
+            let mut carry = 0u64;
+            for j in 0..len {
+                let sum = u64::from(t[j]) + u64::from(a[j]) * u64::from(b_i) + carry;
+                t[j] = sum as u32;
+                carry = sum >> 32;
+            }
+            let sum = u64::from(t[len]) + carry;
+            t[len] = sum as u32;
+            t[len + 1] = (sum >> 32) as u32;
+
+            let m = t[0].wrapping_mul(self.inverse);
+            let sum = u64::from(t[0]) + u64::from(m) * u64::from(n[0]);
+            let mut carry = sum >> 32;
+            for j in 1..len {
+                let sum = u64::from(t[j]) + u64::from(m) * u64::from(n[j]) + carry;
+                t[j - 1] = sum as u32;
+                carry = sum >> 32;
+            }
+            let sum = u64::from(t[len]) + carry;
+            t[len - 1] = sum as u32;
+            t[len] = t[len + 1] + (sum >> 32) as u32;
+            t[len + 1] = 0;
+        }
+
+        t.truncate(len + 1);
+        if t[len] != 0 || t[..len].iter().rev().cmp(n.iter().rev()) != Ordering::Less {
+            sub_in_place(&mut t, n);
+        }
+        t.truncate(len);
+        t
+    }
+}
+
+fn pad(limbs: &[u32], len: usize) -> Vec<u32> {
+    let mut padded = limbs.to_vec();
+    padded.resize(len, 0);
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions_and_rem() {
+        let a = BigUint::from_be_bytes(&[0x02, 0, 0, 0, 0, 0x07]);
+        assert_eq!(a.to_be_bytes(6), [0x02, 0, 0, 0, 0, 0x07]);
+        assert_eq!(a.bits(), 42);
+        assert_eq!(
+            BigUint::from_le_bytes(&[0x34, 0x12, 0x00]).to_be_bytes(2),
+            [0x12, 0x34]
+        );
+        assert_eq!(a.rem(&BigUint::from_u32(0x100)), BigUint::from_u32(7));
+        assert_eq!(a.rem(&a), BigUint::default());
+        assert!(BigUint::default().is_zero());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = BigUint::from_be_bytes(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let b = BigUint::from_u32(1);
+        let sum = a.add(&b);
+        assert_eq!(sum.to_be_bytes(6), [0x02, 0, 0, 0, 0, 0]);
+        assert_eq!(sum.sub(&b), a);
+        assert_eq!(sum.bits(), 42);
+        assert_eq!(
+            BigUint::from_le_bytes(&[0x34, 0x12, 0x00]).to_be_bytes(2),
+            [0x12, 0x34]
+        );
+
+        let product = a.mul(&a);
+        assert_eq!(product.rem(&a), BigUint::default());
+        assert_eq!(
+            product.add(&b).rem(&a.add(&b)),
+            BigUint::from_u32(2).rem(&a.add(&b))
+        );
+    }
+
+    #[test]
+    fn test_mod_pow() {
+        // 4^13 mod 497 = 445
+        let result = BigUint::from_u32(4).mod_pow(&BigUint::from_u32(13), &BigUint::from_u32(497));
+        assert_eq!(result, BigUint::from_u32(445));
+
+        // Fermat: a^(p-1) = 1 mod p for the Mersenne prime 2^127 - 1
+        let mut p = vec![0xFFu8; 16];
+        p[0] = 0x7F;
+        let p = BigUint::from_be_bytes(&p);
+        let a = BigUint::from_be_bytes(b"clrmeta bignum test value");
+        let mut exponent = vec![0xFFu8; 16];
+        exponent[0] = 0x7F;
+        exponent[15] = 0xFE;
+        let exponent = BigUint::from_be_bytes(&exponent);
+        assert_eq!(a.mod_pow(&exponent, &p), BigUint::from_u32(1));
+        assert_eq!(
+            BigUint::default().mod_pow(&BigUint::from_u32(3), &p),
+            BigUint::default()
+        );
+    }
+}
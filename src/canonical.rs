@@ -0,0 +1,289 @@
+//! Canonical ordering of sorted tables.
+//!
+//! ECMA-335 II.22 requires fourteen tables to be sorted by their primary key
+//! column, and the runtime binary-searches them. Rows added or edited in
+//! memory are appended in whatever order the caller produced them, so
+//! [`Metadata::canonicalize`] sorts those tables and rewrites the coded
+//! indices that point into them. [`Metadata::write`] canonicalizes a copy
+//! before writing whenever a table is out of order.
+
+use crate::metadata::Metadata;
+use crate::tables::{CodedIndex, TableId};
+
+/// Tables that ECMA-335 requires to be sorted, in canonicalization order:
+/// rows pointing into a table are sorted after it is renumbered.
+const SORTED_TABLES: [TableId; 14] = [
+    TableId::InterfaceImpl,
+    TableId::Constant,
+    TableId::FieldMarshal,
+    TableId::DeclSecurity,
+    TableId::ClassLayout,
+    TableId::FieldLayout,
+    TableId::MethodSemantics,
+    TableId::MethodImpl,
+    TableId::ImplMap,
+    TableId::FieldRva,
+    TableId::NestedClass,
+    TableId::GenericParam,
+    TableId::GenericParamConstraint,
+    TableId::CustomAttribute,
+];
+
+/// Stable-sort `rows` by `keys`, returning the new 1-based row of each old
+/// row, or `None` if the rows were already in order.
+fn sort_rows<T>(rows: &mut Vec<T>, keys: &[(u32, u32)]) -> Option<Vec<u32>> {
+    if keys.is_sorted() {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by_key(|&i| keys[i]);
+
+    let mut new_rows = vec![0; rows.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_rows[old] = new as u32 + 1;
+    }
+    let mut slots: Vec<Option<T>> = std::mem::take(rows).into_iter().map(Some).collect();
+    rows.extend(order.iter().filter_map(|&old| slots[old].take()));
+    Some(new_rows)
+}
+
+/// Point a coded index at the new row of a renumbered table.
+fn remap(index: &mut CodedIndex, table: TableId, new_rows: &[u32]) {
+    if index.table != Some(table) {
+        return;
+    }
+    if let Some(&row) = (index.row as usize)
+        .checked_sub(1)
+        .and_then(|i| new_rows.get(i))
+    {
+        index.row = row;
+    }
+}
+
+impl Metadata {
+    /// Sort the tables ECMA-335 requires to be sorted by their primary keys
+    /// and set the `sorted` mask to match.
+    ///
+    /// Rows with equal keys keep their relative order. CustomAttribute
+    /// parents and GenericParamConstraint owners that point at renumbered
+    /// InterfaceImpl, GenericParam or GenericParamConstraint rows are
+    /// updated. Returns whether any table was reordered.
+    pub fn canonicalize(&mut self) -> bool {
+        let mut changed = false;
+        for table in SORTED_TABLES {
+            let Some(keys) = self.sort_keys(table) else {
+                continue;
+            };
+            let new_rows = match table {
+                TableId::InterfaceImpl => sort_rows(&mut self.interface_impls, &keys),
+                TableId::Constant => sort_rows(&mut self.constants, &keys),
+                TableId::FieldMarshal => sort_rows(&mut self.field_marshals, &keys),
+                TableId::DeclSecurity => sort_rows(&mut self.decl_securities, &keys),
+                TableId::ClassLayout => sort_rows(&mut self.class_layouts, &keys),
+                TableId::FieldLayout => sort_rows(&mut self.field_layouts, &keys),
+                TableId::MethodSemantics => sort_rows(&mut self.method_semantics, &keys),
+                TableId::MethodImpl => sort_rows(&mut self.method_impls, &keys),
+                TableId::ImplMap => sort_rows(&mut self.impl_maps, &keys),
+                TableId::FieldRva => sort_rows(&mut self.field_rvas, &keys),
+                TableId::NestedClass => sort_rows(&mut self.nested_classes, &keys),
+                TableId::GenericParam => sort_rows(&mut self.generic_params, &keys),
+                TableId::GenericParamConstraint => {
+                    sort_rows(&mut self.generic_param_constraints, &keys)
+                }
+                TableId::CustomAttribute => sort_rows(&mut self.custom_attributes, &keys),
+                _ => None,
+            };
+            let Some(new_rows) = new_rows else {
+                continue;
+            };
+            changed = true;
+
+            if table == TableId::GenericParam {
+                for row in &mut self.generic_param_constraints {
+                    if let Some(&owner) = (row.owner as usize)
+                        .checked_sub(1)
+                        .and_then(|i| new_rows.get(i))
+                    {
+                        row.owner = owner;
+                    }
+                }
+            }
+            for row in &mut self.custom_attributes {
+                remap(&mut row.parent, table, &new_rows);
+            }
+        }
+        self.tables_header.sorted = self.sorted_mask();
+        changed
+    }
+
+    /// Check if every table ECMA-335 requires to be sorted is in key order.
+    #[must_use]
+    pub fn is_canonical(&self) -> bool {
+        SORTED_TABLES
+            .iter()
+            .all(|&table| self.sort_keys(table).is_none_or(|keys| keys.is_sorted()))
+    }
+
+    /// Compute the `sorted` mask: a bit for each table that is required to be
+    /// sorted and is in key order.
+    #[must_use]
+    pub fn sorted_mask(&self) -> u64 {
+        SORTED_TABLES
+            .iter()
+            .filter(|&&table| self.sort_keys(table).is_some_and(|keys| keys.is_sorted()))
+            .fold(0, |mask, &table| mask | 1u64 << table as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::{
+        ConstantRow, CustomAttributeRow, GenericParamConstraintRow, GenericParamRow,
+        InterfaceImplRow, ModuleRow, TypeDefRow,
+    };
+
+    fn coded(table: TableId, row: u32) -> CodedIndex {
+        CodedIndex {
+            table: Some(table),
+            row,
+        }
+    }
+
+    /// Metadata whose sorted tables were appended out of order.
+    fn unsorted_metadata() -> Metadata {
+        let mut md = Metadata::new();
+        let name = md.strings.add("Test.dll");
+        md.modules.push(ModuleRow {
+            name,
+            ..Default::default()
+        });
+        for name in ["<Module>", "A`1", "B`1"] {
+            let type_name = md.strings.add(name);
+            md.type_defs.push(TypeDefRow {
+                type_name,
+                field_list: 1,
+                method_list: 1,
+                ..Default::default()
+            });
+        }
+        for (owner, name) in [(3, "U"), (2, "T")] {
+            let name = md.strings.add(name);
+            md.generic_params.push(GenericParamRow {
+                number: 0,
+                flags: 0,
+                owner: coded(TableId::TypeDef, owner),
+                name,
+            });
+        }
+        // U : B, T : A
+        for (owner, constraint) in [(1, 3), (2, 2)] {
+            md.generic_param_constraints
+                .push(GenericParamConstraintRow {
+                    owner,
+                    constraint: coded(TableId::TypeDef, constraint),
+                });
+        }
+        for class in [3, 2] {
+            md.interface_impls.push(InterfaceImplRow {
+                class,
+                interface: coded(TableId::TypeDef, 1),
+            });
+        }
+        // Attributes on U, the InterfaceImpl of B, then on T and type B
+        for (value, parent) in [
+            (1, coded(TableId::GenericParam, 1)),
+            (2, coded(TableId::InterfaceImpl, 1)),
+            (3, coded(TableId::GenericParam, 2)),
+            (4, coded(TableId::TypeDef, 3)),
+        ] {
+            md.custom_attributes.push(CustomAttributeRow {
+                parent,
+                attr_type: CodedIndex::null(),
+                value,
+            });
+        }
+        md
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut md = unsorted_metadata();
+        assert!(!md.is_canonical());
+        let mask = md.sorted_mask();
+        assert_eq!(mask & 1 << TableId::GenericParam as u8, 0);
+        assert_ne!(mask & 1 << TableId::Constant as u8, 0);
+
+        assert!(md.canonicalize());
+        assert!(md.is_canonical());
+        assert!(!md.canonicalize());
+        assert_eq!(
+            md.tables_header.sorted,
+            Metadata::new().tables_header.sorted
+        );
+
+        // T (owned by A) now comes first, and its constraint follows it
+        let names: Vec<_> = md
+            .generic_params
+            .iter()
+            .map(|row| md.strings.get(row.name).unwrap())
+            .collect();
+        assert_eq!(names, ["T", "U"]);
+        let constraints: Vec<_> = md
+            .generic_param_constraints
+            .iter()
+            .map(|row| (row.owner, row.constraint.row))
+            .collect();
+        assert_eq!(constraints, [(1, 2), (2, 3)]);
+        assert_eq!(md.interface_impls[0].class, 2);
+
+        // Each attribute still targets the same row, sorted by parent
+        let attributes: Vec<_> = md
+            .custom_attributes
+            .iter()
+            .map(|row| (row.value, row.parent))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                (3, coded(TableId::GenericParam, 1)),
+                (2, coded(TableId::InterfaceImpl, 2)),
+                (1, coded(TableId::GenericParam, 2)),
+                (4, coded(TableId::TypeDef, 3)),
+            ]
+        );
+        assert!(
+            md.validate()
+                .iter()
+                .all(|d| d.rule != crate::Rule::SortOrder)
+        );
+    }
+
+    #[test]
+    fn test_write_canonicalizes() {
+        let mut md = unsorted_metadata();
+        let value = md.blobs.add(&[1, 0, 0, 0]);
+        for parent in [2, 1] {
+            md.constants.push(ConstantRow {
+                constant_type: 0x08,
+                padding: 0,
+                parent: coded(TableId::Field, parent),
+                value,
+            });
+        }
+        md.tables_header.sorted = 0;
+
+        let written = Metadata::parse(&md.write()).unwrap();
+        assert!(written.is_canonical());
+        assert_eq!(written.tables_header.sorted, written.sorted_mask());
+        assert_eq!(written.constants[0].parent, coded(TableId::Field, 1));
+        assert_eq!(
+            written.custom_attributes[1].parent,
+            coded(TableId::InterfaceImpl, 2)
+        );
+
+        let mut canonical = md.clone();
+        canonical.canonicalize();
+        assert_eq!(md.write(), canonical.write());
+    }
+}
@@ -0,0 +1,402 @@
+//! Heap compaction.
+//!
+//! Editing metadata leaves the heaps holding every entry they ever held:
+//! renamed members keep their old names in #Strings, replaced signatures
+//! stay in #Blob. [`Metadata::compact_heaps`] rebuilds #Strings, #Blob and
+//! #GUID from the entries the tables still reference, deduplicating them and
+//! sharing string suffixes, and rewrites every heap index. Smaller heaps can
+//! drop back to 2-byte indices, shrinking every row that references them.
+//!
+//! #US is referenced only from IL (`ldstr`), so it is compacted only by
+//! [`Metadata::compact_heaps_with_bodies`], which also patches the method
+//! bodies.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::error::Result;
+use crate::heaps::{BlobHeap, GuidHeap, StringsHeap, UserStringsHeap};
+use crate::il::{MethodBody, OperandType};
+use crate::metadata::Metadata;
+
+/// Token table byte of user strings.
+const USER_STRING_TOKEN: u32 = 0x7000_0000;
+
+/// A heap referenced from table columns.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Heap {
+    Strings,
+    Blob,
+    Guid,
+}
+
+/// New heap indices assigned by heap compaction.
+///
+/// Index 0 (the empty string, empty blob or null GUID) maps to itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeapRemap {
+    strings: HashMap<u32, u32>,
+    blobs: HashMap<u32, u32>,
+    guids: HashMap<u32, u32>,
+    user_strings: HashMap<u32, u32>,
+}
+
+impl HeapRemap {
+    fn lookup(map: &HashMap<u32, u32>, index: u32) -> Option<u32> {
+        if index == 0 {
+            Some(0)
+        } else {
+            map.get(&index).copied()
+        }
+    }
+
+    /// Get the new offset of a #Strings entry, if it was live.
+    #[must_use]
+    pub fn string(&self, offset: u32) -> Option<u32> {
+        Self::lookup(&self.strings, offset)
+    }
+
+    /// Get the new offset of a #Blob entry, if it was live.
+    #[must_use]
+    pub fn blob(&self, offset: u32) -> Option<u32> {
+        Self::lookup(&self.blobs, offset)
+    }
+
+    /// Get the new 1-based index of a #GUID entry, if it was live.
+    #[must_use]
+    pub fn guid(&self, index: u32) -> Option<u32> {
+        Self::lookup(&self.guids, index)
+    }
+
+    /// Get the new offset of a #US entry, if #US was compacted and the entry
+    /// was live.
+    #[must_use]
+    pub fn user_string(&self, offset: u32) -> Option<u32> {
+        Self::lookup(&self.user_strings, offset)
+    }
+
+    /// Map an `ldstr` user string token; other tokens are returned unchanged.
+    #[must_use]
+    pub fn map_token(&self, token: u32) -> u32 {
+        if token & 0xFF00_0000 != USER_STRING_TOKEN {
+            return token;
+        }
+        self.user_strings
+            .get(&(token & 0x00FF_FFFF))
+            .map_or(token, |&offset| USER_STRING_TOKEN | offset)
+    }
+}
+
+/// Get the code offsets of the `ldstr` tokens in a method body.
+fn user_string_operands(body: &MethodBody) -> Result<Vec<usize>> {
+    Ok(body
+        .instructions()?
+        .iter()
+        .filter(|instruction| instruction.opcode.operand == OperandType::String)
+        .map(|instruction| instruction.offset as usize + instruction.opcode.size())
+        .collect())
+}
+
+fn read_token(code: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([code[at], code[at + 1], code[at + 2], code[at + 3]])
+}
+
+impl Metadata {
+    /// Rebuild #Strings, #Blob and #GUID from the entries referenced by the
+    /// tables, and rewrite every heap index.
+    ///
+    /// Entries are deduplicated, and strings that end another string are
+    /// stored inside it. #US is left untouched. Fails without changing
+    /// anything if a table references an invalid heap entry.
+    pub fn compact_heaps(&mut self) -> Result<HeapRemap> {
+        let mut strings = BTreeSet::new();
+        let mut blobs = BTreeSet::new();
+        let mut guids = BTreeSet::new();
+        self.visit_heap_indices(&mut |heap, index| {
+            if *index != 0 {
+                match heap {
+                    Heap::Strings => strings.insert(*index),
+                    Heap::Blob => blobs.insert(*index),
+                    Heap::Guid => guids.insert(*index),
+                };
+            }
+        });
+
+        let mut remap = HeapRemap::default();
+
+        let live_strings = strings
+            .iter()
+            .map(|&offset| self.strings.get(offset))
+            .collect::<Result<Vec<_>>>()?;
+        let mut new_strings = StringsHeap::from_strings(live_strings.iter().copied());
+        for (&offset, s) in strings.iter().zip(&live_strings) {
+            remap.strings.insert(offset, new_strings.add(s));
+        }
+
+        // Blobs and GUIDs keep their relative order
+        let mut new_blobs = BlobHeap::new();
+        for &offset in &blobs {
+            let blob = self.blobs.get(offset)?;
+            remap.blobs.insert(offset, new_blobs.add(blob));
+        }
+        let mut new_guids = GuidHeap::new();
+        let mut seen_guids = HashMap::new();
+        for &index in &guids {
+            let guid = self.guids.get(index)?;
+            let new_index = *seen_guids
+                .entry(guid)
+                .or_insert_with(|| new_guids.add(&guid));
+            remap.guids.insert(index, new_index);
+        }
+
+        self.visit_heap_indices(&mut |heap, index| {
+            let map = match heap {
+                Heap::Strings => &remap.strings,
+                Heap::Blob => &remap.blobs,
+                Heap::Guid => &remap.guids,
+            };
+            if let Some(&new_index) = map.get(index) {
+                *index = new_index;
+            }
+        });
+        self.strings = new_strings;
+        self.blobs = new_blobs;
+        self.guids = new_guids;
+        Ok(remap)
+    }
+
+    /// Compact the heaps like [`Metadata::compact_heaps`], and also rebuild
+    /// #US from the strings loaded by `ldstr` in `bodies`, patching their
+    /// tokens.
+    ///
+    /// `bodies` must be every method body of the module: strings used only
+    /// by other bodies are dropped.
+    pub fn compact_heaps_with_bodies<'a>(
+        &mut self,
+        bodies: impl IntoIterator<Item = &'a mut MethodBody>,
+    ) -> Result<HeapRemap> {
+        let mut bodies: Vec<_> = bodies.into_iter().collect();
+        let operands = bodies
+            .iter()
+            .map(|body| user_string_operands(body))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut live = BTreeSet::new();
+        for (body, operands) in bodies.iter().zip(&operands) {
+            for &at in operands {
+                live.insert(read_token(&body.code, at) & 0x00FF_FFFF);
+            }
+        }
+        // Copy entries byte for byte: decoding would reject unpaired
+        // surrogates and recompute the flag byte
+        let mut user_strings = UserStringsHeap::new();
+        let mut user_string_map = HashMap::new();
+        for offset in live {
+            let entry = self.user_strings.get_raw(offset)?;
+            user_string_map.insert(offset, user_strings.add_raw(entry));
+        }
+
+        let mut remap = self.compact_heaps()?;
+        remap.user_strings = user_string_map;
+        self.user_strings = user_strings;
+        for (body, operands) in bodies.iter_mut().zip(&operands) {
+            for &at in operands {
+                let token = remap.map_token(read_token(&body.code, at));
+                body.code[at..at + 4].copy_from_slice(&token.to_le_bytes());
+            }
+        }
+        Ok(remap)
+    }
+
+    /// Call `f` with every heap index stored in a table column.
+    fn visit_heap_indices(&mut self, f: &mut dyn FnMut(Heap, &mut u32)) {
+        use Heap::{Blob, Guid, Strings};
+        for row in &mut self.modules {
+            f(Strings, &mut row.name);
+            f(Guid, &mut row.mvid);
+            f(Guid, &mut row.enc_id);
+            f(Guid, &mut row.enc_base_id);
+        }
+        for row in &mut self.type_refs {
+            f(Strings, &mut row.type_name);
+            f(Strings, &mut row.type_namespace);
+        }
+        for row in &mut self.type_defs {
+            f(Strings, &mut row.type_name);
+            f(Strings, &mut row.type_namespace);
+        }
+        for row in &mut self.fields {
+            f(Strings, &mut row.name);
+            f(Blob, &mut row.signature);
+        }
+        for row in &mut self.method_defs {
+            f(Strings, &mut row.name);
+            f(Blob, &mut row.signature);
+        }
+        for row in &mut self.params {
+            f(Strings, &mut row.name);
+        }
+        for row in &mut self.member_refs {
+            f(Strings, &mut row.name);
+            f(Blob, &mut row.signature);
+        }
+        for row in &mut self.constants {
+            f(Blob, &mut row.value);
+        }
+        for row in &mut self.custom_attributes {
+            f(Blob, &mut row.value);
+        }
+        for row in &mut self.field_marshals {
+            f(Blob, &mut row.native_type);
+        }
+        for row in &mut self.decl_securities {
+            f(Blob, &mut row.permission_set);
+        }
+        for row in &mut self.stand_alone_sigs {
+            f(Blob, &mut row.signature);
+        }
+        for row in &mut self.events {
+            f(Strings, &mut row.name);
+        }
+        for row in &mut self.properties {
+            f(Strings, &mut row.name);
+            f(Blob, &mut row.property_type);
+        }
+        for row in &mut self.module_refs {
+            f(Strings, &mut row.name);
+        }
+        for row in &mut self.type_specs {
+            f(Blob, &mut row.signature);
+        }
+        for row in &mut self.impl_maps {
+            f(Strings, &mut row.import_name);
+        }
+        for row in &mut self.assemblies {
+            f(Blob, &mut row.public_key);
+            f(Strings, &mut row.name);
+            f(Strings, &mut row.culture);
+        }
+        for row in &mut self.assembly_refs {
+            f(Blob, &mut row.public_key_or_token);
+            f(Strings, &mut row.name);
+            f(Strings, &mut row.culture);
+            f(Blob, &mut row.hash_value);
+        }
+        for row in &mut self.files {
+            f(Strings, &mut row.name);
+            f(Blob, &mut row.hash_value);
+        }
+        for row in &mut self.exported_types {
+            f(Strings, &mut row.type_name);
+            f(Strings, &mut row.type_namespace);
+        }
+        for row in &mut self.manifest_resources {
+            f(Strings, &mut row.name);
+        }
+        for row in &mut self.generic_params {
+            f(Strings, &mut row.name);
+        }
+        for row in &mut self.method_specs {
+            f(Blob, &mut row.instantiation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::ilasm::assemble;
+
+    const SOURCE: &str = r#"
+        .assembly extern mscorlib {}
+        .assembly Compact {}
+        .class public Greeter extends [mscorlib]System.Object {
+            .field public static string Name
+            .method public static string Hello() { ldstr "Hello" ret }
+            .method public static string Again() { ldstr "Hello" pop ldstr "Bye" ret }
+        }
+    "#;
+
+    #[test]
+    fn test_compact_heaps() {
+        let mut md = assemble(SOURCE).unwrap().metadata;
+        // Orphan a renamed type name and a replaced signature
+        md.type_defs[1].type_name = md.strings.add("TypeName");
+        md.fields[0].signature = md.blobs.add(&[0x06, 0x08]);
+        md.blobs.add(&[0xAB; 300]);
+        md.strings.add(&"x".repeat(0x10000));
+        assert!(md.strings.uses_wide_indices());
+        let blobs_size = md.blobs.size();
+
+        let remap = md.compact_heaps().unwrap();
+        assert!(!md.strings.uses_wide_indices());
+        assert!(md.blobs.size() < blobs_size);
+        assert_eq!(remap.string(0), Some(0));
+
+        // "Name" is stored inside "TypeName"
+        let type_name = md.type_defs[1].type_name;
+        let field_name = md.fields[0].name;
+        assert_eq!(md.strings.get(type_name).unwrap(), "TypeName");
+        assert_eq!(field_name, type_name + 4);
+        assert_eq!(md.blobs.get(md.fields[0].signature).unwrap(), [0x06, 0x08]);
+        assert_eq!(md.assembly().unwrap().name, "Compact");
+        assert!(md.validate().iter().all(|d| !d.is_error()));
+
+        // Compacting again changes nothing
+        let data = md.write();
+        md.compact_heaps().unwrap();
+        assert_eq!(md.write(), data);
+
+        md.fields[0].name = 0xFFFF;
+        assert!(matches!(md.compact_heaps(), Err(Error::InvalidString(_))));
+    }
+
+    #[test]
+    fn test_compact_user_strings() {
+        let module = assemble(SOURCE).unwrap();
+        let mut md = module.metadata;
+        let mut bodies: Vec<MethodBody> = module.method_bodies.into_iter().flatten().collect();
+        md.user_strings.add("unused string");
+        let size = md.user_strings.size();
+
+        let remap = md.compact_heaps_with_bodies(&mut bodies).unwrap();
+        assert!(md.user_strings.size() < size);
+
+        let loaded: Vec<String> = bodies
+            .iter()
+            .flat_map(|body| {
+                user_string_operands(body)
+                    .unwrap()
+                    .into_iter()
+                    .map(move |at| (body, at))
+            })
+            .map(|(body, at)| {
+                let token = read_token(&body.code, at);
+                md.user_strings.get(token & 0x00FF_FFFF).unwrap()
+            })
+            .collect();
+        assert_eq!(loaded, ["Hello", "Hello", "Bye"]);
+        assert_eq!(remap.map_token(0x0A00_0001), 0x0A00_0001);
+    }
+
+    #[test]
+    fn test_compact_raw_user_strings() {
+        let module = assemble(SOURCE).unwrap();
+        let mut md = module.metadata;
+        let mut bodies: Vec<MethodBody> = module.method_bodies.into_iter().flatten().collect();
+
+        // Point the first ldstr at an unpaired surrogate with its flag clear
+        let entry = [0x00, 0xDC, 0x21, 0x00, 0x00];
+        let offset = md.user_strings.add_raw(&entry);
+        let body = bodies
+            .iter_mut()
+            .find(|body| !user_string_operands(body).unwrap().is_empty())
+            .unwrap();
+        let at = user_string_operands(body).unwrap()[0];
+        body.code[at..at + 4].copy_from_slice(&(0x7000_0000 | offset).to_le_bytes());
+
+        let remap = md.compact_heaps_with_bodies(&mut bodies).unwrap();
+        let new_offset = remap.user_string(offset).unwrap();
+        assert_eq!(md.user_strings.get_raw(new_offset).unwrap(), entry);
+    }
+}
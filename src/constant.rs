@@ -0,0 +1,382 @@
+//! Constant values (Constant table).
+//!
+//! ECMA-335 II.22.9 stores default values of fields, parameters and properties
+//! as an element type plus a little-endian value blob. Strings are UTF-16, and
+//! a null reference is a `CLASS` constant with a 4-byte zero value.
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+use crate::signature::ElementType;
+use crate::tables::{CodedIndex, CodedIndexKind, ConstantRow, TableId};
+
+/// A decoded constant value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstantValue {
+    Boolean(bool),
+    /// UTF-16 code unit.
+    Char(u16),
+    I1(i8),
+    U1(u8),
+    I2(i16),
+    U2(u16),
+    I4(i32),
+    U4(u32),
+    I8(i64),
+    U8(u64),
+    R4(f32),
+    R8(f64),
+    String(String),
+    /// Null object reference.
+    Null,
+}
+
+impl ConstantValue {
+    /// Decode a constant from its element type and value blob.
+    ///
+    /// `offset` is the #Blob index of `data`, reported in errors.
+    pub fn parse(constant_type: u8, data: &[u8], offset: usize) -> Result<Self> {
+        let element_type = ElementType::from_u8(constant_type).ok_or(Error::InvalidBlob(offset))?;
+        let bytes = |n: usize| -> Result<&[u8]> {
+            data.get(..n).ok_or(Error::UnexpectedEof {
+                offset: offset + data.len(),
+                needed: n - data.len(),
+            })
+        };
+        let array = |n: usize| -> Result<[u8; 8]> {
+            let mut buf = [0u8; 8];
+            buf[..n].copy_from_slice(bytes(n)?);
+            Ok(buf)
+        };
+
+        Ok(match element_type {
+            ElementType::Boolean => Self::Boolean(bytes(1)?[0] != 0),
+            ElementType::Char => Self::Char(u64::from_le_bytes(array(2)?) as u16),
+            ElementType::I1 => Self::I1(bytes(1)?[0] as i8),
+            ElementType::U1 => Self::U1(bytes(1)?[0]),
+            ElementType::I2 => Self::I2(u64::from_le_bytes(array(2)?) as i16),
+            ElementType::U2 => Self::U2(u64::from_le_bytes(array(2)?) as u16),
+            ElementType::I4 => Self::I4(u64::from_le_bytes(array(4)?) as i32),
+            ElementType::U4 => Self::U4(u64::from_le_bytes(array(4)?) as u32),
+            ElementType::I8 => Self::I8(u64::from_le_bytes(array(8)?) as i64),
+            ElementType::U8 => Self::U8(u64::from_le_bytes(array(8)?)),
+            ElementType::R4 => Self::R4(f32::from_bits(u64::from_le_bytes(array(4)?) as u32)),
+            ElementType::R8 => Self::R8(f64::from_bits(u64::from_le_bytes(array(8)?))),
+            ElementType::String => {
+                if data.len() % 2 != 0 {
+                    return Err(Error::InvalidBlob(offset));
+                }
+                let units: Vec<u16> = data
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect();
+                Self::String(String::from_utf16(&units).map_err(|_| Error::InvalidBlob(offset))?)
+            }
+            ElementType::Class if data.iter().all(|&b| b == 0) => Self::Null,
+            _ => return Err(Error::InvalidBlob(offset)),
+        })
+    }
+
+    /// The element type stored in the Constant row.
+    #[must_use]
+    pub fn element_type(&self) -> ElementType {
+        match self {
+            Self::Boolean(_) => ElementType::Boolean,
+            Self::Char(_) => ElementType::Char,
+            Self::I1(_) => ElementType::I1,
+            Self::U1(_) => ElementType::U1,
+            Self::I2(_) => ElementType::I2,
+            Self::U2(_) => ElementType::U2,
+            Self::I4(_) => ElementType::I4,
+            Self::U4(_) => ElementType::U4,
+            Self::I8(_) => ElementType::I8,
+            Self::U8(_) => ElementType::U8,
+            Self::R4(_) => ElementType::R4,
+            Self::R8(_) => ElementType::R8,
+            Self::String(_) => ElementType::String,
+            Self::Null => ElementType::Class,
+        }
+    }
+
+    /// Encode the value blob.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        match self {
+            Self::Boolean(v) => vec![u8::from(*v)],
+            Self::Char(v) | Self::U2(v) => v.to_le_bytes().to_vec(),
+            Self::I1(v) => vec![*v as u8],
+            Self::U1(v) => vec![*v],
+            Self::I2(v) => v.to_le_bytes().to_vec(),
+            Self::I4(v) => v.to_le_bytes().to_vec(),
+            Self::U4(v) => v.to_le_bytes().to_vec(),
+            Self::I8(v) => v.to_le_bytes().to_vec(),
+            Self::U8(v) => v.to_le_bytes().to_vec(),
+            Self::R4(v) => v.to_le_bytes().to_vec(),
+            Self::R8(v) => v.to_le_bytes().to_vec(),
+            Self::String(s) => s.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Self::Null => vec![0; 4],
+        }
+    }
+
+    /// Get the value as a signed 64-bit integer, if it is integral.
+    ///
+    /// Useful for enum members, whose underlying type varies.
+    #[must_use]
+    pub fn as_i64(&self) -> Option<i64> {
+        Some(match *self {
+            Self::Boolean(v) => i64::from(v),
+            Self::Char(v) | Self::U2(v) => i64::from(v),
+            Self::I1(v) => i64::from(v),
+            Self::U1(v) => i64::from(v),
+            Self::I2(v) => i64::from(v),
+            Self::I4(v) => i64::from(v),
+            Self::U4(v) => i64::from(v),
+            Self::I8(v) => v,
+            Self::U8(v) => v as i64,
+            _ => return None,
+        })
+    }
+}
+
+/// Formats the value as a C#-style literal.
+impl fmt::Display for ConstantValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Boolean(v) => write!(f, "{v}"),
+            Self::Char(v) => match char::from_u32(u32::from(*v)) {
+                Some(c) => write!(f, "{:?}", c),
+                None => write!(f, "'\\u{v:04x}'"),
+            },
+            Self::I1(v) => write!(f, "{v}"),
+            Self::U1(v) => write!(f, "{v}"),
+            Self::I2(v) => write!(f, "{v}"),
+            Self::U2(v) => write!(f, "{v}"),
+            Self::I4(v) => write!(f, "{v}"),
+            Self::U4(v) => write!(f, "{v}"),
+            Self::I8(v) => write!(f, "{v}"),
+            Self::U8(v) => write!(f, "{v}"),
+            Self::R4(v) => write!(f, "{v}"),
+            Self::R8(v) => write!(f, "{v}"),
+            Self::String(s) => write!(f, "{s:?}"),
+            Self::Null => f.write_str("null"),
+        }
+    }
+}
+
+impl Metadata {
+    /// Get the constant attached to a field, parameter or property.
+    ///
+    /// `parent` is a HasConstant target. Returns `None` if no Constant row
+    /// references it.
+    pub fn constant_value(&self, parent: CodedIndex) -> Option<Result<ConstantValue>> {
+        let key = parent.encode(CodedIndexKind::HasConstant);
+        let row = self
+            .constants
+            .iter()
+            .find(|row| row.parent.encode(CodedIndexKind::HasConstant) == key)?;
+        Some(
+            self.blobs
+                .get(row.value)
+                .and_then(|data| ConstantValue::parse(row.constant_type, data, row.value as usize)),
+        )
+    }
+
+    /// Get the default value of a field (1-based index), e.g. an enum member
+    /// or a `const` field.
+    pub fn field_default_value(&self, field_index: u32) -> Option<Result<ConstantValue>> {
+        self.constant_value(CodedIndex {
+            table: Some(TableId::Field),
+            row: field_index,
+        })
+    }
+
+    /// Get the default value of an optional parameter (1-based index).
+    pub fn param_default_value(&self, param_index: u32) -> Option<Result<ConstantValue>> {
+        self.constant_value(CodedIndex {
+            table: Some(TableId::Param),
+            row: param_index,
+        })
+    }
+
+    /// Get the default value of a property (1-based index).
+    pub fn property_default_value(&self, property_index: u32) -> Option<Result<ConstantValue>> {
+        self.constant_value(CodedIndex {
+            table: Some(TableId::Property),
+            row: property_index,
+        })
+    }
+
+    /// Set or replace the constant attached to a field, parameter or property.
+    ///
+    /// A new row goes before the first row with a greater parent, so a sorted
+    /// Constant table stays sorted. Parsed tables need not be sorted, so the
+    /// existing row is found by a linear scan.
+    pub fn set_constant(&mut self, parent: CodedIndex, value: &ConstantValue) {
+        let blob = value.to_blob();
+        // Empty strings are stored as a zero-length blob
+        let value_index = if blob.is_empty() {
+            0
+        } else {
+            self.blobs.add(&blob)
+        };
+        let row = ConstantRow {
+            constant_type: value.element_type() as u8,
+            padding: 0,
+            parent,
+            value: value_index,
+        };
+
+        let key = parent.encode(CodedIndexKind::HasConstant);
+        let parent_key = |row: &ConstantRow| row.parent.encode(CodedIndexKind::HasConstant);
+        if let Some(existing) = self.constants.iter_mut().find(|row| parent_key(row) == key) {
+            *existing = row;
+        } else {
+            let index = self
+                .constants
+                .iter()
+                .position(|row| parent_key(row) > key)
+                .unwrap_or(self.constants.len());
+            self.constants.insert(index, row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_values() {
+        let values = [
+            ConstantValue::Boolean(true),
+            ConstantValue::Char(0x41),
+            ConstantValue::I1(-5),
+            ConstantValue::U2(0xBEEF),
+            ConstantValue::I4(-123_456),
+            ConstantValue::U8(u64::MAX),
+            ConstantValue::R4(1.5),
+            ConstantValue::R8(-2.25),
+            ConstantValue::String("héllo".into()),
+            ConstantValue::String(String::new()),
+            ConstantValue::Null,
+        ];
+        for value in values {
+            let parsed =
+                ConstantValue::parse(value.element_type() as u8, &value.to_blob(), 0).unwrap();
+            assert_eq!(parsed, value);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            ConstantValue::parse(ElementType::I4 as u8, &[1, 2], 9),
+            Err(Error::UnexpectedEof {
+                offset: 11,
+                needed: 2
+            })
+        ));
+        assert!(matches!(
+            ConstantValue::parse(ElementType::Class as u8, &[1, 0, 0, 0], 9),
+            Err(Error::InvalidBlob(9))
+        ));
+        assert!(matches!(
+            ConstantValue::parse(ElementType::String as u8, &[0x41], 9),
+            Err(Error::InvalidBlob(9))
+        ));
+        assert!(matches!(
+            ConstantValue::parse(0x7F, &[], 9),
+            Err(Error::InvalidBlob(9))
+        ));
+
+        // Lookups report the blob index of the Constant row
+        let mut md = Metadata::new();
+        let value = md.blobs.add(&[1, 0, 0, 0]);
+        md.constants.push(ConstantRow {
+            constant_type: ElementType::Class as u8,
+            padding: 0,
+            parent: CodedIndex {
+                table: Some(TableId::Field),
+                row: 1,
+            },
+            value,
+        });
+        assert!(matches!(
+            md.field_default_value(1),
+            Some(Err(Error::InvalidBlob(offset))) if offset == value as usize
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ConstantValue::I4(-1).to_string(), "-1");
+        assert_eq!(
+            ConstantValue::String("a\"b".into()).to_string(),
+            "\"a\\\"b\""
+        );
+        assert_eq!(ConstantValue::Char(0x61).to_string(), "'a'");
+        assert_eq!(ConstantValue::Null.to_string(), "null");
+        assert_eq!(ConstantValue::U1(7).as_i64(), Some(7));
+    }
+
+    #[test]
+    fn test_metadata_default_values() {
+        let mut md = Metadata::new();
+        let param = CodedIndex {
+            table: Some(TableId::Param),
+            row: 1,
+        };
+        let field = CodedIndex {
+            table: Some(TableId::Field),
+            row: 3,
+        };
+        md.set_constant(param, &ConstantValue::String("x".into()));
+        md.set_constant(field, &ConstantValue::I4(7));
+        md.set_constant(field, &ConstantValue::I4(8));
+
+        assert_eq!(md.constants.len(), 2);
+        // Sorted by encoded parent: Param 1 (0x5) before Field 3 (0xC)
+        assert_eq!(md.constants[0].parent, param);
+        assert_eq!(
+            md.field_default_value(3).unwrap().unwrap(),
+            ConstantValue::I4(8)
+        );
+        assert_eq!(
+            md.param_default_value(1).unwrap().unwrap(),
+            ConstantValue::String("x".into())
+        );
+        assert!(md.property_default_value(1).is_none());
+
+        let parsed = Metadata::parse(&md.write()).unwrap();
+        assert_eq!(
+            parsed.field_default_value(3).unwrap().unwrap(),
+            ConstantValue::I4(8)
+        );
+    }
+
+    #[test]
+    fn test_set_constant_unsorted_table() {
+        let field = |row| CodedIndex {
+            table: Some(TableId::Field),
+            row,
+        };
+        let mut md = Metadata::new();
+        md.set_constant(field(5), &ConstantValue::I4(5));
+        md.set_constant(field(1), &ConstantValue::I4(1));
+        md.set_constant(field(3), &ConstantValue::I4(3));
+        md.constants.swap(0, 2);
+
+        // Replacing updates the existing row instead of adding a duplicate
+        md.set_constant(field(1), &ConstantValue::I4(10));
+        md.set_constant(field(3), &ConstantValue::I4(30));
+        md.set_constant(field(5), &ConstantValue::I4(50));
+        assert_eq!(md.constants.len(), 3);
+        for (row, value) in [(1, 10), (3, 30), (5, 50)] {
+            assert_eq!(
+                md.field_default_value(row).unwrap().unwrap(),
+                ConstantValue::I4(value)
+            );
+        }
+    }
+}
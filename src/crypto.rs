@@ -1,87 +1,776 @@
 //! Cryptographic utilities for CLR metadata.
 //!
-//! Contains a minimal SHA-1 implementation for public key token computation.
+//! Contains minimal MD5, SHA-1 and SHA-2 implementations for public key
+//! tokens, strong-name signatures, file hashes and Portable PDB checksums.
+//! Every digest has a streaming `update`/`finalize` interface and a one-shot
+//! function; [`HashAlgorithm`] selects one by its CryptoAPI `ALG_ID`.
 
-/// Compute SHA-1 hash of data (minimal implementation).
+/// Input buffering and Merkle–Damgård padding shared by the digests.
+#[derive(Debug, Clone)]
+struct BlockBuffer<const N: usize> {
+    block: [u8; N],
+    len: usize,
+    total: u128,
+}
+
+impl<const N: usize> BlockBuffer<N> {
+    fn new() -> Self {
+        Self {
+            block: [0; N],
+            len: 0,
+            total: 0,
+        }
+    }
+
+    /// Feed `data`, calling `compress` for every complete block.
+    fn update(&mut self, mut data: &[u8], mut compress: impl FnMut(&[u8; N])) {
+        self.total += data.len() as u128;
+        if self.len > 0 {
+            let take = (N - self.len).min(data.len());
+            self.block[self.len..self.len + take].copy_from_slice(&data[..take]);
+            self.len += take;
+            data = &data[take..];
+            if self.len < N {
+                return;
+            }
+            compress(&self.block);
+            self.len = 0;
+        }
+        let mut blocks = data.chunks_exact(N);
+        for block in &mut blocks {
+            compress(block.try_into().expect("block-sized chunk"));
+        }
+        let rest = blocks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.len = rest.len();
+    }
+
+    /// Pad the message with a 1 bit, zeros and its bit length in the last
+    /// `length_size` bytes, and compress the final blocks.
+    fn finish(self, length_size: usize, big_endian: bool, mut compress: impl FnMut(&[u8; N])) {
+        let bits = self.total.wrapping_mul(8);
+        let mut tail = self.block[..self.len].to_vec();
+        tail.push(0x80);
+        while tail.len() % N != N - length_size {
+            tail.push(0);
+        }
+        if big_endian {
+            tail.extend_from_slice(&bits.to_be_bytes()[16 - length_size..]);
+        } else {
+            tail.extend_from_slice(&bits.to_le_bytes()[..length_size]);
+        }
+        for block in tail.chunks_exact(N) {
+            compress(block.try_into().expect("block-sized chunk"));
+        }
+    }
+}
+
+/// MD5 per-round shift amounts (RFC 1321).
+const MD5_S: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+/// MD5 round constants (RFC 1321).
+const MD5_K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+fn md5_compress(state: &mut [u32; 4], block: &[u8; 64]) {
+    let mut m = [0u32; 16];
+    for (i, word) in block.chunks(4).enumerate() {
+        m[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+
+    let [mut a, mut b, mut c, mut d] = *state;
+    for i in 0..64 {
+        let (f, g) = match i / 16 {
+            0 => ((b & c) | (!b & d), i),
+            1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+            2 => (b ^ c ^ d, (3 * i + 5) % 16),
+            _ => (c ^ (b | !d), (7 * i) % 16),
+        };
+        let rotated = a
+            .wrapping_add(f)
+            .wrapping_add(MD5_K[i])
+            .wrapping_add(m[g])
+            .rotate_left(MD5_S[(i / 16) * 4 + i % 4]);
+        a = d;
+        d = c;
+        c = b;
+        b = b.wrapping_add(rotated);
+    }
+
+    for (state, value) in state.iter_mut().zip([a, b, c, d]) {
+        *state = state.wrapping_add(value);
+    }
+}
+
+fn sha1_compress(state: &mut [u32; 5], block: &[u8; 64]) {
+    let mut w = [0u32; 80];
+
+    // Break the block into sixteen 32-bit big-endian words
+    for (i, word_bytes) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word_bytes[0], word_bytes[1], word_bytes[2], word_bytes[3]]);
+    }
+
+    // Extend the sixteen 32-bit words into eighty 32-bit words
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, w_i) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | ((!b) & d), 0x5A827999u32),
+            20..=39 => (b ^ c ^ d, 0x6ED9EBA1u32),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDCu32),
+            _ => (b ^ c ^ d, 0xCA62C1D6u32),
+        };
+
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*w_i);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (state, value) in state.iter_mut().zip([a, b, c, d, e]) {
+        *state = state.wrapping_add(value);
+    }
+}
+
+/// SHA-256 round constants (FIPS 180-4).
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-512 round constants (FIPS 180-4).
+const SHA512_K: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+fn sha256_compress(state: &mut [u32; 8], block: &[u8; 64]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = *state;
+    for (k, w_i) in SHA256_K.iter().zip(w) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ ((!e) & g);
+        let t1 = hh
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*k)
+            .wrapping_add(w_i);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        hh = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (state, value) in state.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+        *state = state.wrapping_add(value);
+    }
+}
+
+fn sha512_compress(state: &mut [u64; 8], block: &[u8; 128]) {
+    let mut w = [0u64; 80];
+    for (i, word) in block.chunks(8).enumerate() {
+        w[i] = u64::from_be_bytes(word.try_into().expect("8-byte chunk"));
+    }
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = *state;
+    for (k, w_i) in SHA512_K.iter().zip(w) {
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ ((!e) & g);
+        let t1 = hh
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*k)
+            .wrapping_add(w_i);
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        hh = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (state, value) in state.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+        *state = state.wrapping_add(value);
+    }
+}
+
+/// Streaming MD5 digest (RFC 1321).
+///
+/// Only for reading legacy file hashes; MD5 is not collision resistant.
+#[derive(Debug, Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    buffer: BlockBuffer<64>,
+}
+
+impl Md5 {
+    /// Start a new digest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    /// Feed more input.
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| md5_compress(state, block));
+    }
+
+    /// Finish the digest.
+    #[must_use]
+    pub fn finalize(mut self) -> [u8; 16] {
+        let state = &mut self.state;
+        self.buffer
+            .finish(8, false, |block| md5_compress(state, block));
+        let mut result = [0u8; 16];
+        for (out, word) in result.chunks_mut(4).zip(self.state) {
+            out.copy_from_slice(&word.to_le_bytes());
+        }
+        result
+    }
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming SHA-1 digest (FIPS 180-4).
+#[derive(Debug, Clone)]
+pub struct Sha1 {
+    state: [u32; 5],
+    buffer: BlockBuffer<64>,
+}
+
+impl Sha1 {
+    /// Start a new digest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0],
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    /// Feed more input.
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer
+            .update(data, |block| sha1_compress(state, block));
+    }
+
+    /// Finish the digest.
+    #[must_use]
+    pub fn finalize(mut self) -> [u8; 20] {
+        let state = &mut self.state;
+        self.buffer
+            .finish(8, true, |block| sha1_compress(state, block));
+        let mut result = [0u8; 20];
+        for (out, word) in result.chunks_mut(4).zip(self.state) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+        result
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming SHA-256 digest (FIPS 180-4).
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: BlockBuffer<64>,
+}
+
+impl Sha256 {
+    /// Start a new digest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    /// Feed more input.
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer
+            .update(data, |block| sha256_compress(state, block));
+    }
+
+    /// Finish the digest.
+    #[must_use]
+    pub fn finalize(mut self) -> [u8; 32] {
+        let state = &mut self.state;
+        self.buffer
+            .finish(8, true, |block| sha256_compress(state, block));
+        let mut result = [0u8; 32];
+        for (out, word) in result.chunks_mut(4).zip(self.state) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+        result
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SHA-512 engine shared by SHA-384 and SHA-512, which differ only in their
+/// initial state and output length.
+#[derive(Debug, Clone)]
+struct Sha512Engine {
+    state: [u64; 8],
+    buffer: BlockBuffer<128>,
+}
+
+impl Sha512Engine {
+    fn new(state: [u64; 8]) -> Self {
+        Self {
+            state,
+            buffer: BlockBuffer::new(),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer
+            .update(data, |block| sha512_compress(state, block));
+    }
+
+    /// Finish the digest into `out`, truncating the final state.
+    fn finalize_into(mut self, out: &mut [u8]) {
+        let state = &mut self.state;
+        self.buffer
+            .finish(16, true, |block| sha512_compress(state, block));
+        for (out, word) in out.chunks_mut(8).zip(self.state) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+    }
+}
+
+/// Streaming SHA-384 digest (FIPS 180-4).
+#[derive(Debug, Clone)]
+pub struct Sha384(Sha512Engine);
+
+impl Sha384 {
+    /// Start a new digest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Sha512Engine::new([
+            0xcbbb9d5dc1059ed8,
+            0x629a292a367cd507,
+            0x9159015a3070dd17,
+            0x152fecd8f70e5939,
+            0x67332667ffc00b31,
+            0x8eb44a8768581511,
+            0xdb0c2e0d64f98fa7,
+            0x47b5481dbefa4fa4,
+        ]))
+    }
+
+    /// Feed more input.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Finish the digest.
+    #[must_use]
+    pub fn finalize(self) -> [u8; 48] {
+        let mut result = [0u8; 48];
+        self.0.finalize_into(&mut result);
+        result
+    }
+}
+
+impl Default for Sha384 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming SHA-512 digest (FIPS 180-4).
+#[derive(Debug, Clone)]
+pub struct Sha512(Sha512Engine);
+
+impl Sha512 {
+    /// Start a new digest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Sha512Engine::new([
+            0x6a09e667f3bcc908,
+            0xbb67ae8584caa73b,
+            0x3c6ef372fe94f82b,
+            0xa54ff53a5f1d36f1,
+            0x510e527fade682d1,
+            0x9b05688c2b3e6c1f,
+            0x1f83d9abfb41bd6b,
+            0x5be0cd19137e2179,
+        ]))
+    }
+
+    /// Feed more input.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Finish the digest.
+    #[must_use]
+    pub fn finalize(self) -> [u8; 64] {
+        let mut result = [0u8; 64];
+        self.0.finalize_into(&mut result);
+        result
+    }
+}
+
+impl Default for Sha512 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compute MD5 hash of data.
+#[must_use]
+pub fn md5(data: &[u8]) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Compute SHA-1 hash of data.
 ///
 /// Used for computing public key tokens from public keys.
 #[must_use]
 pub fn sha1(data: &[u8]) -> [u8; 20] {
-    // Initialize hash values (FIPS 180-1)
-    let mut h0: u32 = 0x67452301;
-    let mut h1: u32 = 0xEFCDAB89;
-    let mut h2: u32 = 0x98BADCFE;
-    let mut h3: u32 = 0x10325476;
-    let mut h4: u32 = 0xC3D2E1F0;
-
-    // Pre-processing: add padding bits
-    let ml = (data.len() as u64) * 8; // message length in bits
-    let mut padded = data.to_vec();
-    padded.push(0x80); // append bit '1' to message
-
-    // Pad to 448 bits mod 512 (56 bytes mod 64)
-    while (padded.len() % 64) != 56 {
-        padded.push(0);
-    }
-
-    // Append original length in bits as 64-bit big-endian
-    padded.extend_from_slice(&ml.to_be_bytes());
-
-    // Process each 512-bit (64-byte) chunk
-    for chunk in padded.chunks(64) {
-        let mut w = [0u32; 80];
-
-        // Break chunk into sixteen 32-bit big-endian words
-        for (i, word_bytes) in chunk.chunks(4).enumerate() {
-            w[i] = u32::from_be_bytes([word_bytes[0], word_bytes[1], word_bytes[2], word_bytes[3]]);
-        }
-
-        // Extend the sixteen 32-bit words into eighty 32-bit words
-        for i in 16..80 {
-            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
-        }
-
-        // Initialize working variables
-        let (mut a, mut b, mut c, mut d, mut e) = (h0, h1, h2, h3, h4);
-
-        // Main loop
-        for (i, w_i) in w.iter().enumerate() {
-            let (f, k) = match i {
-                0..=19 => ((b & c) | ((!b) & d), 0x5A827999u32),
-                20..=39 => (b ^ c ^ d, 0x6ED9EBA1u32),
-                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDCu32),
-                _ => (b ^ c ^ d, 0xCA62C1D6u32),
-            };
-
-            let temp = a
-                .rotate_left(5)
-                .wrapping_add(f)
-                .wrapping_add(e)
-                .wrapping_add(k)
-                .wrapping_add(*w_i);
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = temp;
-        }
-
-        // Add this chunk's hash to result
-        h0 = h0.wrapping_add(a);
-        h1 = h1.wrapping_add(b);
-        h2 = h2.wrapping_add(c);
-        h3 = h3.wrapping_add(d);
-        h4 = h4.wrapping_add(e);
-    }
-
-    // Produce the final hash value (big-endian)
-    let mut result = [0u8; 20];
-    result[0..4].copy_from_slice(&h0.to_be_bytes());
-    result[4..8].copy_from_slice(&h1.to_be_bytes());
-    result[8..12].copy_from_slice(&h2.to_be_bytes());
-    result[12..16].copy_from_slice(&h3.to_be_bytes());
-    result[16..20].copy_from_slice(&h4.to_be_bytes());
-    result
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Compute SHA-256 hash of data.
+#[must_use]
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Compute SHA-384 hash of data.
+#[must_use]
+pub fn sha384(data: &[u8]) -> [u8; 48] {
+    let mut hasher = Sha384::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Compute SHA-512 hash of data.
+#[must_use]
+pub fn sha512(data: &[u8]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// A hash algorithm named by a CryptoAPI `ALG_ID`, as stored in
+/// `Assembly.HashAlgId`, or by its Portable PDB checksum name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum HashAlgorithm {
+    /// MD5 (`CALG_MD5`).
+    Md5 = 0x8003,
+    /// SHA-1 (`CALG_SHA1`), the default for assemblies.
+    Sha1 = 0x8004,
+    /// SHA-256 (`CALG_SHA_256`).
+    Sha256 = 0x800C,
+    /// SHA-384 (`CALG_SHA_384`).
+    Sha384 = 0x800D,
+    /// SHA-512 (`CALG_SHA_512`).
+    Sha512 = 0x800E,
+}
+
+impl HashAlgorithm {
+    /// All supported algorithms.
+    pub const ALL: [HashAlgorithm; 5] = [
+        Self::Md5,
+        Self::Sha1,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Look up an algorithm by its `ALG_ID`.
+    #[must_use]
+    pub fn from_alg_id(alg_id: u32) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.alg_id() == alg_id)
+    }
+
+    /// The `ALG_ID` of this algorithm.
+    #[must_use]
+    pub const fn alg_id(self) -> u32 {
+        self as u32
+    }
+
+    /// Look up an algorithm by name, ignoring case and an optional dash
+    /// (`"SHA256"`, `"sha-256"`).
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.replace('-', "");
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(&name))
+    }
+
+    /// The name used by Portable PDB checksums and `System.Security.Cryptography`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha384 => "SHA384",
+            Self::Sha512 => "SHA512",
+        }
+    }
+
+    /// Size of the digest in bytes.
+    #[must_use]
+    pub const fn digest_size(self) -> usize {
+        match self {
+            Self::Md5 => 16,
+            Self::Sha1 => 20,
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 => 64,
+        }
+    }
+
+    /// Start a streaming digest.
+    #[must_use]
+    pub fn hasher(self) -> Hasher {
+        match self {
+            Self::Md5 => Hasher::Md5(Md5::new()),
+            Self::Sha1 => Hasher::Sha1(Sha1::new()),
+            Self::Sha256 => Hasher::Sha256(Sha256::new()),
+            Self::Sha384 => Hasher::Sha384(Sha384::new()),
+            Self::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    /// Hash `data` in one go.
+    #[must_use]
+    pub fn hash(self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl std::fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A streaming digest of any [`HashAlgorithm`].
+#[derive(Debug, Clone)]
+pub enum Hasher {
+    /// MD5 digest.
+    Md5(Md5),
+    /// SHA-1 digest.
+    Sha1(Sha1),
+    /// SHA-256 digest.
+    Sha256(Sha256),
+    /// SHA-384 digest.
+    Sha384(Sha384),
+    /// SHA-512 digest.
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    /// The algorithm of this digest.
+    #[must_use]
+    pub fn algorithm(&self) -> HashAlgorithm {
+        match self {
+            Self::Md5(_) => HashAlgorithm::Md5,
+            Self::Sha1(_) => HashAlgorithm::Sha1,
+            Self::Sha256(_) => HashAlgorithm::Sha256,
+            Self::Sha384(_) => HashAlgorithm::Sha384,
+            Self::Sha512(_) => HashAlgorithm::Sha512,
+        }
+    }
+
+    /// Feed more input.
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha1(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha384(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Finish the digest.
+    #[must_use]
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Md5(hasher) => hasher.finalize().to_vec(),
+            Self::Sha1(hasher) => hasher.finalize().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+            Self::Sha384(hasher) => hasher.finalize().to_vec(),
+            Self::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
 }
 
 /// Compute the public key token from a public key.
@@ -128,6 +817,39 @@ mod tests {
         );
     }
 
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn test_sha2() {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        // Two-block message
+        assert_eq!(
+            hex(&sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            hex(&sha384(b"abc")),
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed\
+             8086072ba1e7cc2358baeca134c825a7"
+        );
+        assert_eq!(
+            hex(&sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+    }
+
     #[test]
     fn test_public_key_token() {
         // Test public key token extraction logic:
@@ -150,4 +872,72 @@ mod tests {
         // Reversed: 9d d8 d0 9c 6c c2 50 78
         assert_eq!(token, [0x9d, 0xd8, 0xd0, 0x9c, 0x6c, 0xc2, 0x50, 0x78]);
     }
+
+    #[test]
+    fn test_md5() {
+        assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(&md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hex(&md5(b"The quick brown fox jumps over the lazy dog")),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        assert_eq!(
+            hex(&md5(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            )),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn test_streaming_matches_one_shot() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 31 + 7) as u8).collect();
+        for algorithm in HashAlgorithm::ALL {
+            let expected = algorithm.hash(&data);
+            assert_eq!(expected.len(), algorithm.digest_size());
+            // Chunk sizes straddling the 64- and 128-byte block boundaries
+            for chunk_size in [1, 55, 63, 64, 65, 111, 127, 128, 129, 999] {
+                let mut hasher = algorithm.hasher();
+                for chunk in data.chunks(chunk_size) {
+                    hasher.update(chunk);
+                }
+                assert_eq!(hasher.algorithm(), algorithm);
+                assert_eq!(hasher.finalize(), expected, "{algorithm} in {chunk_size}s");
+            }
+        }
+        assert_eq!(HashAlgorithm::Sha256.hash(&data), sha256(&data));
+        assert_eq!(HashAlgorithm::Sha512.hash(&data), sha512(&data));
+    }
+
+    #[test]
+    fn test_hash_algorithm_ids() {
+        assert_eq!(HashAlgorithm::from_alg_id(0x8003), Some(HashAlgorithm::Md5));
+        assert_eq!(
+            HashAlgorithm::from_alg_id(0x8004),
+            Some(HashAlgorithm::Sha1)
+        );
+        assert_eq!(
+            HashAlgorithm::from_alg_id(0x800C),
+            Some(HashAlgorithm::Sha256)
+        );
+        assert_eq!(
+            HashAlgorithm::from_alg_id(0x800D),
+            Some(HashAlgorithm::Sha384)
+        );
+        assert_eq!(
+            HashAlgorithm::from_alg_id(0x800E),
+            Some(HashAlgorithm::Sha512)
+        );
+        assert_eq!(HashAlgorithm::from_alg_id(0), None);
+        assert_eq!(HashAlgorithm::Sha384.alg_id(), 0x800D);
+
+        assert_eq!(
+            HashAlgorithm::from_name("SHA256"),
+            Some(HashAlgorithm::Sha256)
+        );
+        assert_eq!(HashAlgorithm::from_name("sha-1"), Some(HashAlgorithm::Sha1));
+        assert_eq!(HashAlgorithm::from_name("md5"), Some(HashAlgorithm::Md5));
+        assert_eq!(HashAlgorithm::from_name("SHA3"), None);
+        assert_eq!(HashAlgorithm::Sha512.to_string(), "SHA512");
+    }
 }
@@ -0,0 +1,532 @@
+//! Custom attribute value blob encoding and decoding.
+//!
+//! ECMA-335 II.23.3 defines the blob format: a 0x0001 prolog, the fixed
+//! constructor arguments, and a count of named field/property arguments.
+
+use crate::error::{Error, Result};
+use crate::reader::Reader;
+use crate::signature::ElementType;
+use crate::writer::Writer;
+
+/// Custom attribute blob prolog.
+const PROLOG: u16 = 0x0001;
+
+/// Named argument tag for fields.
+const NAMED_FIELD: u8 = 0x53;
+/// Named argument tag for properties.
+const NAMED_PROPERTY: u8 = 0x54;
+
+/// FieldOrPropType codes beyond the primitive element types.
+const TYPE_SYSTEM_TYPE: u8 = 0x50;
+const TYPE_BOXED: u8 = 0x51;
+const TYPE_ENUM: u8 = 0x55;
+
+/// The type of a custom attribute argument (FieldOrPropType).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CaType {
+    Boolean,
+    Char,
+    I1,
+    U1,
+    I2,
+    U2,
+    I4,
+    U4,
+    I8,
+    U8,
+    R4,
+    R8,
+    String,
+    /// `System.Type`, serialized as a type name string.
+    Type,
+    /// `System.Object`, serialized with a leading type tag.
+    Boxed,
+    /// Enum type, serialized as its underlying primitive.
+    Enum {
+        /// Serialized (assembly-qualified) enum type name.
+        type_name: String,
+        /// Underlying primitive type.
+        underlying: ElementType,
+    },
+    /// Single-dimensional array.
+    SzArray(Box<CaType>),
+}
+
+impl CaType {
+    /// Map a primitive element type to a custom attribute type.
+    #[must_use]
+    pub fn from_element_type(element_type: ElementType) -> Option<Self> {
+        Some(match element_type {
+            ElementType::Boolean => Self::Boolean,
+            ElementType::Char => Self::Char,
+            ElementType::I1 => Self::I1,
+            ElementType::U1 => Self::U1,
+            ElementType::I2 => Self::I2,
+            ElementType::U2 => Self::U2,
+            ElementType::I4 => Self::I4,
+            ElementType::U4 => Self::U4,
+            ElementType::I8 => Self::I8,
+            ElementType::U8 => Self::U8,
+            ElementType::R4 => Self::R4,
+            ElementType::R8 => Self::R8,
+            ElementType::String => Self::String,
+            ElementType::Object => Self::Boxed,
+            _ => return None,
+        })
+    }
+
+    fn element_code(&self) -> u8 {
+        match self {
+            Self::Boolean => ElementType::Boolean as u8,
+            Self::Char => ElementType::Char as u8,
+            Self::I1 => ElementType::I1 as u8,
+            Self::U1 => ElementType::U1 as u8,
+            Self::I2 => ElementType::I2 as u8,
+            Self::U2 => ElementType::U2 as u8,
+            Self::I4 => ElementType::I4 as u8,
+            Self::U4 => ElementType::U4 as u8,
+            Self::I8 => ElementType::I8 as u8,
+            Self::U8 => ElementType::U8 as u8,
+            Self::R4 => ElementType::R4 as u8,
+            Self::R8 => ElementType::R8 as u8,
+            Self::String => ElementType::String as u8,
+            Self::Type => TYPE_SYSTEM_TYPE,
+            Self::Boxed => TYPE_BOXED,
+            Self::Enum { .. } => TYPE_ENUM,
+            Self::SzArray(_) => ElementType::SzArray as u8,
+        }
+    }
+
+    /// Parse a FieldOrPropType, resolving enum underlying types with `resolve_enum`
+    /// (defaulting to `I4` when the resolver does not know the type).
+    pub fn parse(
+        reader: &mut Reader<'_>,
+        resolve_enum: &dyn Fn(&str) -> Option<ElementType>,
+    ) -> Result<Self> {
+        let offset = reader.position();
+        let code = reader.read_u8()?;
+        match code {
+            TYPE_SYSTEM_TYPE => Ok(Self::Type),
+            TYPE_BOXED => Ok(Self::Boxed),
+            TYPE_ENUM => {
+                let type_name = read_ser_string(reader)?.ok_or(Error::InvalidBlob(offset))?;
+                let underlying = resolve_enum(&type_name).unwrap_or(ElementType::I4);
+                Ok(Self::Enum {
+                    type_name,
+                    underlying,
+                })
+            }
+            0x1D => Ok(Self::SzArray(Box::new(Self::parse(reader, resolve_enum)?))),
+            _ => ElementType::from_u8(code)
+                .and_then(Self::from_element_type)
+                .filter(|ty| *ty != Self::Boxed)
+                .ok_or(Error::InvalidBlob(offset)),
+        }
+    }
+
+    /// Write this type as a FieldOrPropType.
+    pub fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.element_code());
+        match self {
+            Self::Enum { type_name, .. } => write_ser_string(writer, Some(type_name)),
+            Self::SzArray(elem) => elem.write(writer),
+            _ => {}
+        }
+    }
+}
+
+/// A custom attribute argument value.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CaValue {
+    Boolean(bool),
+    Char(u16),
+    I1(i8),
+    U1(u8),
+    I2(i16),
+    U2(u16),
+    I4(i32),
+    U4(u32),
+    I8(i64),
+    U8(u64),
+    R4(f32),
+    R8(f64),
+    /// String (None for a null string).
+    String(Option<String>),
+    /// Type name (None for a null type).
+    Type(Option<String>),
+    /// Enum value with its underlying primitive value.
+    Enum {
+        /// Serialized enum type name.
+        type_name: String,
+        /// Underlying value.
+        value: Box<CaValue>,
+    },
+    /// Array (None for a null array).
+    Array {
+        /// Element type.
+        element_type: CaType,
+        /// Elements.
+        values: Option<Vec<CaValue>>,
+    },
+    /// Boxed value for `System.Object` arguments.
+    Boxed(Box<CaValue>),
+}
+
+impl CaValue {
+    /// Get the FieldOrPropType describing this value.
+    #[must_use]
+    pub fn ca_type(&self) -> CaType {
+        match self {
+            Self::Boolean(_) => CaType::Boolean,
+            Self::Char(_) => CaType::Char,
+            Self::I1(_) => CaType::I1,
+            Self::U1(_) => CaType::U1,
+            Self::I2(_) => CaType::I2,
+            Self::U2(_) => CaType::U2,
+            Self::I4(_) => CaType::I4,
+            Self::U4(_) => CaType::U4,
+            Self::I8(_) => CaType::I8,
+            Self::U8(_) => CaType::U8,
+            Self::R4(_) => CaType::R4,
+            Self::R8(_) => CaType::R8,
+            Self::String(_) => CaType::String,
+            Self::Type(_) => CaType::Type,
+            Self::Enum { type_name, value } => CaType::Enum {
+                type_name: type_name.clone(),
+                underlying: value.element_type().unwrap_or(ElementType::I4),
+            },
+            Self::Array { element_type, .. } => CaType::SzArray(Box::new(element_type.clone())),
+            Self::Boxed(_) => CaType::Boxed,
+        }
+    }
+
+    fn element_type(&self) -> Option<ElementType> {
+        Some(match self {
+            Self::Boolean(_) => ElementType::Boolean,
+            Self::Char(_) => ElementType::Char,
+            Self::I1(_) => ElementType::I1,
+            Self::U1(_) => ElementType::U1,
+            Self::I2(_) => ElementType::I2,
+            Self::U2(_) => ElementType::U2,
+            Self::I4(_) => ElementType::I4,
+            Self::U4(_) => ElementType::U4,
+            Self::I8(_) => ElementType::I8,
+            Self::U8(_) => ElementType::U8,
+            _ => return None,
+        })
+    }
+
+    /// Parse a value of the given type.
+    pub fn parse(
+        reader: &mut Reader<'_>,
+        ty: &CaType,
+        resolve_enum: &dyn Fn(&str) -> Option<ElementType>,
+    ) -> Result<Self> {
+        Ok(match ty {
+            CaType::Boolean => Self::Boolean(reader.read_u8()? != 0),
+            CaType::Char => Self::Char(reader.read_u16()?),
+            CaType::I1 => Self::I1(reader.read_u8()? as i8),
+            CaType::U1 => Self::U1(reader.read_u8()?),
+            CaType::I2 => Self::I2(reader.read_u16()? as i16),
+            CaType::U2 => Self::U2(reader.read_u16()?),
+            CaType::I4 => Self::I4(reader.read_u32()? as i32),
+            CaType::U4 => Self::U4(reader.read_u32()?),
+            CaType::I8 => Self::I8(reader.read_u64()? as i64),
+            CaType::U8 => Self::U8(reader.read_u64()?),
+            CaType::R4 => Self::R4(f32::from_bits(reader.read_u32()?)),
+            CaType::R8 => Self::R8(f64::from_bits(reader.read_u64()?)),
+            CaType::String => Self::String(read_ser_string(reader)?),
+            CaType::Type => Self::Type(read_ser_string(reader)?),
+            CaType::Boxed => {
+                let inner = CaType::parse(reader, resolve_enum)?;
+                Self::Boxed(Box::new(Self::parse(reader, &inner, resolve_enum)?))
+            }
+            CaType::Enum {
+                type_name,
+                underlying,
+            } => {
+                let offset = reader.position();
+                let inner = CaType::from_element_type(*underlying)
+                    .filter(|ty| *ty != CaType::Boxed)
+                    .ok_or(Error::InvalidBlob(offset))?;
+                Self::Enum {
+                    type_name: type_name.clone(),
+                    value: Box::new(Self::parse(reader, &inner, resolve_enum)?),
+                }
+            }
+            CaType::SzArray(elem) => {
+                let count = reader.read_u32()?;
+                let values = if count == u32::MAX {
+                    None
+                } else {
+                    let mut values = Vec::with_capacity((count as usize).min(reader.remaining()));
+                    for _ in 0..count {
+                        values.push(Self::parse(reader, elem, resolve_enum)?);
+                    }
+                    Some(values)
+                };
+                Self::Array {
+                    element_type: (**elem).clone(),
+                    values,
+                }
+            }
+        })
+    }
+
+    /// Write this value (without a type tag, except for boxed values).
+    pub fn write(&self, writer: &mut Writer) {
+        match self {
+            Self::Boolean(v) => writer.write_u8(u8::from(*v)),
+            Self::Char(v) | Self::U2(v) => writer.write_u16(*v),
+            Self::I1(v) => writer.write_u8(*v as u8),
+            Self::U1(v) => writer.write_u8(*v),
+            Self::I2(v) => writer.write_u16(*v as u16),
+            Self::I4(v) => writer.write_u32(*v as u32),
+            Self::U4(v) => writer.write_u32(*v),
+            Self::I8(v) => writer.write_u64(*v as u64),
+            Self::U8(v) => writer.write_u64(*v),
+            Self::R4(v) => writer.write_u32(v.to_bits()),
+            Self::R8(v) => writer.write_u64(v.to_bits()),
+            Self::String(v) | Self::Type(v) => write_ser_string(writer, v.as_deref()),
+            Self::Enum { value, .. } => value.write(writer),
+            Self::Array { values, .. } => match values {
+                Some(values) => {
+                    writer.write_u32(values.len() as u32);
+                    for value in values {
+                        value.write(writer);
+                    }
+                }
+                None => writer.write_u32(u32::MAX),
+            },
+            Self::Boxed(inner) => {
+                inner.ca_type().write(writer);
+                inner.write(writer);
+            }
+        }
+    }
+}
+
+/// A named field or property argument.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaNamedArg {
+    /// True for a field, false for a property.
+    pub is_field: bool,
+    /// Field or property name.
+    pub name: String,
+    /// Declared argument type.
+    pub arg_type: CaType,
+    /// Argument value.
+    pub value: CaValue,
+}
+
+impl CaNamedArg {
+    /// Parse a single named argument.
+    pub fn parse(
+        reader: &mut Reader<'_>,
+        resolve_enum: &dyn Fn(&str) -> Option<ElementType>,
+    ) -> Result<Self> {
+        let offset = reader.position();
+        let is_field = match reader.read_u8()? {
+            NAMED_FIELD => true,
+            NAMED_PROPERTY => false,
+            _ => return Err(Error::InvalidBlob(offset)),
+        };
+        let arg_type = CaType::parse(reader, resolve_enum)?;
+        let name = read_ser_string(reader)?.ok_or(Error::InvalidBlob(offset))?;
+        let value = CaValue::parse(reader, &arg_type, resolve_enum)?;
+        Ok(Self {
+            is_field,
+            name,
+            arg_type,
+            value,
+        })
+    }
+
+    /// Write this named argument.
+    pub fn write(&self, writer: &mut Writer) {
+        writer.write_u8(if self.is_field {
+            NAMED_FIELD
+        } else {
+            NAMED_PROPERTY
+        });
+        self.arg_type.write(writer);
+        write_ser_string(writer, Some(&self.name));
+        self.value.write(writer);
+    }
+}
+
+/// A decoded custom attribute value blob.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CustomAttributeValue {
+    /// Constructor arguments.
+    pub fixed_args: Vec<CaValue>,
+    /// Named field and property arguments.
+    pub named_args: Vec<CaNamedArg>,
+}
+
+impl CustomAttributeValue {
+    /// Parse a value blob given the constructor parameter types.
+    ///
+    /// Enum types that appear only by name default to an `I4` underlying type.
+    pub fn parse(data: &[u8], fixed_types: &[CaType]) -> Result<Self> {
+        Self::parse_with(data, fixed_types, &|_| None)
+    }
+
+    /// Parse a value blob, resolving enum underlying types by name.
+    pub fn parse_with(
+        data: &[u8],
+        fixed_types: &[CaType],
+        resolve_enum: &dyn Fn(&str) -> Option<ElementType>,
+    ) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        if reader.read_u16()? != PROLOG {
+            return Err(Error::InvalidBlob(0));
+        }
+
+        let mut fixed_args = Vec::with_capacity(fixed_types.len());
+        for ty in fixed_types {
+            fixed_args.push(CaValue::parse(&mut reader, ty, resolve_enum)?);
+        }
+
+        let count = reader.read_u16()?;
+        let mut named_args = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            named_args.push(CaNamedArg::parse(&mut reader, resolve_enum)?);
+        }
+
+        Ok(Self {
+            fixed_args,
+            named_args,
+        })
+    }
+
+    /// Serialize this value to a blob.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.write_u16(PROLOG);
+        for arg in &self.fixed_args {
+            arg.write(&mut writer);
+        }
+        writer.write_u16(self.named_args.len() as u16);
+        for arg in &self.named_args {
+            arg.write(&mut writer);
+        }
+        writer.into_inner()
+    }
+}
+
+/// Read a SerString (compressed length + UTF-8, 0xFF for null).
+pub fn read_ser_string(reader: &mut Reader<'_>) -> Result<Option<String>> {
+    if reader.peek_u8()? == 0xFF {
+        reader.read_u8()?;
+        return Ok(None);
+    }
+    let len = reader.read_compressed_uint()? as usize;
+    let offset = reader.position();
+    let bytes = reader.read_bytes(len)?;
+    String::from_utf8(bytes.to_vec())
+        .map(Some)
+        .map_err(|_| Error::InvalidString(offset))
+}
+
+/// Write a SerString (compressed length + UTF-8, 0xFF for null).
+pub fn write_ser_string(writer: &mut Writer, value: Option<&str>) {
+    match value {
+        Some(s) => {
+            writer.write_compressed_uint(s.len() as u32);
+            writer.write_bytes(s.as_bytes());
+        }
+        None => writer.write_u8(0xFF),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_value() {
+        let value = CustomAttributeValue::default();
+        assert_eq!(value.to_blob(), [0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(
+            CustomAttributeValue::parse(&[0x01, 0x00, 0x00, 0x00], &[]).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn test_fixed_and_named_roundtrip() {
+        let value = CustomAttributeValue {
+            fixed_args: vec![
+                CaValue::String(Some("hello".to_string())),
+                CaValue::I4(42),
+                CaValue::Array {
+                    element_type: CaType::U1,
+                    values: Some(vec![CaValue::U1(1), CaValue::U1(2)]),
+                },
+                CaValue::Boxed(Box::new(CaValue::R8(1.5))),
+            ],
+            named_args: vec![
+                CaNamedArg {
+                    is_field: false,
+                    name: "Flag".to_string(),
+                    arg_type: CaType::Boolean,
+                    value: CaValue::Boolean(true),
+                },
+                CaNamedArg {
+                    is_field: true,
+                    name: "Kind".to_string(),
+                    arg_type: CaType::Enum {
+                        type_name: "MyEnum".to_string(),
+                        underlying: ElementType::I4,
+                    },
+                    value: CaValue::Enum {
+                        type_name: "MyEnum".to_string(),
+                        value: Box::new(CaValue::I4(3)),
+                    },
+                },
+            ],
+        };
+
+        let blob = value.to_blob();
+        let fixed_types = [
+            CaType::String,
+            CaType::I4,
+            CaType::SzArray(Box::new(CaType::U1)),
+            CaType::Boxed,
+        ];
+        assert_eq!(
+            CustomAttributeValue::parse(&blob, &fixed_types).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn test_null_string_and_enum_resolver() {
+        // Prolog, null string, 1 named property of enum "E" (underlying u1) = 7
+        let blob = [
+            0x01, 0x00, 0xFF, 0x01, 0x00, 0x54, 0x55, 0x01, b'E', 0x01, b'P', 0x07,
+        ];
+        let value = CustomAttributeValue::parse_with(&blob, &[CaType::String], &|name| {
+            (name == "E").then_some(ElementType::U1)
+        })
+        .unwrap();
+        assert_eq!(value.fixed_args, [CaValue::String(None)]);
+        assert_eq!(
+            value.named_args[0].value,
+            CaValue::Enum {
+                type_name: "E".to_string(),
+                value: Box::new(CaValue::U1(7)),
+            }
+        );
+        assert_eq!(value.to_blob(), blob);
+    }
+
+    #[test]
+    fn test_invalid_prolog() {
+        assert!(CustomAttributeValue::parse(&[0x02, 0x00, 0x00, 0x00], &[]).is_err());
+    }
+}
@@ -0,0 +1,547 @@
+//! Deterministic (content-based) module and PDB IDs.
+//!
+//! Compilers building with `/deterministic` derive the MVID and the COFF time
+//! stamp of an image from a hash of the image itself, taken with those slots
+//! zeroed, and the Portable PDB ID from a hash of the PDB with its ID zeroed.
+//! The image's CodeView debug entry then names the PDB by that ID. Recomputing
+//! the hashes checks that an image and its PDB are what a deterministic build
+//! claims them to be, without rebuilding.
+
+use crate::crypto::HashAlgorithm;
+use crate::error::{Error, Result};
+use crate::heaps::Guid;
+use crate::image::{CliHeader, DebugDirectoryEntry, PeImage, SECURITY_DIRECTORY};
+use crate::metadata::Metadata;
+use crate::root::MetadataRoot;
+use crate::stream::StreamHeader;
+
+/// Size of a serialized content ID: a GUID followed by a 4-byte stamp.
+pub const CONTENT_ID_SIZE: usize = 20;
+
+/// CodeView debug data signature (`RSDS`).
+const CODEVIEW_SIGNATURE: &[u8; 4] = b"RSDS";
+
+/// A content-based ID: the MVID and COFF time stamp of an image, or the ID
+/// of a Portable PDB.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ContentId {
+    /// GUID part (the MVID or the PDB GUID).
+    pub guid: Guid,
+    /// Stamp part (the COFF time stamp or the PDB stamp).
+    pub stamp: u32,
+}
+
+impl ContentId {
+    /// Derive an ID from a content hash, as Roslyn's `BlobContentId.FromHash`
+    /// does: the first 16 bytes become a version 4 GUID and the next 4 a stamp
+    /// with the top bit set. Returns `None` for hashes shorter than 20 bytes.
+    #[must_use]
+    pub fn from_hash(hash: &[u8]) -> Option<Self> {
+        let hash = hash.get(..CONTENT_ID_SIZE)?;
+        let mut id = Self::from_bytes(hash)?;
+        id.guid[7] = (id.guid[7] & 0x0F) | 0x40;
+        id.guid[8] = (id.guid[8] & 0x3F) | 0x80;
+        id.stamp |= 0x8000_0000;
+        Some(id)
+    }
+
+    /// Read a serialized ID (GUID, then stamp little-endian).
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; CONTENT_ID_SIZE] = bytes.try_into().ok()?;
+        let mut guid = [0u8; 16];
+        guid.copy_from_slice(&bytes[..16]);
+        let stamp = u32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]);
+        Some(Self { guid, stamp })
+    }
+
+    /// Serialize the ID (GUID, then stamp little-endian).
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; CONTENT_ID_SIZE] {
+        let mut bytes = [0u8; CONTENT_ID_SIZE];
+        bytes[..16].copy_from_slice(&self.guid);
+        bytes[16..].copy_from_slice(&self.stamp.to_le_bytes());
+        bytes
+    }
+}
+
+/// A stored ID next to the one recomputed from the content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentIdCheck {
+    /// ID stored in the image or PDB.
+    pub stored: ContentId,
+    /// ID recomputed from the content.
+    pub computed: ContentId,
+}
+
+impl ContentIdCheck {
+    /// Whether the stored ID is the computed one.
+    #[must_use]
+    pub fn matches(&self) -> bool {
+        self.stored == self.computed
+    }
+}
+
+/// Result of checking a Portable PDB against its image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdbIdCheck {
+    /// The ID in the PDB's `#Pdb` stream against its content.
+    pub id: ContentIdCheck,
+    /// The PDB ID named by the image's CodeView entry, if it has one.
+    pub referenced: Option<ContentId>,
+    /// Whether the image's PdbChecksum entry matches the PDB, if it has one.
+    pub checksum_matches: Option<bool>,
+}
+
+impl PdbIdCheck {
+    /// Whether the PDB ID matches its content and the image's references.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.id.matches()
+            && self.referenced.is_none_or(|id| id == self.id.computed)
+            && self.checksum_matches != Some(false)
+    }
+}
+
+/// Result of [`verify_deterministic_ids`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeterminismReport {
+    /// Whether the image has a Reproducible debug entry, i.e. claims to be
+    /// built deterministically.
+    pub reproducible: bool,
+    /// The MVID and COFF time stamp against the image content.
+    pub module: ContentIdCheck,
+    /// The PDB check, if a PDB was given.
+    pub pdb: Option<PdbIdCheck>,
+}
+
+impl DeterminismReport {
+    /// Whether every stored ID matches its content.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.module.matches() && self.pdb.as_ref().is_none_or(PdbIdCheck::is_valid)
+    }
+}
+
+/// Hash `data` as if the given `(offset, len)` ranges were zero.
+fn hash_zeroed(data: &[u8], mut slots: Vec<(usize, usize)>, algorithm: HashAlgorithm) -> Vec<u8> {
+    slots.sort_unstable();
+    let mut hasher = algorithm.hasher();
+    let mut position = 0;
+    for (offset, len) in slots {
+        let start = offset.clamp(position, data.len());
+        let end = (offset + len).clamp(start, data.len());
+        hasher.update(&data[position..start]);
+        hasher.update(&vec![0; end - start]);
+        position = end;
+    }
+    hasher.update(&data[position..]);
+    hasher.finalize()
+}
+
+/// File offset of the MVID in an image's `#GUID` heap.
+fn mvid_offset(pe: &PeImage<'_>, cli: &CliHeader) -> Result<usize> {
+    let metadata_offset = pe
+        .rva_to_offset(cli.metadata.rva)
+        .ok_or(Error::InvalidImage("metadata outside of image"))?;
+    let data = pe.metadata()?;
+    let metadata = Metadata::parse(data)?;
+    let mvid = metadata
+        .modules
+        .first()
+        .map(|module| module.mvid)
+        .filter(|&mvid| mvid != 0)
+        .ok_or(Error::InvalidImage("module has no MVID"))?;
+    let guids = MetadataRoot::parse(data)?
+        .find_stream(StreamHeader::GUID)
+        .ok_or_else(|| Error::StreamNotFound(StreamHeader::GUID.to_string()))?
+        .offset as usize;
+    Ok(metadata_offset + guids + (mvid as usize - 1) * 16)
+}
+
+/// The ranges of an image left out of its content hash: the ID slots, and
+/// what signing fills in after the build.
+fn module_id_slots(pe: &PeImage<'_>) -> Result<Vec<(usize, usize)>> {
+    let cli = pe.cli_header()?;
+    let mut slots = vec![
+        (mvid_offset(pe, &cli)?, 16),
+        (pe.time_date_stamp_offset(), 4),
+        (pe.checksum_offset(), 4),
+        (pe.data_directory_offset(SECURITY_DIRECTORY), 8),
+    ];
+    if cli.strong_name_signature.size != 0 {
+        let offset = pe
+            .rva_to_offset(cli.strong_name_signature.rva)
+            .ok_or(Error::InvalidImage(
+                "strong name signature outside of image",
+            ))?;
+        slots.push((offset, cli.strong_name_signature.size as usize));
+    }
+    // The certificate table is addressed by file offset, not RVA
+    if let Some(certificates) = pe
+        .data_directory(SECURITY_DIRECTORY)
+        .filter(|directory| directory.size != 0)
+    {
+        slots.push((certificates.rva as usize, certificates.size as usize));
+    }
+    Ok(slots)
+}
+
+/// Recompute the deterministic MVID and COFF time stamp of a PE image.
+///
+/// Roslyn hashes with SHA-256.
+pub fn compute_module_id(image: &[u8], algorithm: HashAlgorithm) -> Result<ContentId> {
+    let pe = PeImage::parse(image)?;
+    let hash = hash_zeroed(image, module_id_slots(&pe)?, algorithm);
+    ContentId::from_hash(&hash).ok_or(Error::UnsupportedHashAlgorithm(algorithm.alg_id()))
+}
+
+/// Get the MVID and COFF time stamp stored in a PE image.
+pub fn module_id(image: &[u8]) -> Result<ContentId> {
+    let pe = PeImage::parse(image)?;
+    let offset = mvid_offset(&pe, &pe.cli_header()?)?;
+    let mut guid = [0u8; 16];
+    guid.copy_from_slice(
+        image
+            .get(offset..offset + 16)
+            .ok_or(Error::InvalidImage("MVID outside of image"))?,
+    );
+    Ok(ContentId {
+        guid,
+        stamp: pe.time_date_stamp(),
+    })
+}
+
+/// File offset of the ID in a Portable PDB.
+fn pdb_id_offset(pdb: &[u8]) -> Result<usize> {
+    let stream = MetadataRoot::parse(pdb)?
+        .find_stream(StreamHeader::PDB)
+        .filter(|stream| stream.size as usize >= CONTENT_ID_SIZE)
+        .ok_or_else(|| Error::StreamNotFound(StreamHeader::PDB.to_string()))?
+        .offset as usize;
+    if stream + CONTENT_ID_SIZE > pdb.len() {
+        return Err(Error::UnexpectedEof {
+            offset: stream,
+            needed: CONTENT_ID_SIZE,
+        });
+    }
+    Ok(stream)
+}
+
+/// Hash a Portable PDB with its ID zeroed. This is both the input of the
+/// deterministic PDB ID and the checksum in the image's PdbChecksum entry.
+pub fn pdb_content_hash(pdb: &[u8], algorithm: HashAlgorithm) -> Result<Vec<u8>> {
+    let offset = pdb_id_offset(pdb)?;
+    Ok(hash_zeroed(pdb, vec![(offset, CONTENT_ID_SIZE)], algorithm))
+}
+
+/// Recompute the deterministic ID of a Portable PDB.
+///
+/// Roslyn hashes with the PDB checksum algorithm, SHA-256 by default.
+pub fn compute_pdb_id(pdb: &[u8], algorithm: HashAlgorithm) -> Result<ContentId> {
+    ContentId::from_hash(&pdb_content_hash(pdb, algorithm)?)
+        .ok_or(Error::UnsupportedHashAlgorithm(algorithm.alg_id()))
+}
+
+/// Get the ID stored in a Portable PDB's `#Pdb` stream.
+pub fn pdb_id(pdb: &[u8]) -> Result<ContentId> {
+    let offset = pdb_id_offset(pdb)?;
+    Ok(ContentId::from_bytes(&pdb[offset..offset + CONTENT_ID_SIZE]).expect("20-byte slice"))
+}
+
+/// Parse the PDB ID from a CodeView entry (`RSDS`, GUID, age, path).
+fn codeview_id(pe: &PeImage<'_>, entry: &DebugDirectoryEntry) -> Option<ContentId> {
+    let data = pe.debug_data(entry)?;
+    if !data.starts_with(CODEVIEW_SIGNATURE) {
+        return None;
+    }
+    let mut guid = [0u8; 16];
+    guid.copy_from_slice(data.get(4..20)?);
+    Some(ContentId {
+        guid,
+        stamp: entry.time_date_stamp,
+    })
+}
+
+/// Parse a PdbChecksum entry (algorithm name, NUL, checksum).
+fn pdb_checksum<'a>(
+    pe: &PeImage<'a>,
+    entry: &DebugDirectoryEntry,
+) -> Result<(HashAlgorithm, &'a [u8])> {
+    let data = pe
+        .debug_data(entry)
+        .ok_or(Error::InvalidImage("debug data outside of image"))?;
+    let name_len = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(Error::InvalidImage("unterminated PDB checksum algorithm"))?;
+    let algorithm = std::str::from_utf8(&data[..name_len])
+        .ok()
+        .and_then(HashAlgorithm::from_name)
+        .ok_or(Error::InvalidImage("unknown PDB checksum algorithm"))?;
+    Ok((algorithm, &data[name_len + 1..]))
+}
+
+/// Recompute the deterministic IDs of a PE image, and of its Portable PDB if
+/// given, and compare them with the stored ones.
+///
+/// The image ID is hashed with SHA-256; the PDB ID with the algorithm of the
+/// image's PdbChecksum entry, or SHA-256 without one.
+pub fn verify_deterministic_ids(image: &[u8], pdb: Option<&[u8]>) -> Result<DeterminismReport> {
+    let pe = PeImage::parse(image)?;
+    let debug_directory = pe.debug_directory()?;
+    let reproducible = debug_directory
+        .iter()
+        .any(|entry| entry.entry_type == DebugDirectoryEntry::REPRODUCIBLE);
+    let module = ContentIdCheck {
+        stored: module_id(image)?,
+        computed: compute_module_id(image, HashAlgorithm::Sha256)?,
+    };
+
+    let pdb = match pdb {
+        Some(pdb) => {
+            let referenced = debug_directory
+                .iter()
+                .filter(|entry| entry.entry_type == DebugDirectoryEntry::CODEVIEW)
+                .find_map(|entry| codeview_id(&pe, entry));
+            let checksum = debug_directory
+                .iter()
+                .find(|entry| entry.entry_type == DebugDirectoryEntry::PDB_CHECKSUM)
+                .map(|entry| pdb_checksum(&pe, entry))
+                .transpose()?;
+            let algorithm = checksum.map_or(HashAlgorithm::Sha256, |(algorithm, _)| algorithm);
+            let hash = pdb_content_hash(pdb, algorithm)?;
+            let computed = ContentId::from_hash(&hash)
+                .ok_or(Error::UnsupportedHashAlgorithm(algorithm.alg_id()))?;
+            Some(PdbIdCheck {
+                id: ContentIdCheck {
+                    stored: pdb_id(pdb)?,
+                    computed,
+                },
+                referenced,
+                checksum_matches: checksum.map(|(_, expected)| expected == hash),
+            })
+        }
+        None => None,
+    };
+
+    Ok(DeterminismReport {
+        reproducible,
+        module,
+        pdb,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{DEBUG_DIRECTORY, test_image};
+    use crate::tables::ModuleRow;
+
+    /// A minimal Portable PDB: a metadata root with just a `#Pdb` stream.
+    fn portable_pdb() -> Vec<u8> {
+        let mut root = MetadataRoot {
+            major_version: 1,
+            minor_version: 1,
+            reserved: 0,
+            version: "PDB v1.0".to_string(),
+            flags: 0,
+            streams: vec![StreamHeader {
+                offset: 0,
+                size: 32,
+                name: StreamHeader::PDB.to_string(),
+            }],
+        };
+        root.streams[0].offset = root.header_size() as u32;
+        let mut pdb = root.write();
+        pdb.extend_from_slice(&[0xAA; CONTENT_ID_SIZE]);
+        pdb.extend_from_slice(&0x0600_0001u32.to_le_bytes()); // entry point
+        pdb.extend_from_slice(&0u64.to_le_bytes()); // referenced tables
+        pdb
+    }
+
+    /// Build an image with CodeView, Reproducible and PdbChecksum entries
+    /// for `pdb`, and stamp both with their deterministic IDs.
+    fn deterministic_build() -> (Vec<u8>, Vec<u8>) {
+        let mut pdb = portable_pdb();
+        let pdb_hash = pdb_content_hash(&pdb, HashAlgorithm::Sha256).unwrap();
+        let pdb_id = ContentId::from_hash(&pdb_hash).unwrap();
+        let offset = pdb_id_offset(&pdb).unwrap();
+        pdb[offset..offset + CONTENT_ID_SIZE].copy_from_slice(&pdb_id.to_bytes());
+
+        let mut md = Metadata::new();
+        let name = md.strings.add("Deterministic.dll");
+        let mvid = md.guids.add(&[0xFF; 16]);
+        md.modules.push(ModuleRow {
+            name,
+            mvid,
+            ..Default::default()
+        });
+
+        // Use the space reserved for a signature for the debug data instead
+        let mut image = test_image(&md.write(), 256);
+        let pe = PeImage::parse(&image).unwrap();
+        let cli_offset = pe.rva_to_offset(0x2000).unwrap();
+        let rva = pe.cli_header().unwrap().strong_name_signature.rva;
+        let offset = pe.rva_to_offset(rva).unwrap();
+        let directory_offset = pe.data_directory_offset(DEBUG_DIRECTORY);
+        image[cli_offset + 32..cli_offset + 40].fill(0);
+
+        let mut codeview = CODEVIEW_SIGNATURE.to_vec();
+        codeview.extend_from_slice(&pdb_id.guid);
+        codeview.extend_from_slice(&1u32.to_le_bytes());
+        codeview.extend_from_slice(b"Deterministic.pdb\0");
+        let mut checksum = b"SHA256\0".to_vec();
+        checksum.extend_from_slice(&pdb_hash);
+
+        let mut data_offset = 3 * 28;
+        let mut directory = Vec::new();
+        for (stamp, entry_type, data) in [
+            (pdb_id.stamp, DebugDirectoryEntry::CODEVIEW, &codeview[..]),
+            (0, DebugDirectoryEntry::REPRODUCIBLE, &[][..]),
+            (0, DebugDirectoryEntry::PDB_CHECKSUM, &checksum[..]),
+        ] {
+            directory.extend_from_slice(&0u32.to_le_bytes());
+            directory.extend_from_slice(&stamp.to_le_bytes());
+            directory.extend_from_slice(&[0; 4]);
+            directory.extend_from_slice(&entry_type.to_le_bytes());
+            directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(rva + data_offset as u32).to_le_bytes());
+            directory.extend_from_slice(&((offset + data_offset) as u32).to_le_bytes());
+            image[offset + data_offset..offset + data_offset + data.len()].copy_from_slice(data);
+            data_offset += data.len();
+        }
+        image[offset..offset + directory.len()].copy_from_slice(&directory);
+        image[directory_offset..directory_offset + 4].copy_from_slice(&rva.to_le_bytes());
+        image[directory_offset + 4..directory_offset + 8]
+            .copy_from_slice(&(directory.len() as u32).to_le_bytes());
+
+        let id = compute_module_id(&image, HashAlgorithm::Sha256).unwrap();
+        let pe = PeImage::parse(&image).unwrap();
+        let mvid_offset = mvid_offset(&pe, &pe.cli_header().unwrap()).unwrap();
+        let stamp_offset = pe.time_date_stamp_offset();
+        image[mvid_offset..mvid_offset + 16].copy_from_slice(&id.guid);
+        image[stamp_offset..stamp_offset + 4].copy_from_slice(&id.stamp.to_le_bytes());
+        (image, pdb)
+    }
+
+    #[test]
+    fn test_content_id_from_hash() {
+        let hash: Vec<u8> = (0..32).map(|i| 0xF0 | i as u8).collect();
+        let id = ContentId::from_hash(&hash).unwrap();
+        assert_eq!(id.guid[6], 0xF6);
+        assert_eq!(id.guid[7], 0x47); // version 4
+        assert_eq!(id.guid[8], 0xB8); // RFC 4122 variant
+        assert_eq!(id.stamp, 0xF3F2_F1F0 | 0x8000_0000);
+        assert_eq!(ContentId::from_bytes(&id.to_bytes()), Some(id));
+        assert_eq!(ContentId::from_hash(&[0; 16]), None);
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_content_id_known_answer() {
+        // SHA-256("abc") from FIPS 180-2; Roslyn's BlobContentId.FromHash
+        // gives the GUID {bf1678ba-018f-4acf-8141-40de5dae2223} and stamp
+        // 0xA36103B0
+        let hash = hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(HashAlgorithm::Sha256.hash(b"abc"), hash);
+        let id = ContentId::from_hash(&hash).unwrap();
+        assert_eq!(id.guid.to_vec(), hex("ba7816bf8f01cf4a814140de5dae2223"));
+        assert_eq!(id.stamp, 0xA361_03B0);
+    }
+
+    #[test]
+    fn test_module_id_known_answer() {
+        let mut md = Metadata::new();
+        let name = md.strings.add("Known.dll");
+        let mvid = md.guids.add(&[0x11; 16]);
+        md.modules.push(ModuleRow {
+            name,
+            mvid,
+            ..Default::default()
+        });
+        let image = test_image(&md.write(), 0);
+
+        // The MVID, time stamp, checksum and certificate directory are zeroed
+        let pe = PeImage::parse(&image).unwrap();
+        let slots = module_id_slots(&pe).unwrap();
+        assert_eq!(slots, [(748, 16), (136, 4), (216, 4), (280, 8)]);
+        // SHA-256 of the image with those slots zeroed, computed independently
+        assert_eq!(
+            hash_zeroed(&image, slots, HashAlgorithm::Sha256),
+            hex("84298355ba65ce7345ea398addab6278e700a4a29a14658f74745bddac88584a")
+        );
+        // {55832984-65ba-43ce-85ea-398addab6278}, stamp 0xA2A400E7
+        let id = compute_module_id(&image, HashAlgorithm::Sha256).unwrap();
+        assert_eq!(id.guid.to_vec(), hex("84298355ba65ce4385ea398addab6278"));
+        assert_eq!(id.stamp, 0xA2A4_00E7);
+    }
+
+    #[test]
+    fn test_verify_deterministic_ids() {
+        let (image, pdb) = deterministic_build();
+        let report = verify_deterministic_ids(&image, Some(&pdb)).unwrap();
+        assert!(report.reproducible);
+        assert!(report.module.matches());
+        let pdb_check = report.pdb.as_ref().unwrap();
+        assert!(pdb_check.id.matches());
+        assert_eq!(pdb_check.referenced, Some(pdb_check.id.computed));
+        assert_eq!(pdb_check.checksum_matches, Some(true));
+        assert!(report.is_valid());
+
+        // The ID slots are not part of the hash
+        assert_eq!(
+            compute_module_id(&image, HashAlgorithm::Sha256).unwrap(),
+            module_id(&image).unwrap()
+        );
+        assert_eq!(
+            compute_pdb_id(&pdb, HashAlgorithm::Sha256).unwrap(),
+            pdb_id(&pdb).unwrap()
+        );
+
+        // A changed module no longer matches its MVID
+        let mut tampered = image.clone();
+        let name = image
+            .windows(17)
+            .position(|window| window == b"Deterministic.dll")
+            .unwrap();
+        tampered[name] = b'd';
+        let report = verify_deterministic_ids(&tampered, None).unwrap();
+        assert!(!report.module.matches());
+        assert!(!report.is_valid());
+
+        // A changed PDB no longer matches its ID or the image's checksum
+        let mut tampered = pdb.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let report = verify_deterministic_ids(&image, Some(&tampered)).unwrap();
+        assert!(report.module.matches());
+        let pdb_check = report.pdb.unwrap();
+        assert!(!pdb_check.id.matches());
+        assert_eq!(pdb_check.checksum_matches, Some(false));
+        assert!(!pdb_check.is_valid());
+    }
+
+    #[test]
+    fn test_non_deterministic_image() {
+        let mut md = Metadata::new();
+        let name = md.strings.add("Random.dll");
+        let mvid = md.guids.add(&[0x42; 16]);
+        md.modules.push(ModuleRow {
+            name,
+            mvid,
+            ..Default::default()
+        });
+        let image = test_image(&md.write(), 0);
+        let report = verify_deterministic_ids(&image, None).unwrap();
+        assert!(!report.reproducible);
+        assert_eq!(report.module.stored.guid, [0x42; 16]);
+        assert!(!report.module.matches());
+        assert!(verify_deterministic_ids(&image, Some(b"not a pdb")).is_err());
+    }
+}
@@ -0,0 +1,1389 @@
+//! Semantic metadata diff.
+//!
+//! [`Metadata::diff`] compares two versions of an assembly by what they
+//! declare rather than by row number: types are matched by full name, members
+//! by name and signature, assembly references by name, culture and public
+//! key token, and resources by name.
+//! Signatures are compared as ILAsm-style text naming the types they
+//! reference and the assemblies that resolve them, so renumbered rows and
+//! rebuilt heaps are not reported but a type moved to another assembly is.
+//!
+//! The result is a list of [`Change`]s, rendered as text by its `Display`
+//! implementation or as JSON by [`MetadataDiff::to_json`].
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Write as _};
+
+use crate::crypto::sha256;
+use crate::identity::AssemblyIdentity;
+use crate::metadata::Metadata;
+use crate::resolve::type_path;
+use crate::resources::ResourceLocation;
+use crate::signature::{CallingConvention, ElementType, FieldSig, MethodSig, PropertySig, TypeSig};
+use crate::tables::{
+    CodedIndex, CodedIndexKind, CustomAttributeRow, EventRow, FieldRow, GenericParamRow,
+    MethodDefRow, PropertyRow, TableId,
+};
+
+/// What happened to an entity between two versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The entity exists only in the newer version.
+    Added,
+    /// The entity exists only in the older version.
+    Removed,
+    /// The entity exists in both versions with a different aspect.
+    Changed,
+}
+
+impl ChangeKind {
+    /// Get the lowercase name used in text and JSON output.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Changed => "changed",
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The kind of entity a [`Change`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EntityKind {
+    /// The assembly's identity.
+    Assembly,
+    /// An AssemblyRef, matched by name, culture and public key token.
+    AssemblyRef,
+    /// A ManifestResource, matched by name.
+    Resource,
+    /// A TypeDef, matched by full name.
+    Type,
+    /// A field, matched by name and type.
+    Field,
+    /// A method, matched by name and signature.
+    Method,
+    /// A property, matched by name and signature.
+    Property,
+    /// An event, matched by name and type.
+    Event,
+    /// A custom attribute, matched by constructor and value.
+    Attribute,
+}
+
+impl EntityKind {
+    /// Get the kebab-case name used in text and JSON output.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Assembly => "assembly",
+            Self::AssemblyRef => "assembly-ref",
+            Self::Resource => "resource",
+            Self::Type => "type",
+            Self::Field => "field",
+            Self::Method => "method",
+            Self::Property => "property",
+            Self::Event => "event",
+            Self::Attribute => "attribute",
+        }
+    }
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A single difference between two versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Whether the entity was added, removed or changed.
+    pub kind: ChangeKind,
+    /// The kind of entity.
+    pub entity: EntityKind,
+    /// The entity: a type's full name, `Type::signature` for a member, or the
+    /// name of the attribute's owner.
+    pub name: String,
+    /// The aspect of a changed entity, such as `"version"`, `"flags"` or
+    /// `"signature"`.
+    pub aspect: Option<&'static str>,
+    /// Old value of the aspect, or the removed attribute (None if empty).
+    pub old: Option<String>,
+    /// New value of the aspect, or the added attribute (None if empty).
+    pub new: Option<String>,
+}
+
+/// Formats one line: `+ kind name`, `- kind name` or
+/// `~ kind name: aspect old -> new`.
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.kind {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Changed => '~',
+        };
+        write!(f, "{sign} {} {}", self.entity, self.name)?;
+        match (self.kind, self.aspect) {
+            (ChangeKind::Changed, Some(aspect)) => write!(
+                f,
+                ": {aspect} {} -> {}",
+                self.old.as_deref().unwrap_or("none"),
+                self.new.as_deref().unwrap_or("none")
+            ),
+            _ => match self.new.as_deref().or(self.old.as_deref()) {
+                Some(value) => write!(f, ": {value}"),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+/// The differences between two versions of an assembly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataDiff {
+    /// Changes, ordered by assembly, assembly references, resources, then
+    /// types by name with their members.
+    pub changes: Vec<Change>,
+}
+
+impl MetadataDiff {
+    /// Check if the versions are equivalent.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Render the changes as a JSON object with a `changes` array.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"changes\":[");
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"kind\":");
+            push_json(&mut out, Some(change.kind.name()));
+            out.push_str(",\"entity\":");
+            push_json(&mut out, Some(change.entity.name()));
+            out.push_str(",\"name\":");
+            push_json(&mut out, Some(&change.name));
+            out.push_str(",\"aspect\":");
+            push_json(&mut out, change.aspect);
+            out.push_str(",\"old\":");
+            push_json(&mut out, change.old.as_deref());
+            out.push_str(",\"new\":");
+            push_json(&mut out, change.new.as_deref());
+            out.push('}');
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+/// Formats one change per line.
+impl fmt::Display for MetadataDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.changes
+            .iter()
+            .try_for_each(|change| writeln!(f, "{change}"))
+    }
+}
+
+/// Append a JSON string, or `null`.
+fn push_json(out: &mut String, value: Option<&str>) {
+    let Some(value) = value else {
+        out.push_str("null");
+        return;
+    };
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Format bytes as ILAsm does: uppercase hex pairs separated by spaces.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Get the ILAsm keyword of a primitive type.
+fn primitive_keyword(element: ElementType) -> &'static str {
+    match element {
+        ElementType::Void => "void",
+        ElementType::Boolean => "bool",
+        ElementType::Char => "char",
+        ElementType::I1 => "int8",
+        ElementType::U1 => "uint8",
+        ElementType::I2 => "int16",
+        ElementType::U2 => "uint16",
+        ElementType::I4 => "int32",
+        ElementType::U4 => "uint32",
+        ElementType::I8 => "int64",
+        ElementType::U8 => "uint64",
+        ElementType::R4 => "float32",
+        ElementType::R8 => "float64",
+        ElementType::String => "string",
+        ElementType::TypedByRef => "typedref",
+        ElementType::IntPtr => "native int",
+        ElementType::UIntPtr => "native uint",
+        ElementType::Object => "object",
+        _ => "?",
+    }
+}
+
+impl Metadata {
+    /// Compare this metadata with a newer version of the same assembly.
+    ///
+    /// Reports added, removed and changed types and members, signatures,
+    /// flags, custom attributes, assembly references and resources, and the
+    /// assembly's name, version, culture and public key token. Method bodies
+    /// and resource data are not compared; see
+    /// [`diff_with_resources`](Self::diff_with_resources).
+    #[must_use]
+    pub fn diff(&self, newer: &Metadata) -> MetadataDiff {
+        diff_snapshots(&Snapshot::new(self, None), &Snapshot::new(newer, None))
+    }
+
+    /// Compare with a newer version like [`diff`](Self::diff), also reporting
+    /// embedded resources whose size or SHA-256 hash changed.
+    ///
+    /// `resources` and `newer_resources` are the CLI resources sections of
+    /// the two versions.
+    #[must_use]
+    pub fn diff_with_resources(
+        &self,
+        resources: &[u8],
+        newer: &Metadata,
+        newer_resources: &[u8],
+    ) -> MetadataDiff {
+        diff_snapshots(
+            &Snapshot::new(self, Some(resources)),
+            &Snapshot::new(newer, Some(newer_resources)),
+        )
+    }
+
+    /// Get the name of a TypeDef, with nested types joined by `+`.
+    pub(crate) fn type_def_full_name(&self, rid: u32) -> String {
+        self.type_index_text(
+            CodedIndex {
+                table: Some(TableId::TypeDef),
+                row: rid,
+            },
+            false,
+        )
+    }
+
+    /// Get the ILAsm-style text of a TypeDef, TypeRef or TypeSpec.
+    ///
+    /// If `scoped`, TypeRefs are prefixed with the assembly or module that
+    /// resolves them, as in `[mscorlib]System.Object`, so a type that moved
+    /// between assemblies reads differently.
+    pub(crate) fn type_index_text(&self, index: CodedIndex, scoped: bool) -> String {
+        if index.table == Some(TableId::TypeSpec) {
+            return match self.type_spec_signature(index.row) {
+                Some(Ok(sig)) => self.type_sig_text(&sig, scoped),
+                _ => format!("<invalid TypeSpec {}>", index.row),
+            };
+        }
+        let Some(path) = type_path(self, index) else {
+            return format!("<invalid {index:?}>");
+        };
+        let mut out = if scoped {
+            self.resolution_scope_text(index)
+        } else {
+            String::new()
+        };
+        for (i, (namespace, name)) in path.iter().enumerate() {
+            if i > 0 {
+                out.push('+');
+            }
+            if !namespace.is_empty() {
+                out.push_str(namespace);
+                out.push('.');
+            }
+            out.push_str(name);
+        }
+        out
+    }
+
+    /// Get the `[scope]` prefix of a TypeRef, or an empty string for a
+    /// TypeDef or a TypeRef resolved in this module.
+    fn resolution_scope_text(&self, mut index: CodedIndex) -> String {
+        while index.table == Some(TableId::TypeRef) {
+            let Some(row) = self.get_type_ref(index.row) else {
+                break;
+            };
+            index = row.resolution_scope;
+        }
+        let row = (index.row as usize).wrapping_sub(1);
+        let name = |name: u32| self.strings.get(name).ok();
+        match index.table {
+            Some(TableId::AssemblyRef) => self
+                .assembly_refs
+                .get(row)
+                .and_then(|row| name(row.name))
+                .map(|name| format!("[{name}]")),
+            Some(TableId::ModuleRef) => self
+                .module_refs
+                .get(row)
+                .and_then(|row| name(row.name))
+                .map(|name| format!("[.module {name}]")),
+            _ => None,
+        }
+        .unwrap_or_default()
+    }
+
+    /// Get the ILAsm-style text of a type signature.
+    pub(crate) fn type_sig_text(&self, sig: &TypeSig, scoped: bool) -> String {
+        match sig {
+            TypeSig::Primitive(element) => primitive_keyword(*element).to_string(),
+            TypeSig::Class(coded) => format!(
+                "class {}",
+                self.type_index_text(
+                    CodedIndex::decode(CodedIndexKind::TypeDefOrRef, *coded),
+                    scoped
+                )
+            ),
+            TypeSig::ValueType(coded) => format!(
+                "valuetype {}",
+                self.type_index_text(
+                    CodedIndex::decode(CodedIndexKind::TypeDefOrRef, *coded),
+                    scoped
+                )
+            ),
+            TypeSig::SzArray(element) => format!("{}[]", self.type_sig_text(element, scoped)),
+            TypeSig::Array {
+                element_type,
+                rank,
+                sizes,
+                lo_bounds,
+            } => {
+                // Each dimension reads `lower...upper`, `lower...` or `size`
+                let dimensions: Vec<String> = (0..*rank as usize)
+                    .map(|i| match (lo_bounds.get(i), sizes.get(i)) {
+                        (Some(&lower), Some(&size)) => {
+                            format!("{lower}...{}", i64::from(lower) + i64::from(size) - 1)
+                        }
+                        (Some(lower), None) => format!("{lower}..."),
+                        (None, Some(size)) => size.to_string(),
+                        (None, None) => String::new(),
+                    })
+                    .collect();
+                format!(
+                    "{}[{}]",
+                    self.type_sig_text(element_type, scoped),
+                    dimensions.join(",")
+                )
+            }
+            TypeSig::Ptr(element) => format!("{}*", self.type_sig_text(element, scoped)),
+            TypeSig::ByRef(element) => format!("{}&", self.type_sig_text(element, scoped)),
+            TypeSig::GenericInst {
+                is_value_type,
+                type_ref,
+                type_args,
+            } => format!(
+                "{}{}<{}>",
+                if *is_value_type {
+                    "valuetype "
+                } else {
+                    "class "
+                },
+                self.type_index_text(
+                    CodedIndex::decode(CodedIndexKind::TypeDefOrRef, *type_ref),
+                    scoped
+                ),
+                type_args
+                    .iter()
+                    .map(|arg| self.type_sig_text(arg, scoped))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TypeSig::Var(number) => format!("!{number}"),
+            TypeSig::MVar(number) => format!("!!{number}"),
+            TypeSig::FnPtr(method) => {
+                format!("method {}", self.method_sig_text("*", method, scoped))
+            }
+            TypeSig::Modified {
+                required,
+                modifier,
+                inner,
+            } => format!(
+                "{} {}({})",
+                self.type_sig_text(inner, scoped),
+                if *required { "modreq" } else { "modopt" },
+                self.type_index_text(
+                    CodedIndex::decode(CodedIndexKind::TypeDefOrRef, *modifier),
+                    scoped
+                )
+            ),
+            TypeSig::Pinned(inner) => format!("{} pinned", self.type_sig_text(inner, scoped)),
+        }
+    }
+
+    /// Get the ILAsm-style text of a method signature, such as
+    /// `instance void Resize<1>(int32, !!0)`.
+    pub(crate) fn method_sig_text(&self, name: &str, sig: &MethodSig, scoped: bool) -> String {
+        let mut out = String::new();
+        if sig.calling_convention.has_this() {
+            out.push_str("instance ");
+        }
+        if sig.calling_convention.0 & 0x0F == CallingConvention::VARARG {
+            out.push_str("vararg ");
+        }
+        out.push_str(&self.type_sig_text(&sig.return_type, scoped));
+        out.push(' ');
+        out.push_str(name);
+        if sig.generic_param_count > 0 {
+            let _ = write!(out, "<{}>", sig.generic_param_count);
+        }
+        let mut params: Vec<String> = sig
+            .params
+            .iter()
+            .map(|p| self.type_sig_text(p, scoped))
+            .collect();
+        if let Some(sentinel) = sig.sentinel {
+            params.insert(sentinel.min(params.len()), "...".to_string());
+        }
+        let _ = write!(out, "({})", params.join(", "));
+        out
+    }
+
+    /// Get the ILAsm-style text of the generic parameters of a type or
+    /// method, such as `<+class (System.IComparable) T>`, or an empty string.
+    pub(crate) fn generic_params_text(&self, owner: CodedIndex, scoped: bool) -> String {
+        let params: Vec<String> = self
+            .generic_params_of(owner)
+            .into_iter()
+            .map(|(rid, row)| {
+                let mut out = self.generic_param_constraints_text(rid, scoped);
+                out.push_str(self.strings.get(row.name).unwrap_or(""));
+                out
+            })
+            .collect();
+        if params.is_empty() {
+            return String::new();
+        }
+        format!("<{}>", params.join(", "))
+    }
+
+    /// Get the GenericParam rows of a type or method, in number order.
+    pub(crate) fn generic_params_of(&self, owner: CodedIndex) -> Vec<(u32, &GenericParamRow)> {
+        let mut params: Vec<(u32, _)> = (1..)
+            .zip(&self.generic_params)
+            .filter(|(_, row)| row.owner == owner)
+            .collect();
+        params.sort_by_key(|(_, row)| row.number);
+        params
+    }
+
+    /// Get the variance, special constraints and constraint types of a
+    /// generic parameter as ILAsm text preceding its name, such as
+    /// `+class (System.IComparable) `.
+    pub(crate) fn generic_param_constraints_text(&self, rid: u32, scoped: bool) -> String {
+        let Some(row) = self.generic_params.get((rid as usize).wrapping_sub(1)) else {
+            return String::new();
+        };
+        let mut out = String::new();
+        match row.flags & 0x0003 {
+            0x0001 => out.push('+'),
+            0x0002 => out.push('-'),
+            _ => {}
+        }
+        for (flag, keyword) in [
+            (0x0004, "class "),
+            (0x0008, "valuetype "),
+            (0x0010, ".ctor "),
+        ] {
+            if row.flags & flag != 0 {
+                out.push_str(keyword);
+            }
+        }
+        let mut constraints: Vec<String> = self
+            .generic_param_constraints
+            .iter()
+            .filter(|constraint| constraint.owner == rid)
+            .map(|constraint| self.type_index_text(constraint.constraint, scoped))
+            .collect();
+        if !constraints.is_empty() {
+            constraints.sort();
+            let _ = write!(out, "({}) ", constraints.join(", "));
+        }
+        out
+    }
+
+    /// Get the ILAsm-style signature of a field, such as `int32 count`.
+    pub(crate) fn field_text(&self, row: &FieldRow, scoped: bool) -> String {
+        let name = self.strings.get(row.name).unwrap_or("");
+        match self.blobs.get(row.signature).map(FieldSig::parse_blob) {
+            Ok(Ok(sig)) => format!("{} {name}", self.type_sig_text(&sig.field_type, scoped)),
+            _ => format!("{} {name}", self.invalid_signature_text(row.signature)),
+        }
+    }
+
+    /// Get the text of a field's constant value, or an empty string.
+    pub(crate) fn field_value_text(&self, field: u32) -> String {
+        match self.field_default_value(field) {
+            Some(Ok(value)) => value.to_string(),
+            Some(Err(_)) => "<invalid>".to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Get the ILAsm-style signature of a method, such as
+    /// `instance void Resize(int32)`.
+    pub(crate) fn method_text(&self, row: &MethodDefRow, scoped: bool) -> String {
+        let name = self.strings.get(row.name).unwrap_or("");
+        match self.blobs.get(row.signature).map(MethodSig::parse_blob) {
+            Ok(Ok(sig)) => self.method_sig_text(name, &sig, scoped),
+            _ => format!("{} {name}", self.invalid_signature_text(row.signature)),
+        }
+    }
+
+    /// Get the ILAsm-style signature of a property, such as
+    /// `instance int32 Count()`.
+    pub(crate) fn property_text(&self, row: &PropertyRow, scoped: bool) -> String {
+        let name = self.strings.get(row.name).unwrap_or("");
+        match self
+            .blobs
+            .get(row.property_type)
+            .map(PropertySig::parse_blob)
+        {
+            Ok(Ok(sig)) => format!(
+                "{}{} {name}({})",
+                if sig.has_this { "instance " } else { "" },
+                self.type_sig_text(&sig.property_type, scoped),
+                sig.params
+                    .iter()
+                    .map(|p| self.type_sig_text(p, scoped))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            _ => format!("{} {name}", self.invalid_signature_text(row.property_type)),
+        }
+    }
+
+    /// Get the ILAsm-style signature of an event, such as
+    /// `System.EventHandler Changed`.
+    pub(crate) fn event_text(&self, row: &EventRow, scoped: bool) -> String {
+        let name = self.strings.get(row.name).unwrap_or("");
+        if row.event_type.is_null() {
+            return name.to_string();
+        }
+        format!("{} {name}", self.type_index_text(row.event_type, scoped))
+    }
+
+    /// Get the ILAsm-style text of a custom attribute, such as
+    /// `System.ObsoleteAttribute(string) = ( 01 00 ... )`.
+    fn attribute_text(&self, row: &CustomAttributeRow, scoped: bool) -> String {
+        let ctor = row.attr_type;
+        let index = (ctor.row as usize).wrapping_sub(1);
+        let (owner, signature) = match ctor.table {
+            Some(TableId::MethodDef) if index < self.method_defs.len() => (
+                self.get_method_owner(ctor.row)
+                    .map(|(rid, _)| self.type_def_full_name(rid)),
+                self.method_defs[index].signature,
+            ),
+            Some(TableId::MemberRef) if index < self.member_refs.len() => {
+                let member = &self.member_refs[index];
+                let owner = match member.class.table {
+                    Some(TableId::TypeDef | TableId::TypeRef | TableId::TypeSpec) => {
+                        Some(self.type_index_text(member.class, scoped))
+                    }
+                    _ => None,
+                };
+                (owner, member.signature)
+            }
+            _ => (None, 0),
+        };
+        let owner = owner.unwrap_or_else(|| format!("<invalid {ctor:?}>"));
+        let params = match self
+            .blobs
+            .get(signature)
+            .ok()
+            .and_then(|blob| MethodSig::parse_blob(blob).ok())
+        {
+            Some(sig) => sig
+                .params
+                .iter()
+                .map(|p| self.type_sig_text(p, scoped))
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => "?".to_string(),
+        };
+        match self.blobs.get(row.value) {
+            Ok([]) => format!("{owner}({params})"),
+            Ok(value) => format!("{owner}({params}) = ( {} )", hex(value)),
+            Err(_) => format!("{owner}({params}) = <invalid>"),
+        }
+    }
+
+    /// Get the text of a signature blob that failed to parse.
+    fn invalid_signature_text(&self, signature: u32) -> String {
+        format!(
+            "<invalid signature {}>",
+            hex(self.blobs.get(signature).unwrap_or_default())
+        )
+    }
+}
+
+/// Values of an entity compared by aspect name, and its custom attributes.
+#[derive(Default)]
+struct Details {
+    aspects: Vec<(&'static str, String)>,
+    attributes: Vec<String>,
+}
+
+/// A field, method, property or event of a type.
+struct Member {
+    entity: EntityKind,
+    name: String,
+    signature: String,
+    details: Details,
+}
+
+struct TypeSnapshot {
+    details: Details,
+    members: Vec<Member>,
+}
+
+/// The entities of one version, keyed by what the diff matches them on.
+struct Snapshot {
+    assembly: Option<(String, Details)>,
+    assembly_refs: BTreeMap<String, Details>,
+    resources: BTreeMap<String, Details>,
+    types: BTreeMap<String, TypeSnapshot>,
+}
+
+impl Snapshot {
+    /// `resources` is the CLI resources section, if embedded resource data
+    /// is to be compared.
+    fn new(md: &Metadata, resources: Option<&[u8]>) -> Self {
+        let mut attributes: HashMap<CodedIndexKey, Vec<String>> = HashMap::new();
+        for row in &md.custom_attributes {
+            attributes
+                .entry(CodedIndexKey::from(row.parent))
+                .or_default()
+                .push(md.attribute_text(row, true));
+        }
+        let details = |table: TableId, row: u32, aspects: Vec<(&'static str, String)>| {
+            let mut attributes = attributes
+                .get(&CodedIndexKey(Some(table), row))
+                .cloned()
+                .unwrap_or_default();
+            attributes.sort();
+            Details {
+                aspects,
+                attributes,
+            }
+        };
+
+        let assembly = md.assembly().map(|assembly| {
+            let aspects = vec![
+                ("name", assembly.name.clone()),
+                ("version", assembly.version_string()),
+                ("culture", assembly.culture.clone().unwrap_or_default()),
+                (
+                    "public key token",
+                    assembly.public_key_token_string().unwrap_or_default(),
+                ),
+                ("flags", format!("0x{:08X}", assembly.flags)),
+                ("hash algorithm", format!("0x{:08X}", assembly.hash_alg_id)),
+                ("runtime version", md.version().to_string()),
+            ];
+            (
+                assembly.name.clone(),
+                details(TableId::Assembly, 1, aspects),
+            )
+        });
+
+        // Match references on name, culture and public key token, so a new
+        // version reads as a change. References to several versions of one
+        // assembly are told apart by their full identity.
+        let identities: Vec<AssemblyIdentity> =
+            md.assembly_refs().iter().map(|r| r.identity()).collect();
+        let keys: Vec<String> = identities
+            .iter()
+            .map(|identity| {
+                AssemblyIdentity {
+                    culture: identity.culture.clone(),
+                    public_key: identity.public_key.clone(),
+                    ..AssemblyIdentity::new(&identity.name)
+                }
+                .to_string()
+            })
+            .collect();
+        let assembly_refs = (1..)
+            .zip(md.assembly_refs())
+            .zip(identities.iter().zip(&keys))
+            .map(|((rid, reference), (identity, key))| {
+                let aspects = vec![
+                    ("version", reference.version_string()),
+                    ("flags", format!("0x{:08X}", reference.flags)),
+                ];
+                let key = if keys.iter().filter(|other| *other == key).count() > 1 {
+                    identity.to_string()
+                } else {
+                    key.clone()
+                };
+                (key, details(TableId::AssemblyRef, rid, aspects))
+            })
+            .collect();
+
+        let resources = (1..)
+            .zip(md.manifest_resources())
+            .map(|(rid, resource)| {
+                let visibility = if resource.is_public() {
+                    "public"
+                } else {
+                    "private"
+                };
+                let location = match &resource.location {
+                    ResourceLocation::Embedded { .. } => "embedded".to_string(),
+                    ResourceLocation::File { name, .. } => format!("file {name}"),
+                    ResourceLocation::AssemblyRef { name, .. } => format!("assembly {name}"),
+                };
+                let (size, hash) = match resources.and_then(|data| resource.embedded_data(data)) {
+                    Some(Ok(data)) => (
+                        data.len().to_string(),
+                        sha256(data).iter().map(|b| format!("{b:02x}")).collect(),
+                    ),
+                    Some(Err(_)) => ("<invalid>".to_string(), String::new()),
+                    None => (String::new(), String::new()),
+                };
+                let aspects = vec![
+                    ("visibility", visibility.to_string()),
+                    ("location", location),
+                    ("size", size),
+                    ("sha256", hash),
+                ];
+                (
+                    resource.name,
+                    details(TableId::ManifestResource, rid, aspects),
+                )
+            })
+            .collect();
+
+        let mut types = BTreeMap::new();
+        for (rid, row) in (1..).zip(&md.type_defs) {
+            let owner = CodedIndex {
+                table: Some(TableId::TypeDef),
+                row: rid,
+            };
+            let base = if row.extends.is_null() {
+                String::new()
+            } else {
+                md.type_index_text(row.extends, true)
+            };
+            let mut interfaces: Vec<String> = md
+                .interface_impls
+                .iter()
+                .filter(|imp| imp.class == rid)
+                .map(|imp| md.type_index_text(imp.interface, true))
+                .collect();
+            interfaces.sort();
+            let aspects = vec![
+                ("flags", format!("0x{:08X}", row.flags)),
+                ("base type", base),
+                ("interfaces", interfaces.join(", ")),
+                ("generic parameters", md.generic_params_text(owner, true)),
+            ];
+            let type_details = details(TableId::TypeDef, rid, aspects);
+
+            let mut members = Vec::new();
+            for (field, row) in md.get_type_fields(rid) {
+                let aspects = vec![
+                    ("flags", format!("0x{:04X}", row.flags)),
+                    ("value", md.field_value_text(field)),
+                ];
+                members.push(Member {
+                    entity: EntityKind::Field,
+                    name: md.strings.get(row.name).unwrap_or("").to_string(),
+                    signature: md.field_text(row, true),
+                    details: details(TableId::Field, field, aspects),
+                });
+            }
+            for (method, row) in md.get_type_methods(rid) {
+                let generic_params = md.generic_params_text(
+                    CodedIndex {
+                        table: Some(TableId::MethodDef),
+                        row: method,
+                    },
+                    true,
+                );
+                let aspects = vec![
+                    ("flags", format!("0x{:04X}", row.flags)),
+                    ("impl flags", format!("0x{:04X}", row.impl_flags)),
+                    ("generic parameters", generic_params),
+                ];
+                members.push(Member {
+                    entity: EntityKind::Method,
+                    name: md.strings.get(row.name).unwrap_or("").to_string(),
+                    signature: md.method_text(row, true),
+                    details: details(TableId::MethodDef, method, aspects),
+                });
+            }
+            for (property, row) in md.get_type_properties(rid) {
+                members.push(Member {
+                    entity: EntityKind::Property,
+                    name: md.strings.get(row.name).unwrap_or("").to_string(),
+                    signature: md.property_text(row, true),
+                    details: details(
+                        TableId::Property,
+                        property,
+                        vec![("flags", format!("0x{:04X}", row.flags))],
+                    ),
+                });
+            }
+            for (event, row) in md.get_type_events(rid) {
+                members.push(Member {
+                    entity: EntityKind::Event,
+                    name: md.strings.get(row.name).unwrap_or("").to_string(),
+                    signature: md.event_text(row, true),
+                    details: details(
+                        TableId::Event,
+                        event,
+                        vec![("flags", format!("0x{:04X}", row.event_flags))],
+                    ),
+                });
+            }
+
+            types.insert(
+                md.type_def_full_name(rid),
+                TypeSnapshot {
+                    details: type_details,
+                    members,
+                },
+            );
+        }
+
+        Self {
+            assembly,
+            assembly_refs,
+            resources,
+            types,
+        }
+    }
+}
+
+/// Compare the entities of two versions.
+fn diff_snapshots(old: &Snapshot, new: &Snapshot) -> MetadataDiff {
+    let mut diff = Differ::default();
+    match (&old.assembly, &new.assembly) {
+        (Some((_, a)), Some((name, b))) => diff.entity(EntityKind::Assembly, name, a, b),
+        (Some((name, _)), None) => diff.push(ChangeKind::Removed, EntityKind::Assembly, name),
+        (None, Some((name, _))) => diff.push(ChangeKind::Added, EntityKind::Assembly, name),
+        (None, None) => {}
+    }
+    diff.map(
+        EntityKind::AssemblyRef,
+        &old.assembly_refs,
+        &new.assembly_refs,
+    );
+    diff.map(EntityKind::Resource, &old.resources, &new.resources);
+
+    let names: BTreeSet<&String> = old.types.keys().chain(new.types.keys()).collect();
+    for name in names {
+        match (old.types.get(name), new.types.get(name)) {
+            (Some(a), Some(b)) => {
+                diff.entity(EntityKind::Type, name, &a.details, &b.details);
+                diff.members(name, &a.members, &b.members);
+            }
+            (Some(_), None) => diff.push(ChangeKind::Removed, EntityKind::Type, name),
+            (None, Some(_)) => diff.push(ChangeKind::Added, EntityKind::Type, name),
+            (None, None) => {}
+        }
+    }
+    MetadataDiff {
+        changes: diff.changes,
+    }
+}
+
+/// A hashable coded index.
+#[derive(PartialEq, Eq, Hash)]
+struct CodedIndexKey(Option<TableId>, u32);
+
+impl From<CodedIndex> for CodedIndexKey {
+    fn from(index: CodedIndex) -> Self {
+        Self(index.table, index.row)
+    }
+}
+
+/// Collects changes between snapshots.
+#[derive(Default)]
+struct Differ {
+    changes: Vec<Change>,
+}
+
+impl Differ {
+    fn push(&mut self, kind: ChangeKind, entity: EntityKind, name: &str) {
+        self.changes.push(Change {
+            kind,
+            entity,
+            name: name.to_string(),
+            aspect: None,
+            old: None,
+            new: None,
+        });
+    }
+
+    fn changed(
+        &mut self,
+        entity: EntityKind,
+        name: &str,
+        aspect: &'static str,
+        old: &str,
+        new: &str,
+    ) {
+        let value = |s: &str| (!s.is_empty()).then(|| s.to_string());
+        self.changes.push(Change {
+            kind: ChangeKind::Changed,
+            entity,
+            name: name.to_string(),
+            aspect: Some(aspect),
+            old: value(old),
+            new: value(new),
+        });
+    }
+
+    /// Compare the aspects and attributes of a matched entity.
+    fn entity(&mut self, entity: EntityKind, name: &str, old: &Details, new: &Details) {
+        for ((aspect, a), (_, b)) in old.aspects.iter().zip(&new.aspects) {
+            if a != b {
+                self.changed(entity, name, aspect, a, b);
+            }
+        }
+
+        // Attributes are sorted, so a merge finds those on one side only
+        let (mut i, mut j) = (0, 0);
+        loop {
+            let (kind, attribute) = match (old.attributes.get(i), new.attributes.get(j)) {
+                (Some(a), Some(b)) if a == b => {
+                    i += 1;
+                    j += 1;
+                    continue;
+                }
+                (Some(a), Some(b)) if a < b => {
+                    i += 1;
+                    (ChangeKind::Removed, a)
+                }
+                (Some(a), None) => {
+                    i += 1;
+                    (ChangeKind::Removed, a)
+                }
+                (_, Some(b)) => {
+                    j += 1;
+                    (ChangeKind::Added, b)
+                }
+                (None, None) => break,
+            };
+            let (old, new) = match kind {
+                ChangeKind::Removed => (Some(attribute.clone()), None),
+                _ => (None, Some(attribute.clone())),
+            };
+            self.changes.push(Change {
+                kind,
+                entity: EntityKind::Attribute,
+                name: name.to_string(),
+                aspect: None,
+                old,
+                new,
+            });
+        }
+    }
+
+    /// Compare entities matched by name.
+    fn map(
+        &mut self,
+        entity: EntityKind,
+        old: &BTreeMap<String, Details>,
+        new: &BTreeMap<String, Details>,
+    ) {
+        let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        for name in names {
+            match (old.get(name), new.get(name)) {
+                (Some(a), Some(b)) => self.entity(entity, name, a, b),
+                (Some(_), None) => self.push(ChangeKind::Removed, entity, name),
+                (None, Some(_)) => self.push(ChangeKind::Added, entity, name),
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// Compare the members of a matched type.
+    ///
+    /// Members are matched by signature. A member left over on both sides
+    /// that is the only one of its kind with its name is reported as a
+    /// signature change rather than a removal and an addition.
+    fn members(&mut self, type_name: &str, old: &[Member], new: &[Member]) {
+        let mut by_signature: HashMap<(EntityKind, &str), Vec<usize>> = HashMap::new();
+        for (j, member) in new.iter().enumerate().rev() {
+            by_signature
+                .entry((member.entity, &member.signature))
+                .or_default()
+                .push(j);
+        }
+        let mut pairs = Vec::new();
+        let mut removed = Vec::new();
+        for (i, member) in old.iter().enumerate() {
+            match by_signature
+                .get_mut(&(member.entity, member.signature.as_str()))
+                .and_then(Vec::pop)
+            {
+                Some(j) => pairs.push((i, j)),
+                None => removed.push(i),
+            }
+        }
+        let mut added: Vec<usize> = by_signature.into_values().flatten().collect();
+        added.sort_unstable();
+
+        let key = |member: &Member| (member.entity, member.name.clone());
+        let mut old_names: HashMap<_, usize> = HashMap::new();
+        for &i in &removed {
+            *old_names.entry(key(&old[i])).or_default() += 1;
+        }
+        let mut new_names: HashMap<_, usize> = HashMap::new();
+        for &j in &added {
+            *new_names.entry(key(&new[j])).or_default() += 1;
+        }
+        let unique =
+            |names: &HashMap<_, usize>, member: &Member| names.get(&key(member)) == Some(&1);
+        let mut renamed = Vec::new();
+        removed.retain(|&i| {
+            if !unique(&old_names, &old[i]) {
+                return true;
+            }
+            match added
+                .iter()
+                .position(|&j| unique(&new_names, &new[j]) && key(&new[j]) == key(&old[i]))
+            {
+                Some(k) => {
+                    renamed.push((i, added.remove(k)));
+                    false
+                }
+                None => true,
+            }
+        });
+
+        for &i in &removed {
+            let name = format!("{type_name}::{}", old[i].signature);
+            self.push(ChangeKind::Removed, old[i].entity, &name);
+        }
+        for &(i, j) in &renamed {
+            let name = format!("{type_name}::{}", new[j].signature);
+            self.changed(
+                new[j].entity,
+                &name,
+                "signature",
+                &old[i].signature,
+                &new[j].signature,
+            );
+        }
+        pairs.extend(renamed);
+        pairs.sort_unstable();
+        for (i, j) in pairs {
+            let name = format!("{type_name}::{}", new[j].signature);
+            self.entity(new[j].entity, &name, &old[i].details, &new[j].details);
+        }
+        for j in added {
+            let name = format!("{type_name}::{}", new[j].signature);
+            self.push(ChangeKind::Added, new[j].entity, &name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ilasm::assemble;
+
+    const V1: &str = r#"
+        .assembly extern mscorlib { .ver 4:0:0:0 }
+        .assembly Lib { .ver 1:0:0:0 }
+        .namespace Lib {
+            .class public Widget extends [mscorlib]System.Object {
+                .field public int32 count
+                .field public static literal int32 Max = int32(10)
+                .method public instance void Resize(int32 w) { ret }
+                .method public instance void Old() { ret }
+                .method public static void Parse(int32 a) { ret }
+                .method public static void Parse(string a) { ret }
+                .class nested public Part extends [mscorlib]System.Object {}
+            }
+            .class public Gone extends [mscorlib]System.Object {}
+        }
+    "#;
+
+    const V2: &str = r#"
+        .assembly extern mscorlib { .ver 4:0:0:0 }
+        .assembly Lib { .ver 2:0:0:0 }
+        .namespace Lib {
+            .class public Widget extends [mscorlib]System.Object {
+                .custom instance void [mscorlib]System.ObsoleteAttribute::.ctor() = ( 01 00 00 00 )
+                .field private int32 count
+                .field public static literal int32 Max = int32(20)
+                .method public instance void Resize(int32 w, int32 h) { ret }
+                .method public static void Parse(string a) { ret }
+                .method public static void Parse(int32 a) { ret }
+                .method public static void Parse(int64 a) { ret }
+                .class nested public Part extends [mscorlib]System.Object {}
+            }
+            .class public Added extends [mscorlib]System.Object {}
+        }
+    "#;
+
+    #[test]
+    fn test_diff() {
+        let v1 = assemble(V1).unwrap().metadata;
+        let v2 = assemble(V2).unwrap().metadata;
+        assert!(v1.diff(&v1).is_empty());
+
+        let diff = v1.diff(&v2);
+        let lines: Vec<String> = diff.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "~ assembly Lib: version 1.0.0.0 -> 2.0.0.0",
+                "+ type Lib.Added",
+                "- type Lib.Gone",
+                "+ attribute Lib.Widget: [mscorlib]System.ObsoleteAttribute() = ( 01 00 00 00 )",
+                "- method Lib.Widget::instance void Old()",
+                "~ method Lib.Widget::instance void Resize(int32, int32): signature \
+                 instance void Resize(int32) -> instance void Resize(int32, int32)",
+                "~ field Lib.Widget::int32 count: flags 0x0006 -> 0x0001",
+                "~ field Lib.Widget::int32 Max: value 10 -> 20",
+                "+ method Lib.Widget::void Parse(int64)",
+            ]
+        );
+        assert_eq!(diff.to_string().lines().count(), lines.len());
+
+        let json = diff.to_json();
+        assert!(json.starts_with(
+            "{\"changes\":[{\"kind\":\"changed\",\"entity\":\"assembly\",\"name\":\"Lib\",\
+             \"aspect\":\"version\",\"old\":\"1.0.0.0\",\"new\":\"2.0.0.0\"},"
+        ));
+        assert!(json.contains("\"kind\":\"added\",\"entity\":\"type\",\"name\":\"Lib.Added\","));
+    }
+
+    #[test]
+    fn test_diff_assembly_refs_by_identity() {
+        // Two references to different versions of the same assembly
+        let version = |major: u16| {
+            let mut md = assemble(".assembly extern Dep { .ver 1:0:0:0 }\n.assembly Lib {}\n")
+                .unwrap()
+                .metadata;
+            let mut reference = md.assembly_refs[0].clone();
+            reference.major_version = major;
+            md.assembly_refs.push(reference);
+            md
+        };
+        let lines: Vec<String> = version(2)
+            .diff(&version(3))
+            .changes
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "- assembly-ref Dep, Version=2.0.0.0, Culture=neutral, PublicKeyToken=null",
+                "+ assembly-ref Dep, Version=3.0.0.0, Culture=neutral, PublicKeyToken=null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_assembly_ref_version() {
+        let version = |ver: &str| {
+            assemble(&format!(
+                ".assembly extern Dep {{ .ver {ver} }}\n.assembly Lib {{}}\n"
+            ))
+            .unwrap()
+            .metadata
+        };
+        let lines: Vec<String> = version("1:0:0:0")
+            .diff(&version("2:0:0:0"))
+            .changes
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "~ assembly-ref Dep, Culture=neutral, PublicKeyToken=null: version 1.0.0.0 -> 2.0.0.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_type_sig_text() {
+        let md = assemble(".assembly Lib {}\n").unwrap().metadata;
+        let module = CodedIndex {
+            table: Some(TableId::TypeDef),
+            row: 1,
+        };
+        let point = TypeSig::ValueType(module.encode(CodedIndexKind::TypeDefOrRef));
+        assert_eq!(md.type_sig_text(&point, false), "valuetype <Module>");
+        let array = |sizes: Vec<u32>, lo_bounds: Vec<i32>| TypeSig::Array {
+            element_type: Box::new(TypeSig::Primitive(ElementType::I4)),
+            rank: 2,
+            sizes,
+            lo_bounds,
+        };
+        assert_eq!(md.type_sig_text(&array(vec![], vec![]), false), "int32[,]");
+        assert_eq!(
+            md.type_sig_text(&array(vec![10, 5], vec![0, -2]), false),
+            "int32[0...9,-2...2]"
+        );
+        assert_eq!(
+            md.type_sig_text(&array(vec![10], vec![1]), false),
+            "int32[1...10,]"
+        );
+        assert_eq!(
+            md.type_sig_text(&array(vec![4], vec![]), false),
+            "int32[4,]"
+        );
+        assert_eq!(
+            md.type_sig_text(&array(vec![], vec![3, 3]), false),
+            "int32[3...,3...]"
+        );
+    }
+
+    #[test]
+    fn test_diff_moved_type() {
+        let version = |scope: &str| {
+            let source = format!(
+                ".assembly extern Old {{}}\n.assembly extern New {{}}\n.assembly Lib {{}}\n\
+                 .class public Lib.Widget extends [{scope}]Base.Shape {{\n\
+                     .field public class [{scope}]Base.Shape shape\n\
+                 }}\n"
+            );
+            assemble(&source).unwrap().metadata
+        };
+        let lines: Vec<String> = version("Old")
+            .diff(&version("New"))
+            .changes
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "~ type Lib.Widget: base type [Old]Base.Shape -> [New]Base.Shape",
+                "~ field Lib.Widget::class [New]Base.Shape shape: signature \
+                 class [Old]Base.Shape shape -> class [New]Base.Shape shape",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_resource_data() {
+        let version = |data: &[u8]| {
+            let mut md = assemble(".assembly Lib {}\n").unwrap().metadata;
+            let mut resources = Vec::new();
+            md.add_embedded_resource(&mut resources, "Lib.data", 0x1, data)
+                .unwrap();
+            md.add_embedded_resource(&mut resources, "Lib.same", 0x1, b"same")
+                .unwrap();
+            (md, resources)
+        };
+        let (v1, r1) = version(b"abc");
+        let (v2, r2) = version(b"abd");
+        assert!(v1.diff(&v2).is_empty());
+        assert!(v1.diff_with_resources(&r1, &v1, &r1).is_empty());
+
+        let diff = v1.diff_with_resources(&r1, &v2, &r2);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].name, "Lib.data");
+        assert_eq!(diff.changes[0].aspect, Some("sha256"));
+
+        let (v3, r3) = version(b"abcd");
+        let lines: Vec<String> = v1
+            .diff_with_resources(&r1, &v3, &r3)
+            .changes
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "~ resource Lib.data: size 3 -> 4");
+    }
+
+    #[test]
+    fn test_diff_ignores_row_order() {
+        let v1 = assemble(V1).unwrap().metadata;
+        let mut rewritten = Metadata::parse(&v1.write()).unwrap();
+        rewritten.compact_heaps().unwrap();
+        assert!(v1.diff(&rewritten).is_empty());
+
+        // The same declarations with types, members and attributes in
+        // another order get different rows
+        let source = |order: [usize; 3]| {
+            let attributes = [
+                ".custom instance void [mscorlib]System.CLSCompliantAttribute::.ctor(bool) = { bool(true) }",
+                ".custom instance void [mscorlib]System.Reflection.AssemblyTitleAttribute::.ctor(string) = { string('Lib') }",
+                ".custom instance void [mscorlib]System.ObsoleteAttribute::.ctor() = ( 01 00 00 00 )",
+            ];
+            let members = [
+                ".field public int32 count",
+                ".method public instance void Resize(int32 w) { ret }",
+                ".method public static void Parse(string a) { ret }",
+            ];
+            let types = [
+                ".class public Widget extends [mscorlib]System.Object { MEMBERS }",
+                ".class public Gadget extends [mscorlib]System.Object { ATTRIBUTES }",
+                ".class public Gizmo extends [mscorlib]System.Object {}",
+            ];
+            let pick = |items: [&str; 3]| order.map(|i| items[i]).join("\n");
+            format!(
+                ".assembly extern mscorlib {{}}\n.assembly Lib {{ {} }}\n.namespace Lib {{ {} }}",
+                pick(attributes),
+                pick(types)
+                    .replace("MEMBERS", &pick(members))
+                    .replace("ATTRIBUTES", &pick(attributes)),
+            )
+        };
+        let a = assemble(&source([0, 1, 2])).unwrap().metadata;
+        let b = assemble(&source([2, 0, 1])).unwrap().metadata;
+        assert_ne!(a.type_def_full_name(2), b.type_def_full_name(2));
+        assert_ne!(
+            a.method_text(&a.method_defs[0], true),
+            b.method_text(&b.method_defs[0], true)
+        );
+        assert_ne!(
+            a.attribute_text(&a.custom_attributes[0], true),
+            b.attribute_text(&b.custom_attributes[0], true)
+        );
+        assert!(a.diff(&b).is_empty());
+
+        // Custom attribute rows need not be sorted
+        let mut reversed = b.clone();
+        reversed.custom_attributes.reverse();
+        assert!(a.diff(&reversed).is_empty());
+
+        let mut json = String::new();
+        push_json(&mut json, Some("a\"b\\c\n\u{1}"));
+        assert_eq!(json, "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
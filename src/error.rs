@@ -74,7 +74,52 @@ pub enum Error {
         max: u32,
     },
 
+    /// Invalid managed resource data.
+    #[error("invalid resource data at offset {0}")]
+    InvalidResourceData(usize),
+
+    /// Malformed or unsupported PE image.
+    #[error("invalid PE image: {0}")]
+    InvalidImage(&'static str),
+
+    /// I/O error while loading an assembly.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Malformed assembly display name.
+    #[error("invalid assembly name: {0}")]
+    InvalidAssemblyName(String),
+
+    /// Malformed reflection type name.
+    #[error("invalid type name: {0}")]
+    InvalidTypeName(String),
+
+    /// File table name that is not a plain file name.
+    #[error("invalid file name: {0}")]
+    InvalidFileName(String),
+
+    /// Malformed or unsupported strong-name key.
+    #[error("invalid strong name key: {0}")]
+    InvalidStrongNameKey(&'static str),
+
+    /// Hash algorithm ID that is unknown or not allowed in this context.
+    #[error("unsupported hash algorithm: 0x{0:04X}")]
+    UnsupportedHashAlgorithm(u32),
+
+    /// Metadata that cannot be turned into a reference assembly.
+    #[error("cannot create reference assembly: {0}")]
+    ReferenceAssembly(&'static str),
+
     /// Metadata validation error.
     #[error("validation error: {0}")]
     ValidationError(String),
+
+    /// ILAsm syntax or semantic error.
+    #[error("ilasm error at line {line}: {message}")]
+    IlAsm {
+        /// Source line (1-based, 0 if unknown).
+        line: usize,
+        /// Error description.
+        message: String,
+    },
 }
@@ -0,0 +1,362 @@
+//! Exported types and type forwarders (ExportedType table).
+//!
+//! ECMA-335 II.22.14 lists types that an assembly makes available but does not
+//! define in its manifest module: types in other modules of a multi-module
+//! assembly, and type forwarders pointing at the assembly that now defines the
+//! type. Facade assemblies such as `netstandard.dll` consist almost entirely
+//! of forwarders, often to other facades.
+
+use crate::metadata::Metadata;
+use crate::tables::TableId;
+
+/// TypeAttributes flag marking an ExportedType as a type forwarder.
+pub const TYPE_FORWARDER_FLAG: u32 = 0x0020_0000;
+
+/// Where an exported type is implemented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportedTypeImplementation {
+    /// Another assembly (a type forwarder).
+    AssemblyRef {
+        /// 1-based AssemblyRef row.
+        index: u32,
+        /// Referenced assembly name.
+        name: String,
+    },
+    /// Another module of this assembly.
+    File {
+        /// 1-based File row.
+        index: u32,
+        /// Module file name.
+        name: String,
+    },
+    /// Nested in another exported type.
+    ExportedType(u32),
+}
+
+/// High-level exported type information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedTypeInfo {
+    /// 1-based ExportedType row.
+    pub index: u32,
+    /// Type name.
+    pub name: String,
+    /// Namespace (None if empty).
+    pub namespace: Option<String>,
+    /// Type attributes/flags.
+    pub flags: u32,
+    /// TypeDef token hint in the implementing module (may be 0).
+    pub type_def_id: u32,
+    /// Where the type is implemented.
+    pub implementation: ExportedTypeImplementation,
+    /// Whether the type is forwarded to another assembly, directly or through
+    /// its enclosing exported type.
+    pub is_forwarder: bool,
+}
+
+impl ExportedTypeInfo {
+    /// Get the full name (namespace.name or just name).
+    #[must_use]
+    pub fn full_name(&self) -> String {
+        match &self.namespace {
+            Some(ns) if !ns.is_empty() => format!("{ns}.{}", self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+impl Metadata {
+    /// Get all exported types.
+    pub fn exported_types(&self) -> Vec<ExportedTypeInfo> {
+        (1..=self.exported_types.len() as u32)
+            .filter_map(|index| self.get_exported_type(index))
+            .collect()
+    }
+
+    /// Get the exported type at the given 1-based index.
+    #[must_use]
+    pub fn get_exported_type(&self, index: u32) -> Option<ExportedTypeInfo> {
+        let row = self.exported_types.get(index.checked_sub(1)? as usize)?;
+        let name = self.strings.get(row.type_name).ok()?.to_string();
+        let namespace = match self.strings.get(row.type_namespace).ok()? {
+            "" => None,
+            namespace => Some(namespace.to_string()),
+        };
+        let implementation = match row.implementation.table? {
+            TableId::AssemblyRef => ExportedTypeImplementation::AssemblyRef {
+                index: row.implementation.row,
+                name: self.get_assembly_ref(row.implementation.row)?.name,
+            },
+            TableId::File => {
+                let file = self
+                    .files
+                    .get(row.implementation.row.checked_sub(1)? as usize)?;
+                ExportedTypeImplementation::File {
+                    index: row.implementation.row,
+                    name: self.strings.get(file.name).ok()?.to_string(),
+                }
+            }
+            TableId::ExportedType => {
+                ExportedTypeImplementation::ExportedType(row.implementation.row)
+            }
+            _ => return None,
+        };
+
+        Some(ExportedTypeInfo {
+            index,
+            name,
+            namespace,
+            flags: row.flags,
+            type_def_id: row.type_def_id,
+            is_forwarder: self.is_forwarder(index),
+            implementation,
+        })
+    }
+
+    /// Find a top-level exported type by namespace and name.
+    #[must_use]
+    pub fn find_exported_type(&self, namespace: &str, name: &str) -> Option<ExportedTypeInfo> {
+        let position = self.exported_types.iter().position(|row| {
+            row.implementation.table != Some(TableId::ExportedType)
+                && self.strings.get(row.type_name).ok() == Some(name)
+                && self.strings.get(row.type_namespace).ok() == Some(namespace)
+        })?;
+        self.get_exported_type(position as u32 + 1)
+    }
+
+    /// Get the type forwarders, including types nested in forwarded types.
+    pub fn type_forwarders(&self) -> Vec<ExportedTypeInfo> {
+        self.exported_types()
+            .into_iter()
+            .filter(|exported| exported.is_forwarder)
+            .collect()
+    }
+
+    fn is_forwarder(&self, mut index: u32) -> bool {
+        // Bounded in case of a cycle through the Implementation column
+        for _ in 0..=self.exported_types.len() {
+            let Some(row) = index
+                .checked_sub(1)
+                .and_then(|i| self.exported_types.get(i as usize))
+            else {
+                return false;
+            };
+            match row.implementation.table {
+                Some(TableId::AssemblyRef) => return true,
+                Some(TableId::ExportedType) => index = row.implementation.row,
+                _ => return false,
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::{AssemblyRefRow, CodedIndex, ExportedTypeRow, FileRow};
+
+    fn exported(md: &mut Metadata, namespace: &str, name: &str, implementation: CodedIndex) {
+        let type_name = md.strings.add(name);
+        let type_namespace = if namespace.is_empty() {
+            0
+        } else {
+            md.strings.add(namespace)
+        };
+        let flags = if implementation.table == Some(TableId::AssemblyRef) {
+            TYPE_FORWARDER_FLAG
+        } else {
+            0x0000_0002 // nested public
+        };
+        md.exported_types.push(ExportedTypeRow {
+            flags,
+            type_def_id: 0,
+            type_name,
+            type_namespace,
+            implementation,
+        });
+    }
+
+    #[test]
+    fn test_exported_types() {
+        let mut md = Metadata::new();
+        let name = md.strings.add("System.Runtime");
+        md.assembly_refs.push(AssemblyRefRow {
+            name,
+            ..Default::default()
+        });
+        let name = md.strings.add("Other.netmodule");
+        md.files.push(FileRow {
+            flags: 0,
+            name,
+            hash_value: 0,
+        });
+
+        exported(
+            &mut md,
+            "System",
+            "Environment",
+            CodedIndex {
+                table: Some(TableId::AssemblyRef),
+                row: 1,
+            },
+        );
+        exported(
+            &mut md,
+            "",
+            "SpecialFolder",
+            CodedIndex {
+                table: Some(TableId::ExportedType),
+                row: 1,
+            },
+        );
+        exported(
+            &mut md,
+            "Sample",
+            "InOtherModule",
+            CodedIndex {
+                table: Some(TableId::File),
+                row: 1,
+            },
+        );
+
+        let types = md.exported_types();
+        assert_eq!(types.len(), 3);
+        assert_eq!(types[0].full_name(), "System.Environment");
+        assert_eq!(
+            types[0].implementation,
+            ExportedTypeImplementation::AssemblyRef {
+                index: 1,
+                name: "System.Runtime".into()
+            }
+        );
+        assert!(types[0].is_forwarder);
+        assert_eq!(
+            types[1].implementation,
+            ExportedTypeImplementation::ExportedType(1)
+        );
+        assert!(types[1].is_forwarder);
+        assert_eq!(types[1].namespace, None);
+        assert!(!types[2].is_forwarder);
+        assert!(matches!(
+            &types[2].implementation,
+            ExportedTypeImplementation::File { name, .. } if name == "Other.netmodule"
+        ));
+
+        assert_eq!(md.type_forwarders().len(), 2);
+        assert_eq!(
+            md.find_exported_type("System", "Environment")
+                .unwrap()
+                .index,
+            1
+        );
+        // Nested types are not found as top-level types
+        assert!(md.find_exported_type("", "SpecialFolder").is_none());
+    }
+
+    fn implemented_by(table: TableId, row: u32) -> CodedIndex {
+        CodedIndex {
+            table: Some(table),
+            row,
+        }
+    }
+
+    #[test]
+    fn test_nested_exported_types() {
+        let mut md = Metadata::new();
+        let name = md.strings.add("System.Runtime");
+        md.assembly_refs.push(AssemblyRefRow {
+            name,
+            ..Default::default()
+        });
+        let name = md.strings.add("Other.netmodule");
+        md.files.push(FileRow {
+            flags: 0,
+            name,
+            hash_value: 0,
+        });
+
+        // Outer -> Middle -> Inner, forwarded through the outermost row
+        exported(
+            &mut md,
+            "System",
+            "Outer",
+            implemented_by(TableId::AssemblyRef, 1),
+        );
+        exported(
+            &mut md,
+            "",
+            "Middle",
+            implemented_by(TableId::ExportedType, 1),
+        );
+        exported(
+            &mut md,
+            "",
+            "Inner",
+            implemented_by(TableId::ExportedType, 2),
+        );
+        // A type in another module of this assembly, with a nested type
+        exported(&mut md, "Sample", "Local", implemented_by(TableId::File, 1));
+        exported(
+            &mut md,
+            "",
+            "LocalNested",
+            implemented_by(TableId::ExportedType, 4),
+        );
+
+        let types = md.exported_types();
+        assert_eq!(types.len(), 5);
+        assert!(types[..3].iter().all(|exported| exported.is_forwarder));
+        assert_eq!(
+            types[2].implementation,
+            ExportedTypeImplementation::ExportedType(2)
+        );
+        assert_eq!(types[2].full_name(), "Inner");
+
+        assert!(!types[3].is_forwarder);
+        assert_eq!(
+            types[3].implementation,
+            ExportedTypeImplementation::File {
+                index: 1,
+                name: "Other.netmodule".into()
+            }
+        );
+        assert!(!types[4].is_forwarder);
+
+        let forwarders: Vec<_> = md
+            .type_forwarders()
+            .iter()
+            .map(|exported| exported.index)
+            .collect();
+        assert_eq!(forwarders, [1, 2, 3]);
+        assert_eq!(md.find_exported_type("Sample", "Local").unwrap().index, 4);
+        assert!(md.find_exported_type("", "LocalNested").is_none());
+    }
+
+    #[test]
+    fn test_exported_type_cycle() {
+        let mut md = Metadata::new();
+        exported(&mut md, "", "A", implemented_by(TableId::ExportedType, 2));
+        exported(&mut md, "", "B", implemented_by(TableId::ExportedType, 1));
+        exported(&mut md, "", "C", implemented_by(TableId::ExportedType, 3));
+        // Pointing past the end of the table
+        exported(&mut md, "", "D", implemented_by(TableId::ExportedType, 9));
+
+        let types = md.exported_types();
+        assert_eq!(types.len(), 4);
+        assert!(types.iter().all(|exported| !exported.is_forwarder));
+        assert!(md.type_forwarders().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_implementation() {
+        let mut md = Metadata::new();
+        // Missing AssemblyRef and File rows
+        exported(&mut md, "", "A", implemented_by(TableId::AssemblyRef, 1));
+        exported(&mut md, "", "B", implemented_by(TableId::File, 1));
+        exported(&mut md, "", "C", implemented_by(TableId::TypeDef, 1));
+
+        assert!(md.exported_types().is_empty());
+        assert!(md.get_exported_type(0).is_none());
+        assert!(md.get_exported_type(4).is_none());
+    }
+}
@@ -0,0 +1,252 @@
+//! Files of multi-file assemblies (File table).
+//!
+//! ECMA-335 II.22.19 lists the other files of an assembly: further modules
+//! and plain resource files. Each row carries a hash of the file contents,
+//! computed with the algorithm named by `Assembly.HashAlgId`, so a loader can
+//! check that the files next to the manifest are the ones it was built with.
+
+use std::path::{Component, Path};
+
+use crate::crypto::HashAlgorithm;
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+
+/// FileAttributes flag marking a file that is not a module.
+pub const FILE_CONTAINS_NO_METADATA: u32 = 0x0001;
+
+/// High-level File table information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    /// 1-based File row.
+    pub index: u32,
+    /// File name, relative to the manifest module.
+    pub name: String,
+    /// File attributes/flags.
+    pub flags: u32,
+    /// Hash of the file contents.
+    pub hash_value: Vec<u8>,
+}
+
+impl FileInfo {
+    /// Whether the file is a module with its own metadata.
+    #[must_use]
+    pub fn contains_metadata(&self) -> bool {
+        self.flags & FILE_CONTAINS_NO_METADATA == 0
+    }
+}
+
+impl Metadata {
+    /// Get all files of the assembly.
+    pub fn files(&self) -> Vec<FileInfo> {
+        (1..=self.files.len() as u32)
+            .filter_map(|index| self.get_file(index))
+            .collect()
+    }
+
+    /// Get the file at the given 1-based index.
+    #[must_use]
+    pub fn get_file(&self, index: u32) -> Option<FileInfo> {
+        let row = self.files.get(index.checked_sub(1)? as usize)?;
+        let hash_value = if row.hash_value != 0 {
+            self.blobs.get(row.hash_value).ok()?.to_vec()
+        } else {
+            Vec::new()
+        };
+        Some(FileInfo {
+            index,
+            name: self.strings.get(row.name).ok()?.to_string(),
+            flags: row.flags,
+            hash_value,
+        })
+    }
+
+    /// Find a file by name, ignoring ASCII case as file systems commonly do.
+    #[must_use]
+    pub fn find_file(&self, name: &str) -> Option<FileInfo> {
+        let position = self.files.iter().position(|row| {
+            self.strings
+                .get(row.name)
+                .is_ok_and(|file| file.eq_ignore_ascii_case(name))
+        })?;
+        self.get_file(position as u32 + 1)
+    }
+
+    /// Get the algorithm that file hashes are computed with.
+    ///
+    /// Defaults to SHA-1 without an Assembly row, as for a stand-alone
+    /// module. Returns `None` if `HashAlgId` names an unknown algorithm.
+    #[must_use]
+    pub fn hash_algorithm(&self) -> Option<HashAlgorithm> {
+        match self.assemblies.first() {
+            Some(row) => HashAlgorithm::from_alg_id(row.hash_alg_id),
+            None => Some(HashAlgorithm::Sha1),
+        }
+    }
+
+    /// Check the contents of the file at the given 1-based index against its
+    /// recorded hash.
+    pub fn verify_file_hash(&self, index: u32, contents: &[u8]) -> Result<bool> {
+        let file = self.get_file(index).ok_or(Error::RowIndexOutOfBounds {
+            table: "File",
+            index,
+            max: self.files.len() as u32,
+        })?;
+        let algorithm = self.file_hash_algorithm()?;
+        Ok(algorithm.hash(contents) == file.hash_value)
+    }
+
+    /// Check every file of the assembly against its recorded hash, reading
+    /// the files from `directory` (normally the manifest module's directory).
+    ///
+    /// Fails if a file cannot be read, or a name is not a plain file name
+    /// (such as `../secret` or an absolute path) and would escape `directory`.
+    pub fn verify_file_hashes(&self, directory: &Path) -> Result<Vec<(FileInfo, bool)>> {
+        let algorithm = self.file_hash_algorithm()?;
+        self.files()
+            .into_iter()
+            .map(|file| {
+                let mut components = Path::new(&file.name).components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(name)), None) if name == file.name.as_str() => {}
+                    _ => return Err(Error::InvalidFileName(file.name)),
+                }
+                let contents = std::fs::read(directory.join(&file.name))?;
+                let valid = algorithm.hash(&contents) == file.hash_value;
+                Ok((file, valid))
+            })
+            .collect()
+    }
+
+    fn file_hash_algorithm(&self) -> Result<HashAlgorithm> {
+        self.hash_algorithm().ok_or_else(|| {
+            Error::UnsupportedHashAlgorithm(
+                self.assemblies.first().map_or(0, |row| row.hash_alg_id),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tables::{AssemblyRow, FileRow};
+    use std::path::PathBuf;
+
+    /// A directory under the system temp directory, removed when dropped so
+    /// that failing tests do not leave it behind.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("clrmeta-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn add_file(md: &mut Metadata, name: &str, flags: u32, contents: &[u8]) {
+        let algorithm = md.hash_algorithm().unwrap();
+        let name = md.strings.add(name);
+        let hash_value = md.blobs.add(&algorithm.hash(contents));
+        md.files.push(FileRow {
+            flags,
+            name,
+            hash_value,
+        });
+    }
+
+    #[test]
+    fn test_file_hashes() {
+        let mut md = Metadata::new();
+        assert_eq!(md.hash_algorithm(), Some(HashAlgorithm::Sha1));
+        let name = md.strings.add("Multi");
+        md.assemblies.push(AssemblyRow {
+            hash_alg_id: HashAlgorithm::Sha256.alg_id(),
+            name,
+            ..Default::default()
+        });
+        assert_eq!(md.hash_algorithm(), Some(HashAlgorithm::Sha256));
+
+        add_file(&mut md, "Part.netmodule", 0, b"module bytes");
+        add_file(
+            &mut md,
+            "Strings.txt",
+            FILE_CONTAINS_NO_METADATA,
+            b"resource",
+        );
+
+        let files = md.files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name, "Part.netmodule");
+        assert!(files[0].contains_metadata());
+        assert!(!files[1].contains_metadata());
+        assert_eq!(files[1].hash_value.len(), 32);
+        assert_eq!(md.find_file("part.NETMODULE").unwrap().index, 1);
+        assert!(md.find_file("Missing.dll").is_none());
+
+        assert!(md.verify_file_hash(1, b"module bytes").unwrap());
+        assert!(!md.verify_file_hash(1, b"tampered bytes").unwrap());
+        assert!(md.verify_file_hash(2, b"resource").unwrap());
+        assert!(matches!(
+            md.verify_file_hash(3, b""),
+            Err(Error::RowIndexOutOfBounds { .. })
+        ));
+
+        let directory = TempDir::new("files");
+        std::fs::write(directory.path().join("Part.netmodule"), b"module bytes").unwrap();
+        std::fs::write(directory.path().join("Strings.txt"), b"changed").unwrap();
+        let results = md.verify_file_hashes(directory.path()).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|(file, valid)| (file.name.as_str(), *valid))
+                .collect::<Vec<_>>(),
+            [("Part.netmodule", true), ("Strings.txt", false)]
+        );
+
+        // A missing file is an I/O error
+        let missing = directory.path().join("missing");
+        assert!(matches!(md.verify_file_hashes(&missing), Err(Error::Io(_))));
+
+        md.assemblies[0].hash_alg_id = 0x1234;
+        assert_eq!(md.hash_algorithm(), None);
+        assert!(matches!(
+            md.verify_file_hash(1, b"module bytes"),
+            Err(Error::UnsupportedHashAlgorithm(0x1234))
+        ));
+    }
+
+    #[test]
+    fn test_file_names_stay_in_directory() {
+        let directory = std::env::temp_dir();
+        for name in [
+            "../secret.dll",
+            "/etc/passwd",
+            "sub/Part.netmodule",
+            ".",
+            "",
+            "Part/",
+        ] {
+            let mut md = Metadata::new();
+            add_file(&mut md, name, 0, b"");
+            assert!(
+                matches!(
+                    md.verify_file_hashes(&directory),
+                    Err(Error::InvalidFileName(ref file)) if file == name
+                ),
+                "{name}"
+            );
+        }
+    }
+}
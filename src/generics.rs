@@ -0,0 +1,393 @@
+//! Generic instantiation and signature substitution.
+//!
+//! Members of a generic type are referenced through a MemberRef whose parent is
+//! a TypeSpec (e.g. `List<string>`), while the MemberRef signature itself is
+//! expressed in terms of the open type parameters (`!0`). Likewise a MethodSpec
+//! supplies the arguments for the method's own parameters (`!!0`). This module
+//! substitutes those arguments to obtain the concrete signature of a call site
+//! (ECMA-335 II.9.4, II.23.2.15).
+
+use crate::error::Result;
+use crate::metadata::Metadata;
+use crate::reader::Reader;
+use crate::signature::{FieldSig, MethodSig, MethodSpecSig, TypeSig};
+use crate::tables::TableId;
+
+/// Type and method arguments used to close `!n` and `!!n` in a signature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenericContext {
+    /// Arguments of the declaring type, substituted for `Var(n)`.
+    pub type_args: Vec<TypeSig>,
+    /// Arguments of the method, substituted for `MVar(n)`.
+    pub method_args: Vec<TypeSig>,
+}
+
+impl GenericContext {
+    /// Create a context from type and method arguments.
+    #[must_use]
+    pub fn new(type_args: Vec<TypeSig>, method_args: Vec<TypeSig>) -> Self {
+        Self {
+            type_args,
+            method_args,
+        }
+    }
+
+    /// Create a context from a generic type instantiation.
+    ///
+    /// Returns an empty context if `sig` is not a `GenericInst`.
+    #[must_use]
+    pub fn from_type_sig(sig: &TypeSig) -> Self {
+        match sig {
+            TypeSig::GenericInst { type_args, .. } => Self::new(type_args.clone(), Vec::new()),
+            _ => Self::default(),
+        }
+    }
+
+    /// Check whether the context carries no arguments.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.type_args.is_empty() && self.method_args.is_empty()
+    }
+
+    /// Substitute generic parameters in a type.
+    ///
+    /// Parameters without a corresponding argument are left open.
+    #[must_use]
+    pub fn substitute(&self, sig: &TypeSig) -> TypeSig {
+        let boxed = |inner: &TypeSig| Box::new(self.substitute(inner));
+        match sig {
+            TypeSig::Var(n) => self
+                .type_args
+                .get(*n as usize)
+                .cloned()
+                .unwrap_or(TypeSig::Var(*n)),
+            TypeSig::MVar(n) => self
+                .method_args
+                .get(*n as usize)
+                .cloned()
+                .unwrap_or(TypeSig::MVar(*n)),
+            TypeSig::SzArray(inner) => TypeSig::SzArray(boxed(inner)),
+            TypeSig::Ptr(inner) => TypeSig::Ptr(boxed(inner)),
+            TypeSig::ByRef(inner) => TypeSig::ByRef(boxed(inner)),
+            TypeSig::Pinned(inner) => TypeSig::Pinned(boxed(inner)),
+            TypeSig::Array {
+                element_type,
+                rank,
+                sizes,
+                lo_bounds,
+            } => TypeSig::Array {
+                element_type: boxed(element_type),
+                rank: *rank,
+                sizes: sizes.clone(),
+                lo_bounds: lo_bounds.clone(),
+            },
+            TypeSig::GenericInst {
+                is_value_type,
+                type_ref,
+                type_args,
+            } => TypeSig::GenericInst {
+                is_value_type: *is_value_type,
+                type_ref: *type_ref,
+                type_args: type_args.iter().map(|arg| self.substitute(arg)).collect(),
+            },
+            TypeSig::FnPtr(method) => TypeSig::FnPtr(Box::new(self.substitute_method_sig(method))),
+            TypeSig::Modified {
+                required,
+                modifier,
+                inner,
+            } => TypeSig::Modified {
+                required: *required,
+                modifier: *modifier,
+                inner: boxed(inner),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Substitute generic parameters in a method signature.
+    #[must_use]
+    pub fn substitute_method_sig(&self, sig: &MethodSig) -> MethodSig {
+        MethodSig {
+            calling_convention: sig.calling_convention,
+            generic_param_count: sig.generic_param_count,
+            return_type: self.substitute(&sig.return_type),
+            params: sig.params.iter().map(|p| self.substitute(p)).collect(),
+            sentinel: sig.sentinel,
+        }
+    }
+
+    /// Substitute generic parameters in a field signature.
+    #[must_use]
+    pub fn substitute_field_sig(&self, sig: &FieldSig) -> FieldSig {
+        FieldSig {
+            field_type: self.substitute(&sig.field_type),
+        }
+    }
+}
+
+impl Metadata {
+    /// Decode the signature of a TypeSpec (1-based index).
+    pub fn type_spec_signature(&self, index: u32) -> Option<Result<TypeSig>> {
+        let row = self.get_type_spec(index)?;
+        Some(
+            self.blobs
+                .get(row.signature)
+                .and_then(|data| TypeSig::parse(&mut Reader::new(data))),
+        )
+    }
+
+    /// Decode the instantiation of a MethodSpec (1-based index).
+    pub fn method_spec_signature(&self, index: u32) -> Option<Result<MethodSpecSig>> {
+        let row = self.method_specs.get(index.checked_sub(1)? as usize)?;
+        Some(
+            self.blobs
+                .get(row.instantiation)
+                .and_then(MethodSpecSig::parse_blob),
+        )
+    }
+
+    /// Get the generic context implied by a MemberRef's parent (1-based index).
+    ///
+    /// A TypeSpec parent that instantiates a generic type supplies the type
+    /// arguments; any other parent yields an empty context.
+    pub fn member_ref_generic_context(&self, index: u32) -> Option<Result<GenericContext>> {
+        let row = self.member_refs.get(index.checked_sub(1)? as usize)?;
+        if row.class.table != Some(TableId::TypeSpec) {
+            return Some(Ok(GenericContext::default()));
+        }
+        Some(
+            self.type_spec_signature(row.class.row)?
+                .map(|sig| GenericContext::from_type_sig(&sig)),
+        )
+    }
+
+    /// Get the concrete method signature of a MemberRef (1-based index).
+    ///
+    /// For `List<string>::Add`, the declared parameter `!0` becomes `string`.
+    pub fn member_ref_method_signature(&self, index: u32) -> Option<Result<MethodSig>> {
+        let row = self.member_refs.get(index.checked_sub(1)? as usize)?;
+        let context = match self.member_ref_generic_context(index)? {
+            Ok(context) => context,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(
+            self.blobs
+                .get(row.signature)
+                .and_then(MethodSig::parse_blob)
+                .map(|sig| context.substitute_method_sig(&sig)),
+        )
+    }
+
+    /// Get the concrete field signature of a MemberRef (1-based index).
+    pub fn member_ref_field_signature(&self, index: u32) -> Option<Result<FieldSig>> {
+        let row = self.member_refs.get(index.checked_sub(1)? as usize)?;
+        let context = match self.member_ref_generic_context(index)? {
+            Ok(context) => context,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(
+            self.blobs
+                .get(row.signature)
+                .and_then(FieldSig::parse_blob)
+                .map(|sig| context.substitute_field_sig(&sig)),
+        )
+    }
+
+    /// Get the concrete method signature of a MethodSpec (1-based index).
+    ///
+    /// Method arguments close `!!n`; if the generic method is a MemberRef on
+    /// a generic type instantiation, its type arguments close `!n` as well.
+    pub fn method_spec_method_signature(&self, index: u32) -> Option<Result<MethodSig>> {
+        let row = self.method_specs.get(index.checked_sub(1)? as usize)?;
+        let method_args = match self.method_spec_signature(index)? {
+            Ok(spec) => spec.type_args,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let (signature, type_args) = match row.method.table? {
+            TableId::MethodDef => {
+                let method = self
+                    .method_defs
+                    .get(row.method.row.checked_sub(1)? as usize)?;
+                (method.signature, Vec::new())
+            }
+            TableId::MemberRef => {
+                let member = self
+                    .member_refs
+                    .get(row.method.row.checked_sub(1)? as usize)?;
+                match self.member_ref_generic_context(row.method.row)? {
+                    Ok(context) => (member.signature, context.type_args),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            _ => return None,
+        };
+
+        let context = GenericContext::new(type_args, method_args);
+        Some(
+            self.blobs
+                .get(signature)
+                .and_then(MethodSig::parse_blob)
+                .map(|sig| context.substitute_method_sig(&sig)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{CallingConvention, ElementType};
+    use crate::tables::{CodedIndex, CodedIndexKind};
+    use crate::tables::{MemberRefRow, MethodSpecRow, TypeRefRow, TypeSpecRow};
+
+    fn type_def_or_ref(table: TableId, row: u32) -> u32 {
+        CodedIndex {
+            table: Some(table),
+            row,
+        }
+        .encode(CodedIndexKind::TypeDefOrRef)
+    }
+
+    fn string() -> TypeSig {
+        TypeSig::Primitive(ElementType::String)
+    }
+
+    /// Build `List<string>` with a MemberRef to `Add(!0)` and a MethodSpec of
+    /// `ConvertAll<int>(!0, !!0[])`.
+    fn list_of_string() -> Metadata {
+        let mut md = Metadata::new();
+        let type_name = md.strings.add("List`1");
+        let type_namespace = md.strings.add("System.Collections.Generic");
+        md.type_refs.push(TypeRefRow {
+            resolution_scope: CodedIndex::null(),
+            type_name,
+            type_namespace,
+        });
+
+        let instance = TypeSig::GenericInst {
+            is_value_type: false,
+            type_ref: type_def_or_ref(TableId::TypeRef, 1),
+            type_args: vec![string()],
+        };
+        let signature = md.blobs.add(&instance.to_blob());
+        md.type_specs.push(TypeSpecRow { signature });
+
+        let parent = CodedIndex {
+            table: Some(TableId::TypeSpec),
+            row: 1,
+        };
+        let add = MethodSig {
+            calling_convention: CallingConvention(CallingConvention::HAS_THIS),
+            generic_param_count: 0,
+            return_type: TypeSig::Primitive(ElementType::Void),
+            params: vec![TypeSig::Var(0)],
+            sentinel: None,
+        };
+        let name = md.strings.add("Add");
+        let signature = md.blobs.add(&add.to_blob());
+        md.member_refs.push(MemberRefRow {
+            class: parent,
+            name,
+            signature,
+        });
+
+        let convert = MethodSig {
+            calling_convention: CallingConvention(
+                CallingConvention::HAS_THIS | CallingConvention::GENERIC,
+            ),
+            generic_param_count: 1,
+            return_type: TypeSig::SzArray(Box::new(TypeSig::MVar(0))),
+            params: vec![TypeSig::Var(0)],
+            sentinel: None,
+        };
+        let name = md.strings.add("ConvertAll");
+        let signature = md.blobs.add(&convert.to_blob());
+        md.member_refs.push(MemberRefRow {
+            class: parent,
+            name,
+            signature,
+        });
+
+        let spec = MethodSpecSig {
+            type_args: vec![TypeSig::Primitive(ElementType::I4)],
+        };
+        let instantiation = md.blobs.add(&spec.to_blob());
+        md.method_specs.push(MethodSpecRow {
+            method: CodedIndex {
+                table: Some(TableId::MemberRef),
+                row: 2,
+            },
+            instantiation,
+        });
+        md
+    }
+
+    #[test]
+    fn test_substitute_nested() {
+        let context = GenericContext::new(vec![string()], vec![TypeSig::Class(5)]);
+        let open = TypeSig::GenericInst {
+            is_value_type: false,
+            type_ref: 9,
+            type_args: vec![
+                TypeSig::SzArray(Box::new(TypeSig::Var(0))),
+                TypeSig::ByRef(Box::new(TypeSig::MVar(0))),
+                TypeSig::Var(3),
+            ],
+        };
+        let closed = context.substitute(&open);
+        assert_eq!(
+            closed,
+            TypeSig::GenericInst {
+                is_value_type: false,
+                type_ref: 9,
+                type_args: vec![
+                    TypeSig::SzArray(Box::new(string())),
+                    TypeSig::ByRef(Box::new(TypeSig::Class(5))),
+                    // Out of range: left open
+                    TypeSig::Var(3),
+                ],
+            }
+        );
+        assert!(GenericContext::from_type_sig(&string()).is_empty());
+    }
+
+    #[test]
+    fn test_type_spec_signature() {
+        let md = list_of_string();
+        let sig = md.type_spec_signature(1).unwrap().unwrap();
+        assert_eq!(
+            GenericContext::from_type_sig(&sig).type_args,
+            vec![string()]
+        );
+        assert!(md.type_spec_signature(2).is_none());
+    }
+
+    #[test]
+    fn test_list_of_string_add() {
+        let md = list_of_string();
+        let sig = md.member_ref_method_signature(1).unwrap().unwrap();
+        assert!(sig.calling_convention.has_this());
+        assert_eq!(sig.params, vec![string()]);
+    }
+
+    #[test]
+    fn test_method_spec_signature() {
+        let md = list_of_string();
+        let spec = md.method_spec_signature(1).unwrap().unwrap();
+        assert_eq!(spec.type_args, vec![TypeSig::Primitive(ElementType::I4)]);
+
+        let sig = md.method_spec_method_signature(1).unwrap().unwrap();
+        assert_eq!(
+            sig.return_type,
+            TypeSig::SzArray(Box::new(TypeSig::Primitive(ElementType::I4)))
+        );
+        assert_eq!(sig.params, vec![string()]);
+
+        // Survives a write/parse roundtrip
+        let parsed = Metadata::parse(&list_of_string().write()).unwrap();
+        assert_eq!(
+            parsed.method_spec_method_signature(1).unwrap().unwrap(),
+            sig
+        );
+    }
+}
@@ -1,6 +1,6 @@
 //! #Strings heap - null-terminated UTF-8 strings.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::error::{Error, Result};
 use crate::writer::Writer;
@@ -12,6 +12,9 @@ pub struct StringsHeap {
     data: Vec<u8>,
     /// String to offset mapping for O(1) deduplication during writes.
     index_map: HashMap<String, u32>,
+    /// Stored strings keyed by their reversed bytes, to find a stored string
+    /// ending with a new one.
+    tails: BTreeMap<Vec<u8>, u32>,
 }
 
 impl StringsHeap {
@@ -24,15 +27,48 @@ impl StringsHeap {
         Self {
             data: vec![0],
             index_map,
+            tails: BTreeMap::new(),
         }
     }
 
+    /// Build a heap holding the given strings, deduplicated, with strings
+    /// that are a suffix of another stored inside it (tail merging, as the
+    /// C# compiler does: "Name" is found at the end of "TypeName").
+    ///
+    /// Strings are laid out in a canonical order, so the same set of strings
+    /// always produces the same heap.
+    pub fn from_strings<'a>(strings: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut strings: Vec<&str> = strings.into_iter().filter(|s| !s.is_empty()).collect();
+        // Sort by reversed bytes, descending, so each string directly follows
+        // the longer strings that end with it
+        strings.sort_unstable_by(|a, b| b.bytes().rev().cmp(a.bytes().rev()));
+        strings.dedup();
+
+        let mut heap = Self::new();
+        let mut previous: Option<(&str, u32)> = None;
+        for s in strings {
+            let offset = match previous {
+                Some((longer, offset)) if longer.ends_with(s) => {
+                    offset + (longer.len() - s.len()) as u32
+                }
+                _ => {
+                    let offset = heap.push(s);
+                    previous = Some((s, offset));
+                    offset
+                }
+            };
+            heap.index_map.insert(s.to_string(), offset);
+        }
+        heap
+    }
+
     /// Parse the strings heap from raw bytes.
     #[must_use]
     pub fn parse(data: &[u8]) -> Self {
         Self {
             data: data.to_vec(),
-            index_map: HashMap::new(), // Populated lazily or on demand
+            index_map: HashMap::new(), // Populated by the first `add`
+            tails: BTreeMap::new(),
         }
     }
 
@@ -54,21 +90,55 @@ impl StringsHeap {
     }
 
     /// Add a string to the heap and return its offset.
-    /// Deduplicates strings that already exist in O(1) time.
+    ///
+    /// Deduplicates strings that already exist in O(1) time, including those
+    /// of a parsed heap, and reuses the tail of a stored string that ends with
+    /// `s` ("Name" is found at the end of "TypeName").
     pub fn add(&mut self, s: &str) -> u32 {
+        if self.index_map.is_empty() {
+            self.index_parsed();
+        }
+
         // Check if string already exists (O(1) lookup)
         if let Some(&offset) = self.index_map.get(s) {
             return offset;
         }
 
-        // Add new string
+        // Reuse the tail of the smallest stored string, in reversed byte
+        // order, that ends with `s`
+        let reversed: Vec<u8> = s.bytes().rev().collect();
+        let offset = match self.tails.range(reversed.clone()..).next() {
+            Some((tail, &offset)) if tail.starts_with(&reversed) => {
+                offset + (tail.len() - reversed.len()) as u32
+            }
+            _ => self.push(s),
+        };
+        self.index_map.insert(s.to_string(), offset);
+        offset
+    }
+
+    /// Append a string, returning its offset.
+    fn push(&mut self, s: &str) -> u32 {
         let offset = self.data.len() as u32;
         self.data.extend_from_slice(s.as_bytes());
         self.data.push(0); // Null terminator
-        self.index_map.insert(s.to_string(), offset);
+        self.tails.insert(s.bytes().rev().collect(), offset);
         offset
     }
 
+    /// Index the strings of a parsed heap, keeping the first offset of
+    /// duplicates.
+    fn index_parsed(&mut self) {
+        let mut index_map = HashMap::from([(String::new(), 0)]);
+        let mut tails = BTreeMap::new();
+        for (offset, s) in self.iter().filter(|(_, s)| !s.is_empty()) {
+            index_map.entry(s.to_string()).or_insert(offset);
+            tails.entry(s.bytes().rev().collect()).or_insert(offset);
+        }
+        self.index_map = index_map;
+        self.tails = tails;
+    }
+
     /// Get the raw heap data.
     #[must_use]
     pub fn data(&self) -> &[u8] {
@@ -188,6 +258,36 @@ mod tests {
         assert_eq!(data, b"\0Test\0");
     }
 
+    #[test]
+    fn test_from_strings_merges_suffixes() {
+        let mut heap = StringsHeap::from_strings(["Name", "TypeName", "", "Value", "Name", "e"]);
+        assert_eq!(heap.data(), b"\0Value\0TypeName\0");
+        assert_eq!(heap.add("TypeName"), 7);
+        assert_eq!(heap.add("Name"), 11);
+        let e = heap.add("e");
+        assert_eq!(heap.get(e).unwrap(), "e");
+        assert_eq!(heap.add(""), 0);
+
+        let reordered = StringsHeap::from_strings(["e", "Value", "TypeName", "Name"]);
+        assert_eq!(reordered.data(), heap.data());
+    }
+
+    #[test]
+    fn test_add_to_parsed_heap() {
+        let mut heap = StringsHeap::parse(b"\0Hello\0TypeName\0Hello\0");
+        assert_eq!(heap.add("Hello"), 1);
+        assert_eq!(heap.add("TypeName"), 7);
+        assert_eq!(heap.add("Name"), 11);
+        assert_eq!(heap.add(""), 0);
+        assert_eq!(heap.size(), 22);
+
+        // New strings are tail merged too
+        let offset = heap.add("Namespace");
+        assert_eq!(heap.add("space"), offset + 4);
+        assert_eq!(heap.get(offset + 4).unwrap(), "space");
+        assert_eq!(heap.size(), 32);
+    }
+
     #[test]
     fn test_iter() {
         let data = b"\0Hello\0World\0";
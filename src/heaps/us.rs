@@ -1,5 +1,7 @@
 //! #US (User Strings) heap - length-prefixed UTF-16LE strings.
 
+use std::collections::HashMap;
+
 use crate::error::{Error, Result};
 use crate::reader::Reader;
 use crate::writer::Writer;
@@ -9,6 +11,8 @@ use crate::writer::Writer;
 pub struct UserStringsHeap {
     /// Raw heap data.
     data: Vec<u8>,
+    /// Entry bytes to offset mapping for O(1) deduplication during writes.
+    index_map: HashMap<Vec<u8>, u32>,
 }
 
 impl UserStringsHeap {
@@ -16,7 +20,10 @@ impl UserStringsHeap {
     #[must_use]
     pub fn new() -> Self {
         // Heap always starts with a null byte
-        Self { data: vec![0] }
+        Self {
+            data: vec![0],
+            index_map: HashMap::new(),
+        }
     }
 
     /// Parse the user strings heap from raw bytes.
@@ -24,6 +31,7 @@ impl UserStringsHeap {
     pub fn parse(data: &[u8]) -> Self {
         Self {
             data: data.to_vec(),
+            index_map: HashMap::new(), // Populated by the first `add`
         }
     }
 
@@ -44,7 +52,7 @@ impl UserStringsHeap {
         // The blob length includes a trailing byte indicating if any chars are > 0x7F
         let str_len = blob_len.saturating_sub(1);
 
-        if !str_len.is_multiple_of(2) {
+        if str_len % 2 != 0 {
             return Err(Error::InvalidUserString(offset));
         }
 
@@ -59,13 +67,80 @@ impl UserStringsHeap {
         String::from_utf16(&utf16).map_err(|_| Error::InvalidUserString(offset))
     }
 
-    /// Add a user string to the heap and return its offset.
-    pub fn add(&mut self, s: &str) -> u32 {
+    /// Get the raw entry at the given offset: the UTF-16LE code units
+    /// followed by the trailing flag byte, without the length prefix.
+    ///
+    /// Unlike [`get`](Self::get), this accepts unpaired surrogates and keeps
+    /// the flag byte as written.
+    pub fn get_raw(&self, offset: u32) -> Result<&[u8]> {
+        let start = offset as usize;
+        if start >= self.data.len() {
+            return Err(Error::InvalidUserString(start));
+        }
+
+        let mut reader = Reader::new(&self.data[start..]);
+        let blob_len = reader.read_compressed_uint()? as usize;
+        if blob_len % 2 == 0 && blob_len != 0 {
+            return Err(Error::InvalidUserString(start));
+        }
+        reader.read_bytes(blob_len)
+    }
+
+    /// Add a raw entry as returned by [`get_raw`](Self::get_raw) and return
+    /// its offset.
+    ///
+    /// Deduplicates on the entry bytes, so strings that differ only in an
+    /// unpaired surrogate or in the flag byte are kept apart.
+    pub fn add_raw(&mut self, entry: &[u8]) -> u32 {
+        if self.index_map.is_empty() {
+            self.index_map = self.index_entries();
+        }
+        if let Some(&offset) = self.index_map.get(entry) {
+            return offset;
+        }
+
         let offset = self.data.len() as u32;
+        let mut writer = Writer::new();
+        writer.write_compressed_uint(entry.len() as u32);
+        self.data.extend_from_slice(writer.as_slice());
+        self.data.extend_from_slice(entry);
+        self.index_map.insert(entry.to_vec(), offset);
+        offset
+    }
+
+    /// Map each entry of the heap to its first offset.
+    ///
+    /// Walks the raw entries rather than [`iter`](Self::iter), which stops
+    /// at the first entry that is not valid UTF-16.
+    fn index_entries(&self) -> HashMap<Vec<u8>, u32> {
+        let mut index_map = HashMap::new();
+        let mut offset = 0;
+        while offset < self.data.len() {
+            let mut reader = Reader::new(&self.data[offset..]);
+            let Ok(blob_len) = reader.read_compressed_uint() else {
+                break;
+            };
+            let Ok(entry) = reader.read_bytes(blob_len as usize) else {
+                break;
+            };
+            // Skip the null byte and any zero padding
+            if !entry.is_empty() {
+                index_map.entry(entry.to_vec()).or_insert(offset as u32);
+            }
+            offset += reader.position();
+        }
+        index_map
+    }
 
-        // Convert to UTF-16LE
+    /// Add a user string to the heap and return its offset.
+    /// Deduplicates strings that already exist, including those of a parsed
+    /// heap, in O(1) time.
+    ///
+    /// The null byte at offset 0 is not a user string, so the empty string
+    /// is only shared with a real `01 00` entry; `ldstr` token 0x70000000 is
+    /// invalid.
+    pub fn add(&mut self, s: &str) -> u32 {
         let utf16: Vec<u16> = s.encode_utf16().collect();
-        let byte_len = utf16.len() * 2;
 
         // Calculate if any char has high byte set or is in specific ranges
         let has_special = utf16.iter().any(|&c| {
@@ -83,23 +158,10 @@ impl UserStringsHeap {
                 || c == 0x2D
         });
 
-        // Blob length = string bytes + 1 (trailing flag byte)
-        let blob_len = byte_len + 1;
-
-        // Write compressed length
-        let mut writer = Writer::new();
-        writer.write_compressed_uint(blob_len as u32);
-        self.data.extend_from_slice(writer.as_slice());
-
-        // Write UTF-16LE bytes
-        for &c in &utf16 {
-            self.data.extend_from_slice(&c.to_le_bytes());
-        }
-
-        // Write trailing flag byte
-        self.data.push(if has_special { 1 } else { 0 });
-
-        offset
+        // UTF-16LE bytes followed by the trailing flag byte
+        let mut entry: Vec<u8> = utf16.iter().flat_map(|c| c.to_le_bytes()).collect();
+        entry.push(u8::from(has_special));
+        self.add_raw(&entry)
     }
 
     /// Get the raw heap data.
@@ -175,7 +237,7 @@ impl Iterator for UserStringsIter<'_> {
         // The blob length includes a trailing byte
         let str_len = blob_len.saturating_sub(1);
 
-        if !str_len.is_multiple_of(2) {
+        if str_len % 2 != 0 {
             return None;
         }
 
@@ -225,6 +287,20 @@ mod tests {
         assert_eq!(heap.get(offset).unwrap(), "");
     }
 
+    #[test]
+    fn test_raw_entry_round_trip() {
+        // "a" followed by an unpaired high surrogate, with the flag set
+        let entry = [0x61, 0x00, 0x00, 0xD8, 0x01];
+        let mut heap = UserStringsHeap::new();
+        let offset = heap.add_raw(&entry);
+        assert_eq!(heap.get_raw(offset).unwrap(), entry);
+        assert!(heap.get(offset).is_err());
+
+        let offset = heap.add("Hi");
+        assert_eq!(heap.get_raw(offset).unwrap(), [b'H', 0, b'i', 0, 0]);
+        assert!(heap.get_raw(heap.size() as u32).is_err());
+    }
+
     #[test]
     fn test_unicode_string() {
         let mut heap = UserStringsHeap::new();
@@ -248,6 +324,47 @@ mod tests {
         assert_eq!(heap.get(1).unwrap(), "Hi");
     }
 
+    #[test]
+    fn test_string_deduplication() {
+        let mut heap = UserStringsHeap::parse(&[0x00, 0x05, 0x48, 0x00, 0x69, 0x00, 0x00]);
+        assert_eq!(heap.add("Hi"), 1);
+        let offset = heap.add("Hello");
+        assert_eq!(heap.add("Hello"), offset);
+        assert_eq!(heap.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_raw_entry_deduplication() {
+        // A lone surrogate is parsed before "Hi", which iter() cannot reach
+        let data = [
+            0x00, 0x03, 0x00, 0xD8, 0x01, 0x05, 0x48, 0x00, 0x69, 0x00, 0x00,
+        ];
+        let mut heap = UserStringsHeap::parse(&data);
+        assert_eq!(heap.add("Hi"), 5);
+        assert_eq!(heap.add_raw(&[0x00, 0xD8, 0x01]), 1);
+
+        // Unpaired surrogates that would decode to the same lossy text
+        // stay distinct
+        let high = heap.add_raw(&[0x01, 0xD8, 0x01]);
+        let low = heap.add_raw(&[0x01, 0xDC, 0x01]);
+        assert_ne!(high, low);
+        assert_eq!(heap.size(), data.len() + 8);
+    }
+
+    #[test]
+    fn test_empty_string_not_null_byte() {
+        let mut heap = UserStringsHeap::new();
+        let offset = heap.add("");
+        assert_eq!(offset, 1);
+        assert_eq!(heap.data(), [0x00, 0x01, 0x00]);
+        assert_eq!(heap.add(""), offset);
+
+        // A parsed heap reuses its existing empty string entry
+        let mut heap = UserStringsHeap::parse(&[0x00, 0x03, 0x41, 0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(heap.add(""), 5);
+        assert_eq!(heap.size(), 7);
+    }
+
     #[test]
     fn test_write_heap() {
         let mut heap = UserStringsHeap::new();
@@ -0,0 +1,663 @@
+//! Assembly identities and display names.
+//!
+//! An assembly is identified by its simple name, version, culture and public
+//! key (or the 8-byte token derived from it), plus a few flags. The display
+//! name format is the one used by `System.Reflection.AssemblyName`:
+//! `System.Runtime, Version=8.0.0.0, Culture=neutral, PublicKeyToken=b03f5f7f11d50a3a`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::metadata::{AssemblyInfo, AssemblyRefInfo, Metadata};
+
+/// AssemblyFlags: the public key blob holds the full key, not a token.
+pub const ASSEMBLY_FLAG_PUBLIC_KEY: u32 = 0x0001;
+/// AssemblyFlags: the reference may bind to an assembly from another publisher.
+pub const ASSEMBLY_FLAG_RETARGETABLE: u32 = 0x0100;
+
+/// Core library names, most specific first.
+pub(crate) const CORLIB_NAMES: &[&str] = &[
+    "System.Private.CoreLib",
+    "mscorlib",
+    "System.Runtime",
+    "netstandard",
+];
+
+const PROCESSOR_ARCHITECTURE_MASK: u32 = 0x0070;
+const PROCESSOR_ARCHITECTURE_SPECIFIED: u32 = 0x0080;
+const CONTENT_TYPE_MASK: u32 = 0x0E00;
+
+/// Public key information of an identity.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum PublicKeyOrToken {
+    /// Not given, as in a partial name; matches any publisher.
+    #[default]
+    Unspecified,
+    /// Not strong-named (`PublicKeyToken=null`).
+    None,
+    /// Full public key blob.
+    PublicKey(Vec<u8>),
+    /// Public key token.
+    Token([u8; 8]),
+}
+
+impl PublicKeyOrToken {
+    /// Get the public key token, computing it from the key if necessary.
+    #[must_use]
+    pub fn token(&self) -> Option<[u8; 8]> {
+        match self {
+            Self::Unspecified | Self::None => None,
+            Self::PublicKey(key) => Some(crate::crypto::public_key_token(key)),
+            Self::Token(token) => Some(*token),
+        }
+    }
+}
+
+/// Target processor architecture (AssemblyFlags bits 4-6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ProcessorArchitecture {
+    #[default]
+    None,
+    Msil,
+    X86,
+    Ia64,
+    Amd64,
+    Arm,
+    Arm64,
+    /// Reference assemblies without a specific platform.
+    NoPlatform,
+}
+
+impl ProcessorArchitecture {
+    /// Get the architecture encoded in AssemblyFlags.
+    #[must_use]
+    pub fn from_flags(flags: u32) -> Self {
+        match (flags & PROCESSOR_ARCHITECTURE_MASK) >> 4 {
+            1 => Self::Msil,
+            2 => Self::X86,
+            3 => Self::Ia64,
+            4 => Self::Amd64,
+            5 => Self::Arm,
+            6 => Self::Arm64,
+            7 => Self::NoPlatform,
+            _ => Self::None,
+        }
+    }
+
+    /// Encode the architecture as AssemblyFlags bits.
+    #[must_use]
+    pub fn to_flags(self) -> u32 {
+        let value = match self {
+            Self::None => return 0,
+            Self::Msil => 1,
+            Self::X86 => 2,
+            Self::Ia64 => 3,
+            Self::Amd64 => 4,
+            Self::Arm => 5,
+            Self::Arm64 => 6,
+            Self::NoPlatform => 7,
+        };
+        (value << 4) | PROCESSOR_ARCHITECTURE_SPECIFIED
+    }
+
+    /// Get the display name used in `ProcessorArchitecture=`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Msil => "MSIL",
+            Self::X86 => "X86",
+            Self::Ia64 => "IA64",
+            Self::Amd64 => "Amd64",
+            Self::Arm => "Arm",
+            Self::Arm64 => "Arm64",
+            Self::NoPlatform => "NoPlatform",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [
+            Self::None,
+            Self::Msil,
+            Self::X86,
+            Self::Ia64,
+            Self::Amd64,
+            Self::Arm,
+            Self::Arm64,
+            Self::NoPlatform,
+        ]
+        .into_iter()
+        .find(|arch| arch.name().eq_ignore_ascii_case(value))
+    }
+}
+
+/// How strictly two identities must agree to match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdentityComparison {
+    /// Name, version, culture, public key token and content type all agree.
+    Exact,
+    /// As `Exact`, but any version matches.
+    IgnoreVersion,
+    /// Binding semantics: the candidate's version may be higher than the
+    /// reference's, and retargetable references ignore version and publisher.
+    Unification,
+}
+
+/// An assembly identity.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct AssemblyIdentity {
+    /// Simple name.
+    pub name: String,
+    /// Version (major, minor, build, revision), or `None` if unspecified.
+    pub version: Option<(u16, u16, u16, u16)>,
+    /// Culture, empty for neutral, or `None` if unspecified.
+    pub culture: Option<String>,
+    /// Public key or token.
+    pub public_key: PublicKeyOrToken,
+    /// Reference may be retargeted to another publisher.
+    pub retargetable: bool,
+    /// Assembly holds Windows Runtime metadata (`ContentType=WindowsRuntime`).
+    pub windows_runtime: bool,
+    /// Target processor architecture.
+    pub processor_architecture: ProcessorArchitecture,
+}
+
+impl AssemblyIdentity {
+    /// Create an identity with only a simple name.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Build an identity from Assembly or AssemblyRef columns.
+    ///
+    /// The blob is a full public key if `flags` has [`ASSEMBLY_FLAG_PUBLIC_KEY`]
+    /// or it is longer than a token.
+    #[must_use]
+    pub fn from_parts(
+        name: &str,
+        version: (u16, u16, u16, u16),
+        culture: Option<&str>,
+        public_key_or_token: Option<&[u8]>,
+        flags: u32,
+    ) -> Self {
+        let public_key = match public_key_or_token {
+            Some(blob) if flags & ASSEMBLY_FLAG_PUBLIC_KEY != 0 || blob.len() > 8 => {
+                PublicKeyOrToken::PublicKey(blob.to_vec())
+            }
+            Some(blob) => match <[u8; 8]>::try_from(blob) {
+                Ok(token) => PublicKeyOrToken::Token(token),
+                Err(_) => PublicKeyOrToken::None,
+            },
+            None => PublicKeyOrToken::None,
+        };
+        Self {
+            name: name.to_string(),
+            version: Some(version),
+            culture: Some(specific_culture(culture).unwrap_or_default().to_string()),
+            public_key,
+            retargetable: flags & ASSEMBLY_FLAG_RETARGETABLE != 0,
+            windows_runtime: flags & CONTENT_TYPE_MASK == 0x0200,
+            processor_architecture: ProcessorArchitecture::from_flags(flags),
+        }
+    }
+
+    /// Parse a display name.
+    pub fn parse(display_name: &str) -> Result<Self> {
+        display_name.parse()
+    }
+
+    /// Get the AssemblyFlags implied by this identity.
+    #[must_use]
+    pub fn flags(&self) -> u32 {
+        let mut flags = self.processor_architecture.to_flags();
+        if matches!(self.public_key, PublicKeyOrToken::PublicKey(_)) {
+            flags |= ASSEMBLY_FLAG_PUBLIC_KEY;
+        }
+        if self.retargetable {
+            flags |= ASSEMBLY_FLAG_RETARGETABLE;
+        }
+        if self.windows_runtime {
+            flags |= 0x0200;
+        }
+        flags
+    }
+
+    /// Get the public key token, computing it from the key if necessary.
+    #[must_use]
+    pub fn public_key_token(&self) -> Option<[u8; 8]> {
+        self.public_key.token()
+    }
+
+    /// Get an AssemblyRef describing this identity, for use with resolvers.
+    /// An unspecified version becomes 0.0.0.0.
+    #[must_use]
+    pub fn to_assembly_ref(&self) -> AssemblyRefInfo {
+        AssemblyRefInfo {
+            name: self.name.clone(),
+            version: self.version.unwrap_or_default(),
+            culture: specific_culture(self.culture.as_deref()).map(str::to_string),
+            public_key_token: match &self.public_key {
+                PublicKeyOrToken::Unspecified | PublicKeyOrToken::None => None,
+                PublicKeyOrToken::PublicKey(key) => Some(key.clone()),
+                PublicKeyOrToken::Token(token) => Some(token.to_vec()),
+            },
+            flags: self.flags(),
+        }
+    }
+
+    /// Check whether `candidate` satisfies this identity as a reference.
+    ///
+    /// Names and cultures compare case-insensitively. An unspecified version,
+    /// culture or public key in `self` matches any value; the processor
+    /// architecture is ignored.
+    #[must_use]
+    pub fn matches(&self, candidate: &AssemblyIdentity, comparison: IdentityComparison) -> bool {
+        if !self.name.eq_ignore_ascii_case(&candidate.name)
+            || self.windows_runtime != candidate.windows_runtime
+        {
+            return false;
+        }
+        if self.culture.is_some()
+            && !culture_eq(self.culture.as_deref(), candidate.culture.as_deref())
+        {
+            return false;
+        }
+
+        let retargeted = comparison == IdentityComparison::Unification && self.retargetable;
+        if !retargeted
+            && self.public_key != PublicKeyOrToken::Unspecified
+            && self.public_key_token() != candidate.public_key_token()
+        {
+            return false;
+        }
+
+        match (comparison, self.version, candidate.version) {
+            (_, None, _) | (IdentityComparison::IgnoreVersion, _, _) => true,
+            (IdentityComparison::Unification, _, _) if retargeted => true,
+            (IdentityComparison::Exact, Some(version), candidate) => candidate == Some(version),
+            (IdentityComparison::Unification, Some(version), Some(candidate)) => {
+                candidate >= version
+            }
+            (IdentityComparison::Unification, Some(_), None) => false,
+        }
+    }
+}
+
+/// Map the empty and `neutral` cultures to `None`.
+fn specific_culture(culture: Option<&str>) -> Option<&str> {
+    culture.filter(|c| !c.is_empty() && !c.eq_ignore_ascii_case("neutral"))
+}
+
+fn culture_eq(a: Option<&str>, b: Option<&str>) -> bool {
+    match (specific_culture(a), specific_culture(b)) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+/// Formats the display name. Unspecified components are left out, matching
+/// `AssemblyName.FullName`.
+impl fmt::Display for AssemblyIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.name.chars() {
+            if matches!(c, ',' | '=' | '"' | '\'' | '\\') {
+                f.write_str("\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+        if let Some((major, minor, build, revision)) = self.version {
+            write!(f, ", Version={major}.{minor}.{build}.{revision}")?;
+        }
+        if let Some(culture) = &self.culture {
+            let culture = specific_culture(Some(culture)).unwrap_or("neutral");
+            write!(f, ", Culture={culture}")?;
+        }
+        if self.public_key != PublicKeyOrToken::Unspecified {
+            f.write_str(", PublicKeyToken=")?;
+            match self.public_key_token() {
+                Some(token) => token.iter().try_for_each(|b| write!(f, "{b:02x}"))?,
+                None => f.write_str("null")?,
+            }
+        }
+        if self.processor_architecture != ProcessorArchitecture::None {
+            write!(
+                f,
+                ", ProcessorArchitecture={}",
+                self.processor_architecture.name()
+            )?;
+        }
+        if self.retargetable {
+            f.write_str(", Retargetable=Yes")?;
+        }
+        if self.windows_runtime {
+            f.write_str(", ContentType=WindowsRuntime")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AssemblyIdentity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |message: &str| Error::InvalidAssemblyName(format!("{message} in '{s}'"));
+        let parts = split_unescaped(s).ok_or_else(|| invalid("unterminated quote"))?;
+        let mut parts = parts.into_iter();
+
+        let name = parts.next().unwrap_or_default();
+        if name.is_empty() {
+            return Err(invalid("missing simple name"));
+        }
+        let mut identity = Self::new(&name);
+
+        for part in parts {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid("expected key=value"))?;
+            let (key, value) = (key.trim(), value.trim());
+            match key.to_ascii_lowercase().as_str() {
+                "version" => {
+                    let numbers: Vec<u16> = value
+                        .split('.')
+                        .map(str::parse)
+                        .collect::<std::result::Result<_, _>>()
+                        .map_err(|_| invalid("invalid version"))?;
+                    if !(2..=4).contains(&numbers.len()) {
+                        return Err(invalid("invalid version"));
+                    }
+                    let part = |i: usize| numbers.get(i).copied().unwrap_or(0);
+                    identity.version = Some((part(0), part(1), part(2), part(3)));
+                }
+                "culture" => {
+                    identity.culture = Some(
+                        specific_culture(Some(value))
+                            .unwrap_or_default()
+                            .to_string(),
+                    );
+                }
+                "publickeytoken" => {
+                    identity.public_key = if value.eq_ignore_ascii_case("null") {
+                        PublicKeyOrToken::None
+                    } else {
+                        let bytes = parse_hex(value).ok_or_else(|| invalid("invalid token"))?;
+                        PublicKeyOrToken::Token(
+                            bytes.try_into().map_err(|_| invalid("invalid token"))?,
+                        )
+                    };
+                }
+                "publickey" => {
+                    identity.public_key = if value.eq_ignore_ascii_case("null") {
+                        PublicKeyOrToken::None
+                    } else {
+                        PublicKeyOrToken::PublicKey(
+                            parse_hex(value).ok_or_else(|| invalid("invalid public key"))?,
+                        )
+                    };
+                }
+                "retargetable" => {
+                    identity.retargetable = match value.to_ascii_lowercase().as_str() {
+                        "yes" => true,
+                        "no" => false,
+                        _ => return Err(invalid("invalid Retargetable value")),
+                    };
+                }
+                "contenttype" => {
+                    identity.windows_runtime = match value.to_ascii_lowercase().as_str() {
+                        "windowsruntime" => true,
+                        "default" => false,
+                        _ => return Err(invalid("invalid ContentType value")),
+                    };
+                }
+                "processorarchitecture" => {
+                    identity.processor_architecture = ProcessorArchitecture::parse(value)
+                        .ok_or_else(|| invalid("invalid ProcessorArchitecture value"))?;
+                }
+                // Unknown attributes are ignored, as by the runtime
+                _ => {}
+            }
+        }
+        Ok(identity)
+    }
+}
+
+/// Split a display name at unescaped, unquoted commas, removing escapes and
+/// quotes. Returns `None` on an unterminated quote.
+fn split_unescaped(s: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => current.extend(chars.next()),
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (',', None) => parts.push(std::mem::take(&mut current).trim().to_string()),
+            (c, _) => current.push(c),
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    parts.push(current.trim().to_string());
+    Some(parts)
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl AssemblyInfo {
+    /// Get the identity of this assembly.
+    #[must_use]
+    pub fn identity(&self) -> AssemblyIdentity {
+        AssemblyIdentity::from_parts(
+            &self.name,
+            self.version,
+            self.culture.as_deref(),
+            self.public_key.as_deref(),
+            self.flags | ASSEMBLY_FLAG_PUBLIC_KEY,
+        )
+    }
+}
+
+impl AssemblyRefInfo {
+    /// Get the identity this reference names.
+    #[must_use]
+    pub fn identity(&self) -> AssemblyIdentity {
+        AssemblyIdentity::from_parts(
+            &self.name,
+            self.version,
+            self.culture.as_deref(),
+            self.public_key_token.as_deref(),
+            self.flags,
+        )
+    }
+}
+
+impl Metadata {
+    /// Get the identity of this assembly, if it is one (not a netmodule).
+    #[must_use]
+    pub fn assembly_identity(&self) -> Option<AssemblyIdentity> {
+        self.assembly().map(|assembly| assembly.identity())
+    }
+
+    /// Get the identity of the core library this module builds against:
+    /// itself if it defines `System.Object`, otherwise the first reference to
+    /// a well-known core library name.
+    #[must_use]
+    pub fn corlib_identity(&self) -> Option<AssemblyIdentity> {
+        if self.find_type_def("System", "Object", None).is_some() {
+            return self.assembly_identity();
+        }
+        let references = self.assembly_refs();
+        CORLIB_NAMES.iter().find_map(|corlib| {
+            references
+                .iter()
+                .find(|reference| reference.name.eq_ignore_ascii_case(corlib))
+                .map(AssemblyRefInfo::identity)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUNTIME: &str =
+        "System.Runtime, Version=8.0.0.0, Culture=neutral, PublicKeyToken=b03f5f7f11d50a3a";
+
+    #[test]
+    fn test_parse_format() {
+        let identity = AssemblyIdentity::parse(RUNTIME).unwrap();
+        assert_eq!(identity.name, "System.Runtime");
+        assert_eq!(identity.version, Some((8, 0, 0, 0)));
+        assert_eq!(identity.culture.as_deref(), Some(""));
+        assert_eq!(
+            identity.public_key_token(),
+            Some([0xb0, 0x3f, 0x5f, 0x7f, 0x11, 0xd5, 0x0a, 0x3a])
+        );
+        assert_eq!(identity.to_string(), RUNTIME);
+
+        let portable = AssemblyIdentity::parse(
+            "System.Runtime, Version=4.0.0.0, Culture=en-US, PublicKeyToken=null, Retargetable=Yes, ContentType=WindowsRuntime, ProcessorArchitecture=msil",
+        )
+        .unwrap();
+        assert!(portable.retargetable && portable.windows_runtime);
+        assert_eq!(portable.culture.as_deref(), Some("en-US"));
+        assert_eq!(portable.processor_architecture, ProcessorArchitecture::Msil);
+        assert_eq!(portable.flags(), 0x0100 | 0x0200 | 0x0090);
+        assert_eq!(
+            portable.to_string(),
+            "System.Runtime, Version=4.0.0.0, Culture=en-US, PublicKeyToken=null, ProcessorArchitecture=MSIL, Retargetable=Yes, ContentType=WindowsRuntime"
+        );
+
+        // Partial names keep exactly the components they specify
+        for partial in [
+            "Lib",
+            "Lib, Version=1.0.0.0",
+            "Lib, Culture=neutral",
+            "Lib, PublicKeyToken=null",
+            "Lib, Culture=de, PublicKeyToken=b03f5f7f11d50a3a",
+        ] {
+            let identity = AssemblyIdentity::parse(partial).unwrap();
+            assert_eq!(identity.to_string(), partial);
+            assert_eq!(AssemblyIdentity::parse(partial).unwrap(), identity);
+        }
+        let unspecified = AssemblyIdentity::parse("Lib").unwrap();
+        assert_eq!(unspecified.culture, None);
+        assert_eq!(unspecified.public_key, PublicKeyOrToken::Unspecified);
+        let null = AssemblyIdentity::parse("Lib, PublicKeyToken=null").unwrap();
+        assert_eq!(null.public_key, PublicKeyOrToken::None);
+
+        // Escapes and quoting
+        let odd = AssemblyIdentity::parse(r#"My\,Lib, Version="1.2""#).unwrap();
+        assert_eq!(odd.name, "My,Lib");
+        assert_eq!(odd.version, Some((1, 2, 0, 0)));
+        assert!(odd.to_string().starts_with(r"My\,Lib, Version=1.2.0.0"));
+
+        for bad in [
+            "",
+            ", Version=1.0.0.0",
+            "A, Version=x",
+            "A, PublicKeyToken=abc",
+            "A, Culture",
+            "A, Retargetable=maybe",
+            "\"A",
+        ] {
+            assert!(AssemblyIdentity::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_public_key_vs_token() {
+        let key = vec![0x00, 0x24, 0x00, 0x00, 0x04, 0x80, 0x00, 0x00, 0x94];
+        let with_key = AssemblyIdentity::from_parts("A", (1, 0, 0, 0), None, Some(&key), 0x0001);
+        let token = crate::crypto::public_key_token(&key);
+        assert_eq!(
+            with_key.public_key,
+            PublicKeyOrToken::PublicKey(key.clone())
+        );
+        assert_eq!(with_key.public_key_token(), Some(token));
+
+        let with_token = AssemblyIdentity::from_parts("A", (1, 0, 0, 0), None, Some(&token), 0);
+        assert_eq!(with_token.public_key, PublicKeyOrToken::Token(token));
+        assert!(with_key.matches(&with_token, IdentityComparison::Exact));
+        assert_eq!(with_key.to_string(), with_token.to_string());
+    }
+
+    #[test]
+    fn test_comparison() {
+        let reference = AssemblyIdentity::parse(
+            "System.Runtime, Version=4.2.0.0, Culture=neutral, PublicKeyToken=b03f5f7f11d50a3a",
+        )
+        .unwrap();
+        let newer = AssemblyIdentity::parse(RUNTIME).unwrap();
+        let mut older = newer.clone();
+        older.version = Some((4, 0, 0, 0));
+        let mut other_publisher = newer.clone();
+        other_publisher.public_key = PublicKeyOrToken::None;
+
+        assert!(reference.matches(&reference, IdentityComparison::Exact));
+        assert!(!reference.matches(&newer, IdentityComparison::Exact));
+        assert!(reference.matches(&newer, IdentityComparison::IgnoreVersion));
+        assert!(reference.matches(&older, IdentityComparison::IgnoreVersion));
+        assert!(reference.matches(&newer, IdentityComparison::Unification));
+        assert!(!reference.matches(&older, IdentityComparison::Unification));
+        assert!(!reference.matches(&other_publisher, IdentityComparison::Unification));
+
+        let mut retargetable = reference.clone();
+        retargetable.retargetable = true;
+        assert!(retargetable.matches(&other_publisher, IdentityComparison::Unification));
+        assert!(!retargetable.matches(&other_publisher, IdentityComparison::Exact));
+
+        // Case-insensitive names; partial references match any version,
+        // culture and publisher they leave out
+        let partial = AssemblyIdentity::new("system.runtime");
+        assert!(partial.matches(&newer, IdentityComparison::Exact));
+        assert!(partial.matches(&other_publisher, IdentityComparison::Exact));
+        let unsigned = AssemblyIdentity::parse("System.Runtime, PublicKeyToken=null").unwrap();
+        assert!(!unsigned.matches(&newer, IdentityComparison::Exact));
+        assert!(unsigned.matches(&other_publisher, IdentityComparison::Exact));
+        let german = AssemblyIdentity::parse("System.Runtime, Culture=de").unwrap();
+        assert!(!german.matches(&newer, IdentityComparison::Exact));
+        let neutral = AssemblyIdentity::parse("System.Runtime, Culture=neutral").unwrap();
+        assert!(neutral.matches(&newer, IdentityComparison::Exact));
+    }
+
+    #[test]
+    fn test_metadata_identities() {
+        let md = crate::ilasm::assemble(
+            r#"
+            .assembly extern retargetable mscorlib {
+                .publickeytoken = (7C EC 85 D7 BE A7 79 8E)
+                .ver 2:0:5:0
+            }
+            .assembly Sample { .ver 1:2:3:4 }
+            "#,
+        )
+        .unwrap()
+        .metadata;
+        assert_eq!(
+            md.assembly_identity().unwrap().to_string(),
+            "Sample, Version=1.2.3.4, Culture=neutral, PublicKeyToken=null"
+        );
+        let reference = md.assembly_refs()[0].identity();
+        assert_eq!(
+            reference.to_string(),
+            "mscorlib, Version=2.0.5.0, Culture=neutral, PublicKeyToken=7cec85d7bea7798e, Retargetable=Yes"
+        );
+        assert_eq!(reference.flags(), ASSEMBLY_FLAG_RETARGETABLE);
+    }
+}
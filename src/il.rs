@@ -0,0 +1,768 @@
+//! CIL instruction set and method body encoding.
+//!
+//! ECMA-335 III defines the instruction set and II.25.4 the method body
+//! format (tiny/fat headers and exception handling sections).
+
+use crate::error::{Error, Result};
+use crate::reader::Reader;
+use crate::writer::Writer;
+
+/// Operand kinds for CIL instructions (ECMA-335 III.1.9).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OperandType {
+    /// No operand.
+    None,
+    /// 1-byte signed branch offset.
+    ShortBrTarget,
+    /// 4-byte signed branch offset.
+    BrTarget,
+    /// 1-byte integer.
+    ShortI,
+    /// 4-byte integer.
+    I,
+    /// 8-byte integer.
+    I8,
+    /// 4-byte float.
+    ShortR,
+    /// 8-byte float.
+    R,
+    /// 1-byte argument or local index.
+    ShortVar,
+    /// 2-byte argument or local index.
+    Var,
+    /// MethodDef, MemberRef or MethodSpec token.
+    Method,
+    /// Field or MemberRef token.
+    Field,
+    /// TypeDef, TypeRef or TypeSpec token.
+    Type,
+    /// Any type, method or field token (ldtoken).
+    Tok,
+    /// User string token.
+    String,
+    /// StandAloneSig token (calli).
+    Sig,
+    /// Jump table (switch).
+    Switch,
+}
+
+/// A CIL opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpCode {
+    /// Instruction mnemonic (e.g., "ldarg.0").
+    pub name: &'static str,
+    /// Opcode value; two-byte opcodes are stored as 0xFExx.
+    pub value: u16,
+    /// Operand kind.
+    pub operand: OperandType,
+}
+
+impl OpCode {
+    /// Size of the encoded opcode in bytes (1 or 2).
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        if self.value > 0xFF { 2 } else { 1 }
+    }
+
+    /// Size of the operand in bytes (0 for none; switch is variable and returns 4).
+    #[must_use]
+    pub const fn operand_size(&self) -> usize {
+        match self.operand {
+            OperandType::None => 0,
+            OperandType::ShortBrTarget | OperandType::ShortI | OperandType::ShortVar => 1,
+            OperandType::Var => 2,
+            OperandType::I8 | OperandType::R => 8,
+            _ => 4,
+        }
+    }
+
+    /// Look up an opcode by its encoded value.
+    #[must_use]
+    pub fn from_value(value: u16) -> Option<&'static OpCode> {
+        OPCODES.iter().find(|op| op.value == value)
+    }
+
+    /// Look up an opcode by mnemonic (including common ILAsm aliases).
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<&'static OpCode> {
+        let name = match name {
+            "ldelem.any" => "ldelem",
+            "stelem.any" => "stelem",
+            "ldelem.u8" => "ldelem.i8",
+            "ldind.u8" => "ldind.i8",
+            "ldc.i4.M1" => "ldc.i4.m1",
+            "brnull" | "brzero" => "brfalse",
+            "brnull.s" | "brzero.s" => "brfalse.s",
+            "brinst" => "brtrue",
+            "brinst.s" => "brtrue.s",
+            "endfault" => "endfinally",
+            _ => name,
+        };
+        OPCODES.iter().find(|op| op.name == name)
+    }
+}
+
+macro_rules! opcodes {
+    ($($value:literal $name:literal $operand:ident,)*) => {
+        /// All CIL opcodes defined by ECMA-335 Partition III.
+        pub static OPCODES: &[OpCode] = &[
+            $(OpCode { name: $name, value: $value, operand: OperandType::$operand },)*
+        ];
+    };
+}
+
+opcodes! {
+    0x00 "nop" None,
+    0x01 "break" None,
+    0x02 "ldarg.0" None,
+    0x03 "ldarg.1" None,
+    0x04 "ldarg.2" None,
+    0x05 "ldarg.3" None,
+    0x06 "ldloc.0" None,
+    0x07 "ldloc.1" None,
+    0x08 "ldloc.2" None,
+    0x09 "ldloc.3" None,
+    0x0A "stloc.0" None,
+    0x0B "stloc.1" None,
+    0x0C "stloc.2" None,
+    0x0D "stloc.3" None,
+    0x0E "ldarg.s" ShortVar,
+    0x0F "ldarga.s" ShortVar,
+    0x10 "starg.s" ShortVar,
+    0x11 "ldloc.s" ShortVar,
+    0x12 "ldloca.s" ShortVar,
+    0x13 "stloc.s" ShortVar,
+    0x14 "ldnull" None,
+    0x15 "ldc.i4.m1" None,
+    0x16 "ldc.i4.0" None,
+    0x17 "ldc.i4.1" None,
+    0x18 "ldc.i4.2" None,
+    0x19 "ldc.i4.3" None,
+    0x1A "ldc.i4.4" None,
+    0x1B "ldc.i4.5" None,
+    0x1C "ldc.i4.6" None,
+    0x1D "ldc.i4.7" None,
+    0x1E "ldc.i4.8" None,
+    0x1F "ldc.i4.s" ShortI,
+    0x20 "ldc.i4" I,
+    0x21 "ldc.i8" I8,
+    0x22 "ldc.r4" ShortR,
+    0x23 "ldc.r8" R,
+    0x25 "dup" None,
+    0x26 "pop" None,
+    0x27 "jmp" Method,
+    0x28 "call" Method,
+    0x29 "calli" Sig,
+    0x2A "ret" None,
+    0x2B "br.s" ShortBrTarget,
+    0x2C "brfalse.s" ShortBrTarget,
+    0x2D "brtrue.s" ShortBrTarget,
+    0x2E "beq.s" ShortBrTarget,
+    0x2F "bge.s" ShortBrTarget,
+    0x30 "bgt.s" ShortBrTarget,
+    0x31 "ble.s" ShortBrTarget,
+    0x32 "blt.s" ShortBrTarget,
+    0x33 "bne.un.s" ShortBrTarget,
+    0x34 "bge.un.s" ShortBrTarget,
+    0x35 "bgt.un.s" ShortBrTarget,
+    0x36 "ble.un.s" ShortBrTarget,
+    0x37 "blt.un.s" ShortBrTarget,
+    0x38 "br" BrTarget,
+    0x39 "brfalse" BrTarget,
+    0x3A "brtrue" BrTarget,
+    0x3B "beq" BrTarget,
+    0x3C "bge" BrTarget,
+    0x3D "bgt" BrTarget,
+    0x3E "ble" BrTarget,
+    0x3F "blt" BrTarget,
+    0x40 "bne.un" BrTarget,
+    0x41 "bge.un" BrTarget,
+    0x42 "bgt.un" BrTarget,
+    0x43 "ble.un" BrTarget,
+    0x44 "blt.un" BrTarget,
+    0x45 "switch" Switch,
+    0x46 "ldind.i1" None,
+    0x47 "ldind.u1" None,
+    0x48 "ldind.i2" None,
+    0x49 "ldind.u2" None,
+    0x4A "ldind.i4" None,
+    0x4B "ldind.u4" None,
+    0x4C "ldind.i8" None,
+    0x4D "ldind.i" None,
+    0x4E "ldind.r4" None,
+    0x4F "ldind.r8" None,
+    0x50 "ldind.ref" None,
+    0x51 "stind.ref" None,
+    0x52 "stind.i1" None,
+    0x53 "stind.i2" None,
+    0x54 "stind.i4" None,
+    0x55 "stind.i8" None,
+    0x56 "stind.r4" None,
+    0x57 "stind.r8" None,
+    0x58 "add" None,
+    0x59 "sub" None,
+    0x5A "mul" None,
+    0x5B "div" None,
+    0x5C "div.un" None,
+    0x5D "rem" None,
+    0x5E "rem.un" None,
+    0x5F "and" None,
+    0x60 "or" None,
+    0x61 "xor" None,
+    0x62 "shl" None,
+    0x63 "shr" None,
+    0x64 "shr.un" None,
+    0x65 "neg" None,
+    0x66 "not" None,
+    0x67 "conv.i1" None,
+    0x68 "conv.i2" None,
+    0x69 "conv.i4" None,
+    0x6A "conv.i8" None,
+    0x6B "conv.r4" None,
+    0x6C "conv.r8" None,
+    0x6D "conv.u4" None,
+    0x6E "conv.u8" None,
+    0x6F "callvirt" Method,
+    0x70 "cpobj" Type,
+    0x71 "ldobj" Type,
+    0x72 "ldstr" String,
+    0x73 "newobj" Method,
+    0x74 "castclass" Type,
+    0x75 "isinst" Type,
+    0x76 "conv.r.un" None,
+    0x79 "unbox" Type,
+    0x7A "throw" None,
+    0x7B "ldfld" Field,
+    0x7C "ldflda" Field,
+    0x7D "stfld" Field,
+    0x7E "ldsfld" Field,
+    0x7F "ldsflda" Field,
+    0x80 "stsfld" Field,
+    0x81 "stobj" Type,
+    0x82 "conv.ovf.i1.un" None,
+    0x83 "conv.ovf.i2.un" None,
+    0x84 "conv.ovf.i4.un" None,
+    0x85 "conv.ovf.i8.un" None,
+    0x86 "conv.ovf.u1.un" None,
+    0x87 "conv.ovf.u2.un" None,
+    0x88 "conv.ovf.u4.un" None,
+    0x89 "conv.ovf.u8.un" None,
+    0x8A "conv.ovf.i.un" None,
+    0x8B "conv.ovf.u.un" None,
+    0x8C "box" Type,
+    0x8D "newarr" Type,
+    0x8E "ldlen" None,
+    0x8F "ldelema" Type,
+    0x90 "ldelem.i1" None,
+    0x91 "ldelem.u1" None,
+    0x92 "ldelem.i2" None,
+    0x93 "ldelem.u2" None,
+    0x94 "ldelem.i4" None,
+    0x95 "ldelem.u4" None,
+    0x96 "ldelem.i8" None,
+    0x97 "ldelem.i" None,
+    0x98 "ldelem.r4" None,
+    0x99 "ldelem.r8" None,
+    0x9A "ldelem.ref" None,
+    0x9B "stelem.i" None,
+    0x9C "stelem.i1" None,
+    0x9D "stelem.i2" None,
+    0x9E "stelem.i4" None,
+    0x9F "stelem.i8" None,
+    0xA0 "stelem.r4" None,
+    0xA1 "stelem.r8" None,
+    0xA2 "stelem.ref" None,
+    0xA3 "ldelem" Type,
+    0xA4 "stelem" Type,
+    0xA5 "unbox.any" Type,
+    0xB3 "conv.ovf.i1" None,
+    0xB4 "conv.ovf.u1" None,
+    0xB5 "conv.ovf.i2" None,
+    0xB6 "conv.ovf.u2" None,
+    0xB7 "conv.ovf.i4" None,
+    0xB8 "conv.ovf.u4" None,
+    0xB9 "conv.ovf.i8" None,
+    0xBA "conv.ovf.u8" None,
+    0xC2 "refanyval" Type,
+    0xC3 "ckfinite" None,
+    0xC6 "mkrefany" Type,
+    0xD0 "ldtoken" Tok,
+    0xD1 "conv.u2" None,
+    0xD2 "conv.u1" None,
+    0xD3 "conv.i" None,
+    0xD4 "conv.ovf.i" None,
+    0xD5 "conv.ovf.u" None,
+    0xD6 "add.ovf" None,
+    0xD7 "add.ovf.un" None,
+    0xD8 "mul.ovf" None,
+    0xD9 "mul.ovf.un" None,
+    0xDA "sub.ovf" None,
+    0xDB "sub.ovf.un" None,
+    0xDC "endfinally" None,
+    0xDD "leave" BrTarget,
+    0xDE "leave.s" ShortBrTarget,
+    0xDF "stind.i" None,
+    0xE0 "conv.u" None,
+    0xFE00 "arglist" None,
+    0xFE01 "ceq" None,
+    0xFE02 "cgt" None,
+    0xFE03 "cgt.un" None,
+    0xFE04 "clt" None,
+    0xFE05 "clt.un" None,
+    0xFE06 "ldftn" Method,
+    0xFE07 "ldvirtftn" Method,
+    0xFE09 "ldarg" Var,
+    0xFE0A "ldarga" Var,
+    0xFE0B "starg" Var,
+    0xFE0C "ldloc" Var,
+    0xFE0D "ldloca" Var,
+    0xFE0E "stloc" Var,
+    0xFE0F "localloc" None,
+    0xFE11 "endfilter" None,
+    0xFE12 "unaligned." ShortI,
+    0xFE13 "volatile." None,
+    0xFE14 "tail." None,
+    0xFE15 "initobj" Type,
+    0xFE16 "constrained." Type,
+    0xFE17 "cpblk" None,
+    0xFE18 "initblk" None,
+    0xFE19 "no." ShortI,
+    0xFE1A "rethrow" None,
+    0xFE1C "sizeof" Type,
+    0xFE1D "refanytype" None,
+    0xFE1E "readonly." None,
+}
+
+/// A decoded instruction operand.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Operand {
+    /// No operand.
+    None,
+    /// Integer immediate (ShortI, I, I8).
+    Int(i64),
+    /// Floating-point immediate (ShortR, R).
+    Float(f64),
+    /// Argument or local variable index.
+    Var(u16),
+    /// Metadata token (method, field, type, string or signature).
+    Token(u32),
+    /// Absolute branch target offset.
+    Target(u32),
+    /// Absolute switch target offsets.
+    Switch(Vec<u32>),
+}
+
+/// A decoded CIL instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    /// Offset of the instruction within the method's code.
+    pub offset: u32,
+    /// The opcode.
+    pub opcode: &'static OpCode,
+    /// The operand.
+    pub operand: Operand,
+}
+
+/// Decode all instructions in a method's code bytes.
+pub fn decode_instructions(code: &[u8]) -> Result<Vec<Instruction>> {
+    let mut reader = Reader::new(code);
+    let mut instructions = Vec::new();
+
+    while !reader.is_empty() {
+        let offset = reader.position() as u32;
+        let first = reader.read_u8()?;
+        let value = if first == 0xFE {
+            0xFE00 | u16::from(reader.read_u8()?)
+        } else {
+            u16::from(first)
+        };
+        let opcode = OpCode::from_value(value).ok_or(Error::InvalidBlob(offset as usize))?;
+
+        let operand = match opcode.operand {
+            OperandType::None => Operand::None,
+            OperandType::ShortI => Operand::Int(i64::from(reader.read_u8()? as i8)),
+            OperandType::I => Operand::Int(i64::from(reader.read_u32()? as i32)),
+            OperandType::I8 => Operand::Int(reader.read_u64()? as i64),
+            OperandType::ShortR => Operand::Float(f64::from(f32::from_bits(reader.read_u32()?))),
+            OperandType::R => Operand::Float(f64::from_bits(reader.read_u64()?)),
+            OperandType::ShortVar => Operand::Var(u16::from(reader.read_u8()?)),
+            OperandType::Var => Operand::Var(reader.read_u16()?),
+            OperandType::ShortBrTarget => {
+                let delta = i32::from(reader.read_u8()? as i8);
+                Operand::Target((reader.position() as i32 + delta) as u32)
+            }
+            OperandType::BrTarget => {
+                let delta = reader.read_u32()? as i32;
+                Operand::Target((reader.position() as i32).wrapping_add(delta) as u32)
+            }
+            OperandType::Switch => {
+                let count = reader.read_u32()? as usize;
+                let base = reader.position() + count * 4;
+                let mut targets = Vec::with_capacity(count.min(reader.remaining() / 4));
+                for _ in 0..count {
+                    let delta = reader.read_u32()? as i32;
+                    targets.push((base as i32).wrapping_add(delta) as u32);
+                }
+                Operand::Switch(targets)
+            }
+            OperandType::Method
+            | OperandType::Field
+            | OperandType::Type
+            | OperandType::Tok
+            | OperandType::String
+            | OperandType::Sig => Operand::Token(reader.read_u32()?),
+        };
+
+        instructions.push(Instruction {
+            offset,
+            opcode,
+            operand,
+        });
+    }
+
+    Ok(instructions)
+}
+
+/// Kind of an exception handling clause (ECMA-335 II.25.4.6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionHandlerKind {
+    /// Typed catch clause (`class_token_or_filter` is a type token).
+    Catch,
+    /// Filter clause (`class_token_or_filter` is the filter offset).
+    Filter,
+    /// Finally clause.
+    Finally,
+    /// Fault clause.
+    Fault,
+}
+
+impl ExceptionHandlerKind {
+    fn from_flags(flags: u32) -> Self {
+        match flags & 0x7 {
+            0x1 => Self::Filter,
+            0x2 => Self::Finally,
+            0x4 => Self::Fault,
+            _ => Self::Catch,
+        }
+    }
+
+    fn flags(self) -> u32 {
+        match self {
+            Self::Catch => 0x0,
+            Self::Filter => 0x1,
+            Self::Finally => 0x2,
+            Self::Fault => 0x4,
+        }
+    }
+}
+
+/// An exception handling clause of a method body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExceptionHandler {
+    /// Clause kind.
+    pub kind: ExceptionHandlerKind,
+    /// Offset of the protected block.
+    pub try_offset: u32,
+    /// Length of the protected block.
+    pub try_length: u32,
+    /// Offset of the handler.
+    pub handler_offset: u32,
+    /// Length of the handler.
+    pub handler_length: u32,
+    /// Catch type token or filter offset (0 for finally/fault).
+    pub class_token_or_filter: u32,
+}
+
+impl ExceptionHandler {
+    fn fits_small(&self) -> bool {
+        self.try_offset <= 0xFFFF
+            && self.try_length <= 0xFF
+            && self.handler_offset <= 0xFFFF
+            && self.handler_length <= 0xFF
+    }
+}
+
+/// A method body (ECMA-335 II.25.4).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodBody {
+    /// Maximum evaluation stack depth.
+    pub max_stack: u16,
+    /// Whether locals are zero-initialized (CorILMethod_InitLocals).
+    pub init_locals: bool,
+    /// StandAloneSig token of the local variables signature (0 if none).
+    pub local_var_sig_token: u32,
+    /// CIL code bytes.
+    pub code: Vec<u8>,
+    /// Exception handling clauses.
+    pub exception_handlers: Vec<ExceptionHandler>,
+}
+
+impl MethodBody {
+    const TINY_FORMAT: u8 = 0x2;
+    const FAT_FORMAT: u16 = 0x3;
+    const MORE_SECTS: u16 = 0x08;
+    const INIT_LOCALS: u16 = 0x10;
+    const SECT_EH_TABLE: u8 = 0x01;
+    const SECT_FAT_FORMAT: u8 = 0x40;
+    const SECT_MORE_SECTS: u8 = 0x80;
+
+    /// Parse a method body from bytes starting at the body header.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        let first = reader.peek_u8()?;
+
+        if first & 0x3 == Self::TINY_FORMAT {
+            reader.read_u8()?;
+            let code = reader.read_bytes(usize::from(first >> 2))?.to_vec();
+            return Ok(Self {
+                max_stack: 8,
+                init_locals: false,
+                local_var_sig_token: 0,
+                code,
+                exception_handlers: Vec::new(),
+            });
+        }
+
+        let flags = reader.read_u16()?;
+        if flags & 0x3 != Self::FAT_FORMAT {
+            return Err(Error::InvalidBlob(0));
+        }
+        let header_size = usize::from(flags >> 12) * 4;
+        let max_stack = reader.read_u16()?;
+        let code_size = reader.read_u32()? as usize;
+        let local_var_sig_token = reader.read_u32()?;
+        reader.seek(header_size)?;
+        let code = reader.read_bytes(code_size)?.to_vec();
+
+        let mut exception_handlers = Vec::new();
+        let mut more_sects = flags & Self::MORE_SECTS != 0;
+        while more_sects {
+            // Data sections are 4-byte aligned
+            reader.seek((reader.position() + 3) & !3)?;
+            let section_start = reader.position();
+            let kind = reader.read_u8()?;
+            more_sects = kind & Self::SECT_MORE_SECTS != 0;
+
+            if kind & Self::SECT_FAT_FORMAT != 0 {
+                let size_bytes = reader.read_bytes(3)?;
+                let size = u32::from_le_bytes([size_bytes[0], size_bytes[1], size_bytes[2], 0]);
+                let count = (size as usize).saturating_sub(4) / 24;
+                if kind & Self::SECT_EH_TABLE != 0 {
+                    for _ in 0..count {
+                        let flags = reader.read_u32()?;
+                        exception_handlers.push(ExceptionHandler {
+                            kind: ExceptionHandlerKind::from_flags(flags),
+                            try_offset: reader.read_u32()?,
+                            try_length: reader.read_u32()?,
+                            handler_offset: reader.read_u32()?,
+                            handler_length: reader.read_u32()?,
+                            class_token_or_filter: reader.read_u32()?,
+                        });
+                    }
+                }
+                reader.seek(section_start + size as usize)?;
+            } else {
+                let size = reader.read_u8()?;
+                reader.read_u16()?; // Reserved
+                let count = usize::from(size).saturating_sub(4) / 12;
+                if kind & Self::SECT_EH_TABLE != 0 {
+                    for _ in 0..count {
+                        let flags = reader.read_u16()?;
+                        exception_handlers.push(ExceptionHandler {
+                            kind: ExceptionHandlerKind::from_flags(u32::from(flags)),
+                            try_offset: u32::from(reader.read_u16()?),
+                            try_length: u32::from(reader.read_u8()?),
+                            handler_offset: u32::from(reader.read_u16()?),
+                            handler_length: u32::from(reader.read_u8()?),
+                            class_token_or_filter: reader.read_u32()?,
+                        });
+                    }
+                }
+                reader.seek(section_start + usize::from(size))?;
+            }
+        }
+
+        Ok(Self {
+            max_stack,
+            init_locals: flags & Self::INIT_LOCALS != 0,
+            local_var_sig_token,
+            code,
+            exception_handlers,
+        })
+    }
+
+    /// Create the `ldnull; throw` body the C# compiler gives every method of
+    /// a reference assembly.
+    #[must_use]
+    pub fn throw_null() -> Self {
+        Self {
+            max_stack: 8,
+            code: vec![0x14, 0x7A],
+            ..Self::default()
+        }
+    }
+
+    /// Check whether this body can use the tiny header format.
+    #[must_use]
+    pub fn is_tiny(&self) -> bool {
+        self.code.len() < 64
+            && self.max_stack <= 8
+            && self.local_var_sig_token == 0
+            && !self.init_locals
+            && self.exception_handlers.is_empty()
+    }
+
+    /// Write the method body (header, code and exception sections).
+    pub fn write_to(&self, writer: &mut Writer) {
+        if self.is_tiny() {
+            writer.write_u8(((self.code.len() as u8) << 2) | Self::TINY_FORMAT);
+            writer.write_bytes(&self.code);
+            return;
+        }
+
+        let mut flags = Self::FAT_FORMAT | (3 << 12);
+        if self.init_locals {
+            flags |= Self::INIT_LOCALS;
+        }
+        if !self.exception_handlers.is_empty() {
+            flags |= Self::MORE_SECTS;
+        }
+        writer.write_u16(flags);
+        writer.write_u16(self.max_stack);
+        writer.write_u32(self.code.len() as u32);
+        writer.write_u32(self.local_var_sig_token);
+        writer.write_bytes(&self.code);
+
+        if self.exception_handlers.is_empty() {
+            return;
+        }
+        writer.align(4);
+
+        let small_size = 4 + self.exception_handlers.len() * 12;
+        if small_size <= 0xFF && self.exception_handlers.iter().all(|eh| eh.fits_small()) {
+            writer.write_u8(Self::SECT_EH_TABLE);
+            writer.write_u8(small_size as u8);
+            writer.write_u16(0);
+            for eh in &self.exception_handlers {
+                writer.write_u16(eh.kind.flags() as u16);
+                writer.write_u16(eh.try_offset as u16);
+                writer.write_u8(eh.try_length as u8);
+                writer.write_u16(eh.handler_offset as u16);
+                writer.write_u8(eh.handler_length as u8);
+                writer.write_u32(eh.class_token_or_filter);
+            }
+        } else {
+            let size = (4 + self.exception_handlers.len() * 24) as u32;
+            writer.write_u8(Self::SECT_EH_TABLE | Self::SECT_FAT_FORMAT);
+            writer.write_bytes(&size.to_le_bytes()[..3]);
+            for eh in &self.exception_handlers {
+                writer.write_u32(eh.kind.flags());
+                writer.write_u32(eh.try_offset);
+                writer.write_u32(eh.try_length);
+                writer.write_u32(eh.handler_offset);
+                writer.write_u32(eh.handler_length);
+                writer.write_u32(eh.class_token_or_filter);
+            }
+        }
+    }
+
+    /// Serialize the method body to bytes.
+    #[must_use]
+    pub fn write(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write_to(&mut writer);
+        writer.into_inner()
+    }
+
+    /// Decode the instructions of this body.
+    pub fn instructions(&self) -> Result<Vec<Instruction>> {
+        decode_instructions(&self.code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_lookup() {
+        let op = OpCode::from_name("ldstr").unwrap();
+        assert_eq!(op.value, 0x72);
+        assert_eq!(op.operand, OperandType::String);
+
+        let op = OpCode::from_value(0xFE01).unwrap();
+        assert_eq!(op.name, "ceq");
+        assert_eq!(op.size(), 2);
+
+        assert_eq!(OpCode::from_name("brnull.s").unwrap().name, "brfalse.s");
+        assert!(OpCode::from_name("bogus").is_none());
+    }
+
+    #[test]
+    fn test_decode_instructions() {
+        // ldstr 0x70000001; br.s +1; nop; switch (2) { -6, 0 }; ret
+        let code = [
+            0x72, 0x01, 0x00, 0x00, 0x70, 0x2B, 0x01, 0x00, 0x45, 0x02, 0x00, 0x00, 0x00, 0xFA,
+            0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x2A,
+        ];
+        let instructions = decode_instructions(&code).unwrap();
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(instructions[0].operand, Operand::Token(0x7000_0001));
+        assert_eq!(instructions[1].operand, Operand::Target(8));
+        assert_eq!(instructions[3].operand, Operand::Switch(vec![15, 21]));
+        assert_eq!(instructions[4].offset, 21);
+    }
+
+    #[test]
+    fn test_tiny_body_roundtrip() {
+        let body = MethodBody {
+            max_stack: 8,
+            code: vec![0x00, 0x2A],
+            ..Default::default()
+        };
+        let bytes = body.write();
+        assert_eq!(bytes, [0x0A, 0x00, 0x2A]);
+        assert_eq!(MethodBody::parse(&bytes).unwrap(), body);
+    }
+
+    #[test]
+    fn test_fat_body_roundtrip() {
+        let body = MethodBody {
+            max_stack: 2,
+            init_locals: true,
+            local_var_sig_token: 0x1100_0001,
+            code: vec![0x00, 0xDE, 0x00, 0xDC, 0x2A],
+            exception_handlers: vec![ExceptionHandler {
+                kind: ExceptionHandlerKind::Finally,
+                try_offset: 0,
+                try_length: 3,
+                handler_offset: 3,
+                handler_length: 1,
+                class_token_or_filter: 0,
+            }],
+        };
+        let bytes = body.write();
+        assert_eq!(bytes[0] & 0x3, 0x3);
+        assert_eq!(MethodBody::parse(&bytes).unwrap(), body);
+    }
+
+    #[test]
+    fn test_fat_exception_section_roundtrip() {
+        let body = MethodBody {
+            max_stack: 1,
+            code: vec![0x00; 300],
+            exception_handlers: vec![ExceptionHandler {
+                kind: ExceptionHandlerKind::Catch,
+                try_offset: 0,
+                try_length: 280,
+                handler_offset: 280,
+                handler_length: 20,
+                class_token_or_filter: 0x0100_0001,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(MethodBody::parse(&body.write()).unwrap(), body);
+    }
+}
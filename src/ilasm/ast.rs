@@ -0,0 +1,350 @@
+//! Syntax tree for parsed ILAsm source.
+
+use crate::constant::ConstantValue;
+use crate::custom_attribute::{CaNamedArg, CaValue};
+use crate::il::OpCode;
+use crate::signature::ElementType;
+
+/// A parsed source file.
+#[derive(Debug, Default)]
+pub(crate) struct SourceFile {
+    pub assembly: Option<AssemblyDecl>,
+    pub assembly_refs: Vec<AssemblyRefDecl>,
+    pub module_name: Option<String>,
+    pub module_refs: Vec<String>,
+    pub module_customs: Vec<CustomDecl>,
+    pub classes: Vec<ClassDecl>,
+    pub exported_types: Vec<ExportedTypeDecl>,
+    pub global_fields: Vec<FieldDecl>,
+    pub global_methods: Vec<MethodDecl>,
+}
+
+/// `.assembly` declaration.
+#[derive(Debug, Default)]
+pub(crate) struct AssemblyDecl {
+    pub name: String,
+    pub flags: u32,
+    pub version: [u16; 4],
+    pub culture: String,
+    pub public_key: Vec<u8>,
+    pub hash_alg_id: u32,
+    pub customs: Vec<CustomDecl>,
+}
+
+/// `.assembly extern` declaration.
+#[derive(Debug, Default)]
+pub(crate) struct AssemblyRefDecl {
+    pub name: String,
+    pub flags: u32,
+    pub version: [u16; 4],
+    pub culture: String,
+    /// Public key (sets the PublicKey flag) or token.
+    pub public_key_or_token: Vec<u8>,
+    pub hash: Vec<u8>,
+    pub customs: Vec<CustomDecl>,
+}
+
+/// `.class extern` declaration (a type forwarder).
+#[derive(Debug, Default)]
+pub(crate) struct ExportedTypeDecl {
+    /// Namespace-qualified name.
+    pub name: String,
+    pub flags: u32,
+    /// Assembly the type is forwarded to.
+    pub assembly: String,
+}
+
+/// `.class` declaration.
+#[derive(Debug, Default)]
+pub(crate) struct ClassDecl {
+    pub line: usize,
+    pub flags: u32,
+    /// Namespace-qualified name for top-level classes, simple name for nested ones.
+    pub name: String,
+    /// Declared with `value` or `enum`.
+    pub value_type: bool,
+    pub is_enum: bool,
+    pub generic_params: Vec<GenericParamDecl>,
+    pub extends: Option<TypeExpr>,
+    pub implements: Vec<TypeExpr>,
+    pub pack: Option<u16>,
+    pub size: Option<u32>,
+    pub customs: Vec<CustomDecl>,
+    pub nested: Vec<ClassDecl>,
+    pub fields: Vec<FieldDecl>,
+    pub methods: Vec<MethodDecl>,
+    pub properties: Vec<PropertyDecl>,
+    pub events: Vec<EventDecl>,
+}
+
+/// Generic parameter declaration.
+#[derive(Debug, Default)]
+pub(crate) struct GenericParamDecl {
+    pub flags: u16,
+    pub name: String,
+    pub constraints: Vec<TypeExpr>,
+}
+
+/// `.field` declaration.
+#[derive(Debug)]
+pub(crate) struct FieldDecl {
+    pub line: usize,
+    pub flags: u16,
+    pub offset: Option<u32>,
+    pub ty: TypeExpr,
+    pub name: String,
+    /// `= int32(5)` style initializer, stored in the Constant table.
+    pub default: Option<ConstantValue>,
+    pub customs: Vec<CustomDecl>,
+}
+
+/// A method parameter declaration.
+#[derive(Debug)]
+pub(crate) struct ParamDecl {
+    pub flags: u16,
+    pub ty: TypeExpr,
+    pub name: Option<String>,
+}
+
+/// `.method` declaration.
+#[derive(Debug)]
+pub(crate) struct MethodDecl {
+    pub line: usize,
+    pub flags: u16,
+    pub impl_flags: u16,
+    pub call_conv: u8,
+    pub return_type: TypeExpr,
+    pub name: String,
+    pub generic_params: Vec<GenericParamDecl>,
+    pub params: Vec<ParamDecl>,
+    pub pinvoke: Option<PInvokeDecl>,
+    pub body: MethodBodyDecl,
+}
+
+/// `pinvokeimpl(...)` clause.
+#[derive(Debug)]
+pub(crate) struct PInvokeDecl {
+    pub module: String,
+    pub entry_point: Option<String>,
+    pub flags: u16,
+}
+
+/// Contents of a method's `{ ... }` block.
+#[derive(Debug, Default)]
+pub(crate) struct MethodBodyDecl {
+    pub max_stack: Option<u16>,
+    pub init_locals: bool,
+    pub locals: Vec<LocalDecl>,
+    pub entry_point: bool,
+    pub customs: Vec<CustomDecl>,
+    /// Custom attributes declared after `.param [n]`, keyed by sequence number.
+    pub param_customs: Vec<(u16, Vec<CustomDecl>)>,
+    pub overrides: Vec<OverrideDecl>,
+    pub items: Vec<BodyItem>,
+}
+
+/// `.override` target.
+#[derive(Debug)]
+pub(crate) enum OverrideDecl {
+    /// `.override method <signature>`.
+    Method(MethodRefExpr),
+    /// `.override Type::Name`, using the overriding method's signature.
+    Named {
+        line: usize,
+        owner: TypeExpr,
+        name: String,
+    },
+}
+
+/// A local variable declaration.
+#[derive(Debug)]
+pub(crate) struct LocalDecl {
+    pub ty: TypeExpr,
+    pub name: Option<String>,
+}
+
+/// An element of a method body's instruction stream.
+#[derive(Debug)]
+pub(crate) enum BodyItem {
+    Label(String),
+    Instruction {
+        line: usize,
+        opcode: &'static OpCode,
+        operand: OperandExpr,
+    },
+    Try {
+        body: Vec<BodyItem>,
+        handlers: Vec<HandlerDecl>,
+    },
+}
+
+/// A `.try` handler block.
+#[derive(Debug)]
+pub(crate) struct HandlerDecl {
+    pub kind: HandlerKindDecl,
+    pub body: Vec<BodyItem>,
+}
+
+/// Kind of a `.try` handler block.
+#[derive(Debug)]
+pub(crate) enum HandlerKindDecl {
+    Catch(TypeExpr),
+    Filter(Vec<BodyItem>),
+    Finally,
+    Fault,
+}
+
+/// An instruction operand.
+#[derive(Debug)]
+pub(crate) enum OperandExpr {
+    None,
+    Int(i64),
+    Float(f64),
+    Label(String),
+    Labels(Vec<String>),
+    Var(VarRef),
+    Method(Box<MethodRefExpr>),
+    Field(Box<FieldRefExpr>),
+    Type(TypeExpr),
+    String(String),
+    Sig(Box<MethodRefExpr>),
+}
+
+/// Argument or local reference by index or name.
+#[derive(Debug, Clone)]
+pub(crate) enum VarRef {
+    Index(u16),
+    Name(String),
+}
+
+/// `.property` declaration.
+#[derive(Debug)]
+pub(crate) struct PropertyDecl {
+    pub line: usize,
+    pub flags: u16,
+    pub has_this: bool,
+    pub ty: TypeExpr,
+    pub name: String,
+    pub params: Vec<TypeExpr>,
+    /// Accessors as (MethodSemantics flag, method).
+    pub accessors: Vec<(u16, MethodRefExpr)>,
+    pub customs: Vec<CustomDecl>,
+}
+
+/// `.event` declaration.
+#[derive(Debug)]
+pub(crate) struct EventDecl {
+    pub line: usize,
+    pub flags: u16,
+    pub ty: Option<TypeExpr>,
+    pub name: String,
+    /// Accessors as (MethodSemantics flag, method).
+    pub accessors: Vec<(u16, MethodRefExpr)>,
+    pub customs: Vec<CustomDecl>,
+}
+
+/// `.custom` declaration.
+#[derive(Debug)]
+pub(crate) struct CustomDecl {
+    pub line: usize,
+    pub ctor: MethodRefExpr,
+    pub value: CustomValueDecl,
+}
+
+/// Custom attribute value as raw bytes or decoded arguments.
+#[derive(Debug)]
+pub(crate) enum CustomValueDecl {
+    Bytes(Vec<u8>),
+    Decoded {
+        fixed_args: Vec<CaValue>,
+        named_args: Vec<CaNamedArg>,
+    },
+}
+
+/// A type in a signature or type specification.
+#[derive(Debug, Clone)]
+pub(crate) enum TypeExpr {
+    Primitive(ElementType),
+    Named {
+        kind: NamedKind,
+        name: TypeNameExpr,
+        args: Vec<TypeExpr>,
+    },
+    SzArray(Box<TypeExpr>),
+    Array {
+        element: Box<TypeExpr>,
+        rank: u32,
+        sizes: Vec<u32>,
+        lo_bounds: Vec<i32>,
+    },
+    Ptr(Box<TypeExpr>),
+    ByRef(Box<TypeExpr>),
+    Pinned(Box<TypeExpr>),
+    Var(VarRef),
+    MVar(VarRef),
+    Modified {
+        required: bool,
+        modifier: TypeNameExpr,
+        inner: Box<TypeExpr>,
+    },
+}
+
+/// How a named type was introduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NamedKind {
+    Class,
+    ValueType,
+    /// Bare class name (TypeDefOrRef position).
+    Bare,
+}
+
+/// A possibly scoped, possibly nested type name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TypeNameExpr {
+    pub scope: ScopeExpr,
+    /// Outermost name first; the first element may be namespace-qualified.
+    pub path: Vec<String>,
+}
+
+impl TypeNameExpr {
+    /// Format as a serialized (reflection) type name, as used in custom attributes.
+    pub(crate) fn serialized(&self) -> String {
+        let mut name = self.path.join("+");
+        if let ScopeExpr::Assembly(assembly) = &self.scope {
+            name.push_str(", ");
+            name.push_str(assembly);
+        }
+        name
+    }
+}
+
+/// Resolution scope of a type name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum ScopeExpr {
+    None,
+    Assembly(String),
+    Module(String),
+}
+
+/// A method reference (call target, attribute constructor, accessor, override).
+#[derive(Debug, Clone)]
+pub(crate) struct MethodRefExpr {
+    pub line: usize,
+    pub call_conv: u8,
+    pub return_type: TypeExpr,
+    pub owner: Option<TypeExpr>,
+    pub name: String,
+    pub generic_args: Vec<TypeExpr>,
+    pub generic_arity: u32,
+    pub params: Vec<TypeExpr>,
+    pub sentinel: Option<usize>,
+}
+
+/// A field reference.
+#[derive(Debug, Clone)]
+pub(crate) struct FieldRefExpr {
+    pub line: usize,
+    pub ty: TypeExpr,
+    pub owner: Option<TypeExpr>,
+    pub name: String,
+}
@@ -0,0 +1,1478 @@
+//! Lowering of the ILAsm syntax tree to metadata tables and method bodies.
+
+use std::collections::HashMap;
+
+use super::AssembledModule;
+use super::ast::{
+    BodyItem, ClassDecl, CustomDecl, CustomValueDecl, FieldDecl, FieldRefExpr, GenericParamDecl,
+    HandlerKindDecl, MethodDecl, MethodRefExpr, NamedKind, OperandExpr, OverrideDecl, ScopeExpr,
+    SourceFile, TypeExpr, TypeNameExpr, VarRef,
+};
+use crate::custom_attribute::CustomAttributeValue;
+use crate::error::{Error, Result};
+use crate::il::{ExceptionHandler, ExceptionHandlerKind, MethodBody, OpCode, OperandType};
+use crate::metadata::Metadata;
+use crate::signature::{
+    CallingConvention, FieldSig, LocalVarSig, MethodSig, MethodSpecSig, PropertySig, TypeSig,
+};
+use crate::tables::{
+    AssemblyRefRow, AssemblyRow, ClassLayoutRow, CodedIndex, CodedIndexKind, CustomAttributeRow,
+    EventMapRow, EventRow, ExportedTypeRow, FIELD_HAS_DEFAULT, FieldLayoutRow, FieldRow,
+    GenericParamConstraintRow, GenericParamRow, ImplMapRow, InterfaceImplRow, METHOD_ABSTRACT,
+    MemberRefRow, MethodDefRow, MethodImplRow, MethodSemanticsRow, MethodSpecRow, ModuleRefRow,
+    ModuleRow, NestedClassRow, ParamRow, PropertyMapRow, PropertyRow, StandAloneSigRow,
+    TYPE_INTERFACE, TableId, TypeDefRow, TypeRefRow, TypeSpecRow,
+};
+
+/// Custom attribute blob with a prolog and no arguments.
+const EMPTY_CUSTOM_ATTRIBUTE: [u8; 4] = [0x01, 0x00, 0x00, 0x00];
+
+fn error(line: usize, message: impl Into<String>) -> Error {
+    Error::IlAsm {
+        line,
+        message: message.into(),
+    }
+}
+
+fn coded(table: TableId, row: u32) -> CodedIndex {
+    CodedIndex {
+        table: Some(table),
+        row,
+    }
+}
+
+fn token(index: CodedIndex) -> u32 {
+    (index.table.map_or(0, |t| t as u32) << 24) | index.row
+}
+
+fn from_token(token: u32) -> CodedIndex {
+    CodedIndex {
+        table: TableId::from_u8((token >> 24) as u8).ok(),
+        row: token & 0x00FF_FFFF,
+    }
+}
+
+/// Split a namespace-qualified name at the last dot.
+fn split_name(full_name: &str) -> (&str, &str) {
+    match full_name.rfind('.') {
+        Some(pos) if pos > 0 => (&full_name[..pos], &full_name[pos + 1..]),
+        _ => ("", full_name),
+    }
+}
+
+/// Generic parameters in scope while lowering a signature.
+#[derive(Clone, Copy)]
+struct Scope<'f> {
+    type_params: &'f [GenericParamDecl],
+    method_params: &'f [GenericParamDecl],
+}
+
+impl<'f> Scope<'f> {
+    const EMPTY: Scope<'static> = Scope {
+        type_params: &[],
+        method_params: &[],
+    };
+
+    fn index(params: &[GenericParamDecl], var: &VarRef, line: usize) -> Result<u32> {
+        match var {
+            VarRef::Index(index) => Ok(u32::from(*index)),
+            VarRef::Name(name) => params
+                .iter()
+                .position(|p| p.name == *name)
+                .map(|i| i as u32)
+                .ok_or_else(|| error(line, format!("unknown generic parameter '{name}'"))),
+        }
+    }
+}
+
+struct ClassEntry<'f> {
+    decl: &'f ClassDecl,
+    enclosing: Option<u32>,
+    value_type: bool,
+}
+
+struct MethodEntry<'f> {
+    decl: &'f MethodDecl,
+    /// TypeDef row of the owner (1 for global methods).
+    owner: u32,
+    rid: u32,
+    scope: Scope<'f>,
+}
+
+struct FieldEntry<'f> {
+    decl: &'f FieldDecl,
+    rid: u32,
+}
+
+struct GenericParamEntry {
+    owner: CodedIndex,
+    number: u16,
+    flags: u16,
+    name: String,
+    constraints: Vec<CodedIndex>,
+}
+
+pub(crate) struct Emitter<'f> {
+    file: &'f SourceFile,
+    md: Metadata,
+    classes: Vec<ClassEntry<'f>>,
+    class_lookup: HashMap<Vec<String>, u32>,
+    methods: Vec<MethodEntry<'f>>,
+    fields: Vec<FieldEntry<'f>>,
+    method_lookup: HashMap<(u32, String, Vec<u8>), u32>,
+    field_lookup: HashMap<(u32, String), u32>,
+    assembly_refs: HashMap<String, u32>,
+    module_refs: HashMap<String, u32>,
+    type_refs: HashMap<(u32, String, String), u32>,
+    type_specs: HashMap<Vec<u8>, u32>,
+    member_refs: HashMap<(u32, String, Vec<u8>), u32>,
+    method_specs: HashMap<(u32, Vec<u8>), u32>,
+    stand_alone_sigs: HashMap<Vec<u8>, u32>,
+    user_strings: HashMap<String, u32>,
+    corlib: String,
+    custom_attributes: Vec<CustomAttributeRow>,
+    method_semantics: Vec<MethodSemanticsRow>,
+    interface_impls: Vec<InterfaceImplRow>,
+    generic_params: Vec<GenericParamEntry>,
+    method_bodies: Vec<Option<MethodBody>>,
+    entry_point: Option<u32>,
+}
+
+impl<'f> Emitter<'f> {
+    pub(crate) fn new(file: &'f SourceFile) -> Self {
+        // Core library used for implicit base types, as ilasm does
+        let corlib = [
+            "mscorlib",
+            "System.Runtime",
+            "netstandard",
+            "System.Private.CoreLib",
+        ]
+        .into_iter()
+        .find(|name| file.assembly_refs.iter().any(|r| r.name == *name))
+        .unwrap_or("mscorlib")
+        .to_string();
+
+        Self {
+            file,
+            md: Metadata::new(),
+            classes: Vec::new(),
+            class_lookup: HashMap::new(),
+            methods: Vec::new(),
+            fields: Vec::new(),
+            method_lookup: HashMap::new(),
+            field_lookup: HashMap::new(),
+            assembly_refs: HashMap::new(),
+            module_refs: HashMap::new(),
+            type_refs: HashMap::new(),
+            type_specs: HashMap::new(),
+            member_refs: HashMap::new(),
+            method_specs: HashMap::new(),
+            stand_alone_sigs: HashMap::new(),
+            user_strings: HashMap::new(),
+            corlib,
+            custom_attributes: Vec::new(),
+            method_semantics: Vec::new(),
+            interface_impls: Vec::new(),
+            generic_params: Vec::new(),
+            method_bodies: Vec::new(),
+            entry_point: None,
+        }
+    }
+
+    /// Lower the source file.
+    pub(crate) fn emit(mut self, mvid: [u8; 16]) -> Result<AssembledModule> {
+        let file = self.file;
+        self.emit_manifest(mvid)?;
+        self.register_classes();
+        self.emit_definitions()?;
+
+        for index in 0..self.methods.len() {
+            let body = self.emit_method_body(index)?;
+            self.method_bodies.push(body);
+        }
+        for index in 0..self.classes.len() {
+            self.emit_class_extras(index)?;
+        }
+        self.emit_member_customs()?;
+
+        for custom in &file.module_customs {
+            self.add_custom(coded(TableId::Module, 1), custom, Scope::EMPTY)?;
+        }
+        if let Some(assembly) = &file.assembly {
+            for custom in &assembly.customs {
+                self.add_custom(coded(TableId::Assembly, 1), custom, Scope::EMPTY)?;
+            }
+        }
+        for (i, assembly_ref) in file.assembly_refs.iter().enumerate() {
+            for custom in &assembly_ref.customs {
+                self.add_custom(
+                    coded(TableId::AssemblyRef, i as u32 + 1),
+                    custom,
+                    Scope::EMPTY,
+                )?;
+            }
+        }
+
+        self.finish_sorted_tables();
+        Ok(AssembledModule {
+            metadata: self.md,
+            method_bodies: self.method_bodies,
+            entry_point: self.entry_point,
+        })
+    }
+
+    // ------------------------------------------------------------------------
+    // Heaps and reference tables
+    // ------------------------------------------------------------------------
+
+    fn blob(&mut self, data: &[u8]) -> u32 {
+        if data.is_empty() {
+            0
+        } else {
+            self.md.blobs.add(data)
+        }
+    }
+
+    fn assembly_ref(&mut self, name: &str) -> u32 {
+        if let Some(&rid) = self.assembly_refs.get(name) {
+            return rid;
+        }
+        let name_index = self.md.strings.add(name);
+        self.md.assembly_refs.push(AssemblyRefRow {
+            name: name_index,
+            ..Default::default()
+        });
+        let rid = self.md.assembly_refs.len() as u32;
+        self.assembly_refs.insert(name.to_string(), rid);
+        rid
+    }
+
+    fn module_ref(&mut self, name: &str) -> u32 {
+        if let Some(&rid) = self.module_refs.get(name) {
+            return rid;
+        }
+        let name_index = self.md.strings.add(name);
+        self.md.module_refs.push(ModuleRefRow { name: name_index });
+        let rid = self.md.module_refs.len() as u32;
+        self.module_refs.insert(name.to_string(), rid);
+        rid
+    }
+
+    fn type_ref(&mut self, scope: CodedIndex, namespace: &str, name: &str) -> u32 {
+        let key = (
+            scope.encode(CodedIndexKind::ResolutionScope),
+            namespace.to_string(),
+            name.to_string(),
+        );
+        if let Some(&rid) = self.type_refs.get(&key) {
+            return rid;
+        }
+        let type_name = self.md.strings.add(name);
+        let type_namespace = self.md.strings.add(namespace);
+        self.md.type_refs.push(TypeRefRow {
+            resolution_scope: scope,
+            type_name,
+            type_namespace,
+        });
+        let rid = self.md.type_refs.len() as u32;
+        self.type_refs.insert(key, rid);
+        rid
+    }
+
+    fn type_spec(&mut self, signature: Vec<u8>) -> u32 {
+        if let Some(&rid) = self.type_specs.get(&signature) {
+            return rid;
+        }
+        let blob = self.blob(&signature);
+        self.md.type_specs.push(TypeSpecRow { signature: blob });
+        let rid = self.md.type_specs.len() as u32;
+        self.type_specs.insert(signature, rid);
+        rid
+    }
+
+    fn member_ref(&mut self, parent: CodedIndex, name: &str, signature: Vec<u8>) -> u32 {
+        let key = (
+            parent.encode(CodedIndexKind::MemberRefParent),
+            name.to_string(),
+            signature,
+        );
+        if let Some(&rid) = self.member_refs.get(&key) {
+            return rid;
+        }
+        let name_index = self.md.strings.add(name);
+        let blob = self.blob(&key.2);
+        self.md.member_refs.push(MemberRefRow {
+            class: parent,
+            name: name_index,
+            signature: blob,
+        });
+        let rid = self.md.member_refs.len() as u32;
+        self.member_refs.insert(key, rid);
+        rid
+    }
+
+    fn stand_alone_sig(&mut self, signature: Vec<u8>) -> u32 {
+        if let Some(&rid) = self.stand_alone_sigs.get(&signature) {
+            return rid;
+        }
+        let blob = self.blob(&signature);
+        self.md
+            .stand_alone_sigs
+            .push(StandAloneSigRow { signature: blob });
+        let rid = self.md.stand_alone_sigs.len() as u32;
+        self.stand_alone_sigs.insert(signature, rid);
+        rid
+    }
+
+    fn user_string(&mut self, value: &str) -> u32 {
+        if let Some(&offset) = self.user_strings.get(value) {
+            return offset;
+        }
+        let offset = self.md.user_strings.add(value);
+        self.user_strings.insert(value.to_string(), offset);
+        offset
+    }
+
+    // ------------------------------------------------------------------------
+    // Manifest and definitions
+    // ------------------------------------------------------------------------
+
+    fn emit_manifest(&mut self, mvid: [u8; 16]) -> Result<()> {
+        let file = self.file;
+        let module_name = match (&file.module_name, &file.assembly) {
+            (Some(name), _) => name.clone(),
+            (None, Some(assembly)) => format!("{}.dll", assembly.name),
+            (None, None) => "module".to_string(),
+        };
+        let name = self.md.strings.add(&module_name);
+        let mvid = self.md.guids.add(&mvid);
+        self.md.modules.push(ModuleRow {
+            generation: 0,
+            name,
+            mvid,
+            enc_id: 0,
+            enc_base_id: 0,
+        });
+
+        if let Some(assembly) = &file.assembly {
+            let public_key = self.blob(&assembly.public_key);
+            let name = self.md.strings.add(&assembly.name);
+            let culture = self.md.strings.add(&assembly.culture);
+            self.md.assemblies.push(AssemblyRow {
+                hash_alg_id: assembly.hash_alg_id,
+                major_version: assembly.version[0],
+                minor_version: assembly.version[1],
+                build_number: assembly.version[2],
+                revision_number: assembly.version[3],
+                flags: assembly.flags,
+                public_key,
+                name,
+                culture,
+            });
+        }
+
+        for assembly_ref in &file.assembly_refs {
+            if self.assembly_refs.contains_key(&assembly_ref.name) {
+                return Err(error(
+                    0,
+                    format!("duplicate .assembly extern '{}'", assembly_ref.name),
+                ));
+            }
+            let public_key_or_token = self.blob(&assembly_ref.public_key_or_token);
+            let name = self.md.strings.add(&assembly_ref.name);
+            let culture = self.md.strings.add(&assembly_ref.culture);
+            let hash_value = self.blob(&assembly_ref.hash);
+            self.md.assembly_refs.push(AssemblyRefRow {
+                major_version: assembly_ref.version[0],
+                minor_version: assembly_ref.version[1],
+                build_number: assembly_ref.version[2],
+                revision_number: assembly_ref.version[3],
+                flags: assembly_ref.flags,
+                public_key_or_token,
+                name,
+                culture,
+                hash_value,
+            });
+            self.assembly_refs.insert(
+                assembly_ref.name.clone(),
+                self.md.assembly_refs.len() as u32,
+            );
+        }
+
+        for module_ref in &file.module_refs {
+            self.module_ref(module_ref);
+        }
+
+        for exported in &file.exported_types {
+            let (namespace, name) = split_name(&exported.name);
+            let implementation = coded(TableId::AssemblyRef, self.assembly_ref(&exported.assembly));
+            let type_name = self.md.strings.add(name);
+            let type_namespace = self.md.strings.add(namespace);
+            self.md.exported_types.push(ExportedTypeRow {
+                flags: exported.flags,
+                type_def_id: 0,
+                type_name,
+                type_namespace,
+                implementation,
+            });
+        }
+        Ok(())
+    }
+
+    /// Assign TypeDef rows to classes in declaration (pre-)order; `<Module>` is row 1.
+    fn register_classes(&mut self) {
+        fn visit<'f>(
+            emitter: &mut Emitter<'f>,
+            decl: &'f ClassDecl,
+            parent_path: &[String],
+            enclosing: Option<u32>,
+        ) {
+            let mut path = parent_path.to_vec();
+            path.push(decl.name.clone());
+            let rid = emitter.classes.len() as u32 + 2;
+
+            let extends_value_type = matches!(
+                &decl.extends,
+                Some(TypeExpr::Named { name, .. })
+                    if matches!(name.path.as_slice(), [n] if n == "System.ValueType" || n == "System.Enum")
+            );
+            emitter.class_lookup.insert(path.clone(), rid);
+            emitter.classes.push(ClassEntry {
+                decl,
+                enclosing,
+                value_type: decl.value_type || extends_value_type,
+            });
+
+            for nested in &decl.nested {
+                visit(emitter, nested, &path, Some(rid));
+            }
+        }
+
+        for class in &self.file.classes {
+            visit(self, class, &[], None);
+        }
+    }
+
+    fn emit_definitions(&mut self) -> Result<()> {
+        let file = self.file;
+
+        // <Module> holds global fields and methods
+        let name = self.md.strings.add("<Module>");
+        self.md.type_defs.push(TypeDefRow {
+            type_name: name,
+            field_list: 1,
+            method_list: 1,
+            ..Default::default()
+        });
+        self.emit_members(1, &file.global_fields, &file.global_methods, &[])?;
+
+        for index in 0..self.classes.len() {
+            let rid = index as u32 + 2;
+            let decl = self.classes[index].decl;
+            let scope = Scope {
+                type_params: &decl.generic_params,
+                method_params: &[],
+            };
+
+            let extends = match &decl.extends {
+                Some(ty) => self.type_token(ty, scope, decl.line)?,
+                None if decl.flags & TYPE_INTERFACE != 0 => CodedIndex::null(),
+                None => {
+                    let base = if decl.is_enum {
+                        "Enum"
+                    } else if decl.value_type {
+                        "ValueType"
+                    } else {
+                        "Object"
+                    };
+                    let corlib = self.corlib.clone();
+                    let scope = coded(TableId::AssemblyRef, self.assembly_ref(&corlib));
+                    coded(TableId::TypeRef, self.type_ref(scope, "System", base))
+                }
+            };
+
+            let (namespace, name) = if self.classes[index].enclosing.is_some() {
+                ("", decl.name.as_str())
+            } else {
+                split_name(&decl.name)
+            };
+            let type_name = self.md.strings.add(name);
+            let type_namespace = self.md.strings.add(namespace);
+            self.md.type_defs.push(TypeDefRow {
+                flags: decl.flags,
+                type_name,
+                type_namespace,
+                extends,
+                field_list: self.md.fields.len() as u32 + 1,
+                method_list: self.md.method_defs.len() as u32 + 1,
+            });
+
+            for interface in &decl.implements {
+                let interface = self.type_token(interface, scope, decl.line)?;
+                self.interface_impls.push(InterfaceImplRow {
+                    class: rid,
+                    interface,
+                });
+            }
+            self.add_generic_params(
+                coded(TableId::TypeDef, rid),
+                &decl.generic_params,
+                scope,
+                decl.line,
+            )?;
+            self.emit_members(rid, &decl.fields, &decl.methods, &decl.generic_params)?;
+        }
+        Ok(())
+    }
+
+    fn emit_members(
+        &mut self,
+        owner: u32,
+        fields: &'f [FieldDecl],
+        methods: &'f [MethodDecl],
+        type_params: &'f [GenericParamDecl],
+    ) -> Result<()> {
+        let class_scope = Scope {
+            type_params,
+            method_params: &[],
+        };
+
+        for field in fields {
+            let field_type = self.type_sig(&field.ty, class_scope, field.line)?;
+            let signature = FieldSig { field_type }.to_blob();
+            let name = self.md.strings.add(&field.name);
+            let signature = self.blob(&signature);
+            let has_default = if field.default.is_some() {
+                FIELD_HAS_DEFAULT
+            } else {
+                0
+            };
+            self.md.fields.push(FieldRow {
+                flags: field.flags | has_default,
+                name,
+                signature,
+            });
+            let rid = self.md.fields.len() as u32;
+            if let Some(value) = &field.default {
+                self.md.set_constant(coded(TableId::Field, rid), value);
+            }
+            if self
+                .field_lookup
+                .insert((owner, field.name.clone()), rid)
+                .is_some()
+            {
+                return Err(error(
+                    field.line,
+                    format!("duplicate field '{}'", field.name),
+                ));
+            }
+            if let Some(offset) = field.offset {
+                self.md
+                    .field_layouts
+                    .push(FieldLayoutRow { offset, field: rid });
+            }
+            self.fields.push(FieldEntry { decl: field, rid });
+        }
+
+        for method in methods {
+            let scope = Scope {
+                type_params,
+                method_params: &method.generic_params,
+            };
+            let signature = self.method_def_sig(method, scope)?.to_blob();
+            let rid = self.md.method_defs.len() as u32 + 1;
+            let key = (owner, method.name.clone(), signature.clone());
+            if self.method_lookup.insert(key, rid).is_some() {
+                return Err(error(
+                    method.line,
+                    format!("duplicate method '{}'", method.name),
+                ));
+            }
+
+            let name = self.md.strings.add(&method.name);
+            let signature = self.blob(&signature);
+            self.md.method_defs.push(MethodDefRow {
+                rva: 0,
+                impl_flags: method.impl_flags,
+                flags: method.flags,
+                name,
+                signature,
+                param_list: self.md.params.len() as u32 + 1,
+            });
+
+            // Return value parameter row only when it carries attributes
+            if method.body.param_customs.iter().any(|(seq, _)| *seq == 0) {
+                self.md.params.push(ParamRow {
+                    flags: 0,
+                    sequence: 0,
+                    name: 0,
+                });
+            }
+            for (i, param) in method.params.iter().enumerate() {
+                let name = self.md.strings.add(param.name.as_deref().unwrap_or(""));
+                self.md.params.push(ParamRow {
+                    flags: param.flags,
+                    sequence: i as u16 + 1,
+                    name,
+                });
+            }
+
+            if let Some(pinvoke) = &method.pinvoke {
+                let import_scope = self.module_ref(&pinvoke.module);
+                let import_name = self
+                    .md
+                    .strings
+                    .add(pinvoke.entry_point.as_deref().unwrap_or(&method.name));
+                self.md.impl_maps.push(ImplMapRow {
+                    mapping_flags: pinvoke.flags,
+                    member_forwarded: coded(TableId::MethodDef, rid),
+                    import_name,
+                    import_scope,
+                });
+            }
+
+            if method.body.entry_point {
+                if self.entry_point.is_some() {
+                    return Err(error(method.line, "multiple .entrypoint methods"));
+                }
+                self.entry_point = Some(token(coded(TableId::MethodDef, rid)));
+            }
+
+            self.add_generic_params(
+                coded(TableId::MethodDef, rid),
+                &method.generic_params,
+                scope,
+                method.line,
+            )?;
+            self.methods.push(MethodEntry {
+                decl: method,
+                owner,
+                rid,
+                scope,
+            });
+        }
+        Ok(())
+    }
+
+    fn add_generic_params(
+        &mut self,
+        owner: CodedIndex,
+        params: &[GenericParamDecl],
+        scope: Scope<'_>,
+        line: usize,
+    ) -> Result<()> {
+        for (number, param) in params.iter().enumerate() {
+            let mut constraints = Vec::with_capacity(param.constraints.len());
+            for constraint in &param.constraints {
+                constraints.push(self.type_token(constraint, scope, line)?);
+            }
+            self.generic_params.push(GenericParamEntry {
+                owner,
+                number: number as u16,
+                flags: param.flags,
+                name: param.name.clone(),
+                constraints,
+            });
+        }
+        Ok(())
+    }
+
+    fn method_def_sig(&mut self, method: &MethodDecl, scope: Scope<'_>) -> Result<MethodSig> {
+        let return_type = self.type_sig(&method.return_type, scope, method.line)?;
+        let mut params = Vec::with_capacity(method.params.len());
+        for param in &method.params {
+            params.push(self.type_sig(&param.ty, scope, method.line)?);
+        }
+        Ok(MethodSig {
+            calling_convention: CallingConvention(method.call_conv),
+            generic_param_count: method.generic_params.len() as u32,
+            return_type,
+            params,
+            sentinel: None,
+        })
+    }
+
+    /// Layout, nesting, properties, events and overrides of a class.
+    fn emit_class_extras(&mut self, index: usize) -> Result<()> {
+        let rid = index as u32 + 2;
+        let decl = self.classes[index].decl;
+        let scope = Scope {
+            type_params: &decl.generic_params,
+            method_params: &[],
+        };
+
+        if let Some(enclosing) = self.classes[index].enclosing {
+            self.md.nested_classes.push(NestedClassRow {
+                nested_class: rid,
+                enclosing_class: enclosing,
+            });
+        }
+        if decl.pack.is_some() || decl.size.is_some() {
+            self.md.class_layouts.push(ClassLayoutRow {
+                packing_size: decl.pack.unwrap_or(0),
+                class_size: decl.size.unwrap_or(0),
+                parent: rid,
+            });
+        }
+        for custom in &decl.customs {
+            self.add_custom(coded(TableId::TypeDef, rid), custom, scope)?;
+        }
+
+        if !decl.properties.is_empty() {
+            self.md.property_maps.push(PropertyMapRow {
+                parent: rid,
+                property_list: self.md.properties.len() as u32 + 1,
+            });
+        }
+        for property in &decl.properties {
+            let property_type = self.type_sig(&property.ty, scope, property.line)?;
+            let mut params = Vec::with_capacity(property.params.len());
+            for param in &property.params {
+                params.push(self.type_sig(param, scope, property.line)?);
+            }
+            let signature = PropertySig {
+                has_this: property.has_this,
+                property_type,
+                params,
+            }
+            .to_blob();
+            let name = self.md.strings.add(&property.name);
+            let signature = self.blob(&signature);
+            self.md.properties.push(PropertyRow {
+                flags: property.flags,
+                name,
+                property_type: signature,
+            });
+            let association = coded(TableId::Property, self.md.properties.len() as u32);
+            for (semantics, accessor) in &property.accessors {
+                self.add_semantics(*semantics, accessor, association, scope)?;
+            }
+            for custom in &property.customs {
+                self.add_custom(association, custom, scope)?;
+            }
+        }
+
+        if !decl.events.is_empty() {
+            self.md.event_maps.push(EventMapRow {
+                parent: rid,
+                event_list: self.md.events.len() as u32 + 1,
+            });
+        }
+        for event in &decl.events {
+            let event_type = match &event.ty {
+                Some(ty) => self.type_token(ty, scope, event.line)?,
+                None => CodedIndex::null(),
+            };
+            let name = self.md.strings.add(&event.name);
+            self.md.events.push(EventRow {
+                event_flags: event.flags,
+                name,
+                event_type,
+            });
+            let association = coded(TableId::Event, self.md.events.len() as u32);
+            for (semantics, accessor) in &event.accessors {
+                self.add_semantics(*semantics, accessor, association, scope)?;
+            }
+            for custom in &event.customs {
+                self.add_custom(association, custom, scope)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_semantics(
+        &mut self,
+        semantics: u16,
+        accessor: &MethodRefExpr,
+        association: CodedIndex,
+        scope: Scope<'_>,
+    ) -> Result<()> {
+        let method = from_token(self.method_token(accessor, scope)?);
+        if method.table != Some(TableId::MethodDef) {
+            return Err(error(
+                accessor.line,
+                format!(
+                    "accessor '{}' is not a method defined in this module",
+                    accessor.name
+                ),
+            ));
+        }
+        self.method_semantics.push(MethodSemanticsRow {
+            semantics,
+            method: method.row,
+            association,
+        });
+        Ok(())
+    }
+
+    /// Custom attributes on fields, methods and parameters, plus method overrides.
+    fn emit_member_customs(&mut self) -> Result<()> {
+        for index in 0..self.fields.len() {
+            let FieldEntry { decl, rid } = self.fields[index];
+            for custom in &decl.customs {
+                self.add_custom(coded(TableId::Field, rid), custom, Scope::EMPTY)?;
+            }
+        }
+
+        let mut param_rid = 1u32;
+        for index in 0..self.methods.len() {
+            let MethodEntry {
+                decl,
+                owner,
+                rid,
+                scope,
+            } = self.methods[index];
+            let parent = coded(TableId::MethodDef, rid);
+            for custom in &decl.body.customs {
+                self.add_custom(parent, custom, scope)?;
+            }
+
+            let has_return_param = decl.body.param_customs.iter().any(|(seq, _)| *seq == 0);
+            let first_param = param_rid;
+            param_rid += decl.params.len() as u32 + u32::from(has_return_param);
+            for (sequence, customs) in &decl.body.param_customs {
+                if usize::from(*sequence) > decl.params.len() {
+                    return Err(error(
+                        decl.line,
+                        format!("parameter {sequence} out of range"),
+                    ));
+                }
+                let row = first_param + u32::from(*sequence) - u32::from(!has_return_param);
+                for custom in customs {
+                    self.add_custom(coded(TableId::Param, row), custom, scope)?;
+                }
+            }
+
+            for target in &decl.body.overrides {
+                let declaration = match target {
+                    OverrideDecl::Method(method) => self.method_token(method, scope)?,
+                    OverrideDecl::Named { line, owner, name } => {
+                        let signature = self.method_def_sig(decl, scope)?;
+                        let parent = self.type_token(owner, scope, *line)?;
+                        self.resolve_method(parent, name, signature, *line)?
+                    }
+                };
+                self.md.method_impls.push(MethodImplRow {
+                    class: owner,
+                    method_body: coded(TableId::MethodDef, rid),
+                    method_declaration: from_token(declaration),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn add_custom(
+        &mut self,
+        parent: CodedIndex,
+        custom: &CustomDecl,
+        scope: Scope<'_>,
+    ) -> Result<()> {
+        let ctor = from_token(self.method_token(&custom.ctor, scope)?);
+        if !matches!(ctor.table, Some(TableId::MethodDef | TableId::MemberRef))
+            || custom.ctor.name != ".ctor"
+        {
+            return Err(error(
+                custom.line,
+                "custom attribute constructor must be a .ctor",
+            ));
+        }
+        let value = match &custom.value {
+            CustomValueDecl::Bytes(bytes) => bytes.clone(),
+            CustomValueDecl::Decoded {
+                fixed_args,
+                named_args,
+            } if fixed_args.is_empty() && named_args.is_empty() => EMPTY_CUSTOM_ATTRIBUTE.to_vec(),
+            CustomValueDecl::Decoded {
+                fixed_args,
+                named_args,
+            } => CustomAttributeValue {
+                fixed_args: fixed_args.clone(),
+                named_args: named_args.clone(),
+            }
+            .to_blob(),
+        };
+        let value = self.blob(&value);
+        self.custom_attributes.push(CustomAttributeRow {
+            parent,
+            attr_type: ctor,
+            value,
+        });
+        Ok(())
+    }
+
+    /// Sort and emit tables that must be ordered by their parent column.
+    fn finish_sorted_tables(&mut self) {
+        let mut custom_attributes = std::mem::take(&mut self.custom_attributes);
+        custom_attributes.sort_by_key(|row| row.parent.encode(CodedIndexKind::HasCustomAttribute));
+        self.md.custom_attributes = custom_attributes;
+
+        let mut interface_impls = std::mem::take(&mut self.interface_impls);
+        interface_impls.sort_by_key(|row| {
+            (
+                row.class,
+                row.interface.encode(CodedIndexKind::TypeDefOrRef),
+            )
+        });
+        self.md.interface_impls = interface_impls;
+
+        let mut method_semantics = std::mem::take(&mut self.method_semantics);
+        method_semantics.sort_by_key(|row| row.association.encode(CodedIndexKind::HasSemantics));
+        self.md.method_semantics = method_semantics;
+
+        self.md.method_impls.sort_by_key(|row| row.class);
+
+        let mut generic_params = std::mem::take(&mut self.generic_params);
+        generic_params
+            .sort_by_key(|gp| (gp.owner.encode(CodedIndexKind::TypeOrMethodDef), gp.number));
+        for gp in generic_params {
+            let name = self.md.strings.add(&gp.name);
+            self.md.generic_params.push(GenericParamRow {
+                number: gp.number,
+                flags: gp.flags,
+                owner: gp.owner,
+                name,
+            });
+            let owner = self.md.generic_params.len() as u32;
+            for constraint in gp.constraints {
+                self.md
+                    .generic_param_constraints
+                    .push(GenericParamConstraintRow { owner, constraint });
+            }
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Types and members
+    // ------------------------------------------------------------------------
+
+    /// Resolve a type name to a TypeDef or TypeRef.
+    fn resolve_type_name(&mut self, name: &TypeNameExpr, line: usize) -> Result<CodedIndex> {
+        let local_assembly = self.file.assembly.as_ref().map(|a| a.name.as_str());
+        let mut scope = match &name.scope {
+            ScopeExpr::Assembly(assembly) if Some(assembly.as_str()) != local_assembly => {
+                coded(TableId::AssemblyRef, self.assembly_ref(assembly))
+            }
+            ScopeExpr::Module(module) => coded(TableId::ModuleRef, self.module_ref(module)),
+            _ => {
+                return self
+                    .class_lookup
+                    .get(&name.path)
+                    .map(|&rid| coded(TableId::TypeDef, rid))
+                    .ok_or_else(|| {
+                        error(line, format!("undefined type '{}'", name.path.join("/")))
+                    });
+            }
+        };
+
+        for (i, part) in name.path.iter().enumerate() {
+            let (namespace, type_name) = if i == 0 {
+                split_name(part)
+            } else {
+                ("", part.as_str())
+            };
+            scope = coded(TableId::TypeRef, self.type_ref(scope, namespace, type_name));
+        }
+        Ok(scope)
+    }
+
+    fn is_local_value_type(&self, index: CodedIndex) -> bool {
+        index.table == Some(TableId::TypeDef)
+            && index.row >= 2
+            && self.classes[index.row as usize - 2].value_type
+    }
+
+    fn type_sig(&mut self, ty: &TypeExpr, scope: Scope<'_>, line: usize) -> Result<TypeSig> {
+        Ok(match ty {
+            TypeExpr::Primitive(element_type) => TypeSig::Primitive(*element_type),
+            TypeExpr::Named { kind, name, args } => {
+                let index = self.resolve_type_name(name, line)?;
+                let is_value_type = *kind == NamedKind::ValueType
+                    || (*kind == NamedKind::Bare && self.is_local_value_type(index));
+                let type_ref = index.encode(CodedIndexKind::TypeDefOrRef);
+                if args.is_empty() {
+                    if is_value_type {
+                        TypeSig::ValueType(type_ref)
+                    } else {
+                        TypeSig::Class(type_ref)
+                    }
+                } else {
+                    let mut type_args = Vec::with_capacity(args.len());
+                    for arg in args {
+                        type_args.push(self.type_sig(arg, scope, line)?);
+                    }
+                    TypeSig::GenericInst {
+                        is_value_type,
+                        type_ref,
+                        type_args,
+                    }
+                }
+            }
+            TypeExpr::SzArray(inner) => {
+                TypeSig::SzArray(Box::new(self.type_sig(inner, scope, line)?))
+            }
+            TypeExpr::Array {
+                element,
+                rank,
+                sizes,
+                lo_bounds,
+            } => TypeSig::Array {
+                element_type: Box::new(self.type_sig(element, scope, line)?),
+                rank: *rank,
+                sizes: sizes.clone(),
+                lo_bounds: lo_bounds.clone(),
+            },
+            TypeExpr::Ptr(inner) => TypeSig::Ptr(Box::new(self.type_sig(inner, scope, line)?)),
+            TypeExpr::ByRef(inner) => TypeSig::ByRef(Box::new(self.type_sig(inner, scope, line)?)),
+            TypeExpr::Pinned(inner) => {
+                TypeSig::Pinned(Box::new(self.type_sig(inner, scope, line)?))
+            }
+            TypeExpr::Var(var) => TypeSig::Var(Scope::index(scope.type_params, var, line)?),
+            TypeExpr::MVar(var) => TypeSig::MVar(Scope::index(scope.method_params, var, line)?),
+            TypeExpr::Modified {
+                required,
+                modifier,
+                inner,
+            } => TypeSig::Modified {
+                required: *required,
+                modifier: self
+                    .resolve_type_name(modifier, line)?
+                    .encode(CodedIndexKind::TypeDefOrRef),
+                inner: Box::new(self.type_sig(inner, scope, line)?),
+            },
+        })
+    }
+
+    /// Resolve a type to a TypeDefOrRef index, creating a TypeSpec when needed.
+    fn type_token(&mut self, ty: &TypeExpr, scope: Scope<'_>, line: usize) -> Result<CodedIndex> {
+        if let TypeExpr::Named { name, args, .. } = ty {
+            if args.is_empty() {
+                return self.resolve_type_name(name, line);
+            }
+        }
+        let signature = self.type_sig(ty, scope, line)?.to_blob();
+        Ok(coded(TableId::TypeSpec, self.type_spec(signature)))
+    }
+
+    /// Find a MethodDef on a local type, or create a MemberRef.
+    fn resolve_method(
+        &mut self,
+        parent: CodedIndex,
+        name: &str,
+        signature: MethodSig,
+        line: usize,
+    ) -> Result<u32> {
+        let blob = signature.to_blob();
+        if parent.table == Some(TableId::TypeDef) {
+            if let Some(&rid) =
+                self.method_lookup
+                    .get(&(parent.row, name.to_string(), blob.clone()))
+            {
+                return Ok(token(coded(TableId::MethodDef, rid)));
+            }
+            if parent.row == 1 {
+                return Err(error(line, format!("undefined global method '{name}'")));
+            }
+        }
+        Ok(token(coded(
+            TableId::MemberRef,
+            self.member_ref(parent, name, blob),
+        )))
+    }
+
+    /// Resolve a method reference to a MethodDef, MemberRef or MethodSpec token.
+    fn method_token(&mut self, method: &MethodRefExpr, scope: Scope<'_>) -> Result<u32> {
+        let parent = match &method.owner {
+            Some(owner) => self.type_token(owner, scope, method.line)?,
+            None => coded(TableId::TypeDef, 1),
+        };
+
+        let return_type = self.type_sig(&method.return_type, scope, method.line)?;
+        let mut params = Vec::with_capacity(method.params.len());
+        for param in &method.params {
+            params.push(self.type_sig(param, scope, method.line)?);
+        }
+        let signature = MethodSig {
+            calling_convention: CallingConvention(method.call_conv),
+            generic_param_count: method.generic_arity,
+            return_type,
+            params,
+            sentinel: method.sentinel,
+        };
+
+        let mut resolved = match method.sentinel {
+            // Vararg call sites reference the definition through a MemberRef
+            Some(sentinel) if parent.table == Some(TableId::TypeDef) => {
+                let mut definition = signature.clone();
+                definition.params.truncate(sentinel);
+                definition.sentinel = None;
+                let target = from_token(self.resolve_method(
+                    parent,
+                    &method.name,
+                    definition,
+                    method.line,
+                )?);
+                let blob = signature.to_blob();
+                token(coded(
+                    TableId::MemberRef,
+                    self.member_ref(target, &method.name, blob),
+                ))
+            }
+            _ => self.resolve_method(parent, &method.name, signature, method.line)?,
+        };
+
+        if !method.generic_args.is_empty() {
+            let type_args = method
+                .generic_args
+                .iter()
+                .map(|arg| self.type_sig(arg, scope, method.line))
+                .collect::<Result<Vec<_>>>()?;
+            let instantiation = MethodSpecSig { type_args }.to_blob();
+            let target = from_token(resolved);
+            let key = (target.encode(CodedIndexKind::MethodDefOrRef), instantiation);
+            let rid = match self.method_specs.get(&key) {
+                Some(&rid) => rid,
+                None => {
+                    let blob = self.blob(&key.1);
+                    self.md.method_specs.push(MethodSpecRow {
+                        method: target,
+                        instantiation: blob,
+                    });
+                    let rid = self.md.method_specs.len() as u32;
+                    self.method_specs.insert(key, rid);
+                    rid
+                }
+            };
+            resolved = token(coded(TableId::MethodSpec, rid));
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve a field reference to a Field or MemberRef token.
+    fn field_token(&mut self, field: &FieldRefExpr, scope: Scope<'_>) -> Result<u32> {
+        let parent = match &field.owner {
+            Some(owner) => self.type_token(owner, scope, field.line)?,
+            None => coded(TableId::TypeDef, 1),
+        };
+        if parent.table == Some(TableId::TypeDef) {
+            if let Some(&rid) = self.field_lookup.get(&(parent.row, field.name.clone())) {
+                return Ok(token(coded(TableId::Field, rid)));
+            }
+            if parent.row == 1 {
+                return Err(error(
+                    field.line,
+                    format!("undefined global field '{}'", field.name),
+                ));
+            }
+        }
+        let field_type = self.type_sig(&field.ty, scope, field.line)?;
+        let signature = FieldSig { field_type }.to_blob();
+        Ok(token(coded(
+            TableId::MemberRef,
+            self.member_ref(parent, &field.name, signature),
+        )))
+    }
+
+    // ------------------------------------------------------------------------
+    // Method bodies
+    // ------------------------------------------------------------------------
+
+    fn emit_method_body(&mut self, index: usize) -> Result<Option<MethodBody>> {
+        let MethodEntry { decl, scope, .. } = self.methods[index];
+        let body = &decl.body;
+        if body.items.is_empty() {
+            if decl.flags & METHOD_ABSTRACT == 0
+                && decl.pinvoke.is_none()
+                && decl.impl_flags & 0x1003 == 0
+            {
+                return Err(error(
+                    decl.line,
+                    format!("method '{}' has no body", decl.name),
+                ));
+            }
+            return Ok(None);
+        }
+
+        let local_var_sig_token = if body.locals.is_empty() {
+            0
+        } else {
+            let mut locals = Vec::with_capacity(body.locals.len());
+            for local in &body.locals {
+                locals.push(self.type_sig(&local.ty, scope, decl.line)?);
+            }
+            let signature = LocalVarSig { locals }.to_blob();
+            token(coded(
+                TableId::StandAloneSig,
+                self.stand_alone_sig(signature),
+            ))
+        };
+
+        let mut code = CodeBuilder::new(decl, scope);
+        code.emit_items(self, &body.items)?;
+        let (code, exception_handlers) = code.finish()?;
+
+        Ok(Some(MethodBody {
+            max_stack: body.max_stack.unwrap_or(8),
+            init_locals: body.init_locals,
+            local_var_sig_token,
+            code,
+            exception_handlers,
+        }))
+    }
+}
+
+/// A branch operand awaiting its label's offset.
+struct Fixup {
+    line: usize,
+    position: usize,
+    /// Offset that the branch delta is relative to.
+    base: usize,
+    short: bool,
+    label: String,
+}
+
+/// Encoder for a single method's instruction stream.
+struct CodeBuilder<'f> {
+    method: &'f MethodDecl,
+    scope: Scope<'f>,
+    code: Vec<u8>,
+    labels: HashMap<String, usize>,
+    fixups: Vec<Fixup>,
+    handlers: Vec<ExceptionHandler>,
+}
+
+impl<'f> CodeBuilder<'f> {
+    fn new(method: &'f MethodDecl, scope: Scope<'f>) -> Self {
+        Self {
+            method,
+            scope,
+            code: Vec::new(),
+            labels: HashMap::new(),
+            fixups: Vec::new(),
+            handlers: Vec::new(),
+        }
+    }
+
+    fn emit_items(&mut self, emitter: &mut Emitter<'f>, items: &[BodyItem]) -> Result<()> {
+        for item in items {
+            match item {
+                BodyItem::Label(label) => {
+                    if self.labels.insert(label.clone(), self.code.len()).is_some() {
+                        let line = self.method.line;
+                        return Err(error(line, format!("duplicate label '{label}'")));
+                    }
+                }
+                BodyItem::Instruction {
+                    line,
+                    opcode,
+                    operand,
+                } => {
+                    self.emit_instruction(emitter, *line, opcode, operand)?;
+                }
+                BodyItem::Try { body, handlers } => {
+                    let try_offset = self.code.len() as u32;
+                    self.emit_items(emitter, body)?;
+                    let try_length = self.code.len() as u32 - try_offset;
+
+                    for handler in handlers {
+                        let (kind, class_token_or_filter) = match &handler.kind {
+                            HandlerKindDecl::Catch(ty) => {
+                                let line = self.method.line;
+                                let scope = self.scope;
+                                let class = emitter.type_token(ty, scope, line)?;
+                                (ExceptionHandlerKind::Catch, token(class))
+                            }
+                            HandlerKindDecl::Filter(filter) => {
+                                let filter_offset = self.code.len() as u32;
+                                self.emit_items(emitter, filter)?;
+                                (ExceptionHandlerKind::Filter, filter_offset)
+                            }
+                            HandlerKindDecl::Finally => (ExceptionHandlerKind::Finally, 0),
+                            HandlerKindDecl::Fault => (ExceptionHandlerKind::Fault, 0),
+                        };
+                        let handler_offset = self.code.len() as u32;
+                        self.emit_items(emitter, &handler.body)?;
+                        self.handlers.push(ExceptionHandler {
+                            kind,
+                            try_offset,
+                            try_length,
+                            handler_offset,
+                            handler_length: self.code.len() as u32 - handler_offset,
+                            class_token_or_filter,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn var_index(&self, opcode: &OpCode, var: &VarRef, line: usize) -> Result<u16> {
+        let name = match var {
+            VarRef::Index(index) => return Ok(*index),
+            VarRef::Name(name) => name,
+        };
+        let method = self.method;
+
+        if opcode.name.contains("arg") {
+            let has_this = method.call_conv & CallingConvention::HAS_THIS != 0;
+            method
+                .params
+                .iter()
+                .position(|p| p.name.as_deref() == Some(name.as_str()))
+                .map(|i| i as u16 + u16::from(has_this))
+                .ok_or_else(|| error(line, format!("unknown argument '{name}'")))
+        } else {
+            method
+                .body
+                .locals
+                .iter()
+                .position(|l| l.name.as_deref() == Some(name.as_str()))
+                .map(|i| i as u16)
+                .ok_or_else(|| error(line, format!("unknown local '{name}'")))
+        }
+    }
+
+    fn emit_instruction(
+        &mut self,
+        emitter: &mut Emitter<'f>,
+        line: usize,
+        opcode: &OpCode,
+        operand: &OperandExpr,
+    ) -> Result<()> {
+        if opcode.value > 0xFF {
+            self.code.push(0xFE);
+        }
+        self.code.push(opcode.value as u8);
+
+        let scope = self.scope;
+        let out_of_range = || error(line, format!("operand out of range for '{}'", opcode.name));
+
+        match (opcode.operand, operand) {
+            (OperandType::None, OperandExpr::None) => {}
+            (OperandType::ShortI, OperandExpr::Int(v)) => {
+                if !(-128..=255).contains(v) {
+                    return Err(out_of_range());
+                }
+                self.code.push(*v as u8);
+            }
+            (OperandType::I, OperandExpr::Int(v)) => {
+                if !(i64::from(i32::MIN)..=i64::from(u32::MAX)).contains(v) {
+                    return Err(out_of_range());
+                }
+                self.code.extend_from_slice(&(*v as u32).to_le_bytes());
+            }
+            (OperandType::I8, OperandExpr::Int(v)) => self.code.extend_from_slice(&v.to_le_bytes()),
+            (OperandType::ShortR, OperandExpr::Float(v)) => {
+                self.code.extend_from_slice(&(*v as f32).to_le_bytes());
+            }
+            (OperandType::R, OperandExpr::Float(v)) => {
+                self.code.extend_from_slice(&v.to_le_bytes())
+            }
+            (OperandType::ShortVar, OperandExpr::Var(var)) => {
+                let index =
+                    u8::try_from(self.var_index(opcode, var, line)?).map_err(|_| out_of_range())?;
+                self.code.push(index);
+            }
+            (OperandType::Var, OperandExpr::Var(var)) => {
+                let index = self.var_index(opcode, var, line)?;
+                self.code.extend_from_slice(&index.to_le_bytes());
+            }
+            (OperandType::ShortBrTarget | OperandType::BrTarget, OperandExpr::Label(label)) => {
+                let short = opcode.operand == OperandType::ShortBrTarget;
+                let position = self.code.len();
+                let size = if short { 1 } else { 4 };
+                self.code.resize(position + size, 0);
+                self.fixups.push(Fixup {
+                    line,
+                    position,
+                    base: position + size,
+                    short,
+                    label: label.clone(),
+                });
+            }
+            (OperandType::Switch, OperandExpr::Labels(labels)) => {
+                self.code
+                    .extend_from_slice(&(labels.len() as u32).to_le_bytes());
+                let start = self.code.len();
+                let base = start + labels.len() * 4;
+                for (i, label) in labels.iter().enumerate() {
+                    self.fixups.push(Fixup {
+                        line,
+                        position: start + i * 4,
+                        base,
+                        short: false,
+                        label: label.clone(),
+                    });
+                }
+                self.code.resize(base, 0);
+            }
+            (OperandType::Method | OperandType::Tok, OperandExpr::Method(method)) => {
+                let token = emitter.method_token(method, scope)?;
+                self.code.extend_from_slice(&token.to_le_bytes());
+            }
+            (OperandType::Field | OperandType::Tok, OperandExpr::Field(field)) => {
+                let token = emitter.field_token(field, scope)?;
+                self.code.extend_from_slice(&token.to_le_bytes());
+            }
+            (OperandType::Type | OperandType::Tok, OperandExpr::Type(ty)) => {
+                let token = token(emitter.type_token(ty, scope, line)?);
+                self.code.extend_from_slice(&token.to_le_bytes());
+            }
+            (OperandType::String, OperandExpr::String(value)) => {
+                let token = 0x7000_0000 | emitter.user_string(value);
+                self.code.extend_from_slice(&token.to_le_bytes());
+            }
+            (OperandType::Sig, OperandExpr::Sig(sig)) => {
+                let return_type = emitter.type_sig(&sig.return_type, scope, line)?;
+                let mut params = Vec::with_capacity(sig.params.len());
+                for param in &sig.params {
+                    params.push(emitter.type_sig(param, scope, line)?);
+                }
+                let signature = MethodSig {
+                    calling_convention: CallingConvention(sig.call_conv),
+                    generic_param_count: 0,
+                    return_type,
+                    params,
+                    sentinel: sig.sentinel,
+                }
+                .to_blob();
+                let token = token(coded(
+                    TableId::StandAloneSig,
+                    emitter.stand_alone_sig(signature),
+                ));
+                self.code.extend_from_slice(&token.to_le_bytes());
+            }
+            _ => {
+                return Err(error(
+                    line,
+                    format!("invalid operand for '{}'", opcode.name),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(Vec<u8>, Vec<ExceptionHandler>)> {
+        for fixup in &self.fixups {
+            let target = *self
+                .labels
+                .get(&fixup.label)
+                .ok_or_else(|| error(fixup.line, format!("undefined label '{}'", fixup.label)))?;
+            let delta = target as i64 - fixup.base as i64;
+            if fixup.short {
+                let delta = i8::try_from(delta).map_err(|_| {
+                    error(
+                        fixup.line,
+                        format!("branch to '{}' out of short range", fixup.label),
+                    )
+                })?;
+                self.code[fixup.position] = delta as u8;
+            } else {
+                self.code[fixup.position..fixup.position + 4]
+                    .copy_from_slice(&(delta as i32).to_le_bytes());
+            }
+        }
+        Ok((self.code, self.handlers))
+    }
+}
@@ -0,0 +1,360 @@
+//! Tokenizer for ILAsm source text.
+
+use crate::error::{Error, Result};
+use crate::il::OpCode;
+
+/// A lexical token.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    /// Identifier, keyword or dotted name (e.g., `System.Console`, `ldc.i4.0`).
+    Ident(String),
+    /// Directive (e.g., `.class`, `.ctor`).
+    Directive(String),
+    /// Single-quoted identifier.
+    Quoted(String),
+    /// Double-quoted string literal.
+    Str(String),
+    /// Integer literal.
+    Int(i64),
+    /// Floating-point literal.
+    Float(f64),
+    /// Punctuation.
+    Punct(&'static str),
+    /// End of input.
+    Eof,
+}
+
+/// Multi-character punctuation, longest first.
+const PUNCTUATION: &[&str] = &[
+    "...", "::", "!!", "{", "}", "(", ")", "[", "]", "<", ">", ",", ":", "=", "&", "*", "!", "/",
+    "+", "-",
+];
+
+/// Cursor over ILAsm source text.
+pub(crate) struct Lexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(src: &'a str) -> Self {
+        Self {
+            src: src.as_bytes(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    /// Current line number (1-based).
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::IlAsm {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn peek_byte(&self, ahead: usize) -> Option<u8> {
+        self.src.get(self.pos + ahead).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let b = self.peek_byte(0)?;
+        self.pos += 1;
+        if b == b'\n' {
+            self.line += 1;
+        }
+        Some(b)
+    }
+
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            match (self.peek_byte(0), self.peek_byte(1)) {
+                (Some(b), _) if b.is_ascii_whitespace() => {
+                    self.bump();
+                }
+                (Some(b'/'), Some(b'/')) => {
+                    while let Some(b) = self.peek_byte(0) {
+                        if b == b'\n' {
+                            break;
+                        }
+                        self.bump();
+                    }
+                }
+                (Some(b'/'), Some(b'*')) => {
+                    self.pos += 2;
+                    loop {
+                        match (self.peek_byte(0), self.peek_byte(1)) {
+                            (Some(b'*'), Some(b'/')) => {
+                                self.pos += 2;
+                                break;
+                            }
+                            (Some(_), _) => {
+                                self.bump();
+                            }
+                            (None, _) => return Err(self.error("unterminated comment")),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Peek at the first significant character after the current position.
+    pub(crate) fn peek_char(&mut self) -> Result<Option<u8>> {
+        self.skip_trivia()?;
+        Ok(self.peek_byte(0))
+    }
+
+    fn is_ident_start(b: u8) -> bool {
+        b.is_ascii_alphabetic() || matches!(b, b'_' | b'$' | b'@' | b'`' | b'?')
+    }
+
+    fn is_ident_char(b: u8) -> bool {
+        b.is_ascii_alphanumeric() || matches!(b, b'_' | b'$' | b'@' | b'`' | b'?')
+    }
+
+    /// Read the next token.
+    pub(crate) fn next_token(&mut self) -> Result<Token> {
+        self.skip_trivia()?;
+        let Some(b) = self.peek_byte(0) else {
+            return Ok(Token::Eof);
+        };
+
+        if Self::is_ident_start(b) {
+            return Ok(Token::Ident(self.read_ident()));
+        }
+        if b == b'.' && self.peek_byte(1).is_some_and(Self::is_ident_start) {
+            self.pos += 1;
+            let mut name = String::from(".");
+            while let Some(b) = self.peek_byte(0).filter(|&b| Self::is_ident_char(b)) {
+                name.push(b as char);
+                self.pos += 1;
+            }
+            return Ok(Token::Directive(name));
+        }
+        if b.is_ascii_digit() {
+            return self.read_number();
+        }
+        if b == b'"' || b == b'\'' {
+            self.pos += 1;
+            let s = self.read_quoted(b)?;
+            return Ok(if b == b'"' {
+                Token::Str(s)
+            } else {
+                Token::Quoted(s)
+            });
+        }
+
+        for p in PUNCTUATION {
+            if self.src[self.pos..].starts_with(p.as_bytes()) {
+                self.pos += p.len();
+                return Ok(Token::Punct(p));
+            }
+        }
+
+        Err(self.error(format!("unexpected character '{}'", b as char)))
+    }
+
+    fn read_ident(&mut self) -> String {
+        let start = self.pos;
+        while let Some(b) = self.peek_byte(0) {
+            let dotted = b == b'.' && self.peek_byte(1).is_some_and(Self::is_ident_char);
+            if Self::is_ident_char(b) || dotted {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        // Prefix opcodes end with a dot (e.g., "volatile.", "tail.")
+        if self.peek_byte(0) == Some(b'.') {
+            let with_dot = format!("{}.", self.text(start));
+            if OpCode::from_name(&with_dot).is_some() {
+                self.pos += 1;
+                return with_dot;
+            }
+        }
+        self.text(start)
+    }
+
+    fn text(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+
+    fn read_number(&mut self) -> Result<Token> {
+        let start = self.pos;
+        if self.peek_byte(0) == Some(b'0') && matches!(self.peek_byte(1), Some(b'x' | b'X')) {
+            self.pos += 2;
+            let digits = self.pos;
+            while self.peek_byte(0).is_some_and(|b| b.is_ascii_hexdigit()) {
+                self.pos += 1;
+            }
+            let text = self.text(digits);
+            return u64::from_str_radix(&text, 16)
+                .map(|v| Token::Int(v as i64))
+                .map_err(|_| self.error(format!("invalid hex literal '0x{text}'")));
+        }
+
+        while self.peek_byte(0).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let mut is_float = false;
+        if self.peek_byte(0) == Some(b'.') && self.peek_byte(1).is_some_and(|b| b.is_ascii_digit())
+        {
+            is_float = true;
+            self.pos += 1;
+            while self.peek_byte(0).is_some_and(|b| b.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        if matches!(self.peek_byte(0), Some(b'e' | b'E')) {
+            let sign = usize::from(matches!(self.peek_byte(1), Some(b'+' | b'-')));
+            if self.peek_byte(1 + sign).is_some_and(|b| b.is_ascii_digit()) {
+                is_float = true;
+                self.pos += 1 + sign;
+                while self.peek_byte(0).is_some_and(|b| b.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            }
+        }
+
+        let text = self.text(start);
+        if is_float {
+            text.parse()
+                .map(Token::Float)
+                .map_err(|_| self.error(format!("invalid float literal '{text}'")))
+        } else {
+            text.parse::<u64>()
+                .map(|v| Token::Int(v as i64))
+                .map_err(|_| self.error(format!("invalid integer literal '{text}'")))
+        }
+    }
+
+    fn read_quoted(&mut self, quote: u8) -> Result<String> {
+        let mut bytes = Vec::new();
+        loop {
+            let Some(b) = self.bump() else {
+                return Err(self.error("unterminated string"));
+            };
+            match b {
+                b if b == quote => break,
+                b'\\' => {
+                    let Some(escaped) = self.bump() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    match escaped {
+                        b'n' => bytes.push(b'\n'),
+                        b't' => bytes.push(b'\t'),
+                        b'r' => bytes.push(b'\r'),
+                        b'0' => bytes.push(0),
+                        b'\n' => {}
+                        other => bytes.push(other),
+                    }
+                }
+                b => bytes.push(b),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    /// Read hex byte pairs up to and including the closing parenthesis.
+    pub(crate) fn read_hex_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            match self.peek_byte(0) {
+                Some(b')') => {
+                    self.pos += 1;
+                    return Ok(bytes);
+                }
+                Some(hi) if hi.is_ascii_hexdigit() => {
+                    let lo = self
+                        .peek_byte(1)
+                        .filter(u8::is_ascii_hexdigit)
+                        .ok_or_else(|| self.error("expected hex byte pair"))?;
+                    let pair = [hi, lo];
+                    let text = std::str::from_utf8(&pair).unwrap_or_default();
+                    bytes.push(
+                        u8::from_str_radix(text, 16).map_err(|_| self.error("invalid hex byte"))?,
+                    );
+                    self.pos += 2;
+                }
+                Some(other) => {
+                    return Err(self.error(format!("unexpected '{}' in byte list", other as char)));
+                }
+                None => return Err(self.error("unterminated byte list")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(src: &str) -> Vec<Token> {
+        let mut lexer = Lexer::new(src);
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.next_token().unwrap();
+            if token == Token::Eof {
+                return tokens;
+            }
+            tokens.push(token);
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            tokens("call void [mscorlib]System.Console::WriteLine(string) // comment\nldc.i4.s -1"),
+            [
+                Token::Ident("call".into()),
+                Token::Ident("void".into()),
+                Token::Punct("["),
+                Token::Ident("mscorlib".into()),
+                Token::Punct("]"),
+                Token::Ident("System.Console".into()),
+                Token::Punct("::"),
+                Token::Ident("WriteLine".into()),
+                Token::Punct("("),
+                Token::Ident("string".into()),
+                Token::Punct(")"),
+                Token::Ident("ldc.i4.s".into()),
+                Token::Punct("-"),
+                Token::Int(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directives_strings_numbers() {
+        assert_eq!(
+            tokens(".method .ctor 'it''s' \"a\\n\" 0x10 1.5 tail. !!0"),
+            [
+                Token::Directive(".method".into()),
+                Token::Directive(".ctor".into()),
+                Token::Quoted("it".into()),
+                Token::Quoted("s".into()),
+                Token::Str("a\n".into()),
+                Token::Int(16),
+                Token::Float(1.5),
+                Token::Ident("tail.".into()),
+                Token::Punct("!!"),
+                Token::Int(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hex_bytes() {
+        let mut lexer = Lexer::new("01 00 AB 1E )");
+        assert_eq!(lexer.read_hex_bytes().unwrap(), [0x01, 0x00, 0xAB, 0x1E]);
+    }
+}
@@ -0,0 +1,264 @@
+//! ILAsm text assembler.
+//!
+//! Assembles a practical subset of ILAsm source (the syntax produced by ildasm
+//! and accepted by ilasm) into [`Metadata`] plus IL method bodies. This is
+//! primarily intended for writing readable test fixtures:
+//!
+//! - `.assembly`, `.assembly extern`, `.module`, `.module extern`
+//! - `.namespace` and `.class` (nested, generic, value types, enums, interfaces)
+//! - `.field` (including `= int32(5)` style initializers), `.method`
+//!   (including `pinvokeimpl`), `.property`, `.event`
+//! - IL instructions with labels, `.locals`, `.try` / `catch` / `filter` /
+//!   `finally` / `fault`
+//! - `.custom` attributes given as raw bytes or decoded `{ ... }` arguments
+//!
+//! Method bodies are not placed in a PE image; RVAs are left as zero and the
+//! bodies are returned alongside the metadata, indexed by MethodDef row.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! let module = clrmeta::ilasm::assemble(source)?;
+//! let bytes = module.metadata.write();
+//! let main = module.method_body(1);
+//! ```
+
+mod ast;
+mod emit;
+mod lexer;
+mod parser;
+
+use crate::crypto;
+use crate::error::Result;
+use crate::il::MethodBody;
+use crate::metadata::Metadata;
+
+/// Result of assembling ILAsm source.
+#[derive(Debug)]
+pub struct AssembledModule {
+    /// Assembled metadata.
+    pub metadata: Metadata,
+    /// Method bodies, indexed by MethodDef row - 1 (`None` for abstract,
+    /// runtime-implemented or P/Invoke methods).
+    pub method_bodies: Vec<Option<MethodBody>>,
+    /// Entry point MethodDef token, if a method declares `.entrypoint`.
+    pub entry_point: Option<u32>,
+}
+
+impl AssembledModule {
+    /// Get the body of a method by MethodDef row (1-based).
+    pub fn method_body(&self, rid: u32) -> Option<&MethodBody> {
+        let index = rid.checked_sub(1)? as usize;
+        self.method_bodies.get(index)?.as_ref()
+    }
+}
+
+/// Assemble ILAsm source text.
+///
+/// The module MVID is derived from a hash of the source, so assembling the
+/// same text twice yields identical metadata.
+pub fn assemble(source: &str) -> Result<AssembledModule> {
+    let file = parser::Parser::new(source).parse_file()?;
+    let hash = crypto::sha1(source.as_bytes());
+    let mut mvid = [0u8; 16];
+    mvid.copy_from_slice(&hash[..16]);
+    emit::Emitter::new(&file).emit(mvid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::ConstantValue;
+    use crate::error::Error;
+    use crate::il::Operand;
+    use crate::tables::TableId;
+
+    const HELLO: &str = include_str!("../../tests/fixtures/hello.il");
+
+    fn assemble_err(source: &str) -> (usize, String) {
+        match assemble(source) {
+            Err(Error::IlAsm { line, message }) => (line, message),
+            other => panic!("expected ilasm error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_assemble_hello() {
+        let module = assemble(HELLO).unwrap();
+        let md = &module.metadata;
+
+        let assembly = md.assembly().unwrap();
+        assert_eq!(assembly.name, "Hello");
+        assert_eq!(assembly.version, (1, 2, 3, 4));
+        assert_eq!(md.assembly_refs.len(), 1);
+        assert_eq!(md.strings.get(md.modules[0].name).unwrap(), "Hello.exe");
+
+        let types = md.types();
+        assert!(
+            types
+                .iter()
+                .any(|t| t.name == "Program" && t.namespace.as_deref() == Some("Samples"))
+        );
+        assert_eq!(md.method_defs.len(), 3);
+        assert_eq!(md.fields.len(), 1);
+        assert_eq!(md.custom_attributes.len(), 1);
+        assert_eq!(module.entry_point, Some(0x0600_0001));
+
+        let main = module.method_body(1).unwrap();
+        assert_eq!(main.max_stack, 2);
+        assert!(main.init_locals);
+        assert_eq!(
+            main.local_var_sig_token >> 24,
+            TableId::StandAloneSig as u32
+        );
+        assert_eq!(main.exception_handlers.len(), 1);
+
+        let instructions = main.instructions().unwrap();
+        assert!(
+            instructions
+                .iter()
+                .any(|i| i.opcode.name == "ldstr" && i.operand == Operand::Token(0x7000_0001))
+        );
+        // Intra-module calls bind to the MethodDef, not a MemberRef
+        assert!(
+            instructions
+                .iter()
+                .any(|i| i.opcode.name == "call" && i.operand == Operand::Token(0x0600_0002))
+        );
+        assert_eq!(md.user_strings.get(1).unwrap(), "Hello, World!");
+    }
+
+    #[test]
+    fn test_roundtrip_through_writer() {
+        let module = assemble(HELLO).unwrap();
+        let bytes = module.metadata.write();
+        let parsed = Metadata::parse(&bytes).unwrap();
+
+        assert_eq!(parsed.type_defs.len(), module.metadata.type_defs.len());
+        assert_eq!(parsed.member_refs.len(), module.metadata.member_refs.len());
+        assert_eq!(parsed.assembly().unwrap().name, "Hello");
+        assert_eq!(parsed.user_strings.get(1).unwrap(), "Hello, World!");
+    }
+
+    #[test]
+    fn test_empty_user_string() {
+        let source = r#"
+            .assembly extern mscorlib {}
+            .assembly S {}
+            .class public C extends [mscorlib]System.Object {
+                .method public static string Empty() {
+                    ldstr ""
+                    ret
+                }
+            }
+        "#;
+        let module = assemble(source).unwrap();
+        let ldstr = &module.method_body(1).unwrap().instructions().unwrap()[0];
+        assert_eq!(ldstr.operand, Operand::Token(0x7000_0001));
+
+        let parsed = Metadata::parse(&module.metadata.write()).unwrap();
+        assert_eq!(parsed.user_strings.get(1).unwrap(), "");
+        assert!(parsed.user_strings.data().starts_with(&[0x00, 0x01, 0x00]));
+    }
+
+    #[test]
+    fn test_field_initializers() {
+        let source = r#"
+            .assembly extern mscorlib {}
+            .assembly F {}
+            .class public C extends [mscorlib]System.Object {
+                .field public static literal int32 Max = int32(-5)
+                .field public static literal unsigned int8 Byte = uint8(200)
+                .field public static literal bool Flag = bool(true)
+                .field public static literal float64 Pi = float64(3.5)
+                .field public static literal string Name = "a" + "b"
+                .field public static literal object Nothing = nullref
+                .field public int32 plain
+            }
+        "#;
+        let md = assemble(source).unwrap().metadata;
+        let values: Vec<ConstantValue> = (1..=6)
+            .map(|row| md.field_default_value(row).unwrap().unwrap())
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ConstantValue::I4(-5),
+                ConstantValue::U1(200),
+                ConstantValue::Boolean(true),
+                ConstantValue::R8(3.5),
+                ConstantValue::String("ab".into()),
+                ConstantValue::Null,
+            ]
+        );
+        assert!(md.field_default_value(7).is_none());
+        assert_eq!(md.fields[0].flags & 0x8000, 0x8000);
+        assert_eq!(md.fields[6].flags & 0x8000, 0);
+
+        let (_, message) = assemble_err(
+            ".assembly A {}\n.class C {\n  .field static literal int32 X = bytearray(01)\n}\n",
+        );
+        assert!(message.contains("bytearray"), "{message}");
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let a = assemble(HELLO).unwrap().metadata.write();
+        let b = assemble(HELLO).unwrap().metadata.write();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_error_line_numbers() {
+        let (line, message) =
+            assemble_err(".assembly A {}\n.class C {\n  .method void M() {\n    bogus\n  }\n}\n");
+        assert_eq!(line, 4);
+        assert!(message.contains("bogus"), "{message}");
+
+        let (line, message) = assemble_err(
+            ".assembly A {}\n.class C {\n  .method static void M() {\n    br L\n  }\n}\n",
+        );
+        assert_eq!(line, 4);
+        assert!(message.contains("undefined label"), "{message}");
+
+        let (_, message) = assemble_err(".class C extends Missing {}\n");
+        assert!(message.contains("undefined type"), "{message}");
+    }
+
+    #[test]
+    fn test_generics_and_properties() {
+        let source = r#"
+            .assembly extern mscorlib {}
+            .assembly G {}
+            .class public interface abstract IBox`1<T> {
+                .method public abstract virtual instance !T get_Value() {}
+                .property instance !T Value() { .get instance !0 IBox`1::get_Value() }
+            }
+            .class public Box`1<T> extends [mscorlib]System.Object implements class IBox`1<!T> {
+                .field private !T value
+                .method public virtual instance !T get_Value() {
+                    ldarg.0
+                    ldfld !0 class Box`1<!T>::value
+                    ret
+                }
+                .method public static !!U Id<U>(!!U x) {
+                    ldarg x
+                    ret
+                }
+            }
+        "#;
+        let module = assemble(source).unwrap();
+        let md = &module.metadata;
+        assert_eq!(md.generic_params.len(), 3);
+        assert_eq!(md.interface_impls.len(), 1);
+        assert_eq!(md.type_specs.len(), 2);
+        assert_eq!(md.properties.len(), 1);
+        assert_eq!(md.method_semantics.len(), 1);
+        assert!(module.method_body(1).is_none());
+
+        // Field access through the generic instantiation goes through a MemberRef
+        let body = module.method_body(2).unwrap();
+        let ldfld = &body.instructions().unwrap()[1];
+        assert_eq!(ldfld.operand, Operand::Token(0x0A00_0001));
+    }
+}
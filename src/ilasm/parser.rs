@@ -0,0 +1,1658 @@
+//! Recursive-descent parser from ILAsm tokens to the syntax tree.
+
+use super::ast::{
+    AssemblyDecl, AssemblyRefDecl, BodyItem, ClassDecl, CustomDecl, CustomValueDecl, EventDecl,
+    ExportedTypeDecl, FieldDecl, FieldRefExpr, GenericParamDecl, HandlerDecl, HandlerKindDecl,
+    LocalDecl, MethodBodyDecl, MethodDecl, MethodRefExpr, NamedKind, OperandExpr, OverrideDecl,
+    PInvokeDecl, ParamDecl, PropertyDecl, ScopeExpr, SourceFile, TypeExpr, TypeNameExpr, VarRef,
+};
+use super::lexer::{Lexer, Token};
+use crate::constant::ConstantValue;
+use crate::custom_attribute::{CaNamedArg, CaType, CaValue};
+use crate::error::{Error, Result};
+use crate::il::{OpCode, OperandType};
+use crate::signature::{CallingConvention, ElementType};
+
+/// Default hash algorithm for `.assembly` (SHA-1).
+const DEFAULT_HASH_ALG_ID: u32 = 0x8004;
+
+pub(crate) struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Token>,
+    line: usize,
+    namespaces: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(source: &'a str) -> Self {
+        Self {
+            lexer: Lexer::new(source),
+            peeked: None,
+            line: 1,
+            namespaces: Vec::new(),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Token helpers
+    // ------------------------------------------------------------------------
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::IlAsm {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<&Token> {
+        if self.peeked.is_none() {
+            let token = self.lexer.next_token()?;
+            self.line = self.lexer.line();
+            self.peeked = Some(token);
+        }
+        Ok(self.peeked.as_ref().unwrap_or(&Token::Eof))
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        self.peek()?;
+        Ok(self.peeked.take().unwrap_or(Token::Eof))
+    }
+
+    fn is_punct(&mut self, punct: &str) -> Result<bool> {
+        Ok(matches!(self.peek()?, Token::Punct(p) if *p == punct))
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> Result<bool> {
+        if self.is_punct(punct)? {
+            self.next()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<()> {
+        match self.next()? {
+            Token::Punct(p) if p == punct => Ok(()),
+            other => Err(self.error(format!("expected '{punct}', found {other:?}"))),
+        }
+    }
+
+    fn is_ident(&mut self, keyword: &str) -> Result<bool> {
+        Ok(matches!(self.peek()?, Token::Ident(s) if s == keyword))
+    }
+
+    fn eat_ident(&mut self, keyword: &str) -> Result<bool> {
+        if self.is_ident(keyword)? {
+            self.next()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn expect_ident(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_ident(keyword)? {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{keyword}'")))
+        }
+    }
+
+    /// Peek an identifier keyword (without consuming it).
+    fn peek_keyword(&mut self) -> Result<Option<String>> {
+        Ok(match self.peek()? {
+            Token::Ident(s) => Some(s.clone()),
+            _ => None,
+        })
+    }
+
+    fn is_name(&mut self) -> Result<bool> {
+        Ok(matches!(self.peek()?, Token::Ident(_) | Token::Quoted(_)))
+    }
+
+    /// Parse an identifier, quoted name or `.ctor`/`.cctor`.
+    fn expect_name(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(s) | Token::Quoted(s) => Ok(s),
+            Token::Directive(s) if s == ".ctor" || s == ".cctor" => Ok(s),
+            other => Err(self.error(format!("expected name, found {other:?}"))),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<String> {
+        let mut value = match self.next()? {
+            Token::Str(s) | Token::Quoted(s) => s,
+            other => return Err(self.error(format!("expected string, found {other:?}"))),
+        };
+        while self.eat_punct("+")? {
+            match self.next()? {
+                Token::Str(s) | Token::Quoted(s) => value.push_str(&s),
+                other => return Err(self.error(format!("expected string, found {other:?}"))),
+            }
+        }
+        Ok(value)
+    }
+
+    fn expect_int(&mut self) -> Result<i64> {
+        let negative = self.eat_punct("-")?;
+        match self.next()? {
+            Token::Int(v) => Ok(if negative { v.wrapping_neg() } else { v }),
+            other => Err(self.error(format!("expected integer, found {other:?}"))),
+        }
+    }
+
+    fn expect_float(&mut self) -> Result<f64> {
+        let negative = self.eat_punct("-")?;
+        let value = match self.next()? {
+            Token::Float(v) => v,
+            Token::Int(v) => v as f64,
+            other => return Err(self.error(format!("expected number, found {other:?}"))),
+        };
+        Ok(if negative { -value } else { value })
+    }
+
+    fn expect_u16(&mut self) -> Result<u16> {
+        let value = self.expect_int()?;
+        u16::try_from(value).map_err(|_| self.error(format!("value {value} out of range")))
+    }
+
+    fn expect_u32(&mut self) -> Result<u32> {
+        let value = self.expect_int()?;
+        u32::try_from(value).map_err(|_| self.error(format!("value {value} out of range")))
+    }
+
+    /// Parse `( hex bytes )`.
+    fn byte_list(&mut self) -> Result<Vec<u8>> {
+        self.expect_punct("(")?;
+        self.lexer.read_hex_bytes()
+    }
+
+    fn unsupported(&self, what: &str) -> Error {
+        self.error(format!("unsupported {what}"))
+    }
+
+    // ------------------------------------------------------------------------
+    // Top level
+    // ------------------------------------------------------------------------
+
+    /// Parse a complete source file.
+    pub(crate) fn parse_file(mut self) -> Result<SourceFile> {
+        let mut file = SourceFile::default();
+        self.parse_declarations(&mut file, false)?;
+        Ok(file)
+    }
+
+    fn parse_declarations(&mut self, file: &mut SourceFile, in_namespace: bool) -> Result<()> {
+        loop {
+            let directive = match self.next()? {
+                Token::Eof if !in_namespace => return Ok(()),
+                Token::Punct("}") if in_namespace => return Ok(()),
+                Token::Directive(d) => d,
+                other => return Err(self.error(format!("unexpected {other:?}"))),
+            };
+
+            match directive.as_str() {
+                ".assembly" => {
+                    if self.eat_ident("extern")? {
+                        let assembly_ref = self.parse_assembly_ref()?;
+                        file.assembly_refs.push(assembly_ref);
+                    } else {
+                        file.assembly = Some(self.parse_assembly()?);
+                    }
+                }
+                ".module" => {
+                    if self.eat_ident("extern")? {
+                        let name = self.expect_name()?;
+                        file.module_refs.push(name);
+                    } else {
+                        file.module_name = Some(self.expect_name()?);
+                    }
+                }
+                ".namespace" => {
+                    let name = self.expect_name()?;
+                    self.expect_punct("{")?;
+                    self.namespaces.push(name);
+                    self.parse_declarations(file, true)?;
+                    self.namespaces.pop();
+                }
+                ".class" if self.eat_ident("extern")? => {
+                    let exported = self.parse_exported_type()?;
+                    file.exported_types.push(exported);
+                }
+                ".class" => {
+                    let class = self.parse_class(false)?;
+                    file.classes.push(class);
+                }
+                ".field" => {
+                    let field = self.parse_field()?;
+                    file.global_fields.push(field);
+                }
+                ".method" => {
+                    let method = self.parse_method()?;
+                    file.global_methods.push(method);
+                }
+                ".custom" => {
+                    let custom = self.parse_custom()?;
+                    file.module_customs.push(custom);
+                }
+                ".corflags" | ".subsystem" | ".imagebase" | ".stackreserve" => {
+                    self.expect_int()?;
+                }
+                ".file" if self.eat_ident("alignment")? => {
+                    self.expect_int()?;
+                }
+                ".mscorlib" => {}
+                other => return Err(self.unsupported(&format!("directive '{other}'"))),
+            }
+        }
+    }
+
+    fn parse_version(&mut self) -> Result<[u16; 4]> {
+        let mut version = [0u16; 4];
+        for (i, part) in version.iter_mut().enumerate() {
+            if i > 0 {
+                self.expect_punct(":")?;
+            }
+            *part = self.expect_u16()?;
+        }
+        Ok(version)
+    }
+
+    fn parse_assembly(&mut self) -> Result<AssemblyDecl> {
+        let mut assembly = AssemblyDecl {
+            hash_alg_id: DEFAULT_HASH_ALG_ID,
+            ..Default::default()
+        };
+        while self.eat_ident("retargetable")? {
+            assembly.flags |= 0x0100;
+        }
+        assembly.name = self.expect_name()?;
+        self.expect_punct("{")?;
+
+        loop {
+            match self.next()? {
+                Token::Punct("}") => return Ok(assembly),
+                Token::Directive(d) => match d.as_str() {
+                    ".ver" => assembly.version = self.parse_version()?,
+                    ".publickey" => {
+                        self.expect_punct("=")?;
+                        assembly.public_key = self.byte_list()?;
+                        assembly.flags |= 0x0001;
+                    }
+                    ".hash" => {
+                        self.expect_ident("algorithm")?;
+                        assembly.hash_alg_id = self.expect_u32()?;
+                    }
+                    ".locale" | ".culture" => assembly.culture = self.expect_string()?,
+                    ".custom" => {
+                        let custom = self.parse_custom()?;
+                        assembly.customs.push(custom);
+                    }
+                    other => {
+                        return Err(self.unsupported(&format!("directive '{other}' in .assembly")));
+                    }
+                },
+                other => return Err(self.error(format!("unexpected {other:?} in .assembly"))),
+            }
+        }
+    }
+
+    fn parse_assembly_ref(&mut self) -> Result<AssemblyRefDecl> {
+        let mut assembly_ref = AssemblyRefDecl::default();
+        while self.eat_ident("retargetable")? {
+            assembly_ref.flags |= 0x0100;
+        }
+        assembly_ref.name = self.expect_name()?;
+        self.expect_punct("{")?;
+
+        loop {
+            match self.next()? {
+                Token::Punct("}") => return Ok(assembly_ref),
+                Token::Directive(d) => match d.as_str() {
+                    ".ver" => assembly_ref.version = self.parse_version()?,
+                    ".publickey" => {
+                        self.expect_punct("=")?;
+                        assembly_ref.public_key_or_token = self.byte_list()?;
+                        assembly_ref.flags |= 0x0001;
+                    }
+                    ".publickeytoken" => {
+                        self.expect_punct("=")?;
+                        assembly_ref.public_key_or_token = self.byte_list()?;
+                    }
+                    ".hash" => {
+                        self.expect_punct("=")?;
+                        assembly_ref.hash = self.byte_list()?;
+                    }
+                    ".locale" | ".culture" => assembly_ref.culture = self.expect_string()?,
+                    ".custom" => {
+                        let custom = self.parse_custom()?;
+                        assembly_ref.customs.push(custom);
+                    }
+                    other => {
+                        return Err(
+                            self.unsupported(&format!("directive '{other}' in .assembly extern"))
+                        );
+                    }
+                },
+                other => {
+                    return Err(self.error(format!("unexpected {other:?} in .assembly extern")));
+                }
+            }
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Classes and members
+    // ------------------------------------------------------------------------
+
+    fn parse_exported_type(&mut self) -> Result<ExportedTypeDecl> {
+        let mut exported = ExportedTypeDecl::default();
+        while let Some(keyword) = self.peek_keyword()? {
+            match keyword.as_str() {
+                "forwarder" => exported.flags |= 0x0020_0000,
+                "public" => exported.flags |= 0x1,
+                "private" => exported.flags &= !0x7,
+                _ => break,
+            }
+            self.next()?;
+        }
+        let name = self.expect_name()?;
+        exported.name = if self.namespaces.is_empty() {
+            name
+        } else {
+            format!("{}.{name}", self.namespaces.join("."))
+        };
+
+        self.expect_punct("{")?;
+        loop {
+            match self.next()? {
+                Token::Punct("}") => break,
+                Token::Directive(d) if d == ".assembly" => {
+                    self.expect_ident("extern")?;
+                    exported.assembly = self.expect_name()?;
+                }
+                Token::Directive(d) => {
+                    return Err(self.unsupported(&format!("directive '{d}' in .class extern")));
+                }
+                other => return Err(self.error(format!("unexpected {other:?} in .class extern"))),
+            }
+        }
+        if exported.assembly.is_empty() {
+            return Err(self.error("missing .assembly extern in .class extern"));
+        }
+        Ok(exported)
+    }
+
+    fn parse_class(&mut self, nested: bool) -> Result<ClassDecl> {
+        let mut class = ClassDecl {
+            line: self.line,
+            ..Default::default()
+        };
+
+        while let Some(keyword) = self.peek_keyword()? {
+            let flags = &mut class.flags;
+            match keyword.as_str() {
+                "public" => *flags = (*flags & !0x7) | 0x1,
+                "private" => *flags &= !0x7,
+                "nested" => {
+                    self.next()?;
+                    let visibility = match self.expect_name()?.as_str() {
+                        "public" => 0x2,
+                        "private" => 0x3,
+                        "family" => 0x4,
+                        "assembly" => 0x5,
+                        "famandassem" => 0x6,
+                        "famorassem" => 0x7,
+                        other => {
+                            return Err(self.error(format!("invalid nested visibility '{other}'")));
+                        }
+                    };
+                    class.flags = (class.flags & !0x7) | visibility;
+                    continue;
+                }
+                "auto" => *flags &= !0x18,
+                "sequential" => *flags = (*flags & !0x18) | 0x8,
+                "explicit" => *flags = (*flags & !0x18) | 0x10,
+                "ansi" => *flags &= !0x30000,
+                "unicode" => *flags = (*flags & !0x30000) | 0x10000,
+                "autochar" => *flags = (*flags & !0x30000) | 0x20000,
+                "interface" => *flags |= 0x20 | 0x80,
+                "abstract" => *flags |= 0x80,
+                "sealed" => *flags |= 0x100,
+                "specialname" => *flags |= 0x400,
+                "rtspecialname" => *flags |= 0x800,
+                "import" => *flags |= 0x1000,
+                "serializable" => *flags |= 0x2000,
+                "windowsruntime" => *flags |= 0x4000,
+                "beforefieldinit" => *flags |= 0x0010_0000,
+                "value" => class.value_type = true,
+                "enum" => {
+                    class.value_type = true;
+                    class.is_enum = true;
+                }
+                _ => break,
+            }
+            self.next()?;
+        }
+
+        let name = self.expect_name()?;
+        class.name = if nested || self.namespaces.is_empty() {
+            name
+        } else {
+            format!("{}.{name}", self.namespaces.join("."))
+        };
+
+        if self.eat_punct("<")? {
+            class.generic_params = self.parse_generic_params()?;
+        }
+        if self.eat_ident("extends")? {
+            class.extends = Some(self.parse_type()?);
+        }
+        if self.eat_ident("implements")? {
+            loop {
+                class.implements.push(self.parse_type()?);
+                if !self.eat_punct(",")? {
+                    break;
+                }
+            }
+        }
+
+        self.expect_punct("{")?;
+        let mut after_field = false;
+        loop {
+            let directive = match self.next()? {
+                Token::Punct("}") => return Ok(class),
+                Token::Directive(d) => d,
+                other => return Err(self.error(format!("unexpected {other:?} in .class"))),
+            };
+
+            match directive.as_str() {
+                ".custom" => {
+                    let custom = self.parse_custom()?;
+                    match class.fields.last_mut() {
+                        Some(field) if after_field => field.customs.push(custom),
+                        _ => class.customs.push(custom),
+                    }
+                    continue;
+                }
+                ".field" => {
+                    let field = self.parse_field()?;
+                    class.fields.push(field);
+                    after_field = true;
+                    continue;
+                }
+                ".class" => {
+                    let nested = self.parse_class(true)?;
+                    class.nested.push(nested);
+                }
+                ".method" => {
+                    let method = self.parse_method()?;
+                    class.methods.push(method);
+                }
+                ".property" => {
+                    let property = self.parse_property()?;
+                    class.properties.push(property);
+                }
+                ".event" => {
+                    let event = self.parse_event()?;
+                    class.events.push(event);
+                }
+                ".pack" => class.pack = Some(self.expect_u16()?),
+                ".size" => class.size = Some(self.expect_u32()?),
+                other => return Err(self.unsupported(&format!("directive '{other}' in .class"))),
+            }
+            after_field = false;
+        }
+    }
+
+    /// Parse generic parameters after the opening `<`.
+    fn parse_generic_params(&mut self) -> Result<Vec<GenericParamDecl>> {
+        let mut params = Vec::new();
+        loop {
+            let mut param = GenericParamDecl::default();
+            loop {
+                if self.eat_punct("+")? {
+                    param.flags |= 0x1;
+                } else if self.eat_punct("-")? {
+                    param.flags |= 0x2;
+                } else if self.eat_ident("class")? {
+                    param.flags |= 0x4;
+                } else if self.eat_ident("valuetype")? {
+                    param.flags |= 0x8;
+                } else if self.eat_ident("byreflike")? {
+                    param.flags |= 0x20;
+                } else if matches!(self.peek()?, Token::Directive(d) if d == ".ctor") {
+                    self.next()?;
+                    param.flags |= 0x10;
+                } else {
+                    break;
+                }
+            }
+            if self.eat_punct("(")? {
+                loop {
+                    param.constraints.push(self.parse_type()?);
+                    if !self.eat_punct(",")? {
+                        break;
+                    }
+                }
+                self.expect_punct(")")?;
+            }
+            param.name = self.expect_name()?;
+            params.push(param);
+
+            if !self.eat_punct(",")? {
+                self.expect_punct(">")?;
+                return Ok(params);
+            }
+        }
+    }
+
+    fn parse_field(&mut self) -> Result<FieldDecl> {
+        let line = self.line;
+        let offset = if self.eat_punct("[")? {
+            let offset = self.expect_u32()?;
+            self.expect_punct("]")?;
+            Some(offset)
+        } else {
+            None
+        };
+
+        let mut flags = 0u16;
+        while let Some(keyword) = self.peek_keyword()? {
+            match keyword.as_str() {
+                "privatescope" | "compilercontrolled" => flags &= !0x7,
+                "private" => flags = (flags & !0x7) | 0x1,
+                "famandassem" => flags = (flags & !0x7) | 0x2,
+                "assembly" => flags = (flags & !0x7) | 0x3,
+                "family" => flags = (flags & !0x7) | 0x4,
+                "famorassem" => flags = (flags & !0x7) | 0x5,
+                "public" => flags = (flags & !0x7) | 0x6,
+                "static" => flags |= 0x10,
+                "initonly" => flags |= 0x20,
+                "literal" => flags |= 0x40,
+                "notserialized" => flags |= 0x80,
+                "specialname" => flags |= 0x200,
+                "rtspecialname" => flags |= 0x400,
+                _ => break,
+            }
+            self.next()?;
+        }
+
+        let ty = self.parse_type()?;
+        let name = self.expect_name()?;
+        let default = if self.eat_punct("=")? {
+            Some(self.parse_field_init()?)
+        } else {
+            None
+        };
+
+        Ok(FieldDecl {
+            line,
+            flags,
+            offset,
+            ty,
+            name,
+            default,
+            customs: Vec::new(),
+        })
+    }
+
+    /// Parse a field initializer after `=`: `int32(5)`, `"text"`, `nullref`...
+    fn parse_field_init(&mut self) -> Result<ConstantValue> {
+        if matches!(self.peek()?, Token::Str(_)) {
+            return Ok(ConstantValue::String(self.expect_string()?));
+        }
+        let keyword = self.expect_name()?;
+        if keyword == "nullref" {
+            return Ok(ConstantValue::Null);
+        }
+        let keyword = if keyword == "unsigned" {
+            format!("u{}", self.expect_name()?)
+        } else {
+            keyword
+        };
+
+        self.expect_punct("(")?;
+        let int = |parser: &mut Self| parser.expect_int();
+        let value = match keyword.as_str() {
+            "bool" => match self.expect_name()?.as_str() {
+                "true" => ConstantValue::Boolean(true),
+                "false" => ConstantValue::Boolean(false),
+                other => return Err(self.error(format!("invalid bool '{other}'"))),
+            },
+            "char" => ConstantValue::Char(int(self)? as u16),
+            "int8" => ConstantValue::I1(int(self)? as i8),
+            "uint8" => ConstantValue::U1(int(self)? as u8),
+            "int16" => ConstantValue::I2(int(self)? as i16),
+            "uint16" => ConstantValue::U2(int(self)? as u16),
+            "int32" => ConstantValue::I4(int(self)? as i32),
+            "uint32" => ConstantValue::U4(int(self)? as u32),
+            "int64" => ConstantValue::I8(int(self)?),
+            "uint64" => ConstantValue::U8(int(self)? as u64),
+            "float32" => ConstantValue::R4(self.expect_float()? as f32),
+            "float64" => ConstantValue::R8(self.expect_float()?),
+            other => return Err(self.unsupported(&format!("field initializer '{other}'"))),
+        };
+        self.expect_punct(")")?;
+        Ok(value)
+    }
+
+    fn parse_method(&mut self) -> Result<MethodDecl> {
+        let line = self.line;
+        let mut flags = 0u16;
+        let mut pinvoke = None;
+        while let Some(keyword) = self.peek_keyword()? {
+            match keyword.as_str() {
+                "privatescope" | "compilercontrolled" => flags &= !0x7,
+                "private" => flags = (flags & !0x7) | 0x1,
+                "famandassem" => flags = (flags & !0x7) | 0x2,
+                "assembly" => flags = (flags & !0x7) | 0x3,
+                "family" => flags = (flags & !0x7) | 0x4,
+                "famorassem" => flags = (flags & !0x7) | 0x5,
+                "public" => flags = (flags & !0x7) | 0x6,
+                "static" => flags |= 0x10,
+                "final" => flags |= 0x20,
+                "virtual" => flags |= 0x40,
+                "hidebysig" => flags |= 0x80,
+                "newslot" => flags |= 0x100,
+                "strict" => flags |= 0x200,
+                "abstract" => flags |= 0x400,
+                "specialname" => flags |= 0x800,
+                "rtspecialname" => flags |= 0x1000,
+                "unmanagedexp" => flags |= 0x8,
+                "reqsecobj" => flags |= 0x8000,
+                "pinvokeimpl" => {
+                    self.next()?;
+                    flags |= 0x2000;
+                    pinvoke = Some(self.parse_pinvoke()?);
+                    continue;
+                }
+                _ => break,
+            }
+            self.next()?;
+        }
+
+        let mut call_conv = self.parse_call_conv()?;
+        if flags & 0x10 == 0 {
+            call_conv |= CallingConvention::HAS_THIS;
+        }
+        let return_type = self.parse_type()?;
+        let name = self.expect_name()?;
+        let generic_params = if self.eat_punct("<")? {
+            call_conv |= CallingConvention::GENERIC;
+            self.parse_generic_params()?
+        } else {
+            Vec::new()
+        };
+        let (params, _) = self.parse_params(true)?;
+
+        let mut impl_flags = 0u16;
+        while let Some(keyword) = self.peek_keyword()? {
+            match keyword.as_str() {
+                "cil" | "managed" => {}
+                "native" => impl_flags |= 0x1,
+                "optil" => impl_flags |= 0x2,
+                "runtime" => impl_flags |= 0x3,
+                "unmanaged" => impl_flags |= 0x4,
+                "noinlining" => impl_flags |= 0x8,
+                "forwardref" => impl_flags |= 0x10,
+                "synchronized" => impl_flags |= 0x20,
+                "nooptimization" => impl_flags |= 0x40,
+                "preservesig" => impl_flags |= 0x80,
+                "aggressiveinlining" => impl_flags |= 0x100,
+                "aggressiveoptimization" => impl_flags |= 0x200,
+                "internalcall" => impl_flags |= 0x1000,
+                _ => break,
+            }
+            self.next()?;
+        }
+
+        self.expect_punct("{")?;
+        let mut body = MethodBodyDecl::default();
+        let mut current_param = None;
+        body.items = self.parse_body_items(&mut body, &mut current_param)?;
+
+        Ok(MethodDecl {
+            line,
+            flags,
+            impl_flags,
+            call_conv,
+            return_type,
+            name,
+            generic_params,
+            params,
+            pinvoke,
+            body,
+        })
+    }
+
+    fn parse_pinvoke(&mut self) -> Result<PInvokeDecl> {
+        self.expect_punct("(")?;
+        let module = self.expect_string()?;
+        let entry_point = if self.eat_ident("as")? {
+            Some(self.expect_string()?)
+        } else {
+            None
+        };
+
+        let mut flags = 0u16;
+        while let Some(keyword) = self.peek_keyword()? {
+            self.next()?;
+            match keyword.as_str() {
+                "nomangle" => flags |= 0x1,
+                "ansi" => flags |= 0x2,
+                "unicode" => flags |= 0x4,
+                "autochar" => flags |= 0x6,
+                "lasterr" => flags |= 0x40,
+                "winapi" => flags |= 0x100,
+                "cdecl" => flags |= 0x200,
+                "stdcall" => flags |= 0x300,
+                "thiscall" => flags |= 0x400,
+                "fastcall" => flags |= 0x500,
+                "bestfit" | "charmaperror" => {
+                    self.expect_punct(":")?;
+                    let on = match self.expect_name()?.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        other => {
+                            return Err(self.error(format!("expected on/off, found '{other}'")));
+                        }
+                    };
+                    flags |= match (keyword.as_str(), on) {
+                        ("bestfit", true) => 0x10,
+                        ("bestfit", false) => 0x20,
+                        (_, true) => 0x1000,
+                        (_, false) => 0x2000,
+                    };
+                }
+                other => return Err(self.error(format!("unknown pinvokeimpl flag '{other}'"))),
+            }
+        }
+        self.expect_punct(")")?;
+
+        Ok(PInvokeDecl {
+            module,
+            entry_point,
+            flags,
+        })
+    }
+
+    fn parse_call_conv(&mut self) -> Result<u8> {
+        let mut call_conv = CallingConvention::DEFAULT;
+        loop {
+            if self.eat_ident("instance")? {
+                call_conv |= CallingConvention::HAS_THIS;
+            } else if self.eat_ident("explicit")? {
+                call_conv |= CallingConvention::EXPLICIT_THIS;
+            } else if self.eat_ident("vararg")? {
+                call_conv = (call_conv & 0xF0) | CallingConvention::VARARG;
+            } else if self.eat_ident("default")? {
+            } else if self.is_ident("unmanaged")? {
+                return Err(self.unsupported("unmanaged calling convention"));
+            } else {
+                return Ok(call_conv);
+            }
+        }
+    }
+
+    /// Parse a parenthesized parameter list; returns the parameters and the
+    /// vararg sentinel position.
+    fn parse_params(&mut self, allow_names: bool) -> Result<(Vec<ParamDecl>, Option<usize>)> {
+        self.expect_punct("(")?;
+        let mut params = Vec::new();
+        let mut sentinel = None;
+        if self.eat_punct(")")? {
+            return Ok((params, sentinel));
+        }
+
+        loop {
+            if self.eat_punct("...")? {
+                sentinel = Some(params.len());
+            } else {
+                let mut flags = 0u16;
+                while self.eat_punct("[")? {
+                    flags |= match self.expect_name()?.as_str() {
+                        "in" => 0x1,
+                        "out" => 0x2,
+                        "opt" => 0x10,
+                        other => {
+                            return Err(
+                                self.error(format!("unknown parameter attribute '{other}'"))
+                            );
+                        }
+                    };
+                    self.expect_punct("]")?;
+                }
+                let ty = self.parse_type()?;
+                let name = if self.is_name()? {
+                    let name = self.expect_name()?;
+                    allow_names.then_some(name)
+                } else {
+                    None
+                };
+                params.push(ParamDecl { flags, ty, name });
+            }
+
+            if !self.eat_punct(",")? {
+                self.expect_punct(")")?;
+                return Ok((params, sentinel));
+            }
+        }
+    }
+
+    fn parse_property(&mut self) -> Result<PropertyDecl> {
+        let line = self.line;
+        let mut flags = 0u16;
+        loop {
+            if self.eat_ident("specialname")? {
+                flags |= 0x200;
+            } else if self.eat_ident("rtspecialname")? {
+                flags |= 0x400;
+            } else {
+                break;
+            }
+        }
+        let call_conv = self.parse_call_conv()?;
+        let ty = self.parse_type()?;
+        let name = self.expect_name()?;
+        let (params, _) = self.parse_params(false)?;
+        if self.is_punct("=")? {
+            return Err(self.unsupported("property initializer"));
+        }
+
+        let mut property = PropertyDecl {
+            line,
+            flags,
+            has_this: call_conv & CallingConvention::HAS_THIS != 0,
+            ty,
+            name,
+            params: params.into_iter().map(|p| p.ty).collect(),
+            accessors: Vec::new(),
+            customs: Vec::new(),
+        };
+
+        self.expect_punct("{")?;
+        loop {
+            let semantics = match self.next()? {
+                Token::Punct("}") => return Ok(property),
+                Token::Directive(d) => match d.as_str() {
+                    ".set" => 0x1,
+                    ".get" => 0x2,
+                    ".other" => 0x4,
+                    ".custom" => {
+                        let custom = self.parse_custom()?;
+                        property.customs.push(custom);
+                        continue;
+                    }
+                    other => {
+                        return Err(self.unsupported(&format!("directive '{other}' in .property")));
+                    }
+                },
+                other => return Err(self.error(format!("unexpected {other:?} in .property"))),
+            };
+            let method = self.parse_method_ref()?;
+            property.accessors.push((semantics, method));
+        }
+    }
+
+    fn parse_event(&mut self) -> Result<EventDecl> {
+        let line = self.line;
+        let mut flags = 0u16;
+        loop {
+            if self.eat_ident("specialname")? {
+                flags |= 0x200;
+            } else if self.eat_ident("rtspecialname")? {
+                flags |= 0x400;
+            } else {
+                break;
+            }
+        }
+
+        let first = self.parse_type()?;
+        let (ty, name) = if self.is_punct("{")? {
+            (
+                None,
+                Self::bare_name(&first)
+                    .ok_or_else(|| self.error("expected event name"))?
+                    .0,
+            )
+        } else {
+            (Some(first), self.expect_name()?)
+        };
+
+        let mut event = EventDecl {
+            line,
+            flags,
+            ty,
+            name,
+            accessors: Vec::new(),
+            customs: Vec::new(),
+        };
+
+        self.expect_punct("{")?;
+        loop {
+            let semantics = match self.next()? {
+                Token::Punct("}") => return Ok(event),
+                Token::Directive(d) => match d.as_str() {
+                    ".other" => 0x4,
+                    ".addon" => 0x8,
+                    ".removeon" => 0x10,
+                    ".fire" => 0x20,
+                    ".custom" => {
+                        let custom = self.parse_custom()?;
+                        event.customs.push(custom);
+                        continue;
+                    }
+                    other => {
+                        return Err(self.unsupported(&format!("directive '{other}' in .event")));
+                    }
+                },
+                other => return Err(self.error(format!("unexpected {other:?} in .event"))),
+            };
+            let method = self.parse_method_ref()?;
+            event.accessors.push((semantics, method));
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Method bodies
+    // ------------------------------------------------------------------------
+
+    fn parse_body_items(
+        &mut self,
+        body: &mut MethodBodyDecl,
+        current_param: &mut Option<u16>,
+    ) -> Result<Vec<BodyItem>> {
+        let mut items = Vec::new();
+        loop {
+            let line = {
+                self.peek()?;
+                self.line
+            };
+            match self.next()? {
+                Token::Punct("}") => return Ok(items),
+                Token::Directive(d) => {
+                    if d != ".custom" {
+                        *current_param = None;
+                    }
+                    match d.as_str() {
+                        ".maxstack" => body.max_stack = Some(self.expect_u16()?),
+                        ".entrypoint" => body.entry_point = true,
+                        ".zeroinit" => body.init_locals = true,
+                        ".locals" => self.parse_locals(body)?,
+                        ".custom" => {
+                            let custom = self.parse_custom()?;
+                            match *current_param {
+                                Some(sequence) => Self::param_customs(body, sequence).push(custom),
+                                None => body.customs.push(custom),
+                            }
+                        }
+                        ".param" => {
+                            self.expect_punct("[")?;
+                            let sequence = self.expect_u16()?;
+                            self.expect_punct("]")?;
+                            if self.is_punct("=")? {
+                                return Err(self.unsupported("parameter default value"));
+                            }
+                            Self::param_customs(body, sequence);
+                            *current_param = Some(sequence);
+                        }
+                        ".override" => {
+                            let target = if self.eat_ident("method")? {
+                                OverrideDecl::Method(self.parse_method_ref()?)
+                            } else {
+                                let owner = self.parse_type()?;
+                                self.expect_punct("::")?;
+                                let name = self.expect_name()?;
+                                OverrideDecl::Named { line, owner, name }
+                            };
+                            body.overrides.push(target);
+                        }
+                        ".try" => {
+                            let item = self.parse_try(body, current_param)?;
+                            items.push(item);
+                        }
+                        other => {
+                            return Err(
+                                self.unsupported(&format!("directive '{other}' in method body"))
+                            );
+                        }
+                    }
+                }
+                Token::Ident(name) => {
+                    *current_param = None;
+                    if self.eat_punct(":")? {
+                        items.push(BodyItem::Label(name));
+                        continue;
+                    }
+                    let opcode = OpCode::from_name(&name).ok_or_else(|| Error::IlAsm {
+                        line,
+                        message: format!("unknown instruction '{name}'"),
+                    })?;
+                    let operand = self.parse_operand(opcode)?;
+                    items.push(BodyItem::Instruction {
+                        line,
+                        opcode,
+                        operand,
+                    });
+                }
+                other => return Err(self.error(format!("unexpected {other:?} in method body"))),
+            }
+        }
+    }
+
+    fn param_customs(body: &mut MethodBodyDecl, sequence: u16) -> &mut Vec<CustomDecl> {
+        let index = match body.param_customs.iter().position(|(s, _)| *s == sequence) {
+            Some(index) => index,
+            None => {
+                body.param_customs.push((sequence, Vec::new()));
+                body.param_customs.len() - 1
+            }
+        };
+        &mut body.param_customs[index].1
+    }
+
+    fn parse_locals(&mut self, body: &mut MethodBodyDecl) -> Result<()> {
+        if self.eat_ident("init")? {
+            body.init_locals = true;
+        }
+        self.expect_punct("(")?;
+        if self.eat_punct(")")? {
+            return Ok(());
+        }
+        loop {
+            if self.eat_punct("[")? {
+                self.expect_int()?;
+                self.expect_punct("]")?;
+            }
+            let ty = self.parse_type()?;
+            let name = if self.is_name()? {
+                Some(self.expect_name()?)
+            } else {
+                None
+            };
+            body.locals.push(LocalDecl { ty, name });
+            if !self.eat_punct(",")? {
+                return self.expect_punct(")");
+            }
+        }
+    }
+
+    fn parse_try(
+        &mut self,
+        body: &mut MethodBodyDecl,
+        current_param: &mut Option<u16>,
+    ) -> Result<BodyItem> {
+        self.expect_punct("{")?;
+        let try_body = self.parse_body_items(body, current_param)?;
+
+        let mut handlers = Vec::new();
+        loop {
+            let kind = if self.eat_ident("catch")? {
+                HandlerKindDecl::Catch(self.parse_type()?)
+            } else if self.eat_ident("finally")? {
+                HandlerKindDecl::Finally
+            } else if self.eat_ident("fault")? {
+                HandlerKindDecl::Fault
+            } else if self.eat_ident("filter")? {
+                self.expect_punct("{")?;
+                HandlerKindDecl::Filter(self.parse_body_items(body, current_param)?)
+            } else {
+                break;
+            };
+            self.expect_punct("{")?;
+            let handler_body = self.parse_body_items(body, current_param)?;
+            handlers.push(HandlerDecl {
+                kind,
+                body: handler_body,
+            });
+        }
+
+        if handlers.is_empty() {
+            return Err(self.error(".try block without handler"));
+        }
+        Ok(BodyItem::Try {
+            body: try_body,
+            handlers,
+        })
+    }
+
+    fn parse_operand(&mut self, opcode: &OpCode) -> Result<OperandExpr> {
+        Ok(match opcode.operand {
+            OperandType::None => OperandExpr::None,
+            OperandType::ShortBrTarget | OperandType::BrTarget => {
+                OperandExpr::Label(self.expect_name()?)
+            }
+            OperandType::ShortI | OperandType::I | OperandType::I8 => {
+                OperandExpr::Int(self.expect_int()?)
+            }
+            OperandType::ShortR | OperandType::R => OperandExpr::Float(self.expect_float()?),
+            OperandType::ShortVar | OperandType::Var => {
+                if matches!(self.peek()?, Token::Int(_)) {
+                    OperandExpr::Var(VarRef::Index(self.expect_u16()?))
+                } else {
+                    OperandExpr::Var(VarRef::Name(self.expect_name()?))
+                }
+            }
+            OperandType::Method => OperandExpr::Method(Box::new(self.parse_method_ref()?)),
+            OperandType::Field => OperandExpr::Field(Box::new(self.parse_field_ref()?)),
+            OperandType::Type => OperandExpr::Type(self.parse_type()?),
+            OperandType::Tok => {
+                if self.eat_ident("method")? {
+                    OperandExpr::Method(Box::new(self.parse_method_ref()?))
+                } else if self.eat_ident("field")? {
+                    OperandExpr::Field(Box::new(self.parse_field_ref()?))
+                } else {
+                    OperandExpr::Type(self.parse_type()?)
+                }
+            }
+            OperandType::String => OperandExpr::String(self.expect_string()?),
+            OperandType::Sig => {
+                let line = self.line;
+                let call_conv = self.parse_call_conv()?;
+                let return_type = self.parse_type()?;
+                let (params, sentinel) = self.parse_params(false)?;
+                OperandExpr::Sig(Box::new(MethodRefExpr {
+                    line,
+                    call_conv,
+                    return_type,
+                    owner: None,
+                    name: String::new(),
+                    generic_args: Vec::new(),
+                    generic_arity: 0,
+                    params: params.into_iter().map(|p| p.ty).collect(),
+                    sentinel,
+                }))
+            }
+            OperandType::Switch => {
+                self.expect_punct("(")?;
+                let mut labels = Vec::new();
+                if !self.eat_punct(")")? {
+                    loop {
+                        labels.push(self.expect_name()?);
+                        if !self.eat_punct(",")? {
+                            break;
+                        }
+                    }
+                    self.expect_punct(")")?;
+                }
+                OperandExpr::Labels(labels)
+            }
+        })
+    }
+
+    // ------------------------------------------------------------------------
+    // Member references
+    // ------------------------------------------------------------------------
+
+    /// If `ty` is a bare unscoped single name, return it with its generic arguments.
+    fn bare_name(ty: &TypeExpr) -> Option<(String, Vec<TypeExpr>)> {
+        match ty {
+            TypeExpr::Named {
+                kind: NamedKind::Bare,
+                name:
+                    TypeNameExpr {
+                        scope: ScopeExpr::None,
+                        path,
+                    },
+                args,
+            } if path.len() == 1 => Some((path[0].clone(), args.clone())),
+            _ => None,
+        }
+    }
+
+    /// Parse `[Type::]Name`, returning the owner, name and any generic arguments
+    /// attached to an unqualified name.
+    fn parse_member_target(&mut self) -> Result<(Option<TypeExpr>, String, Vec<TypeExpr>)> {
+        if matches!(self.peek()?, Token::Directive(_)) {
+            return Ok((None, self.expect_name()?, Vec::new()));
+        }
+        let target = self.parse_type()?;
+        if self.eat_punct("::")? {
+            return Ok((Some(target), self.expect_name()?, Vec::new()));
+        }
+        let (name, args) = Self::bare_name(&target).ok_or_else(|| self.error("expected '::'"))?;
+        Ok((None, name, args))
+    }
+
+    /// Parse a method reference: `callconv rettype [Type::]Name[<args>](params)`.
+    fn parse_method_ref(&mut self) -> Result<MethodRefExpr> {
+        let line = self.line;
+        let mut call_conv = self.parse_call_conv()?;
+        let return_type = self.parse_type()?;
+        let (owner, name, mut generic_args) = self.parse_member_target()?;
+
+        let mut generic_arity = 0;
+        if self.eat_punct("<")? {
+            if self.eat_punct("[")? {
+                generic_arity = self.expect_u32()?;
+                self.expect_punct("]")?;
+            } else {
+                loop {
+                    generic_args.push(self.parse_type()?);
+                    if !self.eat_punct(",")? {
+                        break;
+                    }
+                }
+            }
+            self.expect_punct(">")?;
+        }
+        if !generic_args.is_empty() {
+            generic_arity = generic_args.len() as u32;
+        }
+        if generic_arity > 0 {
+            call_conv |= CallingConvention::GENERIC;
+        }
+
+        let (params, sentinel) = self.parse_params(false)?;
+        Ok(MethodRefExpr {
+            line,
+            call_conv,
+            return_type,
+            owner,
+            name,
+            generic_args,
+            generic_arity,
+            params: params.into_iter().map(|p| p.ty).collect(),
+            sentinel,
+        })
+    }
+
+    /// Parse a field reference: `type [Type::]Name`.
+    fn parse_field_ref(&mut self) -> Result<FieldRefExpr> {
+        let line = self.line;
+        let ty = self.parse_type()?;
+        let (owner, name, _) = self.parse_member_target()?;
+        Ok(FieldRefExpr {
+            line,
+            ty,
+            owner,
+            name,
+        })
+    }
+
+    // ------------------------------------------------------------------------
+    // Types
+    // ------------------------------------------------------------------------
+
+    /// Parse a type. Bare class names are accepted wherever a type is expected.
+    fn parse_type(&mut self) -> Result<TypeExpr> {
+        let mut ty = match self.next()? {
+            Token::Ident(keyword) => match keyword.as_str() {
+                "void" => TypeExpr::Primitive(ElementType::Void),
+                "bool" => TypeExpr::Primitive(ElementType::Boolean),
+                "char" => TypeExpr::Primitive(ElementType::Char),
+                "int8" => TypeExpr::Primitive(ElementType::I1),
+                "int16" => TypeExpr::Primitive(ElementType::I2),
+                "int32" => TypeExpr::Primitive(ElementType::I4),
+                "int64" => TypeExpr::Primitive(ElementType::I8),
+                "uint8" => TypeExpr::Primitive(ElementType::U1),
+                "uint16" => TypeExpr::Primitive(ElementType::U2),
+                "uint32" => TypeExpr::Primitive(ElementType::U4),
+                "uint64" => TypeExpr::Primitive(ElementType::U8),
+                "float32" => TypeExpr::Primitive(ElementType::R4),
+                "float64" => TypeExpr::Primitive(ElementType::R8),
+                "string" => TypeExpr::Primitive(ElementType::String),
+                "object" => TypeExpr::Primitive(ElementType::Object),
+                "typedref" => TypeExpr::Primitive(ElementType::TypedByRef),
+                "native" => {
+                    if self.eat_ident("unsigned")? {
+                        self.expect_ident("int")?;
+                        TypeExpr::Primitive(ElementType::UIntPtr)
+                    } else if self.eat_ident("uint")? {
+                        TypeExpr::Primitive(ElementType::UIntPtr)
+                    } else {
+                        self.expect_ident("int")?;
+                        TypeExpr::Primitive(ElementType::IntPtr)
+                    }
+                }
+                "unsigned" => TypeExpr::Primitive(match self.expect_name()?.as_str() {
+                    "int8" => ElementType::U1,
+                    "int16" => ElementType::U2,
+                    "int32" => ElementType::U4,
+                    "int64" => ElementType::U8,
+                    other => return Err(self.error(format!("invalid type 'unsigned {other}'"))),
+                }),
+                "class" => TypeExpr::Named {
+                    kind: NamedKind::Class,
+                    name: self.parse_class_name(None)?,
+                    args: Vec::new(),
+                },
+                "valuetype" => TypeExpr::Named {
+                    kind: NamedKind::ValueType,
+                    name: self.parse_class_name(None)?,
+                    args: Vec::new(),
+                },
+                "method" => return Err(self.unsupported("function pointer type")),
+                _ => TypeExpr::Named {
+                    kind: NamedKind::Bare,
+                    name: self.parse_class_name(Some(Token::Ident(keyword)))?,
+                    args: Vec::new(),
+                },
+            },
+            token @ (Token::Quoted(_) | Token::Punct("[")) => TypeExpr::Named {
+                kind: NamedKind::Bare,
+                name: self.parse_class_name(Some(token))?,
+                args: Vec::new(),
+            },
+            Token::Punct("!") => TypeExpr::Var(self.parse_var_ref()?),
+            Token::Punct("!!") => TypeExpr::MVar(self.parse_var_ref()?),
+            other => return Err(self.error(format!("expected type, found {other:?}"))),
+        };
+
+        loop {
+            if self.is_punct("<")? {
+                let TypeExpr::Named { args, .. } = &mut ty else {
+                    break;
+                };
+                if !args.is_empty() {
+                    break;
+                }
+                self.next()?;
+                loop {
+                    args.push(self.parse_type()?);
+                    if !self.eat_punct(",")? {
+                        break;
+                    }
+                }
+                self.expect_punct(">")?;
+            } else if self.is_punct("[")? {
+                // `void [mscorlib]System.X::M` - a scope, not array bounds
+                if self
+                    .lexer
+                    .peek_char()?
+                    .is_some_and(|b| b.is_ascii_alphabetic() || matches!(b, b'\'' | b'_' | b'.'))
+                {
+                    return Ok(ty);
+                }
+                self.next()?;
+                ty = self.parse_array_suffix(ty)?;
+            } else if self.eat_punct("*")? {
+                ty = TypeExpr::Ptr(Box::new(ty));
+            } else if self.eat_punct("&")? {
+                ty = TypeExpr::ByRef(Box::new(ty));
+            } else if self.eat_ident("pinned")? {
+                ty = TypeExpr::Pinned(Box::new(ty));
+            } else if self.is_ident("modreq")? || self.is_ident("modopt")? {
+                let required = self.eat_ident("modreq")?;
+                if !required {
+                    self.next()?;
+                }
+                self.expect_punct("(")?;
+                let modifier = self.parse_class_name(None)?;
+                self.expect_punct(")")?;
+                ty = TypeExpr::Modified {
+                    required,
+                    modifier,
+                    inner: Box::new(ty),
+                };
+            } else {
+                return Ok(ty);
+            }
+        }
+        Ok(ty)
+    }
+
+    fn parse_var_ref(&mut self) -> Result<VarRef> {
+        if matches!(self.peek()?, Token::Int(_)) {
+            Ok(VarRef::Index(self.expect_u16()?))
+        } else {
+            Ok(VarRef::Name(self.expect_name()?))
+        }
+    }
+
+    /// Parse array bounds after the opening `[`.
+    fn parse_array_suffix(&mut self, element: TypeExpr) -> Result<TypeExpr> {
+        if self.eat_punct("]")? {
+            return Ok(TypeExpr::SzArray(Box::new(element)));
+        }
+
+        let mut rank = 0;
+        let mut sizes = Vec::new();
+        let mut lo_bounds = Vec::new();
+        loop {
+            rank += 1;
+            if self.eat_punct("...")? || self.is_punct(",")? || self.is_punct("]")? {
+                // Unspecified bounds
+            } else {
+                let lower = self.expect_int()?;
+                if self.eat_punct("...")? {
+                    lo_bounds.push(lower as i32);
+                    if matches!(self.peek()?, Token::Int(_) | Token::Punct("-")) {
+                        let upper = self.expect_int()?;
+                        sizes.push((upper - lower + 1) as u32);
+                    }
+                } else {
+                    lo_bounds.push(0);
+                    sizes.push(lower as u32);
+                }
+            }
+            if !self.eat_punct(",")? {
+                self.expect_punct("]")?;
+                return Ok(TypeExpr::Array {
+                    element: Box::new(element),
+                    rank,
+                    sizes,
+                    lo_bounds,
+                });
+            }
+        }
+    }
+
+    /// Parse `[scope]Name/Nested`, starting from an already-consumed token if given.
+    fn parse_class_name(&mut self, first: Option<Token>) -> Result<TypeNameExpr> {
+        let first = match first {
+            Some(token) => token,
+            None => self.next()?,
+        };
+
+        let (scope, name) = match first {
+            Token::Punct("[") => {
+                let scope = if matches!(self.peek()?, Token::Directive(d) if d == ".module") {
+                    self.next()?;
+                    ScopeExpr::Module(self.expect_name()?)
+                } else {
+                    ScopeExpr::Assembly(self.expect_name()?)
+                };
+                self.expect_punct("]")?;
+                (scope, self.expect_name()?)
+            }
+            Token::Ident(name) | Token::Quoted(name) => (ScopeExpr::None, name),
+            other => return Err(self.error(format!("expected class name, found {other:?}"))),
+        };
+
+        let mut path = vec![name];
+        while self.eat_punct("/")? {
+            path.push(self.expect_name()?);
+        }
+        Ok(TypeNameExpr { scope, path })
+    }
+
+    // ------------------------------------------------------------------------
+    // Custom attributes
+    // ------------------------------------------------------------------------
+
+    fn parse_custom(&mut self) -> Result<CustomDecl> {
+        let line = self.line;
+        let ctor = self.parse_method_ref()?;
+        let value = if self.eat_punct("=")? {
+            if self.is_punct("(")? {
+                CustomValueDecl::Bytes(self.byte_list()?)
+            } else {
+                self.expect_punct("{")?;
+                self.parse_custom_args()?
+            }
+        } else {
+            CustomValueDecl::Decoded {
+                fixed_args: Vec::new(),
+                named_args: Vec::new(),
+            }
+        };
+        Ok(CustomDecl { line, ctor, value })
+    }
+
+    /// Parse decoded custom attribute arguments after the opening `{`.
+    fn parse_custom_args(&mut self) -> Result<CustomValueDecl> {
+        let mut fixed_args = Vec::new();
+        let mut named_args = Vec::new();
+        loop {
+            if self.eat_punct("}")? {
+                return Ok(CustomValueDecl::Decoded {
+                    fixed_args,
+                    named_args,
+                });
+            }
+
+            let is_field = if self.eat_ident("field")? {
+                true
+            } else if self.eat_ident("property")? {
+                false
+            } else {
+                fixed_args.push(self.parse_ca_value()?);
+                continue;
+            };
+
+            let mut arg_type = self.parse_ca_type()?;
+            if self.eat_punct("[")? {
+                self.expect_punct("]")?;
+                arg_type = CaType::SzArray(Box::new(arg_type));
+            }
+            let name = self.expect_name()?;
+            self.expect_punct("=")?;
+            let mut value = self.parse_ca_value()?;
+            if let CaType::Enum { type_name, .. } = &arg_type {
+                value = CaValue::Enum {
+                    type_name: type_name.clone(),
+                    value: Box::new(value),
+                };
+                arg_type = value.ca_type();
+            }
+            named_args.push(CaNamedArg {
+                is_field,
+                name,
+                arg_type,
+                value,
+            });
+        }
+    }
+
+    fn parse_ca_type(&mut self) -> Result<CaType> {
+        Ok(match self.expect_name()?.as_str() {
+            "bool" => CaType::Boolean,
+            "char" => CaType::Char,
+            "int8" => CaType::I1,
+            "int16" => CaType::I2,
+            "int32" => CaType::I4,
+            "int64" => CaType::I8,
+            "uint8" => CaType::U1,
+            "uint16" => CaType::U2,
+            "uint32" => CaType::U4,
+            "uint64" => CaType::U8,
+            "unsigned" => match self.expect_name()?.as_str() {
+                "int8" => CaType::U1,
+                "int16" => CaType::U2,
+                "int32" => CaType::U4,
+                "int64" => CaType::U8,
+                other => return Err(self.error(format!("invalid type 'unsigned {other}'"))),
+            },
+            "float32" => CaType::R4,
+            "float64" => CaType::R8,
+            "string" => CaType::String,
+            "type" => CaType::Type,
+            "object" => CaType::Boxed,
+            "enum" => {
+                if !self.eat_ident("class")? {
+                    self.eat_ident("valuetype")?;
+                }
+                CaType::Enum {
+                    type_name: self.parse_class_name(None)?.serialized(),
+                    underlying: ElementType::I4,
+                }
+            }
+            other => return Err(self.error(format!("invalid custom attribute type '{other}'"))),
+        })
+    }
+
+    /// Parse a typed value such as `int32(5)`, `string('x')` or `int32[2](1 2)`.
+    fn parse_ca_value(&mut self) -> Result<CaValue> {
+        let ty = self.parse_ca_type()?;
+
+        if self.eat_punct("[")? {
+            if !self.is_punct("]")? {
+                self.expect_int()?;
+            }
+            self.expect_punct("]")?;
+            return self.parse_ca_array(ty);
+        }
+
+        self.expect_punct("(")?;
+        let value = if ty == CaType::Boxed {
+            CaValue::Boxed(Box::new(self.parse_ca_value()?))
+        } else {
+            self.parse_ca_literal(&ty)?
+        };
+        self.expect_punct(")")?;
+        Ok(value)
+    }
+
+    fn parse_ca_array(&mut self, element_type: CaType) -> Result<CaValue> {
+        self.expect_punct("(")?;
+        let mut values = Vec::new();
+        while !self.eat_punct(")")? {
+            values.push(if element_type == CaType::Boxed {
+                self.parse_ca_value()?
+            } else {
+                self.parse_ca_literal(&element_type)?
+            });
+        }
+        Ok(CaValue::Array {
+            element_type,
+            values: Some(values),
+        })
+    }
+
+    fn parse_ca_literal(&mut self, ty: &CaType) -> Result<CaValue> {
+        let int = |parser: &mut Self| parser.expect_int();
+        Ok(match ty {
+            CaType::Boolean => match self.expect_name()?.as_str() {
+                "true" => CaValue::Boolean(true),
+                "false" => CaValue::Boolean(false),
+                other => return Err(self.error(format!("invalid bool '{other}'"))),
+            },
+            CaType::Char => CaValue::Char(int(self)? as u16),
+            CaType::I1 => CaValue::I1(int(self)? as i8),
+            CaType::U1 => CaValue::U1(int(self)? as u8),
+            CaType::I2 => CaValue::I2(int(self)? as i16),
+            CaType::U2 => CaValue::U2(int(self)? as u16),
+            CaType::I4 => CaValue::I4(int(self)? as i32),
+            CaType::U4 => CaValue::U4(int(self)? as u32),
+            CaType::I8 => CaValue::I8(int(self)?),
+            CaType::U8 => CaValue::U8(int(self)? as u64),
+            CaType::R4 => CaValue::R4(self.expect_float()? as f32),
+            CaType::R8 => CaValue::R8(self.expect_float()?),
+            CaType::String => {
+                if self.eat_ident("nullref")? {
+                    CaValue::String(None)
+                } else {
+                    CaValue::String(Some(self.expect_string()?))
+                }
+            }
+            CaType::Type => {
+                if self.eat_ident("nullref")? {
+                    CaValue::Type(None)
+                } else if matches!(self.peek()?, Token::Str(_)) {
+                    CaValue::Type(Some(self.expect_string()?))
+                } else {
+                    if !self.eat_ident("class")? {
+                        self.eat_ident("valuetype")?;
+                    }
+                    CaValue::Type(Some(self.parse_class_name(None)?.serialized()))
+                }
+            }
+            CaType::Enum { type_name, .. } => CaValue::Enum {
+                type_name: type_name.clone(),
+                value: Box::new(CaValue::I4(int(self)? as i32)),
+            },
+            _ => return Err(self.unsupported("custom attribute value")),
+        })
+    }
+}
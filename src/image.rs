@@ -0,0 +1,466 @@
+//! Minimal PE image access.
+//!
+//! The crate otherwise works on raw metadata bytes. Loading referenced
+//! assemblies from disk and hashing signed images needs just enough of the PE
+//! format (PE/COFF, ECMA-335 II.25) to find the CLI header and the sections it
+//! points into; everything else is left to a real PE parser.
+
+use crate::error::{Error, Result};
+use crate::reader::Reader;
+
+/// Index of the certificate table in the optional header data directories.
+pub const SECURITY_DIRECTORY: usize = 4;
+/// Index of the debug directory in the optional header data directories.
+pub const DEBUG_DIRECTORY: usize = 6;
+/// Index of the CLI header in the optional header data directories.
+pub const CLI_HEADER_DIRECTORY: usize = 14;
+
+/// Metadata root signature (`BSJB`).
+const METADATA_SIGNATURE: &[u8; 4] = b"BSJB";
+
+/// An RVA and size pair.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataDirectory {
+    /// Relative virtual address.
+    pub rva: u32,
+    /// Size in bytes.
+    pub size: u32,
+}
+
+/// A section table entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionHeader {
+    /// Section name (up to 8 bytes).
+    pub name: String,
+    /// Size of the section when loaded.
+    pub virtual_size: u32,
+    /// RVA of the section.
+    pub virtual_address: u32,
+    /// Size of the initialized data in the file.
+    pub size_of_raw_data: u32,
+    /// File offset of the section data.
+    pub pointer_to_raw_data: u32,
+}
+
+/// The CLI header (ECMA-335 II.25.3.3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CliHeader {
+    /// Runtime major version.
+    pub major_runtime_version: u16,
+    /// Runtime minor version.
+    pub minor_runtime_version: u16,
+    /// Metadata root.
+    pub metadata: DataDirectory,
+    /// Runtime flags (`COMIMAGE_FLAGS_*`).
+    pub flags: u32,
+    /// Entry point token or RVA.
+    pub entry_point: u32,
+    /// Managed resources.
+    pub resources: DataDirectory,
+    /// Strong name signature.
+    pub strong_name_signature: DataDirectory,
+}
+
+impl CliHeader {
+    /// Image is strong-name signed (`COMIMAGE_FLAGS_STRONGNAMESIGNED`).
+    pub const FLAG_STRONG_NAME_SIGNED: u32 = 0x0000_0008;
+}
+
+/// A debug directory entry (`IMAGE_DEBUG_DIRECTORY`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugDirectoryEntry {
+    /// Time stamp; for CodeView entries of deterministic builds, the PDB ID
+    /// stamp.
+    pub time_date_stamp: u32,
+    /// Major version.
+    pub major_version: u16,
+    /// Minor version.
+    pub minor_version: u16,
+    /// Entry type (`IMAGE_DEBUG_TYPE_*`).
+    pub entry_type: u32,
+    /// Size of the entry data.
+    pub size_of_data: u32,
+    /// RVA of the entry data, or 0 if not mapped.
+    pub address_of_raw_data: u32,
+    /// File offset of the entry data.
+    pub pointer_to_raw_data: u32,
+}
+
+impl DebugDirectoryEntry {
+    /// CodeView entry naming the PDB (`IMAGE_DEBUG_TYPE_CODEVIEW`).
+    pub const CODEVIEW: u32 = 2;
+    /// Marks a deterministic build (`IMAGE_DEBUG_TYPE_REPRODUCIBLE`).
+    pub const REPRODUCIBLE: u32 = 16;
+    /// Checksum of the associated PDB (`IMAGE_DEBUG_TYPE_PDBCHECKSUM`).
+    pub const PDB_CHECKSUM: u32 = 19;
+}
+
+/// A parsed view of a PE image.
+#[derive(Debug, Clone)]
+pub struct PeImage<'a> {
+    data: &'a [u8],
+    optional_header_offset: usize,
+    data_directories_offset: usize,
+    data_directories: Vec<DataDirectory>,
+    section_table_end: usize,
+    sections: Vec<SectionHeader>,
+}
+
+impl<'a> PeImage<'a> {
+    /// Parse the headers of a PE image.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        if reader.read_u16()? != 0x5A4D {
+            return Err(Error::InvalidImage("missing MZ header"));
+        }
+        reader.seek(0x3C)?;
+        let pe_offset = reader.read_u32()? as usize;
+        reader.seek(pe_offset)?;
+        if reader.read_u32()? != 0x0000_4550 {
+            return Err(Error::InvalidImage("missing PE signature"));
+        }
+
+        // COFF file header
+        reader.read_u16()?; // machine
+        let section_count = reader.read_u16()?;
+        reader.read_bytes(12)?; // timestamp, symbol table, symbol count
+        let optional_header_size = reader.read_u16()? as usize;
+        reader.read_u16()?; // characteristics
+
+        let optional_header_offset = reader.position();
+        let data_directories_offset = match reader.read_u16()? {
+            0x10B => optional_header_offset + 96,
+            0x20B => optional_header_offset + 112,
+            _ => return Err(Error::InvalidImage("unknown optional header magic")),
+        };
+        reader.seek(data_directories_offset - 4)?;
+        let directory_count = reader.read_u32()? as usize;
+        let mut data_directories = Vec::with_capacity(directory_count.min(16));
+        for _ in 0..directory_count.min(16) {
+            data_directories.push(DataDirectory {
+                rva: reader.read_u32()?,
+                size: reader.read_u32()?,
+            });
+        }
+
+        reader.seek(optional_header_offset + optional_header_size)?;
+        let mut sections = Vec::with_capacity(section_count as usize);
+        for _ in 0..section_count {
+            let name = reader.read_bytes(8)?;
+            let name_len = name.iter().position(|&b| b == 0).unwrap_or(8);
+            let name = String::from_utf8_lossy(&name[..name_len]).into_owned();
+            let virtual_size = reader.read_u32()?;
+            let virtual_address = reader.read_u32()?;
+            let size_of_raw_data = reader.read_u32()?;
+            let pointer_to_raw_data = reader.read_u32()?;
+            reader.read_bytes(16)?; // relocations, line numbers, characteristics
+            sections.push(SectionHeader {
+                name,
+                virtual_size,
+                virtual_address,
+                size_of_raw_data,
+                pointer_to_raw_data,
+            });
+        }
+
+        Ok(Self {
+            data,
+            optional_header_offset,
+            data_directories_offset,
+            data_directories,
+            section_table_end: reader.position(),
+            sections,
+        })
+    }
+
+    /// Check whether the data looks like a PE image (starts with `MZ`).
+    #[must_use]
+    pub fn is_pe_image(data: &[u8]) -> bool {
+        data.starts_with(b"MZ")
+    }
+
+    /// Get the raw image bytes.
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Get the section table.
+    #[must_use]
+    pub fn sections(&self) -> &[SectionHeader] {
+        &self.sections
+    }
+
+    /// File offset just past the section table, where the headers end.
+    #[must_use]
+    pub fn section_table_end(&self) -> usize {
+        self.section_table_end
+    }
+
+    /// Get an optional header data directory by index.
+    #[must_use]
+    pub fn data_directory(&self, index: usize) -> Option<DataDirectory> {
+        self.data_directories.get(index).copied()
+    }
+
+    /// File offset of the COFF header time stamp.
+    #[must_use]
+    pub fn time_date_stamp_offset(&self) -> usize {
+        self.optional_header_offset - 16
+    }
+
+    /// Get the COFF header time stamp.
+    #[must_use]
+    pub fn time_date_stamp(&self) -> u32 {
+        let offset = self.time_date_stamp_offset();
+        u32::from_le_bytes(
+            self.data[offset..offset + 4]
+                .try_into()
+                .expect("4-byte field"),
+        )
+    }
+
+    /// File offset of the optional header checksum field.
+    #[must_use]
+    pub fn checksum_offset(&self) -> usize {
+        self.optional_header_offset + 64
+    }
+
+    /// File offset of a data directory entry in the optional header.
+    #[must_use]
+    pub fn data_directory_offset(&self, index: usize) -> usize {
+        self.data_directories_offset + index * 8
+    }
+
+    /// Convert an RVA to a file offset.
+    #[must_use]
+    pub fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        self.sections.iter().find_map(|section| {
+            let size = section.virtual_size.max(section.size_of_raw_data);
+            let delta = rva.checked_sub(section.virtual_address)?;
+            if delta >= size || delta >= section.size_of_raw_data {
+                return None;
+            }
+            (section.pointer_to_raw_data as usize).checked_add(delta as usize)
+        })
+    }
+
+    /// Get the bytes covered by a data directory.
+    #[must_use]
+    pub fn directory_data(&self, directory: DataDirectory) -> Option<&'a [u8]> {
+        let offset = self.rva_to_offset(directory.rva)?;
+        self.data
+            .get(offset..offset.checked_add(directory.size as usize)?)
+    }
+
+    /// Parse the CLI header.
+    pub fn cli_header(&self) -> Result<CliHeader> {
+        let directory = self
+            .data_directory(CLI_HEADER_DIRECTORY)
+            .filter(|directory| directory.rva != 0)
+            .ok_or(Error::InvalidImage("not a managed image"))?;
+        let data = self
+            .directory_data(directory)
+            .ok_or(Error::InvalidImage("CLI header outside of image"))?;
+
+        let mut reader = Reader::new(data);
+        reader.read_u32()?; // cb
+        let major_runtime_version = reader.read_u16()?;
+        let minor_runtime_version = reader.read_u16()?;
+        let read_directory = |reader: &mut Reader<'_>| -> Result<DataDirectory> {
+            Ok(DataDirectory {
+                rva: reader.read_u32()?,
+                size: reader.read_u32()?,
+            })
+        };
+        let metadata = read_directory(&mut reader)?;
+        let flags = reader.read_u32()?;
+        let entry_point = reader.read_u32()?;
+        let resources = read_directory(&mut reader)?;
+        let strong_name_signature = read_directory(&mut reader)?;
+
+        Ok(CliHeader {
+            major_runtime_version,
+            minor_runtime_version,
+            metadata,
+            flags,
+            entry_point,
+            resources,
+            strong_name_signature,
+        })
+    }
+
+    /// Parse the debug directory. An image without one has no entries.
+    pub fn debug_directory(&self) -> Result<Vec<DebugDirectoryEntry>> {
+        let Some(directory) = self
+            .data_directory(DEBUG_DIRECTORY)
+            .filter(|directory| directory.rva != 0)
+        else {
+            return Ok(Vec::new());
+        };
+        let data = self
+            .directory_data(directory)
+            .ok_or(Error::InvalidImage("debug directory outside of image"))?;
+
+        let mut reader = Reader::new(data);
+        let mut entries = Vec::with_capacity(data.len() / 28);
+        while reader.remaining() >= 28 {
+            reader.read_u32()?; // characteristics
+            entries.push(DebugDirectoryEntry {
+                time_date_stamp: reader.read_u32()?,
+                major_version: reader.read_u16()?,
+                minor_version: reader.read_u16()?,
+                entry_type: reader.read_u32()?,
+                size_of_data: reader.read_u32()?,
+                address_of_raw_data: reader.read_u32()?,
+                pointer_to_raw_data: reader.read_u32()?,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Get the data of a debug directory entry.
+    #[must_use]
+    pub fn debug_data(&self, entry: &DebugDirectoryEntry) -> Option<&'a [u8]> {
+        let offset = entry.pointer_to_raw_data as usize;
+        self.data.get(offset..offset + entry.size_of_data as usize)
+    }
+
+    /// Get the metadata root bytes.
+    pub fn metadata(&self) -> Result<&'a [u8]> {
+        let header = self.cli_header()?;
+        self.directory_data(header.metadata)
+            .ok_or(Error::InvalidImage("metadata outside of image"))
+    }
+}
+
+/// Get the metadata bytes of either a PE image or raw metadata.
+pub fn metadata_bytes(data: &[u8]) -> Result<&[u8]> {
+    if data.starts_with(METADATA_SIGNATURE) {
+        Ok(data)
+    } else {
+        PeImage::parse(data)?.metadata()
+    }
+}
+
+/// Build a minimal single-section PE32 image around metadata, with room for a
+/// strong name signature.
+#[cfg(test)]
+pub(crate) fn test_image(metadata: &[u8], strong_name_size: u32) -> Vec<u8> {
+    use crate::writer::Writer;
+
+    const SECTION_RVA: u32 = 0x2000;
+    const SECTION_OFFSET: u32 = 0x200;
+    const CLI_HEADER_SIZE: u32 = 72;
+
+    let metadata_rva = SECTION_RVA + CLI_HEADER_SIZE;
+    let signature_rva = metadata_rva + (metadata.len() as u32).next_multiple_of(4);
+    let section_size = signature_rva - SECTION_RVA + strong_name_size;
+    let raw_size = section_size.next_multiple_of(0x200);
+
+    let mut w = Writer::new();
+    w.write_u16(0x5A4D);
+    w.write_bytes(&[0; 0x3A]);
+    w.write_u32(0x80);
+    w.write_bytes(&[0; 0x40]);
+    w.write_u32(0x0000_4550);
+    // COFF header: i386, 1 section, 224-byte optional header, DLL
+    w.write_u16(0x014C);
+    w.write_u16(1);
+    w.write_bytes(&[0; 12]);
+    w.write_u16(224);
+    w.write_u16(0x2102);
+    // Optional header (PE32)
+    w.write_u16(0x10B);
+    w.write_bytes(&[0; 26]);
+    w.write_u32(0x0040_0000); // image base
+    w.write_u32(0x2000); // section alignment
+    w.write_u32(0x200); // file alignment
+    w.write_bytes(&[0; 16]);
+    w.write_u32(SECTION_RVA + section_size.next_multiple_of(0x2000)); // size of image
+    w.write_u32(SECTION_OFFSET); // size of headers
+    w.write_u32(0); // checksum
+    w.write_u16(3); // console subsystem
+    w.write_u16(0x8540);
+    w.write_bytes(&[0; 20]);
+    w.write_u32(16);
+    for index in 0..16 {
+        if index == CLI_HEADER_DIRECTORY {
+            w.write_u32(SECTION_RVA);
+            w.write_u32(CLI_HEADER_SIZE);
+        } else {
+            w.write_u64(0);
+        }
+    }
+    // Section table
+    w.write_bytes(b".text\0\0\0");
+    w.write_u32(section_size);
+    w.write_u32(SECTION_RVA);
+    w.write_u32(raw_size);
+    w.write_u32(SECTION_OFFSET);
+    w.write_bytes(&[0; 12]);
+    w.write_u32(0x6000_0020);
+    w.write_bytes(&vec![0; SECTION_OFFSET as usize - w.len()]);
+
+    // CLI header
+    w.write_u32(CLI_HEADER_SIZE);
+    w.write_u16(2);
+    w.write_u16(5);
+    w.write_u32(metadata_rva);
+    w.write_u32(metadata.len() as u32);
+    w.write_u32(1); // IL only
+    w.write_u32(0);
+    w.write_u64(0);
+    if strong_name_size > 0 {
+        w.write_u32(signature_rva);
+        w.write_u32(strong_name_size);
+    } else {
+        w.write_u64(0);
+    }
+    w.write_bytes(&[0; 32]);
+
+    w.write_bytes(metadata);
+    w.write_bytes(&vec![0; (SECTION_OFFSET + raw_size) as usize - w.len()]);
+    w.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Metadata;
+
+    #[test]
+    fn test_image_metadata() {
+        let metadata = Metadata::new().write();
+        let image = test_image(&metadata, 128);
+        let pe = PeImage::parse(&image).unwrap();
+        assert_eq!(pe.sections().len(), 1);
+        assert_eq!(pe.sections()[0].name, ".text");
+        assert_eq!(pe.metadata().unwrap(), metadata.as_slice());
+        assert_eq!(pe.cli_header().unwrap().strong_name_signature.size, 128);
+        assert_eq!(pe.rva_to_offset(0x2000), Some(0x200));
+        assert_eq!(pe.rva_to_offset(0x1000), None);
+
+        assert_eq!(metadata_bytes(&image).unwrap(), metadata.as_slice());
+        assert_eq!(metadata_bytes(&metadata).unwrap(), metadata.as_slice());
+        assert!(metadata_bytes(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_section_past_end_of_file() {
+        let metadata = Metadata::new().write();
+        let mut image = test_image(&metadata, 0);
+        // PointerToRawData of the only section header
+        image[0x18C..0x190].copy_from_slice(&0xFFFF_FF00u32.to_le_bytes());
+
+        let pe = PeImage::parse(&image).unwrap();
+        assert_eq!(pe.rva_to_offset(0x2000), Some(0xFFFF_FF00));
+        assert!(
+            pe.rva_to_offset(0x2100)
+                .is_none_or(|offset| offset > image.len())
+        );
+        assert!(pe.cli_header().is_err());
+        assert!(pe.metadata().is_err());
+        assert!(metadata_bytes(&image).is_err());
+    }
+}
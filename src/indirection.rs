@@ -0,0 +1,408 @@
+//! Ptr table indirection of uncompressed (`#-`) table streams.
+//!
+//! Edit-and-Continue and some obfuscators emit `#-` streams, where the
+//! member lists of TypeDef, MethodDef, EventMap and PropertyMap index the
+//! FieldPtr, MethodPtr, ParamPtr, EventPtr and PropertyPtr tables instead of
+//! the member tables, so members can be added without moving rows.
+//! [`Metadata::resolve_ptr`] follows that indirection, and
+//! [`Metadata::compress_tables`] removes it by putting the member rows in
+//! list order, producing a regular `#~` stream.
+
+use crate::metadata::Metadata;
+use crate::stream::StreamHeader;
+use crate::tables::{CodedIndex, TableId};
+
+/// Renumbering of member rows by [`Metadata::compress_tables`].
+///
+/// Rows not reachable through their Ptr table are dropped and map to `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowRemap {
+    fields: Vec<Option<u32>>,
+    methods: Vec<Option<u32>>,
+    params: Vec<Option<u32>>,
+    events: Vec<Option<u32>>,
+    properties: Vec<Option<u32>>,
+}
+
+impl RowRemap {
+    fn table(&self, table: TableId) -> Option<&[Option<u32>]> {
+        match table {
+            TableId::Field => Some(&self.fields),
+            TableId::MethodDef => Some(&self.methods),
+            TableId::Param => Some(&self.params),
+            TableId::Event => Some(&self.events),
+            TableId::Property => Some(&self.properties),
+            _ => None,
+        }
+    }
+
+    /// Check if no row was renumbered.
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        [
+            &self.fields,
+            &self.methods,
+            &self.params,
+            &self.events,
+            &self.properties,
+        ]
+        .iter()
+        .all(|rows| {
+            rows.iter()
+                .enumerate()
+                .all(|(i, row)| *row == Some(i as u32 + 1))
+        })
+    }
+
+    /// Get the new 1-based row of an old row.
+    ///
+    /// Rows of tables that were not renumbered map to themselves; dropped
+    /// rows map to `None`.
+    #[must_use]
+    pub fn map(&self, table: TableId, row: u32) -> Option<u32> {
+        match self.table(table) {
+            Some(rows) if !rows.is_empty() => *rows.get((row as usize).checked_sub(1)?)?,
+            _ => Some(row),
+        }
+    }
+
+    /// Map a metadata token, such as an operand in a method body.
+    ///
+    /// Tokens of dropped rows become nil tokens of their table.
+    #[must_use]
+    pub fn map_token(&self, token: u32) -> u32 {
+        let Ok(table) = TableId::from_u8((token >> 24) as u8) else {
+            return token;
+        };
+        let row = token & 0x00FF_FFFF;
+        (token & 0xFF00_0000) | self.map(table, row).unwrap_or(0)
+    }
+
+    fn map_index(&self, table: TableId, row: &mut u32) {
+        if *row != 0 {
+            *row = self.map(table, *row).unwrap_or(0);
+        }
+    }
+
+    fn map_coded(&self, index: &mut CodedIndex) {
+        if let Some(table) = index.table {
+            if !index.is_null() {
+                index.row = self.map(table, index.row).unwrap_or(0);
+            }
+        }
+    }
+}
+
+/// Put `rows` in the order of the Ptr table `ptrs`, returning the new row of
+/// each old row. An empty Ptr table leaves the rows in place; a Ptr entry
+/// that points nowhere becomes an empty row so later lists stay aligned.
+fn reorder<T: Clone + Default>(rows: &mut Vec<T>, ptrs: &[u32]) -> Vec<Option<u32>> {
+    if ptrs.is_empty() {
+        return (1..=rows.len() as u32).map(Some).collect();
+    }
+    let mut new_rows = vec![None; rows.len()];
+    let mut reordered = Vec::with_capacity(ptrs.len());
+    for &ptr in ptrs {
+        let Some(row) = (ptr as usize).checked_sub(1).and_then(|i| rows.get(i)) else {
+            reordered.push(T::default());
+            continue;
+        };
+        reordered.push(row.clone());
+        new_rows[ptr as usize - 1].get_or_insert(reordered.len() as u32);
+    }
+    *rows = reordered;
+    new_rows
+}
+
+impl Metadata {
+    /// Check if the tables use Ptr table indirection.
+    #[must_use]
+    pub fn has_ptr_tables(&self) -> bool {
+        !(self.field_ptrs.is_empty()
+            && self.method_ptrs.is_empty()
+            && self.param_ptrs.is_empty()
+            && self.event_ptrs.is_empty()
+            && self.property_ptrs.is_empty())
+    }
+
+    /// Map a logical member index, as stored in a member list, to the 1-based
+    /// row of the Field, MethodDef, Param, Event or Property table.
+    ///
+    /// Without a Ptr table for `table` the index is the row. Returns `None`
+    /// if the index is out of range.
+    #[must_use]
+    pub fn resolve_ptr(&self, table: TableId, index: u32) -> Option<u32> {
+        let slot = (index as usize).checked_sub(1)?;
+        let row = match table {
+            TableId::Field if !self.field_ptrs.is_empty() => self.field_ptrs.get(slot)?.field,
+            TableId::MethodDef if !self.method_ptrs.is_empty() => {
+                self.method_ptrs.get(slot)?.method
+            }
+            TableId::Param if !self.param_ptrs.is_empty() => self.param_ptrs.get(slot)?.param,
+            TableId::Event if !self.event_ptrs.is_empty() => self.event_ptrs.get(slot)?.event,
+            TableId::Property if !self.property_ptrs.is_empty() => {
+                self.property_ptrs.get(slot)?.property
+            }
+            _ => index,
+        };
+        (row != 0 && row <= self.table_row_count(table)).then_some(row)
+    }
+
+    /// Get the number of logical indices of a member table: the length of its
+    /// Ptr table if there is one, otherwise of the table itself.
+    #[must_use]
+    pub fn logical_row_count(&self, table: TableId) -> u32 {
+        let ptr_table = match table {
+            TableId::Field => TableId::FieldPtr,
+            TableId::MethodDef => TableId::MethodPtr,
+            TableId::Param => TableId::ParamPtr,
+            TableId::Event => TableId::EventPtr,
+            TableId::Property => TableId::PropertyPtr,
+            _ => return self.table_row_count(table),
+        };
+        match self.table_row_count(ptr_table) {
+            0 => self.table_row_count(table),
+            count => count,
+        }
+    }
+
+    /// Remove the Ptr tables by putting member rows in list order, turning an
+    /// uncompressed `#-` stream into a compressed `#~` stream.
+    ///
+    /// Every reference to a moved row is renumbered, including EncLog and
+    /// EncMap tokens; EnC entries for the Ptr tables themselves are dropped.
+    /// The sorted tables are canonicalized afterwards. Method bodies and the CLI header entry
+    /// point live outside the metadata: rewrite their tokens with the
+    /// returned [`RowRemap`].
+    pub fn compress_tables(&mut self) -> RowRemap {
+        let field_ptrs: Vec<u32> = self.field_ptrs.iter().map(|p| p.field).collect();
+        let method_ptrs: Vec<u32> = self.method_ptrs.iter().map(|p| p.method).collect();
+        let param_ptrs: Vec<u32> = self.param_ptrs.iter().map(|p| p.param).collect();
+        let event_ptrs: Vec<u32> = self.event_ptrs.iter().map(|p| p.event).collect();
+        let property_ptrs: Vec<u32> = self.property_ptrs.iter().map(|p| p.property).collect();
+        let remap = RowRemap {
+            fields: reorder(&mut self.fields, &field_ptrs),
+            methods: reorder(&mut self.method_defs, &method_ptrs),
+            params: reorder(&mut self.params, &param_ptrs),
+            events: reorder(&mut self.events, &event_ptrs),
+            properties: reorder(&mut self.properties, &property_ptrs),
+        };
+        self.field_ptrs.clear();
+        self.method_ptrs.clear();
+        self.param_ptrs.clear();
+        self.event_ptrs.clear();
+        self.property_ptrs.clear();
+
+        // EnC entries for the Ptr tables would name rows that no longer exist
+        let is_ptr = |token: u32| {
+            matches!(
+                TableId::from_u8((token >> 24) as u8),
+                Ok(TableId::FieldPtr
+                    | TableId::MethodPtr
+                    | TableId::ParamPtr
+                    | TableId::EventPtr
+                    | TableId::PropertyPtr)
+            )
+        };
+        self.enc_logs.retain(|row| !is_ptr(row.token));
+        self.enc_maps.retain(|row| !is_ptr(row.token));
+        if !remap.is_identity() {
+            self.apply_remap(&remap);
+        }
+        self.tables_header.uncompressed = false;
+        for stream in &mut self.root.streams {
+            if stream.name == StreamHeader::TABLES_UNCOMPRESSED {
+                stream.name = StreamHeader::TABLES.to_string();
+            }
+        }
+        self.canonicalize();
+        remap
+    }
+
+    /// Renumber every table column that references a member row.
+    fn apply_remap(&mut self, remap: &RowRemap) {
+        for row in &mut self.member_refs {
+            remap.map_coded(&mut row.class);
+        }
+        for row in &mut self.constants {
+            remap.map_coded(&mut row.parent);
+        }
+        for row in &mut self.custom_attributes {
+            remap.map_coded(&mut row.parent);
+            remap.map_coded(&mut row.attr_type);
+        }
+        for row in &mut self.field_marshals {
+            remap.map_coded(&mut row.parent);
+        }
+        for row in &mut self.decl_securities {
+            remap.map_coded(&mut row.parent);
+        }
+        for row in &mut self.field_layouts {
+            remap.map_index(TableId::Field, &mut row.field);
+        }
+        for row in &mut self.method_semantics {
+            remap.map_index(TableId::MethodDef, &mut row.method);
+            remap.map_coded(&mut row.association);
+        }
+        for row in &mut self.method_impls {
+            remap.map_coded(&mut row.method_body);
+            remap.map_coded(&mut row.method_declaration);
+        }
+        for row in &mut self.impl_maps {
+            remap.map_coded(&mut row.member_forwarded);
+        }
+        for row in &mut self.field_rvas {
+            remap.map_index(TableId::Field, &mut row.field);
+        }
+        for row in &mut self.generic_params {
+            remap.map_coded(&mut row.owner);
+        }
+        for row in &mut self.method_specs {
+            remap.map_coded(&mut row.method);
+        }
+        for row in &mut self.enc_logs {
+            row.token = remap.map_token(row.token);
+        }
+        // EncMap is sorted by token
+        for row in &mut self.enc_maps {
+            row.token = remap.map_token(row.token);
+        }
+        self.enc_maps.sort_by_key(|row| row.token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::{
+        ConstantRow, CustomAttributeRow, EncLogRow, EncMapRow, FieldPtrRow, FieldRow, FieldRvaRow,
+        MethodDefRow, MethodPtrRow, ModuleRow, TypeDefRow,
+    };
+
+    fn coded(table: TableId, row: u32) -> CodedIndex {
+        CodedIndex {
+            table: Some(table),
+            row,
+        }
+    }
+
+    /// An EnC-style module: type A owns fields c and a, type B owns field b,
+    /// and field rows were appended in the order a, b, c.
+    fn indirect_metadata() -> Metadata {
+        let mut md = Metadata::new();
+        let name = md.strings.add("EnC.dll");
+        md.modules.push(ModuleRow {
+            name,
+            ..Default::default()
+        });
+        for (name, field_list) in [("A", 1), ("B", 3)] {
+            let type_name = md.strings.add(name);
+            md.type_defs.push(TypeDefRow {
+                type_name,
+                field_list,
+                method_list: 1,
+                ..Default::default()
+            });
+        }
+        let signature = md.blobs.add(&[0x06, 0x08]);
+        for field in ["a", "b", "c"] {
+            let name = md.strings.add(field);
+            md.fields.push(FieldRow {
+                flags: 0x0016,
+                name,
+                signature,
+            });
+        }
+        for field in [3, 1, 2] {
+            md.field_ptrs.push(FieldPtrRow { field });
+        }
+        let name = md.strings.add("M");
+        md.method_defs.push(MethodDefRow {
+            name,
+            signature: md.blobs.add(&[0x00, 0x00, 0x01]),
+            param_list: 1,
+            ..Default::default()
+        });
+        md.method_ptrs.push(MethodPtrRow { method: 1 });
+
+        let value = md.blobs.add(&[7, 0, 0, 0]);
+        md.constants.push(ConstantRow {
+            constant_type: 0x08,
+            padding: 0,
+            parent: coded(TableId::Field, 2),
+            value,
+        });
+        md.custom_attributes.push(CustomAttributeRow {
+            parent: coded(TableId::Field, 3),
+            attr_type: CodedIndex::null(),
+            value: 0,
+        });
+        md.field_rvas.push(FieldRvaRow {
+            rva: 0x4000,
+            field: 1,
+        });
+        for token in [0x0300_0003, 0x0400_0001, 0x0400_0003] {
+            md.enc_logs.push(EncLogRow {
+                token,
+                func_code: 0,
+            });
+            md.enc_maps.push(EncMapRow { token });
+        }
+        md.tables_header.uncompressed = true;
+        md
+    }
+
+    fn field_names(md: &Metadata, type_def: u32) -> Vec<&str> {
+        md.get_type_fields(type_def)
+            .into_iter()
+            .map(|(_, row)| md.strings.get(row.name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_ptr() {
+        let md = indirect_metadata();
+        assert!(md.has_ptr_tables());
+        assert_eq!(md.resolve_ptr(TableId::Field, 1), Some(3));
+        assert_eq!(md.resolve_ptr(TableId::Field, 3), Some(2));
+        assert_eq!(md.resolve_ptr(TableId::Field, 4), None);
+        assert_eq!(md.resolve_ptr(TableId::MethodDef, 1), Some(1));
+        assert_eq!(md.resolve_ptr(TableId::Param, 1), None);
+        assert_eq!(md.logical_row_count(TableId::Field), 3);
+        assert_eq!(field_names(&md, 1), ["c", "a"]);
+        assert_eq!(field_names(&md, 2), ["b"]);
+    }
+
+    #[test]
+    fn test_compress_tables() {
+        let mut md = indirect_metadata();
+        let remap = md.compress_tables();
+        assert!(!md.has_ptr_tables());
+        assert!(!md.tables_header.uncompressed);
+        assert_eq!(field_names(&md, 1), ["c", "a"]);
+        assert_eq!(field_names(&md, 2), ["b"]);
+
+        assert_eq!(remap.map(TableId::Field, 1), Some(2));
+        assert_eq!(remap.map(TableId::Field, 2), Some(3));
+        assert_eq!(remap.map(TableId::Field, 3), Some(1));
+        assert_eq!(remap.map(TableId::MethodDef, 1), Some(1));
+        assert_eq!(remap.map_token(0x0400_0003), 0x0400_0001);
+        assert_eq!(remap.map_token(0x7000_0003), 0x7000_0003);
+
+        // Field b's constant, c's attribute and a's RVA follow their rows
+        assert_eq!(md.constants[0].parent, coded(TableId::Field, 3));
+        assert_eq!(md.custom_attributes[0].parent, coded(TableId::Field, 1));
+        assert_eq!(md.field_rvas[0].field, 2);
+
+        // EnC tokens follow their rows and the FieldPtr entry is gone
+        let log: Vec<u32> = md.enc_logs.iter().map(|row| row.token).collect();
+        assert_eq!(log, [0x0400_0002, 0x0400_0001]);
+        let map: Vec<u32> = md.enc_maps.iter().map(|row| row.token).collect();
+        assert_eq!(map, [0x0400_0001, 0x0400_0002]);
+
+        let written = Metadata::parse(&md.write()).unwrap();
+        assert!(written.root.tables_stream().unwrap().name == StreamHeader::TABLES);
+        assert!(!written.has_ptr_tables());
+        assert_eq!(field_names(&written, 1), ["c", "a"]);
+    }
+}
@@ -3,16 +3,39 @@
 //! ECMA-335 CLI/.NET metadata parsing library with read/write support.
 //!
 //! This crate provides functionality to parse, modify, and write CLR metadata
-//! from .NET assemblies. It works with raw metadata bytes, making it PE-agnostic
-//! and suitable for use with any PE parser.
+//! from .NET assemblies. It works with raw metadata bytes, so it can be used
+//! with any PE parser, and includes a minimal PE reader for locating the
+//! metadata, strong-name signature and debug directory of an image.
 //!
 //! ## Features
 //!
 //! - Parse BSJB metadata root and stream headers
 //! - Access heaps: #Strings, #US, #GUID, #Blob
 //! - Parse metadata tables: Module, TypeDef, TypeRef, MethodDef, Assembly, AssemblyRef, etc.
+//! - Follow or remove the Ptr tables of uncompressed `#-` table streams
 //! - Modify metadata structures
-//! - Write metadata back to bytes
+//! - Write metadata back to bytes, sorting tables into canonical order
+//! - Compact heaps, dropping orphaned entries and sharing string suffixes
+//! - Validate metadata against ECMA-335 rules with structured diagnostics
+//! - Diff two versions of an assembly by name and signature, as text or JSON
+//! - Extract the public API surface and detect binary-breaking changes
+//! - Generate reference assemblies without bodies or private members
+//! - Decode and encode IL method bodies and custom attribute blobs
+//! - Decode and encode constant values
+//! - Substitute generic arguments into member signatures
+//! - Resolve member references to their definitions, across assemblies
+//! - Load referenced assemblies into a workspace and follow type forwarders
+//! - Enumerate exported types and type forwarders
+//! - Verify the file hashes of multi-file assemblies
+//! - Parse, format and compare assembly identities
+//! - Parse, format and resolve reflection type names
+//! - Verify and create strong-name signatures
+//! - Compute MD5, SHA-1 and SHA-2 digests
+//! - Recompute and verify deterministic MVIDs and PDB IDs
+//! - Decode and encode marshalling descriptors
+//! - Decode declarative security permission sets
+//! - Read managed resources and `.resources` files
+//! - Assemble ILAsm source text into metadata (for test fixtures)
 //!
 //! ## Example
 //!
@@ -35,22 +58,62 @@
 //! let modified_bytes = metadata.write();
 //! ```
 
+pub mod api;
+pub mod assembly_set;
+mod bignum;
+pub mod canonical;
+pub mod compact;
+pub mod constant;
 pub mod crypto;
+pub mod custom_attribute;
+pub mod deterministic;
+pub mod diff;
 pub mod error;
+pub mod exported_type;
+pub mod file;
+pub mod generics;
 pub mod heaps;
+pub mod identity;
+pub mod il;
+pub mod ilasm;
+pub mod image;
+pub mod indirection;
+pub mod marshal;
 pub mod metadata;
 pub mod reader;
+pub mod reference_assembly;
+pub mod resolve;
+pub mod resources;
 pub mod root;
+pub mod security;
 pub mod signature;
 pub mod stream;
+pub mod strong_name;
 pub mod tables;
+pub mod type_name;
+pub mod validation;
 pub mod writer;
 
 // Re-export main types
+pub use api::{ApiMember, ApiSurface, ApiType, BreakingChange, BreakingChangeKind, Visibility};
+pub use compact::HeapRemap;
+pub use crypto::{HashAlgorithm, Hasher};
+pub use deterministic::{ContentId, DeterminismReport};
+pub use diff::{Change, ChangeKind, EntityKind, MetadataDiff};
 pub use error::{Error, Result};
+pub use exported_type::{ExportedTypeImplementation, ExportedTypeInfo};
+pub use file::FileInfo;
+pub use marshal::{MarshalSpec, NativeType};
 pub use metadata::{AssemblyInfo, AssemblyRefInfo, Metadata, MethodInfo, ResolvedType, TypeInfo};
+pub use reference_assembly::InternalMembers;
+pub use resolve::{AssemblyResolver, MemberDefinition, ResolvedMember};
+pub use resources::{
+    ManifestResourceInfo, ResourceEntry, ResourceLocation, ResourceSet, ResourceValue,
+};
 pub use root::MetadataRoot;
+pub use security::{PermissionSet, SecurityAction, SecurityAttribute, SecurityDeclaration};
 pub use stream::StreamHeader;
+pub use strong_name::{SigningMode, StrongNameKeyPair, StrongNamePublicKey, StrongNameStatus};
 
 // Re-export heaps
 pub use heaps::{BlobHeap, GuidHeap, StringsHeap, UserStringsHeap};
@@ -68,7 +131,30 @@ pub use tables::{
     PropertyPtrRow, PropertyRow, StandAloneSigRow, TypeDefRow, TypeRefRow, TypeSpecRow,
 };
 
+// Re-export IL, constant and custom attribute types
+pub use constant::ConstantValue;
+pub use custom_attribute::{CaNamedArg, CaType, CaValue, CustomAttributeValue};
+pub use il::{ExceptionHandler, ExceptionHandlerKind, Instruction, MethodBody, OpCode, Operand};
+pub use ilasm::{AssembledModule, assemble};
+
 // Re-export signature types
+pub use generics::GenericContext;
 pub use signature::{
-    CallingConvention, ElementType, FieldSig, LocalVarSig, MethodSig, PropertySig, TypeSig,
+    CallingConvention, ElementType, FieldSig, LocalVarSig, MethodSig, MethodSpecSig, PropertySig,
+    TypeSig,
+};
+
+// Re-export assembly identity and loading types
+pub use assembly_set::{
+    AssemblyId, AssemblySet, DirectoryResolver, MemoryResolver, ReferencePackResolver, Resolver,
+    TypeHandle,
 };
+pub use identity::{AssemblyIdentity, IdentityComparison, ProcessorArchitecture, PublicKeyOrToken};
+pub use type_name::{TypeName, TypeNameModifier};
+
+// Re-export PE image types
+pub use image::{CliHeader, DebugDirectoryEntry, PeImage};
+
+// Re-export table rewriting and validation types
+pub use indirection::RowRemap;
+pub use validation::{Diagnostic, Rule, Severity};
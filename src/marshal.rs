@@ -0,0 +1,592 @@
+//! Marshalling descriptors (FieldMarshal blobs).
+//!
+//! ECMA-335 II.23.4 defines the native type encoding, extended by CoreCLR with
+//! additional native types (`IInspectable`, `HString`, `LPUTF8Str`) and
+//! optional trailing parameters.
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+use crate::reader::Reader;
+use crate::tables::{CodedIndex, CodedIndexKind};
+use crate::writer::Writer;
+
+/// Native type code (`NATIVE_TYPE_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NativeType(pub u8);
+
+impl NativeType {
+    pub const VOID: Self = Self(0x01);
+    pub const BOOLEAN: Self = Self(0x02);
+    pub const I1: Self = Self(0x03);
+    pub const U1: Self = Self(0x04);
+    pub const I2: Self = Self(0x05);
+    pub const U2: Self = Self(0x06);
+    pub const I4: Self = Self(0x07);
+    pub const U4: Self = Self(0x08);
+    pub const I8: Self = Self(0x09);
+    pub const U8: Self = Self(0x0A);
+    pub const R4: Self = Self(0x0B);
+    pub const R8: Self = Self(0x0C);
+    pub const SYSCHAR: Self = Self(0x0D);
+    pub const VARIANT: Self = Self(0x0E);
+    pub const CURRENCY: Self = Self(0x0F);
+    pub const PTR: Self = Self(0x10);
+    pub const DECIMAL: Self = Self(0x11);
+    pub const DATE: Self = Self(0x12);
+    pub const BSTR: Self = Self(0x13);
+    pub const LPSTR: Self = Self(0x14);
+    pub const LPWSTR: Self = Self(0x15);
+    pub const LPTSTR: Self = Self(0x16);
+    pub const FIXED_SYS_STRING: Self = Self(0x17);
+    pub const OBJECT_REF: Self = Self(0x18);
+    pub const IUNKNOWN: Self = Self(0x19);
+    pub const IDISPATCH: Self = Self(0x1A);
+    pub const STRUCT: Self = Self(0x1B);
+    pub const INTERFACE: Self = Self(0x1C);
+    pub const SAFE_ARRAY: Self = Self(0x1D);
+    pub const FIXED_ARRAY: Self = Self(0x1E);
+    pub const INT: Self = Self(0x1F);
+    pub const UINT: Self = Self(0x20);
+    pub const NESTED_STRUCT: Self = Self(0x21);
+    pub const BYVAL_STR: Self = Self(0x22);
+    pub const ANSI_BSTR: Self = Self(0x23);
+    pub const TBSTR: Self = Self(0x24);
+    pub const VARIANT_BOOL: Self = Self(0x25);
+    pub const FUNC: Self = Self(0x26);
+    pub const AS_ANY: Self = Self(0x28);
+    pub const ARRAY: Self = Self(0x2A);
+    pub const LPSTRUCT: Self = Self(0x2B);
+    pub const CUSTOM_MARSHALER: Self = Self(0x2C);
+    pub const ERROR: Self = Self(0x2D);
+    pub const IINSPECTABLE: Self = Self(0x2E);
+    pub const HSTRING: Self = Self(0x2F);
+    pub const LPUTF8STR: Self = Self(0x30);
+    /// Marks an unspecified array element type.
+    pub const MAX: Self = Self(0x50);
+
+    /// ILAsm keyword for this native type.
+    #[must_use]
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            Self::VOID => "void",
+            Self::BOOLEAN => "bool",
+            Self::I1 => "int8",
+            Self::U1 => "unsigned int8",
+            Self::I2 => "int16",
+            Self::U2 => "unsigned int16",
+            Self::I4 => "int32",
+            Self::U4 => "unsigned int32",
+            Self::I8 => "int64",
+            Self::U8 => "unsigned int64",
+            Self::R4 => "float32",
+            Self::R8 => "float64",
+            Self::SYSCHAR => "syschar",
+            Self::VARIANT => "variant",
+            Self::CURRENCY => "currency",
+            Self::PTR => "*",
+            Self::DECIMAL => "decimal",
+            Self::DATE => "date",
+            Self::BSTR => "bstr",
+            Self::LPSTR => "lpstr",
+            Self::LPWSTR => "lpwstr",
+            Self::LPTSTR => "lptstr",
+            Self::FIXED_SYS_STRING => "fixed sysstring",
+            Self::OBJECT_REF => "objectref",
+            Self::IUNKNOWN => "iunknown",
+            Self::IDISPATCH => "idispatch",
+            Self::STRUCT => "struct",
+            Self::INTERFACE => "interface",
+            Self::SAFE_ARRAY => "safearray",
+            Self::FIXED_ARRAY => "fixed array",
+            Self::INT => "int",
+            Self::UINT => "unsigned int",
+            Self::NESTED_STRUCT => "nested struct",
+            Self::BYVAL_STR => "byvalstr",
+            Self::ANSI_BSTR => "ansi bstr",
+            Self::TBSTR => "tbstr",
+            Self::VARIANT_BOOL => "variant bool",
+            Self::FUNC => "method",
+            Self::AS_ANY => "as any",
+            Self::ARRAY => "[]",
+            Self::LPSTRUCT => "lpstruct",
+            Self::CUSTOM_MARSHALER => "custom",
+            Self::ERROR => "error",
+            Self::IINSPECTABLE => "iinspectable",
+            Self::HSTRING => "hstring",
+            Self::LPUTF8STR => "lputf8str",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for NativeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "native type 0x{:02X}", self.0),
+        }
+    }
+}
+
+/// A decoded marshalling descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarshalSpec {
+    /// A native type without parameters.
+    Simple(NativeType),
+    /// `ByValTStr`: an inline character array of fixed size.
+    ByValTStr {
+        /// Number of characters.
+        size: u32,
+    },
+    /// COM `SAFEARRAY`.
+    SafeArray {
+        /// Element variant type (`VT_*`).
+        variant_type: Option<u32>,
+        /// User-defined subtype name for `VT_RECORD`, `VT_UNKNOWN` or `VT_DISPATCH`.
+        user_defined_subtype: Option<String>,
+    },
+    /// `ByValArray`: an inline array of fixed size.
+    FixedArray {
+        /// Number of elements.
+        size: Option<u32>,
+        /// Element native type.
+        element_type: Option<NativeType>,
+    },
+    /// `LPArray`: a pointer to a C-style array.
+    Array {
+        /// Element native type (`None` if unspecified).
+        element_type: Option<NativeType>,
+        /// Index of the parameter holding the element count.
+        param_index: Option<u32>,
+        /// Constant element count (added to the parameter's value).
+        num_elements: Option<u32>,
+        /// Whether `param_index` was explicitly specified (CoreCLR extension).
+        flags: Option<u32>,
+    },
+    /// Custom marshaler.
+    CustomMarshaler {
+        /// Type library GUID (unused by the runtime, usually empty).
+        guid: String,
+        /// Unmanaged type name (unused by the runtime, usually empty).
+        native_type_name: String,
+        /// Marshaler type name.
+        marshaler_type: String,
+        /// Cookie passed to the marshaler.
+        cookie: String,
+    },
+    /// COM interface pointer (`IUnknown`, `IDispatch`, `Interface` or `IInspectable`).
+    Interface {
+        /// The interface native type.
+        native_type: NativeType,
+        /// Index of the parameter holding the interface IID.
+        iid_param_index: Option<u32>,
+    },
+}
+
+impl MarshalSpec {
+    /// Parse a marshalling descriptor.
+    pub fn parse(reader: &mut Reader<'_>) -> Result<Self> {
+        let native_type = NativeType(reader.read_u8()?);
+        let optional = |reader: &mut Reader<'_>| -> Result<Option<u32>> {
+            if reader.is_empty() {
+                Ok(None)
+            } else {
+                reader.read_compressed_uint().map(Some)
+            }
+        };
+
+        Ok(match native_type {
+            NativeType::FIXED_SYS_STRING => Self::ByValTStr {
+                size: reader.read_compressed_uint()?,
+            },
+            NativeType::SAFE_ARRAY => {
+                let variant_type = optional(reader)?;
+                let user_defined_subtype = if reader.is_empty() {
+                    None
+                } else {
+                    Some(read_string(reader)?)
+                };
+                Self::SafeArray {
+                    variant_type,
+                    user_defined_subtype,
+                }
+            }
+            NativeType::FIXED_ARRAY => Self::FixedArray {
+                size: optional(reader)?,
+                element_type: optional(reader)?.map(|t| NativeType(t as u8)),
+            },
+            NativeType::ARRAY => Self::Array {
+                element_type: optional(reader)?
+                    .map(|t| NativeType(t as u8))
+                    .filter(|&t| t != NativeType::MAX),
+                param_index: optional(reader)?,
+                num_elements: optional(reader)?,
+                flags: optional(reader)?,
+            },
+            NativeType::CUSTOM_MARSHALER => Self::CustomMarshaler {
+                guid: read_string(reader)?,
+                native_type_name: read_string(reader)?,
+                marshaler_type: read_string(reader)?,
+                cookie: read_string(reader)?,
+            },
+            NativeType::IUNKNOWN
+            | NativeType::IDISPATCH
+            | NativeType::INTERFACE
+            | NativeType::IINSPECTABLE => Self::Interface {
+                native_type,
+                iid_param_index: optional(reader)?,
+            },
+            _ => Self::Simple(native_type),
+        })
+    }
+
+    /// Parse a marshalling descriptor from raw bytes.
+    pub fn parse_blob(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        Self::parse(&mut reader)
+    }
+
+    /// The native type code.
+    #[must_use]
+    pub fn native_type(&self) -> NativeType {
+        match self {
+            Self::Simple(native_type) | Self::Interface { native_type, .. } => *native_type,
+            Self::ByValTStr { .. } => NativeType::FIXED_SYS_STRING,
+            Self::SafeArray { .. } => NativeType::SAFE_ARRAY,
+            Self::FixedArray { .. } => NativeType::FIXED_ARRAY,
+            Self::Array { .. } => NativeType::ARRAY,
+            Self::CustomMarshaler { .. } => NativeType::CUSTOM_MARSHALER,
+        }
+    }
+
+    /// Write this descriptor to a blob.
+    pub fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.native_type().0);
+        match self {
+            Self::Simple(_) => {}
+            Self::ByValTStr { size } => writer.write_compressed_uint(*size),
+            Self::SafeArray {
+                variant_type,
+                user_defined_subtype,
+            } => {
+                // A subtype name can only follow an explicit variant type
+                if let Some(variant_type) = variant_type {
+                    writer.write_compressed_uint(*variant_type);
+                    if let Some(name) = user_defined_subtype {
+                        write_string(writer, name);
+                    }
+                }
+            }
+            Self::FixedArray { size, element_type } => {
+                if let Some(size) = size {
+                    writer.write_compressed_uint(*size);
+                    if let Some(element_type) = element_type {
+                        writer.write_compressed_uint(u32::from(element_type.0));
+                    }
+                }
+            }
+            Self::Array {
+                element_type,
+                param_index,
+                num_elements,
+                flags,
+            } => {
+                let trailing = [*param_index, *num_elements, *flags];
+                let count = trailing
+                    .iter()
+                    .rposition(Option::is_some)
+                    .map_or(0, |i| i + 1);
+                if element_type.is_some() || count > 0 {
+                    let element_type = element_type.unwrap_or(NativeType::MAX);
+                    writer.write_compressed_uint(u32::from(element_type.0));
+                }
+                for value in &trailing[..count] {
+                    writer.write_compressed_uint(value.unwrap_or(0));
+                }
+            }
+            Self::CustomMarshaler {
+                guid,
+                native_type_name,
+                marshaler_type,
+                cookie,
+            } => {
+                write_string(writer, guid);
+                write_string(writer, native_type_name);
+                write_string(writer, marshaler_type);
+                write_string(writer, cookie);
+            }
+            Self::Interface {
+                iid_param_index, ..
+            } => {
+                if let Some(index) = iid_param_index {
+                    writer.write_compressed_uint(*index);
+                }
+            }
+        }
+    }
+
+    /// Serialize this descriptor to raw bytes.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write(&mut writer);
+        writer.into_inner()
+    }
+}
+
+/// Formats the descriptor in ILAsm `marshal(...)` syntax.
+impl fmt::Display for MarshalSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Simple(native_type) => write!(f, "{native_type}"),
+            Self::ByValTStr { size } => write!(f, "fixed sysstring [{size}]"),
+            Self::SafeArray {
+                variant_type,
+                user_defined_subtype,
+            } => {
+                f.write_str("safearray")?;
+                if let Some(variant_type) = variant_type {
+                    match variant_type_name(*variant_type) {
+                        Some(name) => write!(f, " {name}")?,
+                        None => write!(f, " 0x{variant_type:X}")?,
+                    }
+                }
+                if let Some(name) = user_defined_subtype {
+                    write!(f, ", \"{name}\"")?;
+                }
+                Ok(())
+            }
+            Self::FixedArray { size, element_type } => {
+                write!(f, "fixed array [{}]", size.unwrap_or(0))?;
+                if let Some(element_type) = element_type {
+                    write!(f, " {element_type}")?;
+                }
+                Ok(())
+            }
+            Self::Array {
+                element_type,
+                param_index,
+                num_elements,
+                ..
+            } => {
+                if let Some(element_type) = element_type {
+                    write!(f, "{element_type} ")?;
+                }
+                f.write_str("[")?;
+                if let Some(num_elements) = num_elements {
+                    write!(f, "{num_elements}")?;
+                }
+                if let Some(param_index) = param_index {
+                    write!(f, " + {param_index}")?;
+                }
+                f.write_str("]")
+            }
+            Self::CustomMarshaler {
+                guid,
+                native_type_name,
+                marshaler_type,
+                cookie,
+            } => write!(
+                f,
+                "custom (\"{marshaler_type}\", \"{cookie}\", \"{guid}\", \"{native_type_name}\")"
+            ),
+            Self::Interface {
+                native_type,
+                iid_param_index,
+            } => {
+                write!(f, "{native_type}")?;
+                if let Some(index) = iid_param_index {
+                    write!(f, "(iidparam = {index})")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// ILAsm name of a COM variant type (`VT_*`).
+fn variant_type_name(variant_type: u32) -> Option<&'static str> {
+    Some(match variant_type {
+        2 => "int16",
+        3 => "int32",
+        4 => "float32",
+        5 => "float64",
+        6 => "currency",
+        7 => "date",
+        8 => "bstr",
+        9 => "idispatch",
+        10 => "error",
+        11 => "bool",
+        12 => "variant",
+        13 => "iunknown",
+        14 => "decimal",
+        16 => "int8",
+        17 => "unsigned int8",
+        18 => "unsigned int16",
+        19 => "unsigned int32",
+        20 => "int64",
+        21 => "unsigned int64",
+        22 => "int",
+        23 => "unsigned int",
+        30 => "lpstr",
+        31 => "lpwstr",
+        36 => "record",
+        _ => return None,
+    })
+}
+
+/// Read a length-prefixed UTF-8 string.
+fn read_string(reader: &mut Reader<'_>) -> Result<String> {
+    let start = reader.position();
+    let len = reader.read_compressed_uint()? as usize;
+    let bytes = reader.read_bytes(len)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| Error::InvalidBlob(start))
+}
+
+fn write_string(writer: &mut Writer, value: &str) {
+    writer.write_compressed_uint(value.len() as u32);
+    writer.write_bytes(value.as_bytes());
+}
+
+impl Metadata {
+    /// Get the marshalling descriptor of a field or parameter.
+    ///
+    /// `parent` is a Field or Param index. Returns `None` if no FieldMarshal
+    /// row references it.
+    pub fn marshal_spec(&self, parent: CodedIndex) -> Option<Result<MarshalSpec>> {
+        let key = parent.encode(CodedIndexKind::HasFieldMarshal);
+        let row = self
+            .field_marshals
+            .iter()
+            .find(|row| row.parent.encode(CodedIndexKind::HasFieldMarshal) == key)?;
+        Some(
+            self.blobs
+                .get(row.native_type)
+                .and_then(MarshalSpec::parse_blob),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::{FieldMarshalRow, TableId};
+
+    fn roundtrip(blob: &[u8], expected: MarshalSpec) {
+        let spec = MarshalSpec::parse_blob(blob).unwrap();
+        assert_eq!(spec, expected);
+        assert_eq!(spec.to_blob(), blob);
+    }
+
+    #[test]
+    fn test_simple() {
+        roundtrip(&[0x14], MarshalSpec::Simple(NativeType::LPSTR));
+        roundtrip(&[0x30], MarshalSpec::Simple(NativeType::LPUTF8STR));
+        assert_eq!(MarshalSpec::Simple(NativeType::BOOLEAN).to_string(), "bool");
+    }
+
+    #[test]
+    fn test_arrays() {
+        roundtrip(&[0x17, 0x20], MarshalSpec::ByValTStr { size: 32 });
+        roundtrip(
+            &[0x1E, 0x10, 0x04],
+            MarshalSpec::FixedArray {
+                size: Some(16),
+                element_type: Some(NativeType::U1),
+            },
+        );
+        roundtrip(
+            &[0x2A, 0x07, 0x02, 0x00, 0x01],
+            MarshalSpec::Array {
+                element_type: Some(NativeType::I4),
+                param_index: Some(2),
+                num_elements: Some(0),
+                flags: Some(1),
+            },
+        );
+        roundtrip(
+            &[0x2A, 0x50, 0x01],
+            MarshalSpec::Array {
+                element_type: None,
+                param_index: Some(1),
+                num_elements: None,
+                flags: None,
+            },
+        );
+        let spec = MarshalSpec::parse_blob(&[0x2A, 0x14, 0x01, 0x04]).unwrap();
+        assert_eq!(spec.to_string(), "lpstr [4 + 1]");
+    }
+
+    #[test]
+    fn test_safe_array() {
+        let mut blob = vec![0x1D, 0x24, 0x0A];
+        blob.extend_from_slice(b"Sample.Rec");
+        roundtrip(
+            &blob,
+            MarshalSpec::SafeArray {
+                variant_type: Some(36),
+                user_defined_subtype: Some("Sample.Rec".into()),
+            },
+        );
+        roundtrip(
+            &[0x1D],
+            MarshalSpec::SafeArray {
+                variant_type: None,
+                user_defined_subtype: None,
+            },
+        );
+    }
+
+    #[test]
+    fn test_custom_marshaler_and_interface() {
+        let mut blob = vec![0x2C, 0x00, 0x00, 0x0B];
+        blob.extend_from_slice(b"My.Marshal,");
+        blob.push(0x03);
+        blob.extend_from_slice(b"abc");
+        let spec = MarshalSpec::CustomMarshaler {
+            guid: String::new(),
+            native_type_name: String::new(),
+            marshaler_type: "My.Marshal,".into(),
+            cookie: "abc".into(),
+        };
+        roundtrip(&blob, spec);
+
+        roundtrip(
+            &[0x1C, 0x03],
+            MarshalSpec::Interface {
+                native_type: NativeType::INTERFACE,
+                iid_param_index: Some(3),
+            },
+        );
+        assert_eq!(
+            MarshalSpec::parse_blob(&[0x1C, 0x03]).unwrap().to_string(),
+            "interface(iidparam = 3)"
+        );
+    }
+
+    #[test]
+    fn test_metadata_lookup() {
+        let mut md = Metadata::new();
+        let native_type = md.blobs.add(&[0x17, 0x08]);
+        let param = CodedIndex {
+            table: Some(TableId::Param),
+            row: 2,
+        };
+        md.field_marshals.push(FieldMarshalRow {
+            parent: param,
+            native_type,
+        });
+
+        assert_eq!(
+            md.marshal_spec(param).unwrap().unwrap(),
+            MarshalSpec::ByValTStr { size: 8 }
+        );
+        let field = CodedIndex {
+            table: Some(TableId::Field),
+            row: 2,
+        };
+        assert!(md.marshal_spec(field).is_none());
+    }
+}
@@ -126,7 +126,104 @@ pub struct Metadata {
     pub generic_param_constraints: Vec<GenericParamConstraintRow>,
 }
 
+impl Default for Metadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Metadata {
+    /// Sorted-table mask written by the Microsoft toolchain.
+    const DEFAULT_SORTED_TABLES: u64 = 0x0000_1600_3301_FA00;
+
+    /// Create empty metadata with the standard stream layout (`#~`, `#Strings`,
+    /// `#US`, `#GUID`, `#Blob`) and runtime version "v4.0.30319".
+    #[must_use]
+    pub fn new() -> Self {
+        let stream = |name: &str| StreamHeader {
+            offset: 0,
+            size: 0,
+            name: name.to_string(),
+        };
+
+        Self {
+            root: MetadataRoot {
+                major_version: 1,
+                minor_version: 1,
+                reserved: 0,
+                version: "v4.0.30319".to_string(),
+                flags: 0,
+                streams: vec![
+                    stream(StreamHeader::TABLES),
+                    stream(StreamHeader::STRINGS),
+                    stream(StreamHeader::USER_STRINGS),
+                    stream(StreamHeader::GUID),
+                    stream(StreamHeader::BLOB),
+                ],
+            },
+            strings: StringsHeap::new(),
+            user_strings: UserStringsHeap::new(),
+            guids: GuidHeap::new(),
+            blobs: BlobHeap::new(),
+            tables_header: TablesHeader {
+                reserved: 0,
+                major_version: 2,
+                minor_version: 0,
+                heap_sizes: 0,
+                reserved2: 1,
+                valid: 0,
+                sorted: Self::DEFAULT_SORTED_TABLES,
+                row_counts: [0; 64],
+                uncompressed: false,
+            },
+            modules: Vec::new(),
+            type_refs: Vec::new(),
+            type_defs: Vec::new(),
+            field_ptrs: Vec::new(),
+            fields: Vec::new(),
+            method_ptrs: Vec::new(),
+            method_defs: Vec::new(),
+            param_ptrs: Vec::new(),
+            params: Vec::new(),
+            interface_impls: Vec::new(),
+            member_refs: Vec::new(),
+            constants: Vec::new(),
+            custom_attributes: Vec::new(),
+            field_marshals: Vec::new(),
+            decl_securities: Vec::new(),
+            class_layouts: Vec::new(),
+            field_layouts: Vec::new(),
+            stand_alone_sigs: Vec::new(),
+            event_maps: Vec::new(),
+            event_ptrs: Vec::new(),
+            events: Vec::new(),
+            property_maps: Vec::new(),
+            property_ptrs: Vec::new(),
+            properties: Vec::new(),
+            method_semantics: Vec::new(),
+            method_impls: Vec::new(),
+            module_refs: Vec::new(),
+            type_specs: Vec::new(),
+            impl_maps: Vec::new(),
+            field_rvas: Vec::new(),
+            enc_logs: Vec::new(),
+            enc_maps: Vec::new(),
+            assemblies: Vec::new(),
+            assembly_processors: Vec::new(),
+            assembly_oses: Vec::new(),
+            assembly_refs: Vec::new(),
+            assembly_ref_processors: Vec::new(),
+            assembly_ref_oses: Vec::new(),
+            files: Vec::new(),
+            exported_types: Vec::new(),
+            manifest_resources: Vec::new(),
+            nested_classes: Vec::new(),
+            generic_params: Vec::new(),
+            method_specs: Vec::new(),
+            generic_param_constraints: Vec::new(),
+        }
+    }
+
     /// Parse metadata from raw bytes.
     pub fn parse(data: &[u8]) -> Result<Self> {
         let root = MetadataRoot::parse(data)?;
@@ -524,38 +621,47 @@ impl Metadata {
     pub fn assembly_refs(&self) -> Vec<AssemblyRefInfo> {
         self.assembly_refs
             .iter()
-            .map(|row| {
-                let name = self.strings.get(row.name).unwrap_or("").to_string();
-                let culture = if row.culture != 0 {
-                    self.strings.get(row.culture).ok().map(|s| s.to_string())
-                } else {
-                    None
-                };
-                let public_key_token = if row.public_key_or_token != 0 {
-                    self.blobs
-                        .get(row.public_key_or_token)
-                        .ok()
-                        .map(|b| b.to_vec())
-                } else {
-                    None
-                };
-
-                AssemblyRefInfo {
-                    name,
-                    version: (
-                        row.major_version,
-                        row.minor_version,
-                        row.build_number,
-                        row.revision_number,
-                    ),
-                    culture,
-                    public_key_token,
-                    flags: row.flags,
-                }
-            })
+            .map(|row| self.assembly_ref_info(row))
             .collect()
     }
 
+    /// Get the AssemblyRef at the given 1-based index.
+    #[must_use]
+    pub fn get_assembly_ref(&self, index: u32) -> Option<AssemblyRefInfo> {
+        let row = self.assembly_refs.get(index.checked_sub(1)? as usize)?;
+        Some(self.assembly_ref_info(row))
+    }
+
+    fn assembly_ref_info(&self, row: &AssemblyRefRow) -> AssemblyRefInfo {
+        let name = self.strings.get(row.name).unwrap_or("").to_string();
+        let culture = if row.culture != 0 {
+            self.strings.get(row.culture).ok().map(|s| s.to_string())
+        } else {
+            None
+        };
+        let public_key_token = if row.public_key_or_token != 0 {
+            self.blobs
+                .get(row.public_key_or_token)
+                .ok()
+                .map(|b| b.to_vec())
+        } else {
+            None
+        };
+
+        AssemblyRefInfo {
+            name,
+            version: (
+                row.major_version,
+                row.minor_version,
+                row.build_number,
+                row.revision_number,
+            ),
+            culture,
+            public_key_token,
+            flags: row.flags,
+        }
+    }
+
     // ========================================================================
     // Type Hierarchy Resolution
     // ========================================================================
@@ -657,6 +763,9 @@ impl Metadata {
     }
 
     /// Get methods belonging to a TypeDef (1-based index).
+    ///
+    /// The method list is followed through the MethodPtr table if there is
+    /// one (see [`Metadata::resolve_ptr`]).
     pub fn get_type_methods(&self, type_def_index: u32) -> Vec<(u32, &MethodDefRow)> {
         let row = match self.get_type_def(type_def_index) {
             Some(r) => r,
@@ -667,20 +776,20 @@ impl Metadata {
         let end = self
             .get_type_def(type_def_index + 1)
             .map(|r| r.method_list)
-            .unwrap_or((self.method_defs.len() + 1) as u32);
+            .unwrap_or(self.logical_row_count(TableId::MethodDef) + 1);
 
-        ((start as usize)..(end as usize))
+        (start..end)
             .filter_map(|i| {
-                if i > 0 && i <= self.method_defs.len() {
-                    Some((i as u32, &self.method_defs[i - 1]))
-                } else {
-                    None
-                }
+                let index = self.resolve_ptr(TableId::MethodDef, i)?;
+                Some((index, &self.method_defs[index as usize - 1]))
             })
             .collect()
     }
 
     /// Get fields belonging to a TypeDef (1-based index).
+    ///
+    /// The field list is followed through the FieldPtr table if there is one
+    /// (see [`Metadata::resolve_ptr`]).
     pub fn get_type_fields(&self, type_def_index: u32) -> Vec<(u32, &FieldRow)> {
         let row = match self.get_type_def(type_def_index) {
             Some(r) => r,
@@ -691,15 +800,68 @@ impl Metadata {
         let end = self
             .get_type_def(type_def_index + 1)
             .map(|r| r.field_list)
-            .unwrap_or((self.fields.len() + 1) as u32);
+            .unwrap_or(self.logical_row_count(TableId::Field) + 1);
 
-        ((start as usize)..(end as usize))
+        (start..end)
             .filter_map(|i| {
-                if i > 0 && i <= self.fields.len() {
-                    Some((i as u32, &self.fields[i - 1]))
-                } else {
-                    None
-                }
+                let index = self.resolve_ptr(TableId::Field, i)?;
+                Some((index, &self.fields[index as usize - 1]))
+            })
+            .collect()
+    }
+
+    /// Get properties belonging to a TypeDef (1-based index).
+    ///
+    /// The type's PropertyMap row is followed, and the property list through
+    /// the PropertyPtr table if there is one.
+    pub fn get_type_properties(&self, type_def_index: u32) -> Vec<(u32, &PropertyRow)> {
+        let Some(i) = self
+            .property_maps
+            .iter()
+            .position(|map| map.parent == type_def_index)
+        else {
+            return Vec::new();
+        };
+
+        let start = self.property_maps[i].property_list;
+        let end = self
+            .property_maps
+            .get(i + 1)
+            .map(|map| map.property_list)
+            .unwrap_or(self.logical_row_count(TableId::Property) + 1);
+
+        (start..end)
+            .filter_map(|i| {
+                let index = self.resolve_ptr(TableId::Property, i)?;
+                Some((index, &self.properties[index as usize - 1]))
+            })
+            .collect()
+    }
+
+    /// Get events belonging to a TypeDef (1-based index).
+    ///
+    /// The type's EventMap row is followed, and the event list through the
+    /// EventPtr table if there is one.
+    pub fn get_type_events(&self, type_def_index: u32) -> Vec<(u32, &EventRow)> {
+        let Some(i) = self
+            .event_maps
+            .iter()
+            .position(|map| map.parent == type_def_index)
+        else {
+            return Vec::new();
+        };
+
+        let start = self.event_maps[i].event_list;
+        let end = self
+            .event_maps
+            .get(i + 1)
+            .map(|map| map.event_list)
+            .unwrap_or(self.logical_row_count(TableId::Event) + 1);
+
+        (start..end)
+            .filter_map(|i| {
+                let index = self.resolve_ptr(TableId::Event, i)?;
+                Some((index, &self.events[index as usize - 1]))
             })
             .collect()
     }
@@ -762,332 +924,8 @@ impl Metadata {
         None
     }
 
-    // ========================================================================
-    // Validation
-    // ========================================================================
-
-    /// Validate the metadata structure.
-    ///
-    /// Performs structural integrity checks including:
-    /// - Required tables are present (Module must have at least 1 row)
-    /// - String indices are within bounds
-    /// - GUID indices are within bounds
-    /// - Blob indices are within bounds
-    /// - Table index references are valid
-    ///
-    /// Returns a list of validation errors. An empty list means the metadata is valid.
-    #[must_use]
-    pub fn validate(&self) -> Vec<String> {
-        let mut errors = Vec::new();
-
-        // Check required tables
-        if self.modules.is_empty() {
-            errors.push("Module table must have at least 1 row".to_string());
-        }
-
-        // Validate Module table
-        for (i, row) in self.modules.iter().enumerate() {
-            self.validate_string_index(&mut errors, "Module", i, "name", row.name);
-            self.validate_guid_index(&mut errors, "Module", i, "mvid", row.mvid);
-        }
-
-        // Validate TypeRef table
-        for (i, row) in self.type_refs.iter().enumerate() {
-            self.validate_string_index(&mut errors, "TypeRef", i, "type_name", row.type_name);
-            self.validate_string_index(
-                &mut errors,
-                "TypeRef",
-                i,
-                "type_namespace",
-                row.type_namespace,
-            );
-        }
-
-        // Validate TypeDef table
-        for (i, row) in self.type_defs.iter().enumerate() {
-            self.validate_string_index(&mut errors, "TypeDef", i, "type_name", row.type_name);
-            self.validate_string_index(
-                &mut errors,
-                "TypeDef",
-                i,
-                "type_namespace",
-                row.type_namespace,
-            );
-            self.validate_table_index(
-                &mut errors,
-                "TypeDef",
-                i,
-                "field_list",
-                row.field_list,
-                self.fields.len(),
-            );
-            self.validate_table_index(
-                &mut errors,
-                "TypeDef",
-                i,
-                "method_list",
-                row.method_list,
-                self.method_defs.len(),
-            );
-        }
-
-        // Validate Field table
-        for (i, row) in self.fields.iter().enumerate() {
-            self.validate_string_index(&mut errors, "Field", i, "name", row.name);
-            self.validate_blob_index(&mut errors, "Field", i, "signature", row.signature);
-        }
-
-        // Validate MethodDef table
-        for (i, row) in self.method_defs.iter().enumerate() {
-            self.validate_string_index(&mut errors, "MethodDef", i, "name", row.name);
-            self.validate_blob_index(&mut errors, "MethodDef", i, "signature", row.signature);
-            self.validate_table_index(
-                &mut errors,
-                "MethodDef",
-                i,
-                "param_list",
-                row.param_list,
-                self.params.len(),
-            );
-        }
-
-        // Validate Param table
-        for (i, row) in self.params.iter().enumerate() {
-            self.validate_string_index(&mut errors, "Param", i, "name", row.name);
-        }
-
-        // Validate MemberRef table
-        for (i, row) in self.member_refs.iter().enumerate() {
-            self.validate_string_index(&mut errors, "MemberRef", i, "name", row.name);
-            self.validate_blob_index(&mut errors, "MemberRef", i, "signature", row.signature);
-        }
-
-        // Validate Constant table
-        for (i, row) in self.constants.iter().enumerate() {
-            self.validate_blob_index(&mut errors, "Constant", i, "value", row.value);
-        }
-
-        // Validate CustomAttribute table
-        for (i, row) in self.custom_attributes.iter().enumerate() {
-            self.validate_blob_index(&mut errors, "CustomAttribute", i, "value", row.value);
-        }
-
-        // Validate Assembly table
-        for (i, row) in self.assemblies.iter().enumerate() {
-            self.validate_string_index(&mut errors, "Assembly", i, "name", row.name);
-            self.validate_string_index(&mut errors, "Assembly", i, "culture", row.culture);
-            self.validate_blob_index(&mut errors, "Assembly", i, "public_key", row.public_key);
-        }
-
-        // Validate AssemblyRef table
-        for (i, row) in self.assembly_refs.iter().enumerate() {
-            self.validate_string_index(&mut errors, "AssemblyRef", i, "name", row.name);
-            self.validate_string_index(&mut errors, "AssemblyRef", i, "culture", row.culture);
-            self.validate_blob_index(
-                &mut errors,
-                "AssemblyRef",
-                i,
-                "public_key_or_token",
-                row.public_key_or_token,
-            );
-            self.validate_blob_index(&mut errors, "AssemblyRef", i, "hash_value", row.hash_value);
-        }
-
-        // Validate coded index references
-        self.validate_coded_indices(&mut errors);
-
-        // Validate sorted table requirements
-        self.validate_sorted_tables(&mut errors);
-
-        errors
-    }
-
-    /// Validate coded index references point to valid rows.
-    fn validate_coded_indices(&self, errors: &mut Vec<String>) {
-        use crate::tables::{CodedIndex, CodedIndexKind};
-
-        // Helper to validate a coded index
-        let validate_coded = |errors: &mut Vec<String>,
-                              table: &str,
-                              row: usize,
-                              field: &str,
-                              idx: &CodedIndex,
-                              kind: CodedIndexKind| {
-            if idx.is_null() {
-                return;
-            }
-            if let Some(target_table) = idx.table {
-                let max_row = self.table_row_count(target_table);
-                if idx.row == 0 || idx.row > max_row {
-                    errors.push(format!(
-                        "{table}[{row}].{field}: invalid {kind:?} index pointing to {target_table:?} row {} (max: {max_row})",
-                        idx.row
-                    ));
-                }
-            } else {
-                errors.push(format!(
-                    "{table}[{row}].{field}: coded index has invalid table tag for {kind:?}"
-                ));
-            }
-        };
-
-        // TypeDef.extends
-        for (i, row) in self.type_defs.iter().enumerate() {
-            validate_coded(
-                errors,
-                "TypeDef",
-                i,
-                "extends",
-                &row.extends,
-                CodedIndexKind::TypeDefOrRef,
-            );
-        }
-
-        // InterfaceImpl.interface
-        for (i, row) in self.interface_impls.iter().enumerate() {
-            validate_coded(
-                errors,
-                "InterfaceImpl",
-                i,
-                "interface",
-                &row.interface,
-                CodedIndexKind::TypeDefOrRef,
-            );
-        }
-
-        // MemberRef.class
-        for (i, row) in self.member_refs.iter().enumerate() {
-            validate_coded(
-                errors,
-                "MemberRef",
-                i,
-                "class",
-                &row.class,
-                CodedIndexKind::MemberRefParent,
-            );
-        }
-
-        // Constant.parent
-        for (i, row) in self.constants.iter().enumerate() {
-            validate_coded(
-                errors,
-                "Constant",
-                i,
-                "parent",
-                &row.parent,
-                CodedIndexKind::HasConstant,
-            );
-        }
-
-        // CustomAttribute.parent and type
-        for (i, row) in self.custom_attributes.iter().enumerate() {
-            validate_coded(
-                errors,
-                "CustomAttribute",
-                i,
-                "parent",
-                &row.parent,
-                CodedIndexKind::HasCustomAttribute,
-            );
-            validate_coded(
-                errors,
-                "CustomAttribute",
-                i,
-                "type",
-                &row.attr_type,
-                CodedIndexKind::CustomAttributeType,
-            );
-        }
-
-        // GenericParamConstraint.constraint
-        for (i, row) in self.generic_param_constraints.iter().enumerate() {
-            validate_coded(
-                errors,
-                "GenericParamConstraint",
-                i,
-                "constraint",
-                &row.constraint,
-                CodedIndexKind::TypeDefOrRef,
-            );
-        }
-    }
-
-    /// Validate that tables that should be sorted are actually sorted.
-    fn validate_sorted_tables(&self, errors: &mut Vec<String>) {
-        // InterfaceImpl must be sorted by Class
-        for window in self.interface_impls.windows(2) {
-            if window[1].class < window[0].class {
-                errors.push("InterfaceImpl table is not sorted by Class column".to_string());
-                break;
-            }
-        }
-
-        // Constant must be sorted by Parent
-        for window in self.constants.windows(2) {
-            let key0 = self.coded_index_sort_key(&window[0].parent);
-            let key1 = self.coded_index_sort_key(&window[1].parent);
-            if key1 < key0 {
-                errors.push("Constant table is not sorted by Parent column".to_string());
-                break;
-            }
-        }
-
-        // FieldMarshal must be sorted by Parent
-        for window in self.field_marshals.windows(2) {
-            let key0 = self.coded_index_sort_key(&window[0].parent);
-            let key1 = self.coded_index_sort_key(&window[1].parent);
-            if key1 < key0 {
-                errors.push("FieldMarshal table is not sorted by Parent column".to_string());
-                break;
-            }
-        }
-
-        // MethodSemantics must be sorted by Association
-        for window in self.method_semantics.windows(2) {
-            let key0 = self.coded_index_sort_key(&window[0].association);
-            let key1 = self.coded_index_sort_key(&window[1].association);
-            if key1 < key0 {
-                errors
-                    .push("MethodSemantics table is not sorted by Association column".to_string());
-                break;
-            }
-        }
-
-        // ClassLayout must be sorted by Parent
-        for window in self.class_layouts.windows(2) {
-            if window[1].parent < window[0].parent {
-                errors.push("ClassLayout table is not sorted by Parent column".to_string());
-                break;
-            }
-        }
-
-        // NestedClass must be sorted by NestedClass column
-        for window in self.nested_classes.windows(2) {
-            if window[1].nested_class < window[0].nested_class {
-                errors.push("NestedClass table is not sorted by NestedClass column".to_string());
-                break;
-            }
-        }
-
-        // GenericParam must be sorted by Owner
-        for window in self.generic_params.windows(2) {
-            let key0 = self.coded_index_sort_key(&window[0].owner);
-            let key1 = self.coded_index_sort_key(&window[1].owner);
-            if key1 < key0 {
-                errors.push("GenericParam table is not sorted by Owner column".to_string());
-                break;
-            }
-        }
-    }
-
-    /// Get a sort key for coded index comparison.
-    fn coded_index_sort_key(&self, idx: &crate::tables::CodedIndex) -> (u8, u32) {
-        let table_id = idx.table.map_or(0xff, |t| t as u8);
-        (table_id, idx.row)
-    }
-
     /// Get the row count for a table.
-    fn table_row_count(&self, table: TableId) -> u32 {
+    pub(crate) fn table_row_count(&self, table: TableId) -> u32 {
         match table {
             TableId::Module => self.modules.len() as u32,
             TableId::TypeRef => self.type_refs.len() as u32,
@@ -1136,81 +974,6 @@ impl Metadata {
             TableId::GenericParamConstraint => self.generic_param_constraints.len() as u32,
         }
     }
-
-    /// Validate that the metadata is structurally correct.
-    ///
-    /// Returns `Ok(())` if valid, or `Err` with the first validation error.
-    pub fn validate_strict(&self) -> Result<()> {
-        let errors = self.validate();
-        if let Some(first_error) = errors.into_iter().next() {
-            Err(Error::ValidationError(first_error))
-        } else {
-            Ok(())
-        }
-    }
-
-    fn validate_string_index(
-        &self,
-        errors: &mut Vec<String>,
-        table: &str,
-        row: usize,
-        field: &str,
-        index: u32,
-    ) {
-        if index != 0 && self.strings.get(index).is_err() {
-            errors.push(format!(
-                "{table}[{row}].{field}: invalid string index {index}"
-            ));
-        }
-    }
-
-    fn validate_guid_index(
-        &self,
-        errors: &mut Vec<String>,
-        table: &str,
-        row: usize,
-        field: &str,
-        index: u32,
-    ) {
-        if index != 0 && self.guids.get(index).is_err() {
-            errors.push(format!(
-                "{table}[{row}].{field}: invalid GUID index {index}"
-            ));
-        }
-    }
-
-    fn validate_blob_index(
-        &self,
-        errors: &mut Vec<String>,
-        table: &str,
-        row: usize,
-        field: &str,
-        index: u32,
-    ) {
-        if index != 0 && self.blobs.get(index).is_err() {
-            errors.push(format!(
-                "{table}[{row}].{field}: invalid blob index {index}"
-            ));
-        }
-    }
-
-    fn validate_table_index(
-        &self,
-        errors: &mut Vec<String>,
-        table: &str,
-        row: usize,
-        field: &str,
-        index: u32,
-        max_rows: usize,
-    ) {
-        // Table indices are 1-based, 0 means null
-        // A "list" index can be max_rows + 1 (meaning empty list at end)
-        if index > (max_rows as u32) + 1 {
-            errors.push(format!(
-                "{table}[{row}].{field}: invalid table index {index} (max {max_rows})"
-            ));
-        }
-    }
 }
 
 /// High-level assembly information.
@@ -1271,9 +1034,7 @@ impl TypeInfo {
     /// Get the full name (namespace.name or just name).
     #[must_use]
     pub fn full_name(&self) -> String {
-        if let Some(ns) = &self.namespace
-            && !ns.is_empty()
-        {
+        if let Some(ns) = self.namespace.as_ref().filter(|ns| !ns.is_empty()) {
             return format!("{}.{}", ns, self.name);
         }
         self.name.clone()
@@ -1361,9 +1122,7 @@ impl ResolvedType {
             | Self::TypeRef {
                 name, namespace, ..
             } => {
-                if let Some(ns) = namespace
-                    && !ns.is_empty()
-                {
+                if let Some(ns) = namespace.as_ref().filter(|ns| !ns.is_empty()) {
                     return format!("{ns}.{name}");
                 }
                 name.clone()
@@ -1405,13 +1164,20 @@ impl Metadata {
     }
 
     /// Write the metadata to a writer.
+    ///
+    /// Tables that must be sorted are written in key order: if any is out of
+    /// order, a canonicalized copy is written instead (see
+    /// [`Metadata::canonicalize`]).
     pub fn write_to(&self, writer: &mut Writer) {
+        if !self.is_canonical() {
+            let mut canonical = self.clone();
+            canonical.canonicalize();
+            return canonical.write_to(writer);
+        }
+
         // For now, we'll write the original structure back
         // A full implementation would rebuild all streams and tables
 
-        // Calculate heap sizes
-        let heap_sizes = self.calculate_heap_sizes();
-
         // Build a modified root with correct offsets
         let mut root = self.root.clone();
 
@@ -1453,7 +1219,7 @@ impl Metadata {
         for stream in &root.streams {
             match stream.name.as_str() {
                 StreamHeader::TABLES | StreamHeader::TABLES_UNCOMPRESSED => {
-                    self.write_tables(writer, heap_sizes);
+                    self.write_tables(writer);
                 }
                 StreamHeader::STRINGS => {
                     self.strings.write_to(writer);
@@ -1490,87 +1256,39 @@ impl Metadata {
         heap_sizes
     }
 
+    /// Build the tables header for writing, with row counts and heap size
+    /// flags matching the current contents.
+    fn build_tables_header(&self) -> TablesHeader {
+        let mut header = self.tables_header.clone();
+        header.heap_sizes = self.calculate_heap_sizes();
+        header.sorted = self.sorted_mask();
+        header.row_counts = [0; 64];
+        header.valid = 0;
+        for id in 0..TableId::COUNT as u8 {
+            if let Ok(table) = TableId::from_u8(id) {
+                header.set_row_count(table, self.table_row_count(table));
+            }
+        }
+        header
+    }
+
     fn calculate_tables_size(&self) -> usize {
-        let ctx = self.tables_header.context();
+        let header = self.build_tables_header();
+        let ctx = header.context();
 
         // Header size
-        let mut size = self.tables_header.size();
+        let mut size = header.size();
 
         // Add size of each table
-        for (table, count) in self.tables_header.tables() {
+        for (table, count) in header.tables() {
             size += count as usize * ctx.row_size(table);
         }
 
         size
     }
 
-    fn write_tables(&self, writer: &mut Writer, heap_sizes: u8) {
-        // Write tables header
-        let mut header = self.tables_header.clone();
-        header.heap_sizes = heap_sizes;
-
-        // Update row counts for all tables
-        header.set_row_count(TableId::Module, self.modules.len() as u32);
-        header.set_row_count(TableId::TypeRef, self.type_refs.len() as u32);
-        header.set_row_count(TableId::TypeDef, self.type_defs.len() as u32);
-        header.set_row_count(TableId::FieldPtr, self.field_ptrs.len() as u32);
-        header.set_row_count(TableId::Field, self.fields.len() as u32);
-        header.set_row_count(TableId::MethodPtr, self.method_ptrs.len() as u32);
-        header.set_row_count(TableId::MethodDef, self.method_defs.len() as u32);
-        header.set_row_count(TableId::ParamPtr, self.param_ptrs.len() as u32);
-        header.set_row_count(TableId::Param, self.params.len() as u32);
-        header.set_row_count(TableId::InterfaceImpl, self.interface_impls.len() as u32);
-        header.set_row_count(TableId::MemberRef, self.member_refs.len() as u32);
-        header.set_row_count(TableId::Constant, self.constants.len() as u32);
-        header.set_row_count(
-            TableId::CustomAttribute,
-            self.custom_attributes.len() as u32,
-        );
-        header.set_row_count(TableId::FieldMarshal, self.field_marshals.len() as u32);
-        header.set_row_count(TableId::DeclSecurity, self.decl_securities.len() as u32);
-        header.set_row_count(TableId::ClassLayout, self.class_layouts.len() as u32);
-        header.set_row_count(TableId::FieldLayout, self.field_layouts.len() as u32);
-        header.set_row_count(TableId::StandAloneSig, self.stand_alone_sigs.len() as u32);
-        header.set_row_count(TableId::EventMap, self.event_maps.len() as u32);
-        header.set_row_count(TableId::EventPtr, self.event_ptrs.len() as u32);
-        header.set_row_count(TableId::Event, self.events.len() as u32);
-        header.set_row_count(TableId::PropertyMap, self.property_maps.len() as u32);
-        header.set_row_count(TableId::PropertyPtr, self.property_ptrs.len() as u32);
-        header.set_row_count(TableId::Property, self.properties.len() as u32);
-        header.set_row_count(TableId::MethodSemantics, self.method_semantics.len() as u32);
-        header.set_row_count(TableId::MethodImpl, self.method_impls.len() as u32);
-        header.set_row_count(TableId::ModuleRef, self.module_refs.len() as u32);
-        header.set_row_count(TableId::TypeSpec, self.type_specs.len() as u32);
-        header.set_row_count(TableId::ImplMap, self.impl_maps.len() as u32);
-        header.set_row_count(TableId::FieldRva, self.field_rvas.len() as u32);
-        header.set_row_count(TableId::EncLog, self.enc_logs.len() as u32);
-        header.set_row_count(TableId::EncMap, self.enc_maps.len() as u32);
-        header.set_row_count(TableId::Assembly, self.assemblies.len() as u32);
-        header.set_row_count(
-            TableId::AssemblyProcessor,
-            self.assembly_processors.len() as u32,
-        );
-        header.set_row_count(TableId::AssemblyOs, self.assembly_oses.len() as u32);
-        header.set_row_count(TableId::AssemblyRef, self.assembly_refs.len() as u32);
-        header.set_row_count(
-            TableId::AssemblyRefProcessor,
-            self.assembly_ref_processors.len() as u32,
-        );
-        header.set_row_count(TableId::AssemblyRefOs, self.assembly_ref_oses.len() as u32);
-        header.set_row_count(TableId::File, self.files.len() as u32);
-        header.set_row_count(TableId::ExportedType, self.exported_types.len() as u32);
-        header.set_row_count(
-            TableId::ManifestResource,
-            self.manifest_resources.len() as u32,
-        );
-        header.set_row_count(TableId::NestedClass, self.nested_classes.len() as u32);
-        header.set_row_count(TableId::GenericParam, self.generic_params.len() as u32);
-        header.set_row_count(TableId::MethodSpec, self.method_specs.len() as u32);
-        header.set_row_count(
-            TableId::GenericParamConstraint,
-            self.generic_param_constraints.len() as u32,
-        );
-
+    fn write_tables(&self, writer: &mut Writer) {
+        let header = self.build_tables_header();
         header.write_to(writer);
 
         let ctx = header.context();
@@ -1758,3 +1476,49 @@ impl Metadata {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_metadata_roundtrip() {
+        let md = Metadata::default();
+        assert_eq!(md.root.version, "v4.0.30319");
+
+        let parsed = Metadata::parse(&md.write()).unwrap();
+        let streams: Vec<&str> = parsed
+            .root
+            .streams
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(streams, ["#~", "#Strings", "#US", "#GUID", "#Blob"]);
+        assert_eq!(parsed.root.version, "v4.0.30319");
+        assert_eq!(parsed.tables_header.valid, 0);
+        assert_eq!(parsed.user_strings.size(), 1);
+    }
+
+    #[test]
+    fn test_write_sizes_tables_from_rows() {
+        let mut md = Metadata::new();
+        let name = md.strings.add(&"m".repeat(0x10000));
+        md.modules.push(ModuleRow {
+            generation: 0,
+            name,
+            mvid: 0,
+            enc_id: 0,
+            enc_base_id: 0,
+        });
+
+        // Row counts and heap index widths follow the rows, not the header
+        let parsed = Metadata::parse(&md.write()).unwrap();
+        assert_eq!(parsed.tables_header.row_count(TableId::Module), 1);
+        assert_eq!(parsed.tables_header.heap_sizes & 0x01, 0x01);
+        assert_eq!(parsed.modules.len(), 1);
+        assert_eq!(
+            parsed.strings.get(parsed.modules[0].name).unwrap().len(),
+            0x10000
+        );
+    }
+}
@@ -150,14 +150,20 @@ impl<'a> Reader<'a> {
 
     /// Read a compressed signed integer (ECMA-335 II.23.2).
     pub fn read_compressed_int(&mut self) -> Result<i32> {
+        let start = self.pos;
         let unsigned = self.read_compressed_uint()?;
         // Rotate right by 1 and sign-extend if the original LSB was 1
-        let rotated = unsigned >> 1;
+        let rotated = (unsigned >> 1) as i32;
         if unsigned & 1 == 0 {
-            Ok(rotated as i32)
+            Ok(rotated)
         } else {
-            // Negative: complement the rotated value
-            Ok(-(rotated as i32) - 1)
+            // Negative: the sign bit is the top bit of the 6, 13 or 28-bit payload
+            let bits = match self.pos - start {
+                1 => 6,
+                2 => 13,
+                _ => 28,
+            };
+            Ok(rotated - (1 << bits))
         }
     }
 
@@ -250,6 +256,21 @@ mod tests {
         assert_eq!(reader.read_compressed_uint().unwrap(), 16384);
     }
 
+    #[test]
+    fn test_read_compressed_int() {
+        // ECMA-335 II.23.2 examples
+        let data = [
+            0x06, 0x7B, 0x80, 0x80, 0x01, 0xC0, 0x00, 0x40, 0x00, 0x80, 0x01,
+        ];
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.read_compressed_int().unwrap(), 3);
+        assert_eq!(reader.read_compressed_int().unwrap(), -3);
+        assert_eq!(reader.read_compressed_int().unwrap(), 64);
+        assert_eq!(reader.read_compressed_int().unwrap(), -64);
+        assert_eq!(reader.read_compressed_int().unwrap(), 8192);
+        assert_eq!(reader.read_compressed_int().unwrap(), -8192);
+    }
+
     #[test]
     fn test_read_index() {
         let data = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
@@ -0,0 +1,980 @@
+//! Reference assembly generation.
+//!
+//! A reference assembly holds only what compilers need to build against an
+//! assembly. [`Metadata::to_reference_assembly`] removes method bodies and
+//! private and internal members, then prunes the references and heap entries
+//! that only they used. It follows the rules of the C# compiler's `/refout`:
+//!
+//! - All types are kept, including private and internal ones.
+//! - Methods are kept if they are visible outside the assembly, virtual (so
+//!   overrides and explicit interface implementations survive), or the
+//!   constructor of a custom attribute.
+//! - Properties and events are kept with their remaining accessors.
+//! - Fields are kept if they are visible outside the assembly. Every instance
+//!   field of a value type is kept, since fields determine struct layout and
+//!   whether a struct is unmanaged.
+//! - Custom attributes are kept on everything that remains.
+//!
+//! As the C# compiler does, every method that needs an IL body shares one
+//! [`MethodBody::throw_null`](crate::il::MethodBody::throw_null) body: the caller chooses its RVA and writes
+//! the body there when laying out the image.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{Error, Result};
+use crate::heaps::UserStringsHeap;
+use crate::identity::CORLIB_NAMES;
+use crate::metadata::Metadata;
+use crate::reader::Reader;
+use crate::resolve::{attribute_class, type_path};
+use crate::signature::{
+    CallingConvention, FieldSig, LocalVarSig, MethodSig, MethodSpecSig, PropertySig, TypeSig,
+};
+use crate::tables::{
+    CodedIndex, CodedIndexKind, CustomAttributeRow, FIELD_STATIC, MEMBER_ACCESS_MASK,
+    MEMBER_ASSEMBLY, MEMBER_FAM_AND_ASSEM, MEMBER_FAM_OR_ASSEM, MEMBER_FAMILY, MEMBER_PUBLIC,
+    METHOD_ABSTRACT, METHOD_IMPL_CODE_TYPE_MASK, METHOD_IMPL_INTERNAL_CALL, METHOD_PINVOKE_IMPL,
+    METHOD_VIRTUAL, MemberRefRow, TableId, TypeRefRow,
+};
+
+const COMPILER_SERVICES: &str = "System.Runtime.CompilerServices";
+
+/// Signature of an instance constructor without parameters.
+const DEFAULT_CTOR_SIGNATURE: [u8; 3] = [CallingConvention::HAS_THIS, 0x00, 0x01];
+
+/// Tables whose rows are removed, as members or as unused references.
+const PRUNED_TABLES: [TableId; 14] = [
+    TableId::Field,
+    TableId::MethodDef,
+    TableId::Param,
+    TableId::Property,
+    TableId::Event,
+    TableId::GenericParam,
+    TableId::GenericParamConstraint,
+    TableId::DeclSecurity,
+    TableId::TypeRef,
+    TableId::TypeSpec,
+    TableId::MemberRef,
+    TableId::MethodSpec,
+    TableId::StandAloneSig,
+    TableId::AssemblyRef,
+];
+
+/// Which internal members a reference assembly keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InternalMembers {
+    /// Drop internal members like private ones.
+    #[default]
+    Drop,
+    /// Keep internal members if the assembly has an
+    /// `InternalsVisibleToAttribute`, since its friend assemblies can use
+    /// them.
+    KeepIfVisibleTo,
+}
+
+/// A decoded signature blob of any kind.
+enum Signature {
+    Method(MethodSig),
+    Field(FieldSig),
+    Property(PropertySig),
+    Locals(LocalVarSig),
+    MethodSpec(MethodSpecSig),
+    Type(TypeSig),
+}
+
+impl Signature {
+    /// Decode a member, local variable or method instantiation signature by
+    /// its calling convention byte.
+    fn parse(blob: &[u8]) -> Result<Self> {
+        Ok(match blob.first().map(|cc| cc & 0x0F) {
+            Some(CallingConvention::FIELD) => Self::Field(FieldSig::parse_blob(blob)?),
+            Some(CallingConvention::LOCAL_SIG) => Self::Locals(LocalVarSig::parse_blob(blob)?),
+            Some(CallingConvention::PROPERTY) => Self::Property(PropertySig::parse_blob(blob)?),
+            Some(CallingConvention::GENERIC_INST) => {
+                Self::MethodSpec(MethodSpecSig::parse_blob(blob)?)
+            }
+            _ => Self::Method(MethodSig::parse_blob(blob)?),
+        })
+    }
+
+    /// Call `f` with every TypeDefOrRef coded index in the signature.
+    fn visit_type_tokens(&mut self, f: &mut dyn FnMut(&mut u32)) {
+        let types: Vec<&mut TypeSig> = match self {
+            Self::Method(sig) => std::iter::once(&mut sig.return_type)
+                .chain(&mut sig.params)
+                .collect(),
+            Self::Field(sig) => vec![&mut sig.field_type],
+            Self::Property(sig) => std::iter::once(&mut sig.property_type)
+                .chain(&mut sig.params)
+                .collect(),
+            Self::Locals(sig) => sig.locals.iter_mut().collect(),
+            Self::MethodSpec(sig) => sig.type_args.iter_mut().collect(),
+            Self::Type(sig) => vec![sig],
+        };
+        for ty in types {
+            ty.visit_type_tokens(f);
+        }
+    }
+
+    fn to_blob(&self) -> Vec<u8> {
+        match self {
+            Self::Method(sig) => sig.to_blob(),
+            Self::Field(sig) => sig.to_blob(),
+            Self::Property(sig) => sig.to_blob(),
+            Self::Locals(sig) => sig.to_blob(),
+            Self::MethodSpec(sig) => sig.to_blob(),
+            Self::Type(sig) => sig.to_blob(),
+        }
+    }
+}
+
+/// The rows kept of each pruned table.
+struct Selection {
+    kept: HashMap<TableId, Vec<bool>>,
+    /// References marked but not yet followed.
+    pending: Vec<(TableId, u32)>,
+}
+
+impl Selection {
+    /// Check if a row is kept. Rows of other tables, null and out-of-range
+    /// indices are never removed.
+    fn is_kept(&self, index: CodedIndex) -> bool {
+        let Some(table) = index.table else {
+            return true;
+        };
+        match self.kept.get(&table) {
+            Some(kept) => (index.row as usize)
+                .checked_sub(1)
+                .and_then(|i| kept.get(i))
+                .is_none_or(|&kept| kept),
+            None => true,
+        }
+    }
+
+    /// Keep a referenced row, queueing it to follow its own references.
+    fn mark(&mut self, index: CodedIndex) {
+        let (Some(table), Some(i)) = (index.table, (index.row as usize).checked_sub(1)) else {
+            return;
+        };
+        if let Some(kept) = self.kept.get_mut(&table).and_then(|kept| kept.get_mut(i)) {
+            if !*kept {
+                *kept = true;
+                self.pending.push((table, index.row));
+            }
+        }
+    }
+
+    /// Keep the types referenced by a signature blob.
+    fn mark_signature(&mut self, md: &Metadata, blob: u32, is_type: bool) -> Result<()> {
+        let data = md.blobs.get(blob)?;
+        let mut signature = if is_type {
+            Signature::Type(TypeSig::parse(&mut Reader::new(data))?)
+        } else {
+            Signature::parse(data)?
+        };
+        signature.visit_type_tokens(&mut |token| {
+            self.mark(CodedIndex::decode(CodedIndexKind::TypeDefOrRef, *token));
+        });
+        Ok(())
+    }
+}
+
+/// New row numbers of the pruned tables.
+struct RowMap {
+    /// Kept flags and the number of kept rows before each row.
+    tables: HashMap<TableId, (Vec<bool>, Vec<u32>)>,
+}
+
+impl RowMap {
+    fn new(selection: Selection) -> Self {
+        let tables = selection
+            .kept
+            .into_iter()
+            .map(|(table, kept)| {
+                let mut before = Vec::with_capacity(kept.len() + 1);
+                let mut count = 0;
+                for &kept in &kept {
+                    before.push(count);
+                    count += u32::from(kept);
+                }
+                before.push(count);
+                (table, (kept, before))
+            })
+            .collect();
+        Self { tables }
+    }
+
+    /// Get the new row of a row, or `None` if it was removed.
+    fn row(&self, table: TableId, row: u32) -> Option<u32> {
+        let Some((kept, before)) = self.tables.get(&table) else {
+            return Some(row);
+        };
+        match (row as usize).checked_sub(1).map(|i| kept.get(i)) {
+            Some(Some(true)) => Some(before[row as usize - 1] + 1),
+            Some(Some(false)) => None,
+            _ => Some(row),
+        }
+    }
+
+    /// Renumber a table index, returning `false` if its row was removed.
+    fn index(&self, table: TableId, row: &mut u32) -> bool {
+        match self.row(table, *row) {
+            Some(new) => {
+                *row = new;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Renumber a coded index, returning `false` if its row was removed.
+    fn coded(&self, index: &mut CodedIndex) -> bool {
+        match index.table {
+            Some(table) if !index.is_null() => self.index(table, &mut index.row),
+            _ => true,
+        }
+    }
+
+    /// Renumber the start of a member list: the first kept row at or after
+    /// the old start.
+    fn list(&self, table: TableId, start: &mut u32) {
+        if let Some((_, before)) = self.tables.get(&table) {
+            let i = (*start as usize).saturating_sub(1).min(before.len() - 1);
+            *start = before[i] + 1;
+        }
+    }
+
+    /// Keep the kept rows of a pruned table.
+    fn retain<T>(&self, table: TableId, rows: &mut Vec<T>) {
+        if let Some((kept, _)) = self.tables.get(&table) {
+            let mut kept = kept.iter();
+            rows.retain(|_| kept.next().copied().unwrap_or(true));
+        }
+    }
+}
+
+impl Metadata {
+    /// Create a reference assembly from this implementation assembly.
+    ///
+    /// Method bodies, private members and, unless `internals` asks to keep
+    /// them for friend assemblies, internal members are removed (see the
+    /// [module documentation](self) for the exact rules). TypeRefs,
+    /// MemberRefs, TypeSpecs, AssemblyRefs and other references left unused
+    /// are pruned, heaps are compacted and #US is emptied. A
+    /// `ReferenceAssemblyAttribute` is added to the assembly.
+    ///
+    /// Every method with an IL body gets the RVA `body_rva`, where the caller
+    /// writes one shared [`MethodBody::throw_null`](crate::il::MethodBody::throw_null) body; abstract, P/Invoke
+    /// and runtime-implemented methods get RVA 0.
+    ///
+    /// Member rows are renumbered, so the entry point of the CLI header, if
+    /// any, no longer applies. Fails if `body_rva` is 0, the metadata has no
+    /// Assembly row or core library reference, or a kept signature cannot be
+    /// decoded.
+    pub fn to_reference_assembly(
+        &self,
+        internals: InternalMembers,
+        body_rva: u32,
+    ) -> Result<Metadata> {
+        if body_rva == 0 {
+            return Err(Error::ReferenceAssembly("method body RVA is 0"));
+        }
+        let mut md = self.clone();
+        if md.has_ptr_tables() {
+            md.compress_tables();
+        }
+        md.add_reference_assembly_attribute()?;
+        let keep_internals = internals == InternalMembers::KeepIfVisibleTo
+            && md.has_assembly_attribute("InternalsVisibleToAttribute");
+
+        let mut selection = md.select_members(keep_internals);
+        md.mark_references(&mut selection)?;
+        md.remove_rows(&RowMap::new(selection))?;
+
+        for method in &mut md.method_defs {
+            let has_body = method.flags & (METHOD_ABSTRACT | METHOD_PINVOKE_IMPL) == 0
+                && method.impl_flags & METHOD_IMPL_CODE_TYPE_MASK == 0
+                && method.impl_flags & METHOD_IMPL_INTERNAL_CALL == 0;
+            method.rva = if has_body { body_rva } else { 0 };
+        }
+        md.enc_logs.clear();
+        md.enc_maps.clear();
+        md.user_strings = UserStringsHeap::new();
+        md.compact_heaps()?;
+        md.canonicalize();
+        Ok(md)
+    }
+
+    /// Check if the assembly has a custom attribute of a class in
+    /// `System.Runtime.CompilerServices`.
+    fn has_assembly_attribute(&self, name: &str) -> bool {
+        self.custom_attributes.iter().any(|row| {
+            row.parent.table == Some(TableId::Assembly)
+                && attribute_class(self, row.attr_type)
+                    .and_then(|class| type_path(self, class))
+                    .is_some_and(|path| path.as_slice() == [(COMPILER_SERVICES, name)])
+        })
+    }
+
+    /// Add `ReferenceAssemblyAttribute` to the assembly, referencing it from
+    /// the core library unless this assembly defines it.
+    fn add_reference_assembly_attribute(&mut self) -> Result<()> {
+        const NAME: &str = "ReferenceAssemblyAttribute";
+        if self.assemblies.is_empty() {
+            return Err(Error::ReferenceAssembly("no Assembly row"));
+        }
+        if self.has_assembly_attribute(NAME) {
+            return Ok(());
+        }
+
+        let attr_type = if let Some(rid) = self.find_type_def(COMPILER_SERVICES, NAME, None) {
+            let ctor = self.get_type_methods(rid).into_iter().find(|(_, row)| {
+                self.strings.get(row.name).ok() == Some(".ctor")
+                    && self.blobs.get(row.signature).ok() == Some(&DEFAULT_CTOR_SIGNATURE[..])
+            });
+            let Some((method, _)) = ctor else {
+                return Err(Error::ReferenceAssembly(
+                    "ReferenceAssemblyAttribute has no parameterless constructor",
+                ));
+            };
+            CodedIndex {
+                table: Some(TableId::MethodDef),
+                row: method,
+            }
+        } else {
+            let scope = CORLIB_NAMES
+                .iter()
+                .find_map(|corlib| {
+                    self.assembly_refs.iter().position(|row| {
+                        self.strings
+                            .get(row.name)
+                            .is_ok_and(|name| name.eq_ignore_ascii_case(corlib))
+                    })
+                })
+                .ok_or(Error::ReferenceAssembly("no core library reference"))?;
+            let resolution_scope = CodedIndex {
+                table: Some(TableId::AssemblyRef),
+                row: scope as u32 + 1,
+            };
+            let type_name = self.strings.add(NAME);
+            let type_namespace = self.strings.add(COMPILER_SERVICES);
+            let type_ref = TypeRefRow {
+                resolution_scope,
+                type_name,
+                type_namespace,
+            };
+            let position = self.type_refs.iter().position(|row| {
+                row.resolution_scope == type_ref.resolution_scope
+                    && row.type_name == type_ref.type_name
+                    && row.type_namespace == type_ref.type_namespace
+            });
+            let class = CodedIndex {
+                table: Some(TableId::TypeRef),
+                row: match position {
+                    Some(i) => i as u32 + 1,
+                    None => {
+                        self.type_refs.push(type_ref);
+                        self.type_refs.len() as u32
+                    }
+                },
+            };
+            let member_ref = MemberRefRow {
+                class,
+                name: self.strings.add(".ctor"),
+                signature: self.blobs.add(&DEFAULT_CTOR_SIGNATURE),
+            };
+            let position = self.member_refs.iter().position(|row| {
+                row.class == member_ref.class
+                    && row.name == member_ref.name
+                    && row.signature == member_ref.signature
+            });
+            CodedIndex {
+                table: Some(TableId::MemberRef),
+                row: match position {
+                    Some(i) => i as u32 + 1,
+                    None => {
+                        self.member_refs.push(member_ref);
+                        self.member_refs.len() as u32
+                    }
+                },
+            }
+        };
+
+        // Prolog and no named arguments
+        let value = self.blobs.add(&[0x01, 0x00, 0x00, 0x00]);
+        self.custom_attributes.push(CustomAttributeRow {
+            parent: CodedIndex {
+                table: Some(TableId::Assembly),
+                row: 1,
+            },
+            attr_type,
+            value,
+        });
+        Ok(())
+    }
+
+    /// Select the members a reference assembly keeps. References start out
+    /// removed and are kept by [`Metadata::mark_references`].
+    fn select_members(&self, keep_internals: bool) -> Selection {
+        let visible = |flags: u16| match flags & MEMBER_ACCESS_MASK {
+            MEMBER_FAMILY | MEMBER_FAM_OR_ASSEM | MEMBER_PUBLIC => true,
+            MEMBER_FAM_AND_ASSEM | MEMBER_ASSEMBLY => keep_internals,
+            _ => false,
+        };
+        let attribute_ctors: HashSet<u32> = self
+            .custom_attributes
+            .iter()
+            .filter(|row| row.attr_type.table == Some(TableId::MethodDef))
+            .map(|row| row.attr_type.row)
+            .collect();
+
+        let mut fields = vec![false; self.fields.len()];
+        let mut methods = vec![false; self.method_defs.len()];
+        for rid in 1..=self.type_defs.len() as u32 {
+            let extends = self.type_defs[rid as usize - 1].extends;
+            let value_type = !extends.is_null()
+                && matches!(
+                    self.type_index_text(extends, false).as_str(),
+                    "System.ValueType" | "System.Enum"
+                );
+            for (field, row) in self.get_type_fields(rid) {
+                fields[field as usize - 1] =
+                    visible(row.flags) || value_type && row.flags & FIELD_STATIC == 0;
+            }
+            for (method, row) in self.get_type_methods(rid) {
+                methods[method as usize - 1] = visible(row.flags)
+                    || row.flags & METHOD_VIRTUAL != 0
+                    || attribute_ctors.contains(&method);
+            }
+        }
+
+        let mut params = vec![false; self.params.len()];
+        for (i, method) in self.method_defs.iter().enumerate() {
+            let end = self
+                .method_defs
+                .get(i + 1)
+                .map_or(self.params.len() as u32 + 1, |next| next.param_list);
+            for param in method.param_list..end {
+                if let Some(kept) = params.get_mut((param as usize).wrapping_sub(1)) {
+                    *kept = methods[i];
+                }
+            }
+        }
+
+        // Properties and events go with their accessors
+        let mut accessors: HashMap<(Option<TableId>, u32), bool> = HashMap::new();
+        for row in &self.method_semantics {
+            let kept = methods
+                .get((row.method as usize).wrapping_sub(1))
+                .is_some_and(|&kept| kept);
+            *accessors
+                .entry((row.association.table, row.association.row))
+                .or_default() |= kept;
+        }
+        let with_accessors = |table, count: usize| -> Vec<bool> {
+            (1..=count as u32)
+                .map(|row| accessors.get(&(Some(table), row)).copied().unwrap_or(true))
+                .collect()
+        };
+        let properties = with_accessors(TableId::Property, self.properties.len());
+        let events = with_accessors(TableId::Event, self.events.len());
+
+        let kept_method = |index: CodedIndex| {
+            index.table != Some(TableId::MethodDef)
+                || methods
+                    .get((index.row as usize).wrapping_sub(1))
+                    .is_none_or(|&kept| kept)
+        };
+        let generic_params: Vec<bool> = self
+            .generic_params
+            .iter()
+            .map(|row| kept_method(row.owner))
+            .collect();
+        let generic_param_constraints = self
+            .generic_param_constraints
+            .iter()
+            .map(|row| {
+                generic_params
+                    .get((row.owner as usize).wrapping_sub(1))
+                    .is_none_or(|&kept| kept)
+            })
+            .collect();
+        let decl_securities = self
+            .decl_securities
+            .iter()
+            .map(|row| kept_method(row.parent))
+            .collect();
+
+        let mut kept = HashMap::from([
+            (TableId::Field, fields),
+            (TableId::MethodDef, methods),
+            (TableId::Param, params),
+            (TableId::Property, properties),
+            (TableId::Event, events),
+            (TableId::GenericParam, generic_params),
+            (TableId::GenericParamConstraint, generic_param_constraints),
+            (TableId::DeclSecurity, decl_securities),
+        ]);
+        for table in PRUNED_TABLES {
+            kept.entry(table)
+                .or_insert_with(|| vec![false; self.table_row_count(table) as usize]);
+        }
+        Selection {
+            kept,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Keep the references used by the kept rows, and by the custom
+    /// attributes of every kept row.
+    fn mark_references(&self, selection: &mut Selection) -> Result<()> {
+        for row in &self.type_defs {
+            selection.mark(row.extends);
+        }
+        for row in &self.interface_impls {
+            selection.mark(row.interface);
+        }
+        for (i, row) in self.fields.iter().enumerate() {
+            if selection.kept[&TableId::Field][i] {
+                selection.mark_signature(self, row.signature, false)?;
+            }
+        }
+        for (i, row) in self.method_defs.iter().enumerate() {
+            if selection.kept[&TableId::MethodDef][i] {
+                selection.mark_signature(self, row.signature, false)?;
+            }
+        }
+        for (i, row) in self.properties.iter().enumerate() {
+            if selection.kept[&TableId::Property][i] {
+                selection.mark_signature(self, row.property_type, false)?;
+            }
+        }
+        for (i, row) in self.events.iter().enumerate() {
+            if selection.kept[&TableId::Event][i] {
+                selection.mark(row.event_type);
+            }
+        }
+        for row in &self.method_impls {
+            if selection.is_kept(row.method_body) {
+                selection.mark(row.method_declaration);
+            }
+        }
+        for (i, row) in self.generic_param_constraints.iter().enumerate() {
+            if selection.kept[&TableId::GenericParamConstraint][i] {
+                selection.mark(row.constraint);
+            }
+        }
+        for row in &self.exported_types {
+            selection.mark(row.implementation);
+        }
+        for row in &self.manifest_resources {
+            selection.mark(row.implementation);
+        }
+
+        // Attributes on references kept by other attributes keep more
+        // references in turn
+        loop {
+            self.follow_references(selection)?;
+            for row in &self.custom_attributes {
+                if selection.is_kept(row.parent) {
+                    selection.mark(row.attr_type);
+                }
+            }
+            if selection.pending.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Keep what the queued references themselves reference.
+    fn follow_references(&self, selection: &mut Selection) -> Result<()> {
+        while let Some((table, row)) = selection.pending.pop() {
+            let i = row as usize - 1;
+            match table {
+                TableId::TypeRef => selection.mark(self.type_refs[i].resolution_scope),
+                TableId::TypeSpec => {
+                    selection.mark_signature(self, self.type_specs[i].signature, true)?;
+                }
+                TableId::MemberRef => {
+                    selection.mark(self.member_refs[i].class);
+                    selection.mark_signature(self, self.member_refs[i].signature, false)?;
+                }
+                TableId::MethodSpec => {
+                    selection.mark(self.method_specs[i].method);
+                    selection.mark_signature(self, self.method_specs[i].instantiation, false)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the rows `map` drops, with every row referencing them, and
+    /// renumber all references to the rows that remain.
+    fn remove_rows(&mut self, map: &RowMap) -> Result<()> {
+        map.retain(TableId::Field, &mut self.fields);
+        map.retain(TableId::MethodDef, &mut self.method_defs);
+        map.retain(TableId::Param, &mut self.params);
+        map.retain(TableId::Property, &mut self.properties);
+        map.retain(TableId::Event, &mut self.events);
+        map.retain(TableId::GenericParam, &mut self.generic_params);
+        map.retain(
+            TableId::GenericParamConstraint,
+            &mut self.generic_param_constraints,
+        );
+        map.retain(TableId::DeclSecurity, &mut self.decl_securities);
+        map.retain(TableId::TypeRef, &mut self.type_refs);
+        map.retain(TableId::TypeSpec, &mut self.type_specs);
+        map.retain(TableId::MemberRef, &mut self.member_refs);
+        map.retain(TableId::MethodSpec, &mut self.method_specs);
+        map.retain(TableId::StandAloneSig, &mut self.stand_alone_sigs);
+        map.retain(TableId::AssemblyRef, &mut self.assembly_refs);
+
+        for row in &mut self.type_refs {
+            map.coded(&mut row.resolution_scope);
+        }
+        for row in &mut self.type_defs {
+            map.coded(&mut row.extends);
+            map.list(TableId::Field, &mut row.field_list);
+            map.list(TableId::MethodDef, &mut row.method_list);
+        }
+        for row in &mut self.method_defs {
+            map.list(TableId::Param, &mut row.param_list);
+        }
+        for row in &mut self.interface_impls {
+            map.coded(&mut row.interface);
+        }
+        for row in &mut self.member_refs {
+            map.coded(&mut row.class);
+        }
+        for row in &mut self.event_maps {
+            map.list(TableId::Event, &mut row.event_list);
+        }
+        for row in &mut self.events {
+            map.coded(&mut row.event_type);
+        }
+        for row in &mut self.property_maps {
+            map.list(TableId::Property, &mut row.property_list);
+        }
+        for row in &mut self.generic_params {
+            map.coded(&mut row.owner);
+        }
+        for row in &mut self.generic_param_constraints {
+            map.index(TableId::GenericParam, &mut row.owner);
+            map.coded(&mut row.constraint);
+        }
+        for row in &mut self.decl_securities {
+            map.coded(&mut row.parent);
+        }
+        for row in &mut self.method_specs {
+            map.coded(&mut row.method);
+        }
+        for row in &mut self.exported_types {
+            map.coded(&mut row.implementation);
+        }
+        for row in &mut self.manifest_resources {
+            map.coded(&mut row.implementation);
+        }
+
+        // Rows describing removed rows go with them
+        self.constants.retain_mut(|row| map.coded(&mut row.parent));
+        self.custom_attributes
+            .retain_mut(|row| map.coded(&mut row.parent) && map.coded(&mut row.attr_type));
+        self.field_marshals
+            .retain_mut(|row| map.coded(&mut row.parent));
+        self.field_layouts
+            .retain_mut(|row| map.index(TableId::Field, &mut row.field));
+        self.field_rvas
+            .retain_mut(|row| map.index(TableId::Field, &mut row.field));
+        self.method_semantics.retain_mut(|row| {
+            map.index(TableId::MethodDef, &mut row.method) && map.coded(&mut row.association)
+        });
+        self.method_impls.retain_mut(|row| {
+            map.coded(&mut row.method_body) && map.coded(&mut row.method_declaration)
+        });
+        self.impl_maps
+            .retain_mut(|row| map.coded(&mut row.member_forwarded));
+        self.assembly_ref_processors
+            .retain_mut(|row| map.index(TableId::AssemblyRef, &mut row.assembly_ref));
+        self.assembly_ref_oses
+            .retain_mut(|row| map.index(TableId::AssemblyRef, &mut row.assembly_ref));
+
+        // Signatures reference TypeRefs and TypeSpecs by row
+        for i in 0..self.fields.len() {
+            self.fields[i].signature =
+                self.renumber_signature(map, self.fields[i].signature, false)?;
+        }
+        for i in 0..self.method_defs.len() {
+            self.method_defs[i].signature =
+                self.renumber_signature(map, self.method_defs[i].signature, false)?;
+        }
+        for i in 0..self.member_refs.len() {
+            self.member_refs[i].signature =
+                self.renumber_signature(map, self.member_refs[i].signature, false)?;
+        }
+        for i in 0..self.properties.len() {
+            self.properties[i].property_type =
+                self.renumber_signature(map, self.properties[i].property_type, false)?;
+        }
+        for i in 0..self.type_specs.len() {
+            self.type_specs[i].signature =
+                self.renumber_signature(map, self.type_specs[i].signature, true)?;
+        }
+        for i in 0..self.method_specs.len() {
+            self.method_specs[i].instantiation =
+                self.renumber_signature(map, self.method_specs[i].instantiation, false)?;
+        }
+        Ok(())
+    }
+
+    /// Renumber the TypeRefs and TypeSpecs of a signature blob, adding a new
+    /// blob if any changed.
+    fn renumber_signature(&mut self, map: &RowMap, blob: u32, is_type: bool) -> Result<u32> {
+        let data = self.blobs.get(blob)?;
+        let mut signature = if is_type {
+            Signature::Type(TypeSig::parse(&mut Reader::new(data))?)
+        } else {
+            Signature::parse(data)?
+        };
+        let mut changed = false;
+        signature.visit_type_tokens(&mut |token| {
+            let mut index = CodedIndex::decode(CodedIndexKind::TypeDefOrRef, *token);
+            if map.coded(&mut index) {
+                let new = index.encode(CodedIndexKind::TypeDefOrRef);
+                changed |= new != *token;
+                *token = new;
+            }
+        });
+        Ok(if changed {
+            self.blobs.add(&signature.to_blob())
+        } else {
+            blob
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ilasm::assemble;
+
+    const BODY_RVA: u32 = 0x2050;
+
+    const SOURCE: &str = r#"
+        .assembly extern mscorlib {}
+        .assembly extern System.Console {}
+        .assembly Lib {
+            .custom instance void [mscorlib]System.Runtime.CompilerServices.InternalsVisibleToAttribute::.ctor(string) = { string('Lib.Tests') }
+        }
+        .namespace Lib {
+            .class public Widget extends [mscorlib]System.Object
+                implements [mscorlib]System.IDisposable {
+                .field public int32 Size
+                .field private string name
+                .method public instance void Run() {
+                    ldstr "running"
+                    call void [System.Console]System.Console::WriteLine(string)
+                    ret
+                }
+                .method private instance void Log() { ret }
+                .method assembly instance void Reset() { ret }
+                .method family virtual newslot instance void OnRun() { ret }
+                .method private final virtual newslot instance void System.IDisposable.Dispose() {
+                    .override [mscorlib]System.IDisposable::Dispose
+                    ret
+                }
+                .method public instance int32 get_Count() { ldc.i4.0 ret }
+                .method private instance void set_Count(int32 v) { ret }
+                .property instance int32 Count() {
+                    .get instance int32 Lib.Widget::get_Count()
+                    .set instance void Lib.Widget::set_Count(int32)
+                }
+            }
+            .class public sealed Point extends [mscorlib]System.ValueType {
+                .field private int32 x
+                .field private static class [mscorlib]System.Text.StringBuilder cache
+            }
+            .class private Helper extends [mscorlib]System.Object {
+                .method assembly static void Help() { ret }
+            }
+        }
+    "#;
+
+    fn member_names(md: &Metadata) -> (Vec<&str>, Vec<&str>) {
+        let fields = md
+            .fields
+            .iter()
+            .map(|row| md.strings.get(row.name).unwrap());
+        let methods = md
+            .method_defs
+            .iter()
+            .map(|row| md.strings.get(row.name).unwrap());
+        (fields.collect(), methods.collect())
+    }
+
+    #[test]
+    fn test_reference_assembly() {
+        let md = assemble(SOURCE).unwrap().metadata;
+        let reference = md
+            .to_reference_assembly(InternalMembers::Drop, BODY_RVA)
+            .unwrap();
+        reference.validate_strict().unwrap();
+
+        // Private instance fields of structs stay for layout
+        let (fields, methods) = member_names(&reference);
+        assert_eq!(fields, ["Size", "x"]);
+        assert_eq!(
+            methods,
+            ["Run", "OnRun", "System.IDisposable.Dispose", "get_Count"]
+        );
+        assert!(reference.method_defs.iter().all(|row| row.rva == BODY_RVA));
+        assert_eq!(reference.type_defs.len(), md.type_defs.len());
+        assert_eq!(reference.api_surface(), md.api_surface());
+
+        // The property keeps only its getter
+        let (_, widget) = reference.find_type("Widget", Some("Lib")).unwrap();
+        assert_eq!(widget.method_list, 1);
+        assert_eq!(reference.method_semantics.len(), 1);
+
+        // The explicit implementation keeps its MethodImpl
+        assert_eq!(reference.method_impls.len(), 1);
+        let method_impl = &reference.method_impls[0];
+        assert_eq!(method_impl.method_body.table, Some(TableId::MethodDef));
+        assert_eq!(method_impl.method_body.row, 3);
+        assert_eq!(
+            method_impl.method_declaration.table,
+            Some(TableId::MemberRef)
+        );
+        let declaration = &reference.member_refs[method_impl.method_declaration.row as usize - 1];
+        assert_eq!(reference.strings.get(declaration.name).unwrap(), "Dispose");
+
+        // Only the removed bodies used System.Console and StringBuilder
+        let assembly_refs: Vec<&str> = reference
+            .assembly_refs
+            .iter()
+            .map(|row| reference.strings.get(row.name).unwrap())
+            .collect();
+        assert_eq!(assembly_refs, ["mscorlib"]);
+        let type_refs: Vec<&str> = reference
+            .type_refs
+            .iter()
+            .map(|row| reference.strings.get(row.type_name).unwrap())
+            .collect();
+        assert_eq!(
+            type_refs,
+            [
+                "Object",
+                "IDisposable",
+                "ValueType",
+                "InternalsVisibleToAttribute",
+                "ReferenceAssemblyAttribute"
+            ]
+        );
+        assert_eq!(reference.user_strings.size(), 1);
+        assert!(reference.strings.iter().all(|(_, s)| s != "Log"));
+        assert!(reference.has_assembly_attribute("ReferenceAssemblyAttribute"));
+
+        // Converting again changes nothing
+        let again = reference
+            .to_reference_assembly(InternalMembers::Drop, BODY_RVA)
+            .unwrap();
+        assert_eq!(again.write(), reference.write());
+        let reparsed = Metadata::parse(&reference.write()).unwrap();
+        assert_eq!(member_names(&reparsed), member_names(&reference));
+    }
+
+    #[test]
+    fn test_reference_assembly_internals() {
+        let md = assemble(SOURCE).unwrap().metadata;
+        let reference = md
+            .to_reference_assembly(InternalMembers::KeepIfVisibleTo, BODY_RVA)
+            .unwrap();
+        let (_, methods) = member_names(&reference);
+        assert_eq!(
+            methods,
+            [
+                "Run",
+                "Reset",
+                "OnRun",
+                "System.IDisposable.Dispose",
+                "get_Count",
+                "Help"
+            ]
+        );
+
+        // Without a friend assembly internals are dropped anyway
+        let source = SOURCE.replace(".custom", "// .custom");
+        let md = assemble(&source).unwrap().metadata;
+        let reference = md
+            .to_reference_assembly(InternalMembers::KeepIfVisibleTo, BODY_RVA)
+            .unwrap();
+        assert!(!member_names(&reference).1.contains(&"Reset"));
+        assert!(!member_names(&reference).1.contains(&"Help"));
+
+        let mut md = assemble(&source).unwrap().metadata;
+        md.assembly_refs.clear();
+        assert!(matches!(
+            md.to_reference_assembly(InternalMembers::Drop, BODY_RVA),
+            Err(Error::ReferenceAssembly(_))
+        ));
+    }
+
+    #[test]
+    fn test_reference_assembly_unused_references() {
+        let source = r#"
+            .assembly extern mscorlib {}
+            .assembly Lib {}
+            .class public abstract Shape extends [mscorlib]System.Object {
+                .method public abstract virtual instance void Draw() {}
+                .method public static void Clear<(class [mscorlib]System.IFormattable) U>() { ret }
+                .method private static void Fill<(class [mscorlib]System.IComparable) T>() {
+                    call !!0[] [mscorlib]System.Array::Empty<class [mscorlib]System.Collections.Generic.List`1<!!T>>()
+                    pop
+                    ldtoken class [mscorlib]System.Collections.Generic.List`1<!!T>
+                    pop
+                    ret
+                }
+            }
+        "#;
+        let md = assemble(source).unwrap().metadata;
+        assert_eq!(md.type_specs.len(), 1);
+        assert_eq!(md.method_specs.len(), 1);
+        assert_eq!(md.generic_param_constraints.len(), 2);
+
+        let reference = md
+            .to_reference_assembly(InternalMembers::Drop, BODY_RVA)
+            .unwrap();
+        reference.validate_strict().unwrap();
+        assert_eq!(member_names(&reference).1, ["Draw", "Clear"]);
+        let rvas: Vec<u32> = reference.method_defs.iter().map(|row| row.rva).collect();
+        assert_eq!(rvas, [0, BODY_RVA]);
+
+        // Only Fill's body used the TypeSpec, MethodSpec and MemberRef
+        assert!(reference.type_specs.is_empty());
+        assert!(reference.method_specs.is_empty());
+        let member_refs: Vec<&str> = reference
+            .member_refs
+            .iter()
+            .map(|row| reference.strings.get(row.name).unwrap())
+            .collect();
+        assert_eq!(member_refs, [".ctor"]);
+
+        // The constraint of the removed generic parameter goes with it
+        assert_eq!(reference.generic_params.len(), 1);
+        assert_eq!(reference.generic_param_constraints.len(), 1);
+        let type_refs: Vec<&str> = reference
+            .type_refs
+            .iter()
+            .map(|row| reference.strings.get(row.type_name).unwrap())
+            .collect();
+        assert_eq!(
+            type_refs,
+            ["Object", "IFormattable", "ReferenceAssemblyAttribute"]
+        );
+
+        assert!(matches!(
+            md.to_reference_assembly(InternalMembers::Drop, 0),
+            Err(Error::ReferenceAssembly(_))
+        ));
+    }
+}
@@ -0,0 +1,646 @@
+//! MemberRef and TypeRef resolution.
+//!
+//! A MemberRef names a method or field by parent type, name and signature
+//! (ECMA-335 II.22.25). Resolving it means finding the MethodDef or Field row
+//! it binds to, either in this module or, through an AssemblyRef, in another
+//! assembly supplied by an [`AssemblyResolver`].
+//!
+//! Signatures are compared structurally in their declared (open) form, with
+//! type references matched by namespace, name and enclosing type rather than
+//! by token, since each module numbers its TypeRefs independently.
+
+use crate::exported_type::ExportedTypeImplementation;
+use crate::generics::GenericContext;
+use crate::metadata::{AssemblyRefInfo, Metadata};
+use crate::signature::{CallingConvention, FieldSig, MethodSig, TypeSig};
+use crate::tables::{CodedIndex, CodedIndexKind, TableId};
+
+/// Supplies the metadata of referenced assemblies.
+pub trait AssemblyResolver {
+    /// Get the manifest module of the assembly named by an AssemblyRef.
+    fn resolve_assembly(&self, reference: &AssemblyRefInfo) -> Option<&Metadata>;
+}
+
+/// The definition a MemberRef binds to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemberDefinition {
+    /// 1-based MethodDef row.
+    Method(u32),
+    /// 1-based Field row.
+    Field(u32),
+}
+
+impl MemberDefinition {
+    /// Get the metadata token of the definition.
+    #[must_use]
+    pub fn token(self) -> u32 {
+        match self {
+            Self::Method(rid) => ((TableId::MethodDef as u32) << 24) | rid,
+            Self::Field(rid) => ((TableId::Field as u32) << 24) | rid,
+        }
+    }
+}
+
+/// A MemberRef resolved to its definition.
+#[derive(Debug, Clone)]
+pub struct ResolvedMember<'a> {
+    /// The module defining the member.
+    pub metadata: &'a Metadata,
+    /// 1-based TypeDef row of the declaring type in `metadata`.
+    pub type_def: u32,
+    /// The member definition in `metadata`.
+    pub member: MemberDefinition,
+    /// Type arguments of the MemberRef's parent, if it is a generic
+    /// instantiation. Coded indices in these signatures refer to the
+    /// referencing module.
+    pub context: GenericContext,
+}
+
+impl Metadata {
+    /// Resolve a MemberRef (1-based index) to a method or field defined in
+    /// this module.
+    ///
+    /// The parent may be a TypeDef, a TypeRef scoped to this module or
+    /// assembly, a TypeSpec instantiating a local generic type, or a MethodDef
+    /// (vararg call sites). Members are looked up on the parent type only,
+    /// not its base types.
+    #[must_use]
+    pub fn resolve_member_ref(&self, index: u32) -> Option<MemberDefinition> {
+        self.resolve_member_ref_in(index, None)
+            .map(|resolved| resolved.member)
+    }
+
+    /// Resolve a MemberRef (1-based index), following AssemblyRefs through
+    /// `resolver`.
+    pub fn resolve_member_ref_with<'a>(
+        &'a self,
+        index: u32,
+        resolver: &'a dyn AssemblyResolver,
+    ) -> Option<ResolvedMember<'a>> {
+        self.resolve_member_ref_in(index, Some(resolver))
+    }
+
+    fn resolve_member_ref_in<'a>(
+        &'a self,
+        index: u32,
+        resolver: Option<&'a dyn AssemblyResolver>,
+    ) -> Option<ResolvedMember<'a>> {
+        let row = self.member_refs.get(index.checked_sub(1)? as usize)?;
+        let name = self.strings.get(row.name).ok()?;
+        let signature = self.blobs.get(row.signature).ok()?;
+
+        let (target, type_def, context) = match row.class.table? {
+            TableId::MethodDef => {
+                let (type_def, _) = self.get_method_owner(row.class.row)?;
+                return Some(ResolvedMember {
+                    metadata: self,
+                    type_def,
+                    member: MemberDefinition::Method(row.class.row),
+                    context: GenericContext::default(),
+                });
+            }
+            TableId::TypeSpec => {
+                let sig = self.type_spec_signature(row.class.row)?.ok()?;
+                let TypeSig::GenericInst { type_ref, .. } = &sig else {
+                    return None;
+                };
+                let generic = CodedIndex::decode(CodedIndexKind::TypeDefOrRef, *type_ref);
+                let (target, type_def) = resolve_type_def(self, generic, resolver)?;
+                (target, type_def, GenericContext::from_type_sig(&sig))
+            }
+            _ => {
+                let (target, type_def) = resolve_type_def(self, row.class, resolver)?;
+                (target, type_def, GenericContext::default())
+            }
+        };
+
+        let comparer = SigComparer {
+            left: self,
+            right: target,
+        };
+        let member = if signature.first() == Some(&CallingConvention::FIELD) {
+            let sig = FieldSig::parse_blob(signature).ok()?;
+            target
+                .get_type_fields(type_def)
+                .into_iter()
+                .find(|(_, field)| {
+                    target.strings.get(field.name).ok() == Some(name)
+                        && target
+                            .blobs
+                            .get(field.signature)
+                            .and_then(FieldSig::parse_blob)
+                            .is_ok_and(|def| comparer.type_eq(&sig.field_type, &def.field_type))
+                })
+                .map(|(rid, _)| MemberDefinition::Field(rid))?
+        } else {
+            let sig = MethodSig::parse_blob(signature).ok()?;
+            target
+                .get_type_methods(type_def)
+                .into_iter()
+                .find(|(_, method)| {
+                    target.strings.get(method.name).ok() == Some(name)
+                        && target
+                            .blobs
+                            .get(method.signature)
+                            .and_then(MethodSig::parse_blob)
+                            .is_ok_and(|def| comparer.method_eq(&sig, &def))
+                })
+                .map(|(rid, _)| MemberDefinition::Method(rid))?
+        };
+
+        Some(ResolvedMember {
+            metadata: target,
+            type_def,
+            member,
+            context,
+        })
+    }
+
+    /// Get the enclosing TypeDef of a nested type (1-based indices).
+    #[must_use]
+    pub fn get_enclosing_type(&self, type_def_index: u32) -> Option<u32> {
+        self.nested_classes
+            .iter()
+            .find(|row| row.nested_class == type_def_index)
+            .map(|row| row.enclosing_class)
+    }
+
+    /// Find a TypeDef by namespace and name within an enclosing type, or
+    /// among top-level types if `enclosing` is `None`.
+    #[must_use]
+    pub fn find_type_def(
+        &self,
+        namespace: &str,
+        name: &str,
+        enclosing: Option<u32>,
+    ) -> Option<u32> {
+        (1..=self.type_defs.len() as u32).find(|&rid| {
+            let row = &self.type_defs[(rid - 1) as usize];
+            self.strings.get(row.type_name).ok() == Some(name)
+                && self.strings.get(row.type_namespace).ok() == Some(namespace)
+                && self.get_enclosing_type(rid) == enclosing
+        })
+    }
+}
+
+/// Maximum number of ExportedType forwarders followed for one lookup.
+const MAX_FORWARDER_DEPTH: usize = 16;
+
+/// Resolve a TypeDefOrRef or ExportedType reference to the module and TypeDef
+/// row defining it. TypeSpecs resolve to their generic type definition.
+pub(crate) fn resolve_type_def<'a>(
+    metadata: &'a Metadata,
+    index: CodedIndex,
+    resolver: Option<&'a dyn AssemblyResolver>,
+) -> Option<(&'a Metadata, u32)> {
+    match index.table? {
+        TableId::TypeDef => metadata
+            .get_type_def(index.row)
+            .map(|_| (metadata, index.row)),
+        TableId::TypeRef => {
+            let row = metadata.get_type_ref(index.row)?;
+            let name = metadata.strings.get(row.type_name).ok()?;
+            let namespace = metadata.strings.get(row.type_namespace).ok()?;
+            let scope = row.resolution_scope;
+
+            // A null scope means "this assembly" (ECMA-335 II.22.38)
+            if scope.is_null() {
+                return find_top_level_type(metadata, namespace, name, resolver, 0);
+            }
+            match scope.table? {
+                TableId::Module => Some((metadata, metadata.find_type_def(namespace, name, None)?)),
+                TableId::TypeRef => {
+                    let (target, enclosing) = resolve_type_def(metadata, scope, resolver)?;
+                    Some((
+                        target,
+                        target.find_type_def(namespace, name, Some(enclosing))?,
+                    ))
+                }
+                TableId::AssemblyRef => {
+                    let target = resolve_assembly_ref(metadata, scope.row, resolver)?;
+                    find_top_level_type(target, namespace, name, resolver, 0)
+                }
+                // ModuleRef scopes name another module of this assembly
+                _ => None,
+            }
+        }
+        TableId::ExportedType => {
+            let exported = metadata.get_exported_type(index.row)?;
+            let namespace = exported.namespace.as_deref().unwrap_or("");
+            match exported.implementation {
+                ExportedTypeImplementation::AssemblyRef { index, .. } => {
+                    let target = resolve_assembly_ref(metadata, index, resolver)?;
+                    find_top_level_type(target, namespace, &exported.name, resolver, 1)
+                }
+                ExportedTypeImplementation::ExportedType(enclosing) => {
+                    // Also rules out a cycle through the Implementation column
+                    if !exported.is_forwarder {
+                        return None;
+                    }
+                    let enclosing = CodedIndex {
+                        table: Some(TableId::ExportedType),
+                        row: enclosing,
+                    };
+                    let (target, enclosing) = resolve_type_def(metadata, enclosing, resolver)?;
+                    Some((
+                        target,
+                        target.find_type_def(namespace, &exported.name, Some(enclosing))?,
+                    ))
+                }
+                ExportedTypeImplementation::File { .. } => None,
+            }
+        }
+        TableId::TypeSpec => match metadata.type_spec_signature(index.row)?.ok()? {
+            TypeSig::GenericInst { type_ref, .. } => resolve_type_def(
+                metadata,
+                CodedIndex::decode(CodedIndexKind::TypeDefOrRef, type_ref),
+                resolver,
+            ),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Get the assembly an AssemblyRef (1-based index) names, which may be the
+/// referencing assembly itself.
+fn resolve_assembly_ref<'a>(
+    metadata: &'a Metadata,
+    index: u32,
+    resolver: Option<&'a dyn AssemblyResolver>,
+) -> Option<&'a Metadata> {
+    let reference = metadata.get_assembly_ref(index)?;
+    let is_self = metadata
+        .assembly()
+        .is_some_and(|assembly| assembly.name.eq_ignore_ascii_case(&reference.name));
+    if is_self {
+        Some(metadata)
+    } else {
+        resolver?.resolve_assembly(&reference)
+    }
+}
+
+/// Find a top-level type in an assembly, following ExportedType forwarders.
+fn find_top_level_type<'a>(
+    metadata: &'a Metadata,
+    namespace: &str,
+    name: &str,
+    resolver: Option<&'a dyn AssemblyResolver>,
+    depth: usize,
+) -> Option<(&'a Metadata, u32)> {
+    if let Some(type_def) = metadata.find_type_def(namespace, name, None) {
+        return Some((metadata, type_def));
+    }
+    if depth >= MAX_FORWARDER_DEPTH {
+        return None;
+    }
+
+    let ExportedTypeImplementation::AssemblyRef { index, .. } =
+        metadata.find_exported_type(namespace, name)?.implementation
+    else {
+        // Types in other modules of a multi-module assembly are not loaded
+        return None;
+    };
+    let target = resolve_assembly_ref(metadata, index, resolver)?;
+    if std::ptr::eq(target, metadata) {
+        return None;
+    }
+    find_top_level_type(target, namespace, name, resolver, depth + 1)
+}
+
+/// Compares signatures from two modules.
+struct SigComparer<'a> {
+    left: &'a Metadata,
+    right: &'a Metadata,
+}
+
+impl SigComparer<'_> {
+    fn method_eq(&self, reference: &MethodSig, definition: &MethodSig) -> bool {
+        // Vararg call sites list the extra arguments after the sentinel
+        let fixed = reference.sentinel.unwrap_or(reference.params.len());
+        reference.calling_convention == definition.calling_convention
+            && reference.generic_param_count == definition.generic_param_count
+            && fixed == definition.params.len()
+            && self.type_eq(&reference.return_type, &definition.return_type)
+            && reference.params[..fixed]
+                .iter()
+                .zip(&definition.params)
+                .all(|(a, b)| self.type_eq(a, b))
+    }
+
+    fn type_eq(&self, a: &TypeSig, b: &TypeSig) -> bool {
+        match (a, b) {
+            (TypeSig::Primitive(a), TypeSig::Primitive(b)) => a == b,
+            (TypeSig::Class(a), TypeSig::Class(b))
+            | (TypeSig::ValueType(a), TypeSig::ValueType(b)) => self.type_ref_eq(*a, *b),
+            (TypeSig::SzArray(a), TypeSig::SzArray(b))
+            | (TypeSig::Ptr(a), TypeSig::Ptr(b))
+            | (TypeSig::ByRef(a), TypeSig::ByRef(b))
+            | (TypeSig::Pinned(a), TypeSig::Pinned(b)) => self.type_eq(a, b),
+            (
+                TypeSig::Array {
+                    element_type: a,
+                    rank: a_rank,
+                    sizes: a_sizes,
+                    lo_bounds: a_bounds,
+                },
+                TypeSig::Array {
+                    element_type: b,
+                    rank: b_rank,
+                    sizes: b_sizes,
+                    lo_bounds: b_bounds,
+                },
+            ) => {
+                a_rank == b_rank && a_sizes == b_sizes && a_bounds == b_bounds && self.type_eq(a, b)
+            }
+            (
+                TypeSig::GenericInst {
+                    is_value_type: a_value,
+                    type_ref: a_ref,
+                    type_args: a_args,
+                },
+                TypeSig::GenericInst {
+                    is_value_type: b_value,
+                    type_ref: b_ref,
+                    type_args: b_args,
+                },
+            ) => {
+                a_value == b_value
+                    && a_args.len() == b_args.len()
+                    && self.type_ref_eq(*a_ref, *b_ref)
+                    && a_args.iter().zip(b_args).all(|(a, b)| self.type_eq(a, b))
+            }
+            (TypeSig::Var(a), TypeSig::Var(b)) | (TypeSig::MVar(a), TypeSig::MVar(b)) => a == b,
+            (TypeSig::FnPtr(a), TypeSig::FnPtr(b)) => self.method_eq(a, b),
+            (
+                TypeSig::Modified {
+                    required: a_required,
+                    modifier: a_modifier,
+                    inner: a,
+                },
+                TypeSig::Modified {
+                    required: b_required,
+                    modifier: b_modifier,
+                    inner: b,
+                },
+            ) => {
+                a_required == b_required
+                    && self.type_ref_eq(*a_modifier, *b_modifier)
+                    && self.type_eq(a, b)
+            }
+            _ => false,
+        }
+    }
+
+    fn type_ref_eq(&self, a: u32, b: u32) -> bool {
+        let a = type_path(
+            self.left,
+            CodedIndex::decode(CodedIndexKind::TypeDefOrRef, a),
+        );
+        let b = type_path(
+            self.right,
+            CodedIndex::decode(CodedIndexKind::TypeDefOrRef, b),
+        );
+        a.is_some() && a == b
+    }
+}
+
+/// Get the (namespace, name) chain of a TypeDef or TypeRef, outermost first.
+pub(crate) fn type_path(metadata: &Metadata, mut index: CodedIndex) -> Option<Vec<(&str, &str)>> {
+    let mut path = Vec::new();
+    loop {
+        match index.table? {
+            TableId::TypeDef => {
+                let row = metadata.get_type_def(index.row)?;
+                path.push((
+                    metadata.strings.get(row.type_namespace).ok()?,
+                    metadata.strings.get(row.type_name).ok()?,
+                ));
+                match metadata.get_enclosing_type(index.row) {
+                    Some(enclosing) => {
+                        index = CodedIndex {
+                            table: Some(TableId::TypeDef),
+                            row: enclosing,
+                        }
+                    }
+                    None => break,
+                }
+            }
+            TableId::TypeRef => {
+                let row = metadata.get_type_ref(index.row)?;
+                path.push((
+                    metadata.strings.get(row.type_namespace).ok()?,
+                    metadata.strings.get(row.type_name).ok()?,
+                ));
+                if row.resolution_scope.is_null()
+                    || row.resolution_scope.table != Some(TableId::TypeRef)
+                {
+                    break;
+                }
+                index = row.resolution_scope;
+            }
+            _ => return None,
+        }
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Get the class of a custom attribute constructor (MethodDef or MemberRef).
+pub(crate) fn attribute_class(metadata: &Metadata, ctor: CodedIndex) -> Option<CodedIndex> {
+    match ctor.table? {
+        TableId::MemberRef => Some(
+            metadata
+                .member_refs
+                .get(ctor.row.checked_sub(1)? as usize)?
+                .class,
+        ),
+        TableId::MethodDef => Some(CodedIndex {
+            table: Some(TableId::TypeDef),
+            row: metadata.get_method_owner(ctor.row)?.0,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ilasm::assemble;
+    use crate::tables::{MemberRefRow, TypeRefRow};
+    use std::collections::HashMap;
+
+    struct MapResolver(HashMap<String, Metadata>);
+
+    impl AssemblyResolver for MapResolver {
+        fn resolve_assembly(&self, reference: &AssemblyRefInfo) -> Option<&Metadata> {
+            self.0.get(&reference.name)
+        }
+    }
+
+    const LIBRARY: &str = r#"
+        .assembly extern mscorlib {}
+        .assembly Lib {}
+        .namespace Lib {
+            .class public Util extends [mscorlib]System.Object {
+                .field public static int32 Count
+                .method public static int32 Twice(int32 x) { ldarg x ret }
+                .method public static int64 Twice(int64 x) { ldarg x ret }
+                .class nested public Inner extends [mscorlib]System.Object {
+                    .method public static void Run() { ret }
+                }
+            }
+            .class public Box`1<T> extends [mscorlib]System.Object {
+                .field public !T Value
+                .method public instance void Set(!T v) { ret }
+                .method public instance void Set(string s) { ret }
+            }
+        }
+    "#;
+
+    fn member_ref_named(md: &Metadata, name: &str) -> u32 {
+        let position = md
+            .member_refs
+            .iter()
+            .position(|row| md.strings.get(row.name).unwrap() == name)
+            .unwrap();
+        position as u32 + 1
+    }
+
+    #[test]
+    fn test_local_type_spec_parent() {
+        let source = format!(
+            "{LIBRARY}
+            .class public Caller extends [mscorlib]System.Object {{
+                .method public static void Go(class Lib.Box`1<int32> b) {{
+                    ldarg b
+                    ldc.i4.1
+                    callvirt instance void class Lib.Box`1<int32>::Set(!0)
+                    ldarg b
+                    ldfld !0 class Lib.Box`1<int32>::Value
+                    pop
+                    ret
+                }}
+            }}"
+        );
+        let md = assemble(&source).unwrap().metadata;
+        let set = md.resolve_member_ref(member_ref_named(&md, "Set")).unwrap();
+        // The open `Set(!T)` overload, not `Set(string)`
+        let (box_type, _) = md.find_type("Box`1", Some("Lib")).unwrap();
+        let (first_method, _) = md.get_type_methods(box_type)[0];
+        assert_eq!(set, MemberDefinition::Method(first_method));
+        assert_eq!(set.token(), 0x0600_0000 | first_method);
+
+        let value = md
+            .resolve_member_ref(member_ref_named(&md, "Value"))
+            .unwrap();
+        assert!(matches!(value, MemberDefinition::Field(_)));
+    }
+
+    #[test]
+    fn test_type_ref_to_self() {
+        let mut md = assemble(LIBRARY).unwrap().metadata;
+        let (util, _) = md.find_type("Util", Some("Lib")).unwrap();
+        let (twice_long, _) = md.get_type_methods(util)[1];
+
+        // TypeRef scoped to this module, and a nested TypeRef under it
+        let type_name = md.strings.add("Util");
+        let type_namespace = md.strings.add("Lib");
+        md.type_refs.push(TypeRefRow {
+            resolution_scope: CodedIndex {
+                table: Some(TableId::Module),
+                row: 1,
+            },
+            type_name,
+            type_namespace,
+        });
+        let util_ref = md.type_refs.len() as u32;
+        let type_name = md.strings.add("Inner");
+        md.type_refs.push(TypeRefRow {
+            resolution_scope: CodedIndex {
+                table: Some(TableId::TypeRef),
+                row: util_ref,
+            },
+            type_name,
+            type_namespace: 0,
+        });
+        let inner_ref = md.type_refs.len() as u32;
+
+        let twice = MethodSig::parse_blob(&[0x00, 0x01, 0x0A, 0x0A]).unwrap();
+        let run = MethodSig::parse_blob(&[0x00, 0x00, 0x01]).unwrap();
+        for (parent, name, sig) in [(util_ref, "Twice", twice), (inner_ref, "Run", run)] {
+            let name = md.strings.add(name);
+            let signature = md.blobs.add(&sig.to_blob());
+            md.member_refs.push(MemberRefRow {
+                class: CodedIndex {
+                    table: Some(TableId::TypeRef),
+                    row: parent,
+                },
+                name,
+                signature,
+            });
+        }
+        let count = md.member_refs.len() as u32;
+
+        assert_eq!(
+            md.resolve_member_ref(count - 1),
+            Some(MemberDefinition::Method(twice_long))
+        );
+        let inner = md.find_type_def("", "Inner", Some(util)).unwrap();
+        let (run, _) = md.get_type_methods(inner)[0];
+        assert_eq!(
+            md.resolve_member_ref(count),
+            Some(MemberDefinition::Method(run))
+        );
+    }
+
+    #[test]
+    fn test_across_assemblies() {
+        let library = assemble(LIBRARY).unwrap().metadata;
+        let app = assemble(
+            r#"
+            .assembly extern mscorlib {}
+            .assembly extern Lib {}
+            .assembly App {}
+            .class public Program extends [mscorlib]System.Object {
+                .method public static void Main() {
+                    ldc.i4.2
+                    call int32 [Lib]Lib.Util::Twice(int32)
+                    stsfld int32 [Lib]Lib.Util::Count
+                    call void [Lib]Lib.Util::Missing()
+                    ret
+                }
+            }
+            "#,
+        )
+        .unwrap()
+        .metadata;
+
+        let twice = member_ref_named(&app, "Twice");
+        // Not resolvable without the referenced assembly
+        assert_eq!(app.resolve_member_ref(twice), None);
+
+        let resolver = MapResolver(HashMap::from([("Lib".to_string(), library)]));
+        let resolved = app.resolve_member_ref_with(twice, &resolver).unwrap();
+        let lib = &resolver.0["Lib"];
+        assert!(std::ptr::eq(resolved.metadata, lib));
+        let (util, _) = lib.find_type("Util", Some("Lib")).unwrap();
+        assert_eq!(resolved.type_def, util);
+        assert_eq!(
+            resolved.member,
+            MemberDefinition::Method(lib.get_type_methods(util)[0].0)
+        );
+
+        let count = app
+            .resolve_member_ref_with(member_ref_named(&app, "Count"), &resolver)
+            .unwrap();
+        assert_eq!(
+            count.member,
+            MemberDefinition::Field(lib.get_type_fields(util)[0].0)
+        );
+        assert!(
+            app.resolve_member_ref_with(member_ref_named(&app, "Missing"), &resolver)
+                .is_none()
+        );
+    }
+}
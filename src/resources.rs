@@ -0,0 +1,1030 @@
+//! Managed resources.
+//!
+//! Manifest resources are either embedded in the PE image's CLI resources
+//! section (the `Resources` directory of the CLI header), stored in a separate
+//! file of a multi-file assembly, or forwarded to another assembly. Embedded
+//! resources are laid out as a 4-byte little-endian length followed by the data,
+//! at the row's offset into the resources section.
+//!
+//! Resources produced by `System.Resources.ResourceWriter` (the `.resources`
+//! format, magic `0xBEEFCACE`) can be decoded with [`ResourceSet::parse`].
+
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+use crate::reader::Reader;
+use crate::tables::{CodedIndex, ManifestResourceRow, TableId};
+use crate::writer::Writer;
+
+/// ManifestResourceAttributes.Public.
+pub const MANIFEST_RESOURCE_PUBLIC: u32 = 0x0001;
+/// ManifestResourceAttributes.Private.
+pub const MANIFEST_RESOURCE_PRIVATE: u32 = 0x0002;
+/// ManifestResourceAttributes.VisibilityMask.
+pub const MANIFEST_RESOURCE_VISIBILITY_MASK: u32 = 0x0007;
+
+/// Magic number of the `.resources` format.
+pub const RESOURCES_MAGIC: u32 = 0xBEEF_CACE;
+
+/// Reader type written by `ResourceWriter`.
+pub const DEFAULT_READER_TYPE: &str = "System.Resources.ResourceReader, mscorlib, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089";
+/// Resource set type written by `ResourceWriter`.
+pub const DEFAULT_RESOURCE_SET_TYPE: &str = "System.Resources.RuntimeResourceSet";
+
+/// Alignment of embedded resources in the CLI resources section.
+const EMBEDDED_RESOURCE_ALIGNMENT: usize = 8;
+
+/// Where a manifest resource's data lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceLocation {
+    /// Embedded in this module's resources section.
+    Embedded {
+        /// Offset of the length-prefixed data in the resources section.
+        offset: u32,
+    },
+    /// Stored in another file of the assembly.
+    File {
+        /// File table row (1-based).
+        index: u32,
+        /// File name.
+        name: String,
+        /// Hash of the file contents.
+        hash: Vec<u8>,
+        /// Offset of the resource within the file.
+        offset: u32,
+    },
+    /// Forwarded to another assembly.
+    AssemblyRef {
+        /// AssemblyRef table row (1-based).
+        index: u32,
+        /// Referenced assembly name.
+        name: String,
+    },
+}
+
+/// A manifest resource with its location resolved.
+#[derive(Debug, Clone)]
+pub struct ManifestResourceInfo {
+    /// Resource name.
+    pub name: String,
+    /// ManifestResourceAttributes flags.
+    pub flags: u32,
+    /// Where the data lives.
+    pub location: ResourceLocation,
+}
+
+impl ManifestResourceInfo {
+    /// Check if the resource is visible outside the assembly.
+    #[must_use]
+    pub fn is_public(&self) -> bool {
+        self.flags & MANIFEST_RESOURCE_VISIBILITY_MASK == MANIFEST_RESOURCE_PUBLIC
+    }
+
+    /// Get the data of an embedded resource from the CLI resources section.
+    ///
+    /// Returns `None` if the resource is not embedded in this module.
+    pub fn embedded_data<'a>(&self, resources: &'a [u8]) -> Option<Result<&'a [u8]>> {
+        match self.location {
+            ResourceLocation::Embedded { offset } => {
+                Some(read_embedded_resource(resources, offset))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Read a length-prefixed resource at `offset` in the CLI resources section.
+pub fn read_embedded_resource(resources: &[u8], offset: u32) -> Result<&[u8]> {
+    let mut reader = Reader::new(resources);
+    reader.seek(offset as usize)?;
+    let len = reader.read_u32()? as usize;
+    reader.read_bytes(len)
+}
+
+impl Metadata {
+    /// Get all manifest resources with their locations resolved.
+    #[must_use]
+    pub fn manifest_resources(&self) -> Vec<ManifestResourceInfo> {
+        self.manifest_resources
+            .iter()
+            .map(|row| {
+                let name = self.strings.get(row.name).unwrap_or("").to_string();
+                let index = row.implementation.row;
+                let location = match row.implementation.table {
+                    Some(TableId::File) => {
+                        let file = (index as usize)
+                            .checked_sub(1)
+                            .and_then(|i| self.files.get(i));
+                        ResourceLocation::File {
+                            index,
+                            name: file
+                                .and_then(|f| self.strings.get(f.name).ok())
+                                .unwrap_or("")
+                                .to_string(),
+                            hash: file
+                                .filter(|f| f.hash_value != 0)
+                                .and_then(|f| self.blobs.get(f.hash_value).ok())
+                                .map(<[u8]>::to_vec)
+                                .unwrap_or_default(),
+                            offset: row.offset,
+                        }
+                    }
+                    Some(TableId::AssemblyRef) => ResourceLocation::AssemblyRef {
+                        index,
+                        name: self
+                            .assembly_refs
+                            .get((index as usize).wrapping_sub(1))
+                            .and_then(|r| self.strings.get(r.name).ok())
+                            .unwrap_or("")
+                            .to_string(),
+                    },
+                    _ => ResourceLocation::Embedded { offset: row.offset },
+                };
+                ManifestResourceInfo {
+                    name,
+                    flags: row.flags,
+                    location,
+                }
+            })
+            .collect()
+    }
+
+    /// Get the data of every resource embedded in this module.
+    ///
+    /// `resources` is the CLI resources section (located by the `Resources`
+    /// directory of the CLI header).
+    pub fn embedded_resources<'a>(
+        &self,
+        resources: &'a [u8],
+    ) -> Result<Vec<(ManifestResourceInfo, &'a [u8])>> {
+        self.manifest_resources()
+            .into_iter()
+            .filter_map(|info| {
+                let data = info.embedded_data(resources)?;
+                Some(data.map(|data| (info, data)))
+            })
+            .collect()
+    }
+
+    fn find_manifest_resource(&self, name: &str) -> Option<usize> {
+        self.manifest_resources
+            .iter()
+            .position(|row| self.strings.get(row.name).is_ok_and(|n| n == name))
+    }
+
+    /// Embed a new resource, appending its data to the CLI resources section.
+    ///
+    /// Returns the ManifestResource row (1-based).
+    pub fn add_embedded_resource(
+        &mut self,
+        resources: &mut Vec<u8>,
+        name: &str,
+        flags: u32,
+        data: &[u8],
+    ) -> Result<u32> {
+        if self.find_manifest_resource(name).is_some() {
+            return Err(Error::ValidationError(format!(
+                "manifest resource '{name}' already exists"
+            )));
+        }
+
+        let mut writer = Writer::new();
+        writer.write_bytes(resources);
+        writer.align(EMBEDDED_RESOURCE_ALIGNMENT);
+        let offset = writer.len() as u32;
+        writer.write_u32(data.len() as u32);
+        writer.write_bytes(data);
+        *resources = writer.into_inner();
+
+        let name = self.strings.add(name);
+        self.manifest_resources.push(ManifestResourceRow {
+            offset,
+            flags,
+            name,
+            implementation: CodedIndex::null(),
+        });
+        Ok(self.manifest_resources.len() as u32)
+    }
+
+    /// Replace the data of an embedded resource.
+    ///
+    /// The resources section is rebuilt, so offsets of other embedded resources
+    /// may change.
+    pub fn replace_embedded_resource(
+        &mut self,
+        resources: &mut Vec<u8>,
+        name: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        let index = self
+            .find_manifest_resource(name)
+            .filter(|&i| self.manifest_resources[i].implementation.is_null())
+            .ok_or_else(|| {
+                Error::ValidationError(format!("no embedded manifest resource '{name}'"))
+            })?;
+        *resources = self.rebuild_resource_section(resources, Some((index, data)))?;
+        Ok(())
+    }
+
+    /// Remove a manifest resource and any custom attributes attached to it.
+    ///
+    /// Embedded data is dropped from the resources section. Returns `false`
+    /// if no resource has the given name.
+    pub fn remove_manifest_resource(
+        &mut self,
+        resources: &mut Vec<u8>,
+        name: &str,
+    ) -> Result<bool> {
+        let Some(index) = self.find_manifest_resource(name) else {
+            return Ok(false);
+        };
+        let embedded = self.manifest_resources[index].implementation.is_null();
+        self.manifest_resources.remove(index);
+
+        // Custom attribute parents past the removed row shift down by one
+        let removed = index as u32 + 1;
+        self.custom_attributes.retain(|ca| {
+            ca.parent.table != Some(TableId::ManifestResource) || ca.parent.row != removed
+        });
+        for ca in &mut self.custom_attributes {
+            if ca.parent.table == Some(TableId::ManifestResource) && ca.parent.row > removed {
+                ca.parent.row -= 1;
+            }
+        }
+
+        if embedded {
+            *resources = self.rebuild_resource_section(resources, None)?;
+        }
+        Ok(true)
+    }
+
+    /// Lay out embedded resources contiguously in row order, optionally
+    /// substituting the data of one row, and update the row offsets.
+    fn rebuild_resource_section(
+        &mut self,
+        resources: &[u8],
+        replacement: Option<(usize, &[u8])>,
+    ) -> Result<Vec<u8>> {
+        let mut writer = Writer::new();
+        let mut offsets = Vec::with_capacity(self.manifest_resources.len());
+        for (i, row) in self.manifest_resources.iter().enumerate() {
+            if !row.implementation.is_null() {
+                offsets.push(row.offset);
+                continue;
+            }
+            let data = match replacement {
+                Some((index, data)) if index == i => data,
+                _ => read_embedded_resource(resources, row.offset)?,
+            };
+            writer.align(EMBEDDED_RESOURCE_ALIGNMENT);
+            offsets.push(writer.len() as u32);
+            writer.write_u32(data.len() as u32);
+            writer.write_bytes(data);
+        }
+        for (row, offset) in self.manifest_resources.iter_mut().zip(offsets) {
+            row.offset = offset;
+        }
+        Ok(writer.into_inner())
+    }
+}
+
+/// Type codes of the `.resources` format (version 2).
+mod type_code {
+    pub const NULL: u32 = 0x00;
+    pub const STRING: u32 = 0x01;
+    pub const BOOLEAN: u32 = 0x02;
+    pub const CHAR: u32 = 0x03;
+    pub const BYTE: u32 = 0x04;
+    pub const SBYTE: u32 = 0x05;
+    pub const INT16: u32 = 0x06;
+    pub const UINT16: u32 = 0x07;
+    pub const INT32: u32 = 0x08;
+    pub const UINT32: u32 = 0x09;
+    pub const INT64: u32 = 0x0A;
+    pub const UINT64: u32 = 0x0B;
+    pub const SINGLE: u32 = 0x0C;
+    pub const DOUBLE: u32 = 0x0D;
+    pub const DECIMAL: u32 = 0x0E;
+    pub const DATE_TIME: u32 = 0x0F;
+    pub const TIME_SPAN: u32 = 0x10;
+    pub const BYTE_ARRAY: u32 = 0x20;
+    pub const STREAM: u32 = 0x21;
+    pub const START_OF_USER_TYPES: u32 = 0x40;
+}
+
+/// A value stored in a `.resources` file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceValue {
+    /// Null reference.
+    Null,
+    /// String.
+    String(String),
+    /// Boolean.
+    Boolean(bool),
+    /// UTF-16 code unit.
+    Char(u16),
+    /// Unsigned 8-bit integer.
+    Byte(u8),
+    /// Signed 8-bit integer.
+    SByte(i8),
+    /// Signed 16-bit integer.
+    Int16(i16),
+    /// Unsigned 16-bit integer.
+    UInt16(u16),
+    /// Signed 32-bit integer.
+    Int32(i32),
+    /// Unsigned 32-bit integer.
+    UInt32(u32),
+    /// Signed 64-bit integer.
+    Int64(i64),
+    /// Unsigned 64-bit integer.
+    UInt64(u64),
+    /// 32-bit float.
+    Single(f32),
+    /// 64-bit float.
+    Double(f64),
+    /// `System.Decimal` as its four 32-bit parts (lo, mid, hi, flags).
+    Decimal([i32; 4]),
+    /// `System.DateTime` in `DateTime.ToBinary` form.
+    DateTime(i64),
+    /// `System.TimeSpan` ticks.
+    TimeSpan(i64),
+    /// Byte array.
+    ByteArray(Vec<u8>),
+    /// `System.IO.Stream` contents.
+    Stream(Vec<u8>),
+    /// Any other type, serialized by the resource writer (opaque).
+    Serialized {
+        /// Assembly-qualified type name.
+        type_name: String,
+        /// Serialized data.
+        data: Vec<u8>,
+    },
+}
+
+/// A named entry in a `.resources` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceEntry {
+    /// Resource name.
+    pub name: String,
+    /// Resource value.
+    pub value: ResourceValue,
+}
+
+/// A decoded `.resources` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceSet {
+    /// Type name of the resource reader.
+    pub reader_type: String,
+    /// Type name of the resource set.
+    pub resource_set_type: String,
+    /// Format version (1 or 2).
+    pub version: u32,
+    /// Type names referenced by entries.
+    pub types: Vec<String>,
+    /// Entries in file order (sorted by name hash).
+    pub entries: Vec<ResourceEntry>,
+}
+
+impl ResourceSet {
+    /// Check whether data starts with the `.resources` magic number.
+    #[must_use]
+    pub fn is_resource_set(data: &[u8]) -> bool {
+        data.len() >= 4
+            && u32::from_le_bytes([data[0], data[1], data[2], data[3]]) == RESOURCES_MAGIC
+    }
+
+    /// Parse a `.resources` file.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+
+        // Resource manager header
+        if reader.read_u32()? != RESOURCES_MAGIC {
+            return Err(Error::InvalidResourceData(0));
+        }
+        let header_version = reader.read_u32()?;
+        let header_skip = reader.read_u32()? as usize;
+        let header_start = reader.position();
+        let (reader_type, resource_set_type) = if header_version == 1 {
+            (read_string(&mut reader)?, read_string(&mut reader)?)
+        } else {
+            (String::new(), String::new())
+        };
+        reader.seek(header_start + header_skip)?;
+
+        // Runtime resource set header
+        let version = reader.read_u32()?;
+        if version != 1 && version != 2 {
+            return Err(Error::InvalidResourceData(reader.position() - 4));
+        }
+        let count = read_count(&mut reader)?;
+        let type_count = read_count(&mut reader)?;
+        let mut types = Vec::with_capacity(type_count);
+        for _ in 0..type_count {
+            types.push(read_string(&mut reader)?);
+        }
+
+        // Padding to an 8-byte boundary ("PAD" bytes)
+        while reader.position() % 8 != 0 {
+            reader.read_u8()?;
+        }
+
+        // Name hashes are only needed for lookup by the runtime
+        reader.read_bytes(count * 4)?;
+        let mut name_positions = Vec::with_capacity(count);
+        for _ in 0..count {
+            name_positions.push(reader.read_u32()? as usize);
+        }
+        let data_section = reader.read_u32()? as usize;
+        let name_section = reader.position();
+
+        let mut named = Vec::with_capacity(count);
+        for position in name_positions {
+            reader.seek(name_section + position)?;
+            let name = read_utf16_string(&mut reader)?;
+            let data_offset = reader.read_u32()? as usize;
+            named.push((name, data_section + data_offset));
+        }
+
+        // Serialized values extend to the next value (or the end of the data)
+        let mut ends: Vec<usize> = named.iter().map(|(_, offset)| *offset).collect();
+        ends.push(data.len());
+        ends.sort_unstable();
+        ends.dedup();
+
+        let mut entries = Vec::with_capacity(count);
+        for (name, offset) in named {
+            let end = ends
+                .iter()
+                .copied()
+                .find(|&end| end > offset)
+                .unwrap_or(data.len());
+            reader.seek(offset)?;
+            let value = if version == 2 {
+                read_value_v2(&mut reader, &types, end)?
+            } else {
+                read_value_v1(&mut reader, &types, end)?
+            };
+            entries.push(ResourceEntry { name, value });
+        }
+
+        Ok(Self {
+            reader_type,
+            resource_set_type,
+            version,
+            types,
+            entries,
+        })
+    }
+
+    /// Find an entry by name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ResourceValue> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| &entry.value)
+    }
+
+    /// Add or replace an entry.
+    pub fn insert(&mut self, name: impl Into<String>, value: ResourceValue) {
+        let name = name.into();
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => entry.value = value,
+            None => self.entries.push(ResourceEntry { name, value }),
+        }
+    }
+
+    /// Remove an entry, returning its value.
+    pub fn remove(&mut self, name: &str) -> Option<ResourceValue> {
+        let index = self.entries.iter().position(|entry| entry.name == name)?;
+        Some(self.entries.remove(index).value)
+    }
+
+    /// Write the resource set in the version 2 `ResourceWriter` format.
+    ///
+    /// The type table is rebuilt from the serialized values, so `types` and
+    /// `version` are not written as-is.
+    #[must_use]
+    pub fn write(&self) -> Vec<u8> {
+        let mut types: Vec<&str> = Vec::new();
+        let mut data = Writer::new();
+        let mut names = Writer::new();
+        let mut index = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            index.push((resource_name_hash(&entry.name), names.len() as u32));
+            let utf16: Vec<u8> = entry
+                .name
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            write_7bit_int(&mut names, utf16.len() as u32);
+            names.write_bytes(&utf16);
+            names.write_u32(data.len() as u32);
+            write_value_v2(&mut data, &entry.value, &mut types);
+        }
+        // The runtime binary-searches the hashes
+        index.sort_by_key(|&(hash, _)| hash as i32);
+
+        let mut writer = Writer::new();
+        writer.write_u32(RESOURCES_MAGIC);
+        writer.write_u32(1);
+        let mut header = Writer::new();
+        write_string(
+            &mut header,
+            or_default(&self.reader_type, DEFAULT_READER_TYPE),
+        );
+        write_string(
+            &mut header,
+            or_default(&self.resource_set_type, DEFAULT_RESOURCE_SET_TYPE),
+        );
+        writer.write_u32(header.len() as u32);
+        writer.write_bytes(header.as_slice());
+
+        writer.write_u32(2);
+        writer.write_u32(self.entries.len() as u32);
+        writer.write_u32(types.len() as u32);
+        for type_name in &types {
+            write_string(&mut writer, type_name);
+        }
+        for &pad in b"PAD".iter().cycle() {
+            if writer.len() % 8 == 0 {
+                break;
+            }
+            writer.write_u8(pad);
+        }
+        for &(hash, _) in &index {
+            writer.write_u32(hash);
+        }
+        for &(_, position) in &index {
+            writer.write_u32(position);
+        }
+        let data_section = writer.len() + 4 + names.len();
+        writer.write_u32(data_section as u32);
+        writer.write_bytes(names.as_slice());
+        writer.write_bytes(data.as_slice());
+        writer.into_inner()
+    }
+}
+
+impl Default for ResourceSet {
+    fn default() -> Self {
+        Self {
+            reader_type: DEFAULT_READER_TYPE.to_string(),
+            resource_set_type: DEFAULT_RESOURCE_SET_TYPE.to_string(),
+            version: 2,
+            types: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+fn or_default<'a>(value: &'a str, default: &'a str) -> &'a str {
+    if value.is_empty() { default } else { value }
+}
+
+/// Hash of a resource name, as computed by `FastResourceComparer`.
+#[must_use]
+pub fn resource_name_hash(name: &str) -> u32 {
+    name.encode_utf16().fold(5381u32, |hash, c| {
+        (hash << 5).wrapping_add(hash) ^ u32::from(c)
+    })
+}
+
+/// Read a `BinaryWriter` 7-bit encoded integer.
+fn read_7bit_int(reader: &mut Reader<'_>) -> Result<u32> {
+    let start = reader.position();
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = reader.read_u8()?;
+        value |= u32::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::InvalidResourceData(start))
+}
+
+/// Write a `BinaryWriter` 7-bit encoded integer.
+fn write_7bit_int(writer: &mut Writer, mut value: u32) {
+    while value >= 0x80 {
+        writer.write_u8(value as u8 | 0x80);
+        value >>= 7;
+    }
+    writer.write_u8(value as u8);
+}
+
+fn read_count(reader: &mut Reader<'_>) -> Result<usize> {
+    let position = reader.position();
+    let count = reader.read_u32()?;
+    if count as usize > reader.remaining() {
+        return Err(Error::InvalidResourceData(position));
+    }
+    Ok(count as usize)
+}
+
+/// Read a `BinaryWriter` length-prefixed UTF-8 string.
+fn read_string(reader: &mut Reader<'_>) -> Result<String> {
+    let start = reader.position();
+    let len = read_7bit_int(reader)? as usize;
+    let bytes = reader.read_bytes(len)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| Error::InvalidResourceData(start))
+}
+
+/// Write a `BinaryWriter` length-prefixed UTF-8 string.
+fn write_string(writer: &mut Writer, s: &str) {
+    write_7bit_int(writer, s.len() as u32);
+    writer.write_bytes(s.as_bytes());
+}
+
+/// Read a length-prefixed UTF-16 string (length in bytes).
+fn read_utf16_string(reader: &mut Reader<'_>) -> Result<String> {
+    let start = reader.position();
+    let len = read_7bit_int(reader)? as usize;
+    let bytes = reader.read_bytes(len)?;
+    if len % 2 != 0 {
+        return Err(Error::InvalidResourceData(start));
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|_| Error::InvalidResourceData(start))
+}
+
+fn read_i64(reader: &mut Reader<'_>) -> Result<i64> {
+    Ok(reader.read_u64()? as i64)
+}
+
+fn read_length_prefixed(reader: &mut Reader<'_>) -> Result<Vec<u8>> {
+    let len = reader.read_u32()? as usize;
+    Ok(reader.read_bytes(len)?.to_vec())
+}
+
+fn read_value_v2(reader: &mut Reader<'_>, types: &[String], end: usize) -> Result<ResourceValue> {
+    let start = reader.position();
+    let code = read_7bit_int(reader)?;
+    Ok(match code {
+        type_code::NULL => ResourceValue::Null,
+        type_code::STRING => ResourceValue::String(read_string(reader)?),
+        type_code::BOOLEAN => ResourceValue::Boolean(reader.read_u8()? != 0),
+        type_code::CHAR => ResourceValue::Char(reader.read_u16()?),
+        type_code::BYTE => ResourceValue::Byte(reader.read_u8()?),
+        type_code::SBYTE => ResourceValue::SByte(reader.read_u8()? as i8),
+        type_code::INT16 => ResourceValue::Int16(reader.read_u16()? as i16),
+        type_code::UINT16 => ResourceValue::UInt16(reader.read_u16()?),
+        type_code::INT32 => ResourceValue::Int32(reader.read_u32()? as i32),
+        type_code::UINT32 => ResourceValue::UInt32(reader.read_u32()?),
+        type_code::INT64 => ResourceValue::Int64(read_i64(reader)?),
+        type_code::UINT64 => ResourceValue::UInt64(reader.read_u64()?),
+        type_code::SINGLE => ResourceValue::Single(f32::from_bits(reader.read_u32()?)),
+        type_code::DOUBLE => ResourceValue::Double(f64::from_bits(reader.read_u64()?)),
+        type_code::DECIMAL => {
+            let mut parts = [0i32; 4];
+            for part in &mut parts {
+                *part = reader.read_u32()? as i32;
+            }
+            ResourceValue::Decimal(parts)
+        }
+        type_code::DATE_TIME => ResourceValue::DateTime(read_i64(reader)?),
+        type_code::TIME_SPAN => ResourceValue::TimeSpan(read_i64(reader)?),
+        type_code::BYTE_ARRAY => ResourceValue::ByteArray(read_length_prefixed(reader)?),
+        type_code::STREAM => ResourceValue::Stream(read_length_prefixed(reader)?),
+        code if code >= type_code::START_OF_USER_TYPES => {
+            let type_name = types
+                .get((code - type_code::START_OF_USER_TYPES) as usize)
+                .ok_or(Error::InvalidResourceData(start))?;
+            read_serialized(reader, type_name, end)?
+        }
+        _ => return Err(Error::InvalidResourceData(start)),
+    })
+}
+
+fn write_value_v2<'a>(writer: &mut Writer, value: &'a ResourceValue, types: &mut Vec<&'a str>) {
+    let code = match value {
+        ResourceValue::Null => type_code::NULL,
+        ResourceValue::String(_) => type_code::STRING,
+        ResourceValue::Boolean(_) => type_code::BOOLEAN,
+        ResourceValue::Char(_) => type_code::CHAR,
+        ResourceValue::Byte(_) => type_code::BYTE,
+        ResourceValue::SByte(_) => type_code::SBYTE,
+        ResourceValue::Int16(_) => type_code::INT16,
+        ResourceValue::UInt16(_) => type_code::UINT16,
+        ResourceValue::Int32(_) => type_code::INT32,
+        ResourceValue::UInt32(_) => type_code::UINT32,
+        ResourceValue::Int64(_) => type_code::INT64,
+        ResourceValue::UInt64(_) => type_code::UINT64,
+        ResourceValue::Single(_) => type_code::SINGLE,
+        ResourceValue::Double(_) => type_code::DOUBLE,
+        ResourceValue::Decimal(_) => type_code::DECIMAL,
+        ResourceValue::DateTime(_) => type_code::DATE_TIME,
+        ResourceValue::TimeSpan(_) => type_code::TIME_SPAN,
+        ResourceValue::ByteArray(_) => type_code::BYTE_ARRAY,
+        ResourceValue::Stream(_) => type_code::STREAM,
+        ResourceValue::Serialized { type_name, .. } => {
+            let index = match types.iter().position(|t| t == type_name) {
+                Some(index) => index,
+                None => {
+                    types.push(type_name);
+                    types.len() - 1
+                }
+            };
+            type_code::START_OF_USER_TYPES + index as u32
+        }
+    };
+    write_7bit_int(writer, code);
+
+    match value {
+        ResourceValue::Null => {}
+        ResourceValue::String(s) => write_string(writer, s),
+        ResourceValue::Boolean(v) => writer.write_u8(u8::from(*v)),
+        ResourceValue::Char(v) | ResourceValue::UInt16(v) => writer.write_u16(*v),
+        ResourceValue::Byte(v) => writer.write_u8(*v),
+        ResourceValue::SByte(v) => writer.write_u8(*v as u8),
+        ResourceValue::Int16(v) => writer.write_u16(*v as u16),
+        ResourceValue::Int32(v) => writer.write_u32(*v as u32),
+        ResourceValue::UInt32(v) => writer.write_u32(*v),
+        ResourceValue::Int64(v) | ResourceValue::DateTime(v) | ResourceValue::TimeSpan(v) => {
+            writer.write_u64(*v as u64);
+        }
+        ResourceValue::UInt64(v) => writer.write_u64(*v),
+        ResourceValue::Single(v) => writer.write_u32(v.to_bits()),
+        ResourceValue::Double(v) => writer.write_u64(v.to_bits()),
+        ResourceValue::Decimal(parts) => {
+            for part in parts {
+                writer.write_u32(*part as u32);
+            }
+        }
+        ResourceValue::ByteArray(bytes) | ResourceValue::Stream(bytes) => {
+            writer.write_u32(bytes.len() as u32);
+            writer.write_bytes(bytes);
+        }
+        ResourceValue::Serialized { data, .. } => writer.write_bytes(data),
+    }
+}
+
+/// Version 1 files identify every value by its index in the type table.
+fn read_value_v1(reader: &mut Reader<'_>, types: &[String], end: usize) -> Result<ResourceValue> {
+    let start = reader.position();
+    let index = read_7bit_int(reader)?;
+    if index == u32::MAX {
+        return Ok(ResourceValue::Null);
+    }
+    let type_name = types
+        .get(index as usize)
+        .ok_or(Error::InvalidResourceData(start))?;
+    let simple_name = type_name.split(',').next().unwrap_or("").trim();
+    Ok(match simple_name {
+        "System.String" => ResourceValue::String(read_string(reader)?),
+        "System.Boolean" => ResourceValue::Boolean(reader.read_u8()? != 0),
+        "System.Char" => ResourceValue::Char(reader.read_u16()?),
+        "System.Byte" => ResourceValue::Byte(reader.read_u8()?),
+        "System.SByte" => ResourceValue::SByte(reader.read_u8()? as i8),
+        "System.Int16" => ResourceValue::Int16(reader.read_u16()? as i16),
+        "System.UInt16" => ResourceValue::UInt16(reader.read_u16()?),
+        "System.Int32" => ResourceValue::Int32(reader.read_u32()? as i32),
+        "System.UInt32" => ResourceValue::UInt32(reader.read_u32()?),
+        "System.Int64" => ResourceValue::Int64(read_i64(reader)?),
+        "System.UInt64" => ResourceValue::UInt64(reader.read_u64()?),
+        "System.Single" => ResourceValue::Single(f32::from_bits(reader.read_u32()?)),
+        "System.Double" => ResourceValue::Double(f64::from_bits(reader.read_u64()?)),
+        "System.DateTime" => ResourceValue::DateTime(read_i64(reader)?),
+        "System.TimeSpan" => ResourceValue::TimeSpan(read_i64(reader)?),
+        _ => read_serialized(reader, type_name, end)?,
+    })
+}
+
+fn read_serialized(reader: &mut Reader<'_>, type_name: &str, end: usize) -> Result<ResourceValue> {
+    let len = end.saturating_sub(reader.position());
+    Ok(ResourceValue::Serialized {
+        type_name: type_name.to_string(),
+        data: reader.read_bytes(len)?.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::FileRow;
+
+    /// Hand-assembled version 2 file with a string, an int and a user type.
+    fn sample_resources() -> Vec<u8> {
+        let names = ["Greeting", "Count", "Point"];
+        let mut data = Writer::new();
+        let mut data_offsets = Vec::new();
+        data_offsets.push(data.len() as u32);
+        data.write_u8(0x01);
+        write_string(&mut data, "Hello");
+        data_offsets.push(data.len() as u32);
+        data.write_u8(0x08);
+        data.write_u32(42);
+        data_offsets.push(data.len() as u32);
+        data.write_u8(0x40);
+        data.write_bytes(&[0xAA, 0xBB, 0xCC]);
+
+        let mut name_section = Writer::new();
+        let mut name_positions = Vec::new();
+        for (name, offset) in names.iter().zip(&data_offsets) {
+            name_positions.push(name_section.len() as u32);
+            let utf16: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            name_section.write_u8(utf16.len() as u8);
+            name_section.write_bytes(&utf16);
+            name_section.write_u32(*offset);
+        }
+
+        let mut writer = Writer::new();
+        writer.write_u32(RESOURCES_MAGIC);
+        writer.write_u32(1);
+        let mut header = Writer::new();
+        write_string(&mut header, "System.Resources.ResourceReader");
+        write_string(&mut header, "System.Resources.RuntimeResourceSet");
+        writer.write_u32(header.len() as u32);
+        writer.write_bytes(header.as_slice());
+        writer.write_u32(2);
+        writer.write_u32(names.len() as u32);
+        writer.write_u32(1);
+        write_string(&mut writer, "Sample.Point, Sample");
+        let mut pad = b"PAD".iter().cycle();
+        while writer.len() % 8 != 0 {
+            writer.write_u8(*pad.next().unwrap());
+        }
+        for name in names {
+            writer.write_u32(resource_name_hash(name));
+        }
+        for position in name_positions {
+            writer.write_u32(position);
+        }
+        let data_section = writer.len() + 4 + name_section.len();
+        writer.write_u32(data_section as u32);
+        writer.write_bytes(name_section.as_slice());
+        writer.write_bytes(data.as_slice());
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_parse_resource_set() {
+        let data = sample_resources();
+        assert!(ResourceSet::is_resource_set(&data));
+
+        let set = ResourceSet::parse(&data).unwrap();
+        assert_eq!(set.version, 2);
+        assert_eq!(set.reader_type, "System.Resources.ResourceReader");
+        assert_eq!(set.resource_set_type, "System.Resources.RuntimeResourceSet");
+        assert_eq!(set.types, ["Sample.Point, Sample"]);
+        assert_eq!(
+            set.get("Greeting"),
+            Some(&ResourceValue::String("Hello".into()))
+        );
+        assert_eq!(set.get("Count"), Some(&ResourceValue::Int32(42)));
+        assert_eq!(
+            set.get("Point"),
+            Some(&ResourceValue::Serialized {
+                type_name: "Sample.Point, Sample".into(),
+                data: vec![0xAA, 0xBB, 0xCC],
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_roundtrip() {
+        let mut set = ResourceSet::default();
+        set.insert("Title", ResourceValue::String("Bonjour".into()));
+        set.insert("Count", ResourceValue::Int32(-7));
+        set.insert("Ratio", ResourceValue::Double(0.5));
+        set.insert("Icon", ResourceValue::ByteArray(vec![1, 2, 3]));
+        set.insert("When", ResourceValue::DateTime(0x0123_4567));
+        set.insert("Missing", ResourceValue::Null);
+        set.insert(
+            "Point",
+            ResourceValue::Serialized {
+                type_name: "Sample.Point, Sample".into(),
+                data: vec![9, 8, 7],
+            },
+        );
+        set.insert("Title", ResourceValue::String("Salut".into()));
+
+        let parsed = ResourceSet::parse(&set.write()).unwrap();
+        assert_eq!(parsed.version, 2);
+        assert_eq!(parsed.reader_type, DEFAULT_READER_TYPE);
+        assert_eq!(parsed.types, ["Sample.Point, Sample"]);
+        assert_eq!(parsed.entries.len(), 7);
+        for entry in &set.entries {
+            assert_eq!(
+                parsed.get(&entry.name),
+                Some(&entry.value),
+                "{}",
+                entry.name
+            );
+        }
+        assert_eq!(
+            parsed.write(),
+            ResourceSet::parse(&parsed.write()).unwrap().write()
+        );
+    }
+
+    #[test]
+    fn test_add_replace_remove_embedded() {
+        let mut md = Metadata::new();
+        let mut section = Vec::new();
+        md.add_embedded_resource(&mut section, "a.bin", MANIFEST_RESOURCE_PUBLIC, b"abc")
+            .unwrap();
+        let b = md
+            .add_embedded_resource(&mut section, "b.bin", MANIFEST_RESOURCE_PRIVATE, b"defgh")
+            .unwrap();
+        assert_eq!(b, 2);
+        assert_eq!(md.manifest_resources[1].offset, 8);
+        assert!(
+            md.add_embedded_resource(&mut section, "a.bin", 1, b"x")
+                .is_err()
+        );
+
+        md.custom_attributes
+            .push(crate::tables::CustomAttributeRow {
+                parent: CodedIndex {
+                    table: Some(TableId::ManifestResource),
+                    row: 2,
+                },
+                attr_type: CodedIndex::null(),
+                value: 0,
+            });
+
+        md.replace_embedded_resource(&mut section, "a.bin", b"0123456789")
+            .unwrap();
+        let data: Vec<_> = md.embedded_resources(&section).unwrap();
+        assert_eq!(data[0].1, b"0123456789");
+        assert_eq!(data[1].1, b"defgh");
+
+        assert!(md.remove_manifest_resource(&mut section, "a.bin").unwrap());
+        assert!(!md.remove_manifest_resource(&mut section, "a.bin").unwrap());
+        assert_eq!(md.manifest_resources.len(), 1);
+        assert_eq!(md.manifest_resources[0].offset, 0);
+        assert_eq!(md.custom_attributes[0].parent.row, 1);
+        assert_eq!(read_embedded_resource(&section, 0).unwrap(), b"defgh");
+
+        // Rows survive a metadata write
+        let parsed = Metadata::parse(&md.write()).unwrap();
+        assert_eq!(parsed.manifest_resources()[0].name, "b.bin");
+    }
+
+    #[test]
+    fn test_bad_magic() {
+        assert!(ResourceSet::parse(&[0, 0, 0, 0]).is_err());
+        assert!(!ResourceSet::is_resource_set(&[0xCE, 0xCA]));
+    }
+
+    #[test]
+    fn test_name_hash() {
+        // Known FastResourceComparer hashes
+        assert_eq!(resource_name_hash(""), 5381);
+        assert_eq!(
+            resource_name_hash("a"),
+            (5381u32 << 5).wrapping_add(5381) ^ 0x61
+        );
+    }
+
+    #[test]
+    fn test_manifest_resources() {
+        let mut md = Metadata::new();
+        let embedded = md.strings.add("App.Strings.resources");
+        let linked = md.strings.add("Linked.txt");
+        let file_name = md.strings.add("linked.txt");
+        md.files.push(FileRow {
+            flags: 1,
+            name: file_name,
+            hash_value: 0,
+        });
+        md.manifest_resources.push(ManifestResourceRow {
+            offset: 4,
+            flags: MANIFEST_RESOURCE_PUBLIC,
+            name: embedded,
+            implementation: CodedIndex::null(),
+        });
+        md.manifest_resources.push(ManifestResourceRow {
+            offset: 0,
+            flags: MANIFEST_RESOURCE_PRIVATE,
+            name: linked,
+            implementation: CodedIndex {
+                table: Some(TableId::File),
+                row: 1,
+            },
+        });
+
+        let resources = md.manifest_resources();
+        assert!(resources[0].is_public());
+        assert!(!resources[1].is_public());
+        assert_eq!(
+            resources[1].location,
+            ResourceLocation::File {
+                index: 1,
+                name: "linked.txt".into(),
+                hash: Vec::new(),
+                offset: 0,
+            }
+        );
+
+        let section = [0xFF, 0xFF, 0xFF, 0xFF, 3, 0, 0, 0, b'a', b'b', b'c'];
+        let embedded = md.embedded_resources(&section).unwrap();
+        assert_eq!(embedded.len(), 1);
+        assert_eq!(embedded[0].1, b"abc");
+        assert!(resources[1].embedded_data(&section).is_none());
+    }
+}
@@ -0,0 +1,292 @@
+//! Declarative security (DeclSecurity permission sets).
+//!
+//! ECMA-335 II.23.1.3 and II.22.11 describe the binary permission set format:
+//! a `.` marker, a count of attributes, and per attribute its serialized type
+//! name followed by named arguments encoded as in custom attributes. Assemblies
+//! compiled before .NET 2.0 store the permission set as UTF-16 XML instead.
+
+use std::fmt;
+
+use crate::custom_attribute::{CaNamedArg, read_ser_string, write_ser_string};
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+use crate::reader::Reader;
+use crate::tables::{CodedIndex, DeclSecurityRow};
+use crate::writer::Writer;
+
+/// Marker byte of the binary permission set format.
+const BINARY_FORMAT_MARKER: u8 = b'.';
+
+/// Security action of a DeclSecurity row (`System.Security.Permissions.SecurityAction`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecurityAction {
+    Request,
+    Demand,
+    Assert,
+    Deny,
+    PermitOnly,
+    LinkDemand,
+    InheritanceDemand,
+    RequestMinimum,
+    RequestOptional,
+    RequestRefuse,
+    PrejitGrant,
+    PrejitDenied,
+    NonCasDemand,
+    NonCasLinkDemand,
+    NonCasInheritance,
+    /// An action value not defined by ECMA-335.
+    Other(u16),
+}
+
+impl SecurityAction {
+    /// Convert from the raw DeclSecurity action value.
+    #[must_use]
+    pub fn from_u16(value: u16) -> Self {
+        match value {
+            1 => Self::Request,
+            2 => Self::Demand,
+            3 => Self::Assert,
+            4 => Self::Deny,
+            5 => Self::PermitOnly,
+            6 => Self::LinkDemand,
+            7 => Self::InheritanceDemand,
+            8 => Self::RequestMinimum,
+            9 => Self::RequestOptional,
+            10 => Self::RequestRefuse,
+            11 => Self::PrejitGrant,
+            12 => Self::PrejitDenied,
+            13 => Self::NonCasDemand,
+            14 => Self::NonCasLinkDemand,
+            15 => Self::NonCasInheritance,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to the raw DeclSecurity action value.
+    #[must_use]
+    pub fn to_u16(self) -> u16 {
+        match self {
+            Self::Request => 1,
+            Self::Demand => 2,
+            Self::Assert => 3,
+            Self::Deny => 4,
+            Self::PermitOnly => 5,
+            Self::LinkDemand => 6,
+            Self::InheritanceDemand => 7,
+            Self::RequestMinimum => 8,
+            Self::RequestOptional => 9,
+            Self::RequestRefuse => 10,
+            Self::PrejitGrant => 11,
+            Self::PrejitDenied => 12,
+            Self::NonCasDemand => 13,
+            Self::NonCasLinkDemand => 14,
+            Self::NonCasInheritance => 15,
+            Self::Other(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for SecurityAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Other(value) => write!(f, "0x{value:04X}"),
+            action => write!(f, "{action:?}"),
+        }
+    }
+}
+
+/// A security attribute in a binary permission set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityAttribute {
+    /// Assembly-qualified attribute type name.
+    pub type_name: String,
+    /// Named field and property arguments.
+    pub named_args: Vec<CaNamedArg>,
+}
+
+/// A decoded permission set blob.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PermissionSet {
+    /// Binary format (.NET 2.0 and later).
+    Binary(Vec<SecurityAttribute>),
+    /// Legacy XML format.
+    Xml(String),
+}
+
+impl PermissionSet {
+    /// Parse a permission set blob.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.first() != Some(&BINARY_FORMAT_MARKER) {
+            return Self::parse_xml(data);
+        }
+
+        let mut reader = Reader::new(data);
+        reader.read_u8()?;
+        let count = reader.read_compressed_uint()?;
+        let mut attributes = Vec::new();
+        for _ in 0..count {
+            let offset = reader.position();
+            let type_name = read_ser_string(&mut reader)?.ok_or(Error::InvalidBlob(offset))?;
+
+            // Named arguments are length-prefixed so unknown ones can be skipped
+            let len = reader.read_compressed_uint()? as usize;
+            let mut args = Reader::new(reader.read_bytes(len)?);
+            let arg_count = args.read_compressed_uint()?;
+            let mut named_args = Vec::new();
+            for _ in 0..arg_count {
+                named_args.push(CaNamedArg::parse(&mut args, &|_| None)?);
+            }
+            attributes.push(SecurityAttribute {
+                type_name,
+                named_args,
+            });
+        }
+        Ok(Self::Binary(attributes))
+    }
+
+    fn parse_xml(data: &[u8]) -> Result<Self> {
+        if data.len() % 2 != 0 {
+            return Err(Error::InvalidBlob(0));
+        }
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        let text = String::from_utf16(&units).map_err(|_| Error::InvalidBlob(0))?;
+        Ok(Self::Xml(
+            text.trim_start_matches('\u{FEFF}')
+                .trim_end_matches('\0')
+                .to_string(),
+        ))
+    }
+
+    /// Serialize this permission set to a blob.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        match self {
+            Self::Binary(attributes) => {
+                writer.write_u8(BINARY_FORMAT_MARKER);
+                writer.write_compressed_uint(attributes.len() as u32);
+                for attribute in attributes {
+                    write_ser_string(&mut writer, Some(&attribute.type_name));
+                    let mut args = Writer::new();
+                    args.write_compressed_uint(attribute.named_args.len() as u32);
+                    for arg in &attribute.named_args {
+                        arg.write(&mut args);
+                    }
+                    writer.write_compressed_uint(args.len() as u32);
+                    writer.write_bytes(args.as_slice());
+                }
+            }
+            Self::Xml(text) => {
+                for unit in text.encode_utf16() {
+                    writer.write_u16(unit);
+                }
+            }
+        }
+        writer.into_inner()
+    }
+}
+
+/// A decoded DeclSecurity row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityDeclaration {
+    /// Security action.
+    pub action: SecurityAction,
+    /// Owner (TypeDef, MethodDef or Assembly).
+    pub parent: CodedIndex,
+    /// Decoded permission set.
+    pub permission_set: PermissionSet,
+}
+
+impl DeclSecurityRow {
+    /// Get the typed security action.
+    #[must_use]
+    pub fn security_action(&self) -> SecurityAction {
+        SecurityAction::from_u16(self.action)
+    }
+}
+
+impl Metadata {
+    /// Decode all DeclSecurity rows.
+    pub fn security_declarations(&self) -> Result<Vec<SecurityDeclaration>> {
+        self.decl_securities
+            .iter()
+            .map(|row| {
+                Ok(SecurityDeclaration {
+                    action: row.security_action(),
+                    parent: row.parent,
+                    permission_set: PermissionSet::parse(self.blobs.get(row.permission_set)?)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_attribute::{CaType, CaValue};
+    use crate::tables::TableId;
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let set = PermissionSet::Binary(vec![SecurityAttribute {
+            type_name: "System.Security.Permissions.SecurityPermissionAttribute, mscorlib".into(),
+            named_args: vec![CaNamedArg {
+                is_field: false,
+                name: "UnmanagedCode".into(),
+                arg_type: CaType::Boolean,
+                value: CaValue::Boolean(true),
+            }],
+        }]);
+        let blob = set.to_blob();
+        assert_eq!(blob[0], b'.');
+        assert_eq!(blob[1], 1);
+        assert_eq!(PermissionSet::parse(&blob).unwrap(), set);
+    }
+
+    #[test]
+    fn test_xml() {
+        let xml = "<PermissionSet class=\"System.Security.PermissionSet\" version=\"1\"/>";
+        let blob: Vec<u8> = xml.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(
+            PermissionSet::parse(&blob).unwrap(),
+            PermissionSet::Xml(xml.into())
+        );
+        assert_eq!(PermissionSet::Xml(xml.into()).to_blob(), blob);
+    }
+
+    #[test]
+    fn test_actions() {
+        assert_eq!(SecurityAction::from_u16(6), SecurityAction::LinkDemand);
+        assert_eq!(SecurityAction::RequestMinimum.to_u16(), 8);
+        assert_eq!(SecurityAction::from_u16(0x40).to_string(), "0x0040");
+        assert_eq!(SecurityAction::Assert.to_string(), "Assert");
+    }
+
+    #[test]
+    fn test_metadata_declarations() {
+        let mut md = Metadata::new();
+        let set = PermissionSet::Binary(vec![SecurityAttribute {
+            type_name: "Sample.CustomPermissionAttribute, Sample".into(),
+            named_args: Vec::new(),
+        }]);
+        let permission_set = md.blobs.add(&set.to_blob());
+        md.decl_securities.push(DeclSecurityRow {
+            action: 8,
+            parent: CodedIndex {
+                table: Some(TableId::Assembly),
+                row: 1,
+            },
+            permission_set,
+        });
+
+        let declarations = md.security_declarations().unwrap();
+        assert_eq!(declarations.len(), 1);
+        assert_eq!(declarations[0].action, SecurityAction::RequestMinimum);
+        assert_eq!(declarations[0].permission_set, set);
+    }
+}
@@ -4,6 +4,7 @@
 
 use crate::error::{Error, Result};
 use crate::reader::Reader;
+use crate::writer::Writer;
 
 /// Element type codes (ECMA-335 II.23.1.16).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -143,6 +144,7 @@ impl CallingConvention {
     pub const FIELD: u8 = 0x06;
     pub const LOCAL_SIG: u8 = 0x07;
     pub const PROPERTY: u8 = 0x08;
+    pub const GENERIC_INST: u8 = 0x0A;
     pub const GENERIC: u8 = 0x10;
     pub const HAS_THIS: u8 = 0x20;
     pub const EXPLICIT_THIS: u8 = 0x40;
@@ -367,6 +369,144 @@ impl TypeSig {
             _ => Err(Error::InvalidBlob(reader.position())),
         }
     }
+
+    /// Write this type to a signature blob.
+    pub fn write(&self, writer: &mut Writer) {
+        match self {
+            TypeSig::Primitive(elem) => writer.write_u8(*elem as u8),
+            TypeSig::Class(token) => {
+                writer.write_u8(ElementType::Class as u8);
+                writer.write_compressed_uint(*token);
+            }
+            TypeSig::ValueType(token) => {
+                writer.write_u8(ElementType::ValueType as u8);
+                writer.write_compressed_uint(*token);
+            }
+            TypeSig::SzArray(elem_type) => {
+                writer.write_u8(ElementType::SzArray as u8);
+                elem_type.write(writer);
+            }
+            TypeSig::Array {
+                element_type,
+                rank,
+                sizes,
+                lo_bounds,
+            } => {
+                writer.write_u8(ElementType::Array as u8);
+                element_type.write(writer);
+                writer.write_compressed_uint(*rank);
+                writer.write_compressed_uint(sizes.len() as u32);
+                for &size in sizes {
+                    writer.write_compressed_uint(size);
+                }
+                writer.write_compressed_uint(lo_bounds.len() as u32);
+                for &lo_bound in lo_bounds {
+                    writer.write_compressed_int(lo_bound);
+                }
+            }
+            TypeSig::Ptr(inner) => {
+                writer.write_u8(ElementType::Ptr as u8);
+                inner.write(writer);
+            }
+            TypeSig::ByRef(inner) => {
+                writer.write_u8(ElementType::ByRef as u8);
+                inner.write(writer);
+            }
+            TypeSig::GenericInst {
+                is_value_type,
+                type_ref,
+                type_args,
+            } => {
+                writer.write_u8(ElementType::GenericInst as u8);
+                writer.write_u8(if *is_value_type {
+                    ElementType::ValueType as u8
+                } else {
+                    ElementType::Class as u8
+                });
+                writer.write_compressed_uint(*type_ref);
+                writer.write_compressed_uint(type_args.len() as u32);
+                for arg in type_args {
+                    arg.write(writer);
+                }
+            }
+            TypeSig::Var(index) => {
+                writer.write_u8(ElementType::Var as u8);
+                writer.write_compressed_uint(*index);
+            }
+            TypeSig::MVar(index) => {
+                writer.write_u8(ElementType::MVar as u8);
+                writer.write_compressed_uint(*index);
+            }
+            TypeSig::FnPtr(method_sig) => {
+                writer.write_u8(ElementType::FnPtr as u8);
+                method_sig.write(writer);
+            }
+            TypeSig::Modified {
+                required,
+                modifier,
+                inner,
+            } => {
+                writer.write_u8(if *required {
+                    ElementType::CModReqd as u8
+                } else {
+                    ElementType::CModOpt as u8
+                });
+                writer.write_compressed_uint(*modifier);
+                inner.write(writer);
+            }
+            TypeSig::Pinned(inner) => {
+                writer.write_u8(ElementType::Pinned as u8);
+                inner.write(writer);
+            }
+        }
+    }
+
+    /// Serialize this type to a standalone blob (used for TypeSpec signatures).
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write(&mut writer);
+        writer.into_inner()
+    }
+
+    /// Call `f` with every TypeDefOrRef coded index in this type, including
+    /// those of custom modifiers and function pointer signatures.
+    pub(crate) fn visit_type_tokens(&mut self, f: &mut dyn FnMut(&mut u32)) {
+        match self {
+            TypeSig::Class(token) | TypeSig::ValueType(token) => f(token),
+            TypeSig::SzArray(inner)
+            | TypeSig::Ptr(inner)
+            | TypeSig::ByRef(inner)
+            | TypeSig::Pinned(inner)
+            | TypeSig::Array {
+                element_type: inner,
+                ..
+            } => inner.visit_type_tokens(f),
+            TypeSig::GenericInst {
+                type_ref,
+                type_args,
+                ..
+            } => {
+                f(type_ref);
+                for arg in type_args {
+                    arg.visit_type_tokens(f);
+                }
+            }
+            TypeSig::FnPtr(sig) => {
+                sig.return_type.visit_type_tokens(f);
+                for param in &mut sig.params {
+                    param.visit_type_tokens(f);
+                }
+            }
+            TypeSig::Modified {
+                modifier, inner, ..
+            } => {
+                f(modifier);
+                inner.visit_type_tokens(f);
+            }
+            TypeSig::Primitive(_) | TypeSig::Var(_) | TypeSig::MVar(_) => {}
+        }
+    }
 }
 
 /// A parsed method signature.
@@ -428,6 +568,30 @@ impl MethodSig {
         let mut reader = Reader::new(data);
         Self::parse(&mut reader)
     }
+
+    /// Write this method signature to a blob.
+    pub fn write(&self, writer: &mut Writer) {
+        writer.write_u8(self.calling_convention.0);
+        if self.calling_convention.is_generic() {
+            writer.write_compressed_uint(self.generic_param_count);
+        }
+        writer.write_compressed_uint(self.params.len() as u32);
+        self.return_type.write(writer);
+        for (i, param) in self.params.iter().enumerate() {
+            if self.sentinel == Some(i) {
+                writer.write_u8(ElementType::Sentinel as u8);
+            }
+            param.write(writer);
+        }
+    }
+
+    /// Serialize this method signature to raw bytes.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write(&mut writer);
+        writer.into_inner()
+    }
 }
 
 /// A parsed field signature.
@@ -453,6 +617,20 @@ impl FieldSig {
         let mut reader = Reader::new(data);
         Self::parse(&mut reader)
     }
+
+    /// Write this field signature to a blob.
+    pub fn write(&self, writer: &mut Writer) {
+        writer.write_u8(CallingConvention::FIELD);
+        self.field_type.write(writer);
+    }
+
+    /// Serialize this field signature to raw bytes.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write(&mut writer);
+        writer.into_inner()
+    }
 }
 
 /// A parsed property signature.
@@ -495,6 +673,28 @@ impl PropertySig {
         let mut reader = Reader::new(data);
         Self::parse(&mut reader)
     }
+
+    /// Write this property signature to a blob.
+    pub fn write(&self, writer: &mut Writer) {
+        let mut cc = CallingConvention::PROPERTY;
+        if self.has_this {
+            cc |= CallingConvention::HAS_THIS;
+        }
+        writer.write_u8(cc);
+        writer.write_compressed_uint(self.params.len() as u32);
+        self.property_type.write(writer);
+        for param in &self.params {
+            param.write(writer);
+        }
+    }
+
+    /// Serialize this property signature to raw bytes.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write(&mut writer);
+        writer.into_inner()
+    }
 }
 
 /// A parsed local variables signature.
@@ -527,6 +727,72 @@ impl LocalVarSig {
         let mut reader = Reader::new(data);
         Self::parse(&mut reader)
     }
+
+    /// Write this local variables signature to a blob.
+    pub fn write(&self, writer: &mut Writer) {
+        writer.write_u8(CallingConvention::LOCAL_SIG);
+        writer.write_compressed_uint(self.locals.len() as u32);
+        for local in &self.locals {
+            local.write(writer);
+        }
+    }
+
+    /// Serialize this local variables signature to raw bytes.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write(&mut writer);
+        writer.into_inner()
+    }
+}
+
+/// A parsed generic method instantiation signature (MethodSpec).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodSpecSig {
+    /// Generic method arguments.
+    pub type_args: Vec<TypeSig>,
+}
+
+impl MethodSpecSig {
+    /// Parse a method instantiation signature from a blob.
+    pub fn parse(reader: &mut Reader<'_>) -> Result<Self> {
+        let cc = reader.read_u8()?;
+        if cc != CallingConvention::GENERIC_INST {
+            return Err(Error::InvalidBlob(reader.position()));
+        }
+
+        let count = reader.read_compressed_uint()?;
+        let mut type_args = Vec::with_capacity(count as usize);
+
+        for _ in 0..count {
+            type_args.push(TypeSig::parse(reader)?);
+        }
+
+        Ok(Self { type_args })
+    }
+
+    /// Parse a method instantiation signature from raw bytes.
+    pub fn parse_blob(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        Self::parse(&mut reader)
+    }
+
+    /// Write this method instantiation signature to a blob.
+    pub fn write(&self, writer: &mut Writer) {
+        writer.write_u8(CallingConvention::GENERIC_INST);
+        writer.write_compressed_uint(self.type_args.len() as u32);
+        for arg in &self.type_args {
+            arg.write(writer);
+        }
+    }
+
+    /// Serialize this method instantiation signature to raw bytes.
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.write(&mut writer);
+        writer.into_inner()
+    }
 }
 
 #[cfg(test)]
@@ -853,6 +1119,60 @@ mod tests {
         assert!(result.is_err());
     }
 
+    // ========================================================================
+    // Encoding tests
+    // ========================================================================
+
+    #[test]
+    fn test_type_sig_roundtrip() {
+        let blobs: [&[u8]; 6] = [
+            &[0x15, 0x12, 0x05, 0x01, 0x08],
+            &[0x14, 0x08, 0x02, 0x01, 0x05, 0x02, 0x00, 0x7B],
+            &[0x1F, 0x09, 0x10, 0x1D, 0x0E],
+            &[0x0F, 0x01],
+            &[0x1B, 0x00, 0x01, 0x01, 0x1E, 0x00],
+            &[0x45, 0x10, 0x11, 0x0D],
+        ];
+        for blob in blobs {
+            let sig = TypeSig::parse(&mut Reader::new(blob)).unwrap();
+            assert_eq!(sig.to_blob(), blob);
+        }
+    }
+
+    #[test]
+    fn test_method_sig_roundtrip() {
+        // Generic instance method with vararg sentinel: HASTHIS|GENERIC|VARARG
+        let blob = [0x35, 0x01, 0x02, 0x01, 0x1E, 0x00, 0x41, 0x08];
+        let sig = MethodSig::parse_blob(&blob).unwrap();
+        assert_eq!(sig.sentinel, Some(1));
+        assert_eq!(sig.to_blob(), blob);
+    }
+
+    #[test]
+    fn test_field_property_local_roundtrip() {
+        let field = FieldSig::parse_blob(&[0x06, 0x1D, 0x08]).unwrap();
+        assert_eq!(field.to_blob(), [0x06, 0x1D, 0x08]);
+
+        let property = PropertySig::parse_blob(&[0x28, 0x01, 0x0E, 0x08]).unwrap();
+        assert_eq!(property.to_blob(), [0x28, 0x01, 0x0E, 0x08]);
+
+        let locals = LocalVarSig::parse_blob(&[0x07, 0x02, 0x45, 0x08, 0x0E]).unwrap();
+        assert_eq!(locals.to_blob(), [0x07, 0x02, 0x45, 0x08, 0x0E]);
+    }
+
+    #[test]
+    fn test_method_spec_sig() {
+        // GENERICINST, 2 args: string, class List`1<!!0>
+        let blob = [0x0A, 0x02, 0x0E, 0x15, 0x12, 0x05, 0x01, 0x1E, 0x00];
+        let sig = MethodSpecSig::parse_blob(&blob).unwrap();
+        assert_eq!(sig.type_args.len(), 2);
+        assert_eq!(sig.type_args[0], TypeSig::Primitive(ElementType::String));
+        assert_eq!(sig.to_blob(), blob);
+
+        // Wrong calling convention
+        assert!(MethodSpecSig::parse_blob(&[0x07, 0x01, 0x08]).is_err());
+    }
+
     // ========================================================================
     // CallingConvention tests
     // ========================================================================
@@ -23,6 +23,7 @@ impl StreamHeader {
     pub const USER_STRINGS: &'static str = "#US";
     pub const GUID: &'static str = "#GUID";
     pub const BLOB: &'static str = "#Blob";
+    pub const PDB: &'static str = "#Pdb";
 
     /// Parse a stream header from the reader.
     pub fn parse(reader: &mut Reader<'_>) -> Result<Self> {
@@ -0,0 +1,1087 @@
+//! Strong-name signatures.
+//!
+//! A strong-named assembly carries its RSA public key in the Assembly table
+//! and an RSA PKCS#1 v1.5 signature in the PE image, at the location given by
+//! the CLI header. The signed hash covers the PE headers and section data,
+//! except for the checksum, the certificate table directory entry and the
+//! signature itself, so that Authenticode signing afterwards does not
+//! invalidate it.
+//!
+//! Images can be verified, and re-signed in place from an SNK key pair after
+//! their contents change. Enhanced strong naming, where a separate signature
+//! key signs the image and the identity key counter-signs it through
+//! `AssemblySignatureKeyAttribute`, is supported in both directions.
+
+use crate::bignum::BigUint;
+use crate::crypto::{HashAlgorithm, public_key_token};
+use crate::custom_attribute::{CaType, CaValue, CustomAttributeValue};
+use crate::error::{Error, Result};
+use crate::image::{CLI_HEADER_DIRECTORY, CliHeader, PeImage, SECURITY_DIRECTORY};
+use crate::metadata::Metadata;
+use crate::reader::Reader;
+use crate::resolve::{attribute_class, type_path};
+use crate::tables::TableId;
+use crate::writer::Writer;
+
+/// Hash algorithm ID for SHA-1 (`CALG_SHA1`).
+pub const CALG_SHA1: u32 = HashAlgorithm::Sha1.alg_id();
+/// Hash algorithm ID for SHA-256 (`CALG_SHA_256`).
+pub const CALG_SHA_256: u32 = HashAlgorithm::Sha256.alg_id();
+/// Hash algorithm ID for SHA-384 (`CALG_SHA_384`).
+pub const CALG_SHA_384: u32 = HashAlgorithm::Sha384.alg_id();
+/// Hash algorithm ID for SHA-512 (`CALG_SHA_512`).
+pub const CALG_SHA_512: u32 = HashAlgorithm::Sha512.alg_id();
+/// Signature algorithm ID for RSA signing keys (`CALG_RSA_SIGN`).
+pub const CALG_RSA_SIGN: u32 = 0x2400;
+
+/// The ECMA neutral public key.
+///
+/// Framework assemblies built against it are signed with a key that is
+/// substituted at verification time, so it cannot be verified on its own.
+pub const ECMA_PUBLIC_KEY: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0];
+
+/// `PUBLICKEYBLOB` blob type.
+const PUBLIC_KEY_BLOB: u8 = 0x06;
+/// `CUR_BLOB_VERSION`.
+const BLOB_VERSION: u8 = 0x02;
+/// `PRIVATEKEYBLOB` blob type.
+const PRIVATE_KEY_BLOB: u8 = 0x07;
+/// `RSA1` magic of an RSA public key.
+const RSA1_MAGIC: u32 = 0x3141_5352;
+/// `RSA2` magic of an RSA private key.
+const RSA2_MAGIC: u32 = 0x3241_5352;
+
+/// An RSA public key in strong-name format: a signature algorithm, hash
+/// algorithm and length header followed by a CryptoAPI `PUBLICKEYBLOB`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrongNamePublicKey {
+    /// Signature algorithm ID (usually `CALG_RSA_SIGN`).
+    pub sig_alg_id: u32,
+    /// Hash algorithm ID used for signatures (`CALG_SHA1`, `CALG_SHA_256`...).
+    pub hash_alg_id: u32,
+    /// Key algorithm ID from the blob header.
+    pub key_alg_id: u32,
+    /// Public exponent.
+    pub exponent: u32,
+    /// Modulus, big-endian.
+    pub modulus: Vec<u8>,
+}
+
+impl StrongNamePublicKey {
+    /// Parse a strong-name public key, as stored in `AssemblyRow.public_key`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if is_ecma_key(data) {
+            return Err(Error::InvalidStrongNameKey("the ECMA key has no RSA key"));
+        }
+        let mut reader = Reader::new(data);
+        let sig_alg_id = reader.read_u32()?;
+        let hash_alg_id = reader.read_u32()?;
+        let blob_size = reader.read_u32()? as usize;
+        if blob_size != reader.remaining() {
+            return Err(Error::InvalidStrongNameKey("key blob length mismatch"));
+        }
+        let (key_alg_id, exponent, modulus) = parse_public_key_blob(&mut reader)?;
+        Ok(Self {
+            sig_alg_id,
+            hash_alg_id,
+            key_alg_id,
+            exponent,
+            modulus,
+        })
+    }
+
+    /// Serialize the key in strong-name format.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.write_u32(self.sig_alg_id);
+        w.write_u32(self.hash_alg_id);
+        w.write_u32(20 + self.modulus.len() as u32);
+        w.write_u8(PUBLIC_KEY_BLOB);
+        w.write_u8(BLOB_VERSION);
+        w.write_u16(0);
+        w.write_u32(self.key_alg_id);
+        w.write_u32(RSA1_MAGIC);
+        w.write_u32(self.bit_length() as u32);
+        w.write_u32(self.exponent);
+        w.write_bytes(&self.modulus.iter().rev().copied().collect::<Vec<_>>());
+        w.into_inner()
+    }
+
+    /// Get the key size in bits.
+    #[must_use]
+    pub fn bit_length(&self) -> usize {
+        self.modulus.len() * 8
+    }
+
+    /// Get the public key token of this key.
+    #[must_use]
+    pub fn token(&self) -> [u8; 8] {
+        public_key_token(&self.to_bytes())
+    }
+
+    /// Verify a signature over a hash computed with `hash_alg_id`.
+    ///
+    /// The signature is little-endian, as stored in images.
+    #[must_use]
+    pub fn verify(&self, hash: &[u8], hash_alg_id: u32, signature: &[u8]) -> bool {
+        if signature.len() != self.modulus.len() {
+            return false;
+        }
+        let Some(expected) = pkcs1_encode(hash, hash_alg_id, self.modulus.len()) else {
+            return false;
+        };
+        let modulus = BigUint::from_be_bytes(&self.modulus);
+        let signature = BigUint::from_le_bytes(signature);
+        if signature >= modulus || modulus.is_zero() {
+            return false;
+        }
+        let message = signature.mod_pow(&BigUint::from_u32(self.exponent), &modulus);
+        message.to_be_bytes(self.modulus.len()) == expected
+    }
+
+    /// Verify an enhanced strong naming counter-signature: this identity
+    /// key's signature over another public key blob.
+    #[must_use]
+    pub fn verify_counter_signature(
+        &self,
+        signature_public_key: &[u8],
+        counter_signature: &[u8],
+    ) -> bool {
+        hash_with(self.hash_alg_id, signature_public_key)
+            .is_some_and(|hash| self.verify(&hash, self.hash_alg_id, counter_signature))
+    }
+
+    /// Check whether two keys have the same RSA parameters, ignoring the
+    /// algorithm IDs in their headers.
+    fn same_rsa_key(&self, other: &Self) -> bool {
+        self.exponent == other.exponent
+            && BigUint::from_be_bytes(&self.modulus) == BigUint::from_be_bytes(&other.modulus)
+    }
+}
+
+/// Parse a `PUBLICKEYBLOB`: BLOBHEADER, RSAPUBKEY and little-endian modulus.
+/// Returns the key algorithm, exponent and big-endian modulus.
+pub(crate) fn parse_public_key_blob(reader: &mut Reader<'_>) -> Result<(u32, u32, Vec<u8>)> {
+    if reader.read_u8()? != PUBLIC_KEY_BLOB || reader.read_u8()? != BLOB_VERSION {
+        return Err(Error::InvalidStrongNameKey("not a version 2 PUBLICKEYBLOB"));
+    }
+    reader.read_u16()?; // reserved
+    let key_alg_id = reader.read_u32()?;
+    if reader.read_u32()? != RSA1_MAGIC {
+        return Err(Error::InvalidStrongNameKey("missing RSA1 magic"));
+    }
+    let bit_length = reader.read_u32()? as usize;
+    if bit_length == 0 || bit_length % 8 != 0 {
+        return Err(Error::InvalidStrongNameKey("invalid modulus length"));
+    }
+    let exponent = reader.read_u32()?;
+    let modulus = reader
+        .read_bytes(bit_length / 8)?
+        .iter()
+        .rev()
+        .copied()
+        .collect();
+    Ok((key_alg_id, exponent, modulus))
+}
+
+/// Check whether a public key blob is the ECMA neutral key.
+#[must_use]
+pub fn is_ecma_key(public_key: &[u8]) -> bool {
+    public_key == ECMA_PUBLIC_KEY
+}
+
+/// Result of strong-name verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrongNameStatus {
+    /// The assembly has no public key or no room for a signature.
+    NotStrongNamed,
+    /// The image is not marked as signed (delay signing).
+    DelaySigned,
+    /// The assembly uses the ECMA neutral key, which cannot be checked without
+    /// the actual signing key.
+    EcmaKey,
+    /// The signature matches the image.
+    Valid,
+    /// The signature does not match the image or the key.
+    Invalid,
+}
+
+/// The hash algorithm of a strong-name key. An ID of 0 means SHA-1; MD5 is
+/// not allowed for signatures.
+fn signature_hash_algorithm(hash_alg_id: u32) -> Result<HashAlgorithm> {
+    match HashAlgorithm::from_alg_id(hash_alg_id) {
+        _ if hash_alg_id == 0 => Ok(HashAlgorithm::Sha1),
+        Some(HashAlgorithm::Md5) | None => Err(Error::UnsupportedHashAlgorithm(hash_alg_id)),
+        Some(algorithm) => Ok(algorithm),
+    }
+}
+
+/// Hash `data` with a strong-name hash algorithm. Returns `None` for
+/// unsupported algorithms.
+pub(crate) fn hash_with(hash_alg_id: u32, data: &[u8]) -> Option<Vec<u8>> {
+    signature_hash_algorithm(hash_alg_id)
+        .ok()
+        .map(|algorithm| algorithm.hash(data))
+}
+
+/// DER-encoded DigestInfo prefix of a hash algorithm (RFC 8017 9.2).
+fn digest_info_prefix(hash_alg_id: u32) -> Option<&'static [u8]> {
+    Some(match hash_alg_id {
+        0 | CALG_SHA1 => &[
+            0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04,
+            0x14,
+        ],
+        CALG_SHA_256 => &[
+            0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x01, 0x05, 0x00, 0x04, 0x20,
+        ],
+        CALG_SHA_384 => &[
+            0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x02, 0x05, 0x00, 0x04, 0x30,
+        ],
+        CALG_SHA_512 => &[
+            0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x03, 0x05, 0x00, 0x04, 0x40,
+        ],
+        _ => return None,
+    })
+}
+
+/// EMSA-PKCS1-v1_5 encoding of a hash into `len` bytes, big-endian.
+pub(crate) fn pkcs1_encode(hash: &[u8], hash_alg_id: u32, len: usize) -> Option<Vec<u8>> {
+    let prefix = digest_info_prefix(hash_alg_id)?;
+    let padding = len.checked_sub(prefix.len() + hash.len() + 3)?;
+    if padding < 8 {
+        return None;
+    }
+    let mut encoded = Vec::with_capacity(len);
+    encoded.extend_from_slice(&[0x00, 0x01]);
+    encoded.resize(2 + padding, 0xFF);
+    encoded.push(0x00);
+    encoded.extend_from_slice(prefix);
+    encoded.extend_from_slice(hash);
+    Some(encoded)
+}
+
+/// Compute the strong-name hash of a PE image.
+///
+/// The hash covers the headers through the section table, with the checksum
+/// and the certificate table directory entry zeroed, followed by the raw data
+/// of each section with the strong-name signature left out.
+pub fn strong_name_hash(image: &[u8], hash_alg_id: u32) -> Result<Vec<u8>> {
+    let pe = PeImage::parse(image)?;
+    let cli = pe.cli_header()?;
+    strong_name_digest(&pe, &cli, signature_hash_algorithm(hash_alg_id)?)
+}
+
+/// Hash the bytes covered by the strong-name hash.
+fn strong_name_digest(
+    pe: &PeImage<'_>,
+    cli: &CliHeader,
+    algorithm: HashAlgorithm,
+) -> Result<Vec<u8>> {
+    let image = pe.data();
+    let mut headers = image
+        .get(..pe.section_table_end())
+        .ok_or(Error::InvalidImage("truncated section table"))?
+        .to_vec();
+    for (offset, len) in [
+        (pe.checksum_offset(), 4),
+        (pe.data_directory_offset(SECURITY_DIRECTORY), 8),
+    ] {
+        if let Some(field) = headers.get_mut(offset..offset + len) {
+            field.fill(0);
+        }
+    }
+    let mut hasher = algorithm.hasher();
+    hasher.update(&headers);
+
+    let signature = match cli.strong_name_signature {
+        directory if directory.size == 0 => 0..0,
+        directory => {
+            let start = pe.rva_to_offset(directory.rva).ok_or(Error::InvalidImage(
+                "strong name signature outside of image",
+            ))?;
+            start..start + directory.size as usize
+        }
+    };
+    for section in pe.sections() {
+        let start = section.pointer_to_raw_data as usize;
+        let end = start + section.size_of_raw_data as usize;
+        let data = image
+            .get(start..end)
+            .ok_or(Error::InvalidImage("section data outside of image"))?;
+        if (start..end).contains(&signature.start) {
+            hasher.update(&image[start..signature.start]);
+            hasher.update(&image[signature.end.min(end)..end]);
+        } else {
+            hasher.update(data);
+        }
+    }
+    Ok(hasher.finalize())
+}
+
+/// Verify the strong-name signature of a PE image against the public key in
+/// its Assembly table.
+pub fn verify_strong_name(image: &[u8]) -> Result<StrongNameStatus> {
+    verify(image, None)
+}
+
+/// Verify the strong-name signature of a PE image against the given public
+/// key instead of the assembly's own, e.g. the real key behind the ECMA key.
+pub fn verify_strong_name_with_key(image: &[u8], public_key: &[u8]) -> Result<StrongNameStatus> {
+    verify(image, Some(public_key))
+}
+
+fn verify(image: &[u8], key_override: Option<&[u8]>) -> Result<StrongNameStatus> {
+    let pe = PeImage::parse(image)?;
+    let cli = pe.cli_header()?;
+    let metadata = Metadata::parse(pe.metadata()?)?;
+    let Some(public_key) = metadata.assembly().and_then(|assembly| assembly.public_key) else {
+        return Ok(StrongNameStatus::NotStrongNamed);
+    };
+    if cli.strong_name_signature.size == 0 {
+        return Ok(StrongNameStatus::NotStrongNamed);
+    }
+    if cli.flags & CliHeader::FLAG_STRONG_NAME_SIGNED == 0 {
+        return Ok(StrongNameStatus::DelaySigned);
+    }
+    let public_key = key_override.unwrap_or(&public_key);
+    if is_ecma_key(public_key) {
+        return Ok(StrongNameStatus::EcmaKey);
+    }
+
+    // With enhanced strong naming the image is signed by a separate key,
+    // which the identity key counter-signs
+    let identity = StrongNamePublicKey::parse(public_key)?;
+    let key = match metadata.assembly_signature_key() {
+        Some((signature_key, counter_signature)) => {
+            match StrongNamePublicKey::parse(&signature_key) {
+                Ok(key)
+                    if identity.verify_counter_signature(&signature_key, &counter_signature) =>
+                {
+                    key
+                }
+                _ => return Ok(StrongNameStatus::Invalid),
+            }
+        }
+        None => identity,
+    };
+    let signature = pe
+        .directory_data(cli.strong_name_signature)
+        .ok_or(Error::InvalidImage(
+            "strong name signature outside of image",
+        ))?;
+    let hash = strong_name_digest(&pe, &cli, signature_hash_algorithm(key.hash_alg_id)?)?;
+    Ok(if key.verify(&hash, key.hash_alg_id, signature) {
+        StrongNameStatus::Valid
+    } else {
+        StrongNameStatus::Invalid
+    })
+}
+
+/// An RSA key pair read from an SNK file (a CryptoAPI `PRIVATEKEYBLOB`).
+#[derive(Clone, PartialEq, Eq)]
+pub struct StrongNameKeyPair {
+    public_key: StrongNamePublicKey,
+    prime1: Vec<u8>,
+    prime2: Vec<u8>,
+    exponent1: Vec<u8>,
+    exponent2: Vec<u8>,
+    coefficient: Vec<u8>,
+    private_exponent: Vec<u8>,
+}
+
+impl StrongNameKeyPair {
+    /// Parse an SNK key pair.
+    ///
+    /// SNK files holding only a public key (as written by `sn -p`) are
+    /// rejected; use [`StrongNamePublicKey::parse`] for them.
+    pub fn from_snk(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        if reader.read_u8()? != PRIVATE_KEY_BLOB || reader.read_u8()? != BLOB_VERSION {
+            return Err(Error::InvalidStrongNameKey(
+                "not a version 2 PRIVATEKEYBLOB",
+            ));
+        }
+        reader.read_u16()?; // reserved
+        let key_alg_id = reader.read_u32()?;
+        if reader.read_u32()? != RSA2_MAGIC {
+            return Err(Error::InvalidStrongNameKey("missing RSA2 magic"));
+        }
+        let bit_length = reader.read_u32()? as usize;
+        if bit_length == 0 || bit_length % 16 != 0 {
+            return Err(Error::InvalidStrongNameKey("invalid modulus length"));
+        }
+        let exponent = reader.read_u32()?;
+        let mut read = |len: usize| -> Result<Vec<u8>> {
+            Ok(reader.read_bytes(len)?.iter().rev().copied().collect())
+        };
+        let modulus = read(bit_length / 8)?;
+        let prime1 = read(bit_length / 16)?;
+        let prime2 = read(bit_length / 16)?;
+        let exponent1 = read(bit_length / 16)?;
+        let exponent2 = read(bit_length / 16)?;
+        let coefficient = read(bit_length / 16)?;
+        let private_exponent = read(bit_length / 8)?;
+
+        Ok(Self {
+            public_key: StrongNamePublicKey {
+                sig_alg_id: CALG_RSA_SIGN,
+                hash_alg_id: CALG_SHA1,
+                key_alg_id,
+                exponent,
+                modulus,
+            },
+            prime1,
+            prime2,
+            exponent1,
+            exponent2,
+            coefficient,
+            private_exponent,
+        })
+    }
+
+    /// Serialize the key pair as an SNK file.
+    #[must_use]
+    pub fn to_snk(&self) -> Vec<u8> {
+        let key = &self.public_key;
+        let mut w = Writer::new();
+        w.write_u8(PRIVATE_KEY_BLOB);
+        w.write_u8(BLOB_VERSION);
+        w.write_u16(0);
+        w.write_u32(key.key_alg_id);
+        w.write_u32(RSA2_MAGIC);
+        w.write_u32(key.bit_length() as u32);
+        w.write_u32(key.exponent);
+        let half = key.modulus.len() / 2;
+        for (value, len) in [
+            (&key.modulus, key.modulus.len()),
+            (&self.prime1, half),
+            (&self.prime2, half),
+            (&self.exponent1, half),
+            (&self.exponent2, half),
+            (&self.coefficient, half),
+            (&self.private_exponent, key.modulus.len()),
+        ] {
+            w.write_bytes(&BigUint::from_be_bytes(value).to_le_bytes(len));
+        }
+        w.into_inner()
+    }
+
+    /// Get the public key. Its hash algorithm is SHA-1 unless changed with
+    /// [`StrongNameKeyPair::with_hash_algorithm`].
+    #[must_use]
+    pub fn public_key(&self) -> &StrongNamePublicKey {
+        &self.public_key
+    }
+
+    /// Use a different signature hash algorithm (`CALG_SHA_256`...).
+    #[must_use]
+    pub fn with_hash_algorithm(mut self, hash_alg_id: u32) -> Self {
+        self.public_key.hash_alg_id = hash_alg_id;
+        self
+    }
+
+    /// Sign a hash computed with `hash_alg_id`. The signature is little-endian,
+    /// as stored in images.
+    pub fn sign_hash(&self, hash: &[u8], hash_alg_id: u32) -> Result<Vec<u8>> {
+        let len = self.public_key.modulus.len();
+        let encoded = pkcs1_encode(hash, hash_alg_id, len).ok_or(Error::InvalidStrongNameKey(
+            "key too small for hash algorithm",
+        ))?;
+        let message = BigUint::from_be_bytes(&encoded);
+
+        // Chinese remainder theorem: s = m2 + q * (qInv * (m1 - m2) mod p)
+        let p = BigUint::from_be_bytes(&self.prime1);
+        let q = BigUint::from_be_bytes(&self.prime2);
+        let m1 = message.mod_pow(&BigUint::from_be_bytes(&self.exponent1), &p);
+        let m2 = message.mod_pow(&BigUint::from_be_bytes(&self.exponent2), &q);
+        let difference = m1.add(&p).sub(&m2.rem(&p)).rem(&p);
+        let h = BigUint::from_be_bytes(&self.coefficient)
+            .mul(&difference)
+            .rem(&p);
+        let signature = m2.add(&q.mul(&h));
+
+        // Guard against a key whose private parts do not match its modulus
+        let modulus = BigUint::from_be_bytes(&self.public_key.modulus);
+        if signature.mod_pow(&BigUint::from_u32(self.public_key.exponent), &modulus) != message {
+            return Err(Error::InvalidStrongNameKey("inconsistent private key"));
+        }
+        Ok(signature.to_le_bytes(len))
+    }
+
+    /// Counter-sign a signature public key with this identity key, for the
+    /// second argument of `AssemblySignatureKeyAttribute`.
+    pub fn counter_sign(&self, signature_public_key: &[u8]) -> Result<Vec<u8>> {
+        let hash_alg_id = self.public_key.hash_alg_id;
+        let hash = signature_hash_algorithm(hash_alg_id)?.hash(signature_public_key);
+        self.sign_hash(&hash, hash_alg_id)
+    }
+
+    /// Get the `AssemblySignatureKeyAttribute` arguments (public key and
+    /// counter-signature, hex-encoded) for signing with `signature_key` while
+    /// keeping this key as the assembly identity.
+    pub fn signature_key_attribute_args(
+        &self,
+        signature_key: &StrongNamePublicKey,
+    ) -> Result<(String, String)> {
+        let public_key = signature_key.to_bytes();
+        let counter_signature = self.counter_sign(&public_key)?;
+        Ok((to_hex(&public_key), to_hex(&counter_signature)))
+    }
+}
+
+impl std::fmt::Debug for StrongNameKeyPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Keep private key material out of logs
+        f.debug_struct("StrongNameKeyPair")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How to strong-name sign an image.
+#[derive(Debug, Clone, Copy)]
+pub enum SigningMode<'a> {
+    /// Sign with a key pair.
+    KeyPair(&'a StrongNameKeyPair),
+    /// Mark the image as signed but leave the signature zeroed (public or
+    /// "OSS" signing). Accepted by runtimes that skip verification.
+    PublicSign,
+    /// Clear the signed flag and zero the signature, to be signed later.
+    DelaySign,
+}
+
+/// Strong-name sign a PE image in place.
+///
+/// The image must already reserve space for the signature in its CLI header,
+/// and its Assembly table must carry the public key: the key pair's own, or
+/// the identity key when the assembly has an `AssemblySignatureKeyAttribute`
+/// naming the key pair's public key.
+pub fn sign_strong_name(image: &mut [u8], mode: SigningMode<'_>) -> Result<()> {
+    let pe = PeImage::parse(image)?;
+    let cli = pe.cli_header()?;
+    let metadata = Metadata::parse(pe.metadata()?)?;
+    let public_key = metadata
+        .assembly()
+        .and_then(|assembly| assembly.public_key)
+        .ok_or(Error::InvalidStrongNameKey("assembly has no public key"))?;
+    if cli.strong_name_signature.size == 0 {
+        return Err(Error::InvalidImage(
+            "no space reserved for a strong name signature",
+        ));
+    }
+    let signature_range = pe
+        .rva_to_offset(cli.strong_name_signature.rva)
+        .map(|start| start..start + cli.strong_name_signature.size as usize)
+        .filter(|range| range.end <= image.len())
+        .ok_or(Error::InvalidImage(
+            "strong name signature outside of image",
+        ))?;
+    let flags_offset = pe
+        .data_directory(CLI_HEADER_DIRECTORY)
+        .and_then(|directory| pe.rva_to_offset(directory.rva))
+        .ok_or(Error::InvalidImage("not a managed image"))?
+        + 16;
+
+    if let SigningMode::KeyPair(key_pair) = mode {
+        let expected = match metadata.assembly_signature_key() {
+            Some((signature_key, _)) => signature_key,
+            None => public_key,
+        };
+        let matches = StrongNamePublicKey::parse(&expected)
+            .is_ok_and(|expected| expected.same_rsa_key(key_pair.public_key()));
+        if !matches {
+            return Err(Error::InvalidStrongNameKey(
+                "key pair does not match the assembly public key",
+            ));
+        }
+        if signature_range.len() != key_pair.public_key().modulus.len() {
+            return Err(Error::InvalidImage(
+                "reserved signature size does not match the key size",
+            ));
+        }
+    }
+
+    let flags = cli.flags;
+    let flags = match mode {
+        SigningMode::DelaySign => flags & !CliHeader::FLAG_STRONG_NAME_SIGNED,
+        _ => flags | CliHeader::FLAG_STRONG_NAME_SIGNED,
+    };
+    image[flags_offset..flags_offset + 4].copy_from_slice(&flags.to_le_bytes());
+    image[signature_range.clone()].fill(0);
+
+    if let SigningMode::KeyPair(key_pair) = mode {
+        let hash_alg_id = key_pair.public_key().hash_alg_id;
+        let hash = strong_name_hash(image, hash_alg_id)?;
+        let signature = key_pair.sign_hash(&hash, hash_alg_id)?;
+        image[signature_range].copy_from_slice(&signature);
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl Metadata {
+    /// Get the signature public key and counter-signature from the assembly's
+    /// `AssemblySignatureKeyAttribute` (enhanced strong naming), if any.
+    #[must_use]
+    pub fn assembly_signature_key(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.custom_attributes.iter().find_map(|row| {
+            if row.parent.table != Some(TableId::Assembly) {
+                return None;
+            }
+            let path = type_path(self, attribute_class(self, row.attr_type)?)?;
+            if path.as_slice() != [("System.Reflection", "AssemblySignatureKeyAttribute")] {
+                return None;
+            }
+            let value = CustomAttributeValue::parse(
+                self.blobs.get(row.value).ok()?,
+                &[CaType::String, CaType::String],
+            )
+            .ok()?;
+            match value.fixed_args.as_slice() {
+                [
+                    CaValue::String(Some(key)),
+                    CaValue::String(Some(counter_signature)),
+                ] => Some((from_hex(key)?, from_hex(counter_signature)?)),
+                _ => None,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::ilasm::assemble;
+    use crate::image::{CLI_HEADER_DIRECTORY, metadata_bytes, test_image};
+
+    pub(crate) fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// 1024-bit test key.
+    pub(crate) const MODULUS: &str = concat!(
+        "b6c81a986198be3512fd282a15d2b796755db065e1ea887b854ee7c46acb96f1",
+        "89dffc8ba33c230ee6bfd65a61117753b1237b737fa77f41137f4903e0ff3bac",
+        "8c01fac202f542aae4f266c54bf1b86316c357f93ce75706b4dfdf351fd6eb51",
+        "032c71526007ee1a9dcde458f5474f69a5533bca7e31eb74daae3d4f0613c2db",
+    );
+    pub(crate) const PRIVATE_EXPONENT: &str = concat!(
+        "b67c6c090d0fa97f65b8f89ba3f4203508728486039adbaeaef68b70cfcaf9a5",
+        "a728c8844571e9f29bb095fae004c724f8aadb3b020d2180c04d898164e68e14",
+        "d199eb776cc6f2abbfbbf2d97cf542ac7fa0dc0c41b71cc3f6998e3a4eecb8dd",
+        "d1197ad4f8aa0a4257abfac4cf90d2a0975e9306278c68c2371a3f0fa5ca9309",
+    );
+
+    const PRIME1: &str = concat!(
+        "c1c690918d10d79fd61645f6610f8b4836bc696f1320d12373044b1bedb97c96",
+        "d4d268218c84bba768a2733f6a8df002e0f0b785b3c146689737f25f9d5a113f",
+    );
+    const PRIME2: &str = concat!(
+        "f179c5b2a60bcbbf0644e833433baa2d6a544c989ff19f15841b3b60f163dad1",
+        "fa2553a3e93816b873a3be004b089db5e8d94e990c6d254e3ea9dcec64a2cb65",
+    );
+    const EXPONENT1: &str = concat!(
+        "074e5c4f10998cf7bad3b62996f946101ff834a57a7c274685e4a88c5207475a",
+        "4c85ee652d6e27c12521d2e49638e9cec1fa7180fa091485870615fe62e96d73",
+    );
+    const EXPONENT2: &str = concat!(
+        "dfe2b26cf7ccfa044bb0b9c545b1dd0fac852a5b5bf82f3248c0e2e5f8094809",
+        "e52e9484e5a7ee50e84ec9cc05c5a04bd7ef1b1385d4f080c252c5959b9ce08d",
+    );
+    const COEFFICIENT: &str = concat!(
+        "0c529d717d833444d05406561bf7a64529cef5c91784a79b57f232beb8f846e8",
+        "9b61febf3100b9940267ac65f962724185c9f29a089543742ba2ebddd57b7f48",
+    );
+
+    /// Second 1024-bit key, distinct from the test key, for enhanced strong
+    /// naming.
+    const SIGNATURE_KEY: [&str; 7] = [
+        concat!(
+            "d97a369dde720d384a8bd51b88a4e3f4b93b3569b811c6bdb099b4752974365f",
+            "7a40daf0378097fe48c4237545807d80fef8476a4e83cafc262e2f74c289caf6",
+            "4791ca8fa6bcb7dd1de702473e79d005e1a7833bfbc10ca3dce67e5a7d3ebd09",
+            "68a998acf814ba18c807b7eccb0383c0c8d008bf4c07f6e39198e84600aab0a3",
+        ),
+        concat!(
+            "26f6ec9e3062c2d79913a0a5044076cf3bf8cdc31945a6140a761f8cf65669ff",
+            "8549e57030a18b99f5b39e9ebe4b1d31657d5a5e439efd595c05de5ecf141d16",
+            "da68aae447e3fce1bdda7e1e37de58bbebbd9d6af158af6f105214e5b12b8e94",
+            "23a56dc83fc833191b1ed8f53253b4a1663c8b4fb09fe284b02d933ba0a74da1",
+        ),
+        concat!(
+            "f7d3d97e87e4b39c9fc3f824b942fa9178540c7892d9684fa5d08beb20983bd9",
+            "c2bd3e5ff555af92945506d8a0e5e220d3d22002a56ae7b9ce521a20c78accf9",
+        ),
+        concat!(
+            "e0a62627f3b6a480caf658902f638efc0db94c47dcd01422afab7b9333fbee3f",
+            "2a93964ea232660558ce60eef13151ce1d6a03813842bae84c3b4353c0521d7b",
+        ),
+        concat!(
+            "0a0c23b836883623f6723a803aa695840a4dd6079964890f1998dfe35379f88b",
+            "3692f7916a5fe3f35c622a19e4440dabe90a8ed3aca32debe18a8096ada059f9",
+        ),
+        concat!(
+            "982cb46817d53ef5e7869c537f6667732230c51560e4aadf1c34834f21c5a48b",
+            "b161ac4db3dd83f25747e5adb6407c73acc5257ea107c2e89ccb003e7966a685",
+        ),
+        concat!(
+            "3a22251e9287489cdb51aca184bf6973e4f058d999d6b4929fd6a365057fe63b",
+            "3056da8612b52a03d4c6634d8169c631faa1ba7c46ba0ce9daa21f91f8a350fd",
+        ),
+    ];
+
+    pub(crate) fn test_key_pair() -> StrongNameKeyPair {
+        StrongNameKeyPair {
+            public_key: test_key(CALG_SHA1),
+            prime1: hex(PRIME1),
+            prime2: hex(PRIME2),
+            exponent1: hex(EXPONENT1),
+            exponent2: hex(EXPONENT2),
+            coefficient: hex(COEFFICIENT),
+            private_exponent: hex(PRIVATE_EXPONENT),
+        }
+    }
+
+    /// Key pair of [`SIGNATURE_KEY`].
+    fn signature_key_pair() -> StrongNameKeyPair {
+        let [
+            modulus,
+            private_exponent,
+            prime1,
+            prime2,
+            exponent1,
+            exponent2,
+            coefficient,
+        ] = SIGNATURE_KEY.map(hex);
+        StrongNameKeyPair {
+            public_key: StrongNamePublicKey {
+                modulus,
+                ..test_key(CALG_SHA1)
+            },
+            prime1,
+            prime2,
+            exponent1,
+            exponent2,
+            coefficient,
+            private_exponent,
+        }
+    }
+
+    pub(crate) fn test_key(hash_alg_id: u32) -> StrongNamePublicKey {
+        StrongNamePublicKey {
+            sig_alg_id: CALG_RSA_SIGN,
+            hash_alg_id,
+            key_alg_id: CALG_RSA_SIGN,
+            exponent: 65537,
+            modulus: hex(MODULUS),
+        }
+    }
+
+    /// Build an image of an assembly with the given public key and room for a
+    /// 128-byte signature.
+    pub(crate) fn unsigned_image(public_key: &[u8]) -> Vec<u8> {
+        image_with_attributes(public_key, "")
+    }
+
+    /// As [`unsigned_image`], with extra assembly-level directives.
+    fn image_with_attributes(public_key: &[u8], directives: &str) -> Vec<u8> {
+        let bytes: Vec<String> = public_key.iter().map(|b| format!("{b:02X}")).collect();
+        let source = format!(
+            ".assembly extern mscorlib {{}} .assembly Signed {{ .publickey = ({}) .ver 1:0:0:0 {directives} }}",
+            bytes.join(" ")
+        );
+        test_image(&assemble(&source).unwrap().metadata.write(), 128)
+    }
+
+    /// Mark an image as signed and sign it in place with the raw private
+    /// exponent.
+    fn sign_with_exponent(image: &mut [u8], hash_alg_id: u32) {
+        let pe = PeImage::parse(image).unwrap();
+        let cli = pe.cli_header().unwrap();
+        let offset = pe.rva_to_offset(cli.strong_name_signature.rva).unwrap();
+        let flags_offset = pe
+            .rva_to_offset(pe.data_directory(CLI_HEADER_DIRECTORY).unwrap().rva)
+            .unwrap()
+            + 16;
+        image[flags_offset] |= CliHeader::FLAG_STRONG_NAME_SIGNED as u8;
+
+        let hash = strong_name_hash(image, hash_alg_id).unwrap();
+        let encoded = pkcs1_encode(&hash, hash_alg_id, 128).unwrap();
+        let signature = BigUint::from_be_bytes(&encoded).mod_pow(
+            &BigUint::from_be_bytes(&hex(PRIVATE_EXPONENT)),
+            &BigUint::from_be_bytes(&hex(MODULUS)),
+        );
+        image[offset..offset + 128].copy_from_slice(
+            &signature
+                .to_be_bytes(128)
+                .into_iter()
+                .rev()
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn test_public_key_blob() {
+        let key = test_key(CALG_SHA1);
+        let bytes = key.to_bytes();
+        assert_eq!(bytes.len(), 12 + 20 + 128);
+        assert_eq!(
+            &bytes[12..20],
+            &[0x06, 0x02, 0x00, 0x00, 0x00, 0x24, 0x00, 0x00]
+        );
+        assert_eq!(&bytes[20..24], b"RSA1");
+        assert_eq!(StrongNamePublicKey::parse(&bytes).unwrap(), key);
+        assert_eq!(key.bit_length(), 1024);
+        assert_eq!(key.token(), public_key_token(&bytes));
+
+        assert!(is_ecma_key(&ECMA_PUBLIC_KEY));
+        assert!(StrongNamePublicKey::parse(&ECMA_PUBLIC_KEY).is_err());
+        assert!(StrongNamePublicKey::parse(&bytes[..100]).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        for hash_alg_id in [CALG_SHA1, CALG_SHA_256, CALG_SHA_384, CALG_SHA_512] {
+            let mut image = unsigned_image(&test_key(hash_alg_id).to_bytes());
+            assert_eq!(
+                verify_strong_name(&image).unwrap(),
+                StrongNameStatus::DelaySigned
+            );
+            sign_with_exponent(&mut image, hash_alg_id);
+            assert_eq!(
+                verify_strong_name(&image).unwrap(),
+                StrongNameStatus::Valid,
+                "{hash_alg_id:#x}"
+            );
+
+            // Excluded regions may change
+            let pe = PeImage::parse(&image).unwrap();
+            let checksum = pe.checksum_offset();
+            let security = pe.data_directory_offset(SECURITY_DIRECTORY);
+            let mut patched = image.clone();
+            patched[checksum] ^= 0xFF;
+            patched[security] ^= 0xFF;
+            assert_eq!(
+                verify_strong_name(&patched).unwrap(),
+                StrongNameStatus::Valid
+            );
+
+            // Anything else may not
+            let mut tampered = image.clone();
+            let last = tampered.len() - 1;
+            tampered[last] ^= 1;
+            assert_eq!(
+                verify_strong_name(&tampered).unwrap(),
+                StrongNameStatus::Invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_special_cases() {
+        let unsigned = test_image(&assemble(".assembly Plain {}").unwrap().metadata.write(), 0);
+        assert_eq!(
+            verify_strong_name(&unsigned).unwrap(),
+            StrongNameStatus::NotStrongNamed
+        );
+
+        let mut image = unsigned_image(&ECMA_PUBLIC_KEY);
+        sign_with_exponent(&mut image, CALG_SHA1);
+        assert_eq!(
+            verify_strong_name(&image).unwrap(),
+            StrongNameStatus::EcmaKey
+        );
+        // The actual signing key can be substituted
+        let key = test_key(CALG_SHA1).to_bytes();
+        assert_eq!(
+            verify_strong_name_with_key(&image, &key).unwrap(),
+            StrongNameStatus::Valid
+        );
+        let other = StrongNamePublicKey {
+            exponent: 3,
+            ..test_key(CALG_SHA1)
+        };
+        assert_eq!(
+            verify_strong_name_with_key(&image, &other.to_bytes()).unwrap(),
+            StrongNameStatus::Invalid
+        );
+    }
+
+    #[test]
+    fn test_snk() {
+        let key_pair = test_key_pair();
+        let snk = key_pair.to_snk();
+        assert_eq!(snk.len(), 20 + 128 * 9 / 2);
+        assert_eq!(&snk[..4], &[0x07, 0x02, 0x00, 0x00]);
+        assert_eq!(&snk[8..12], b"RSA2");
+        assert_eq!(StrongNameKeyPair::from_snk(&snk).unwrap(), key_pair);
+        assert!(StrongNameKeyPair::from_snk(&snk[..200]).is_err());
+        assert!(StrongNameKeyPair::from_snk(&key_pair.public_key().to_bytes()).is_err());
+        assert!(!format!("{key_pair:?}").contains("private_exponent"));
+    }
+
+    #[test]
+    fn test_sign() {
+        for hash_alg_id in [CALG_SHA1, CALG_SHA_256, CALG_SHA_512] {
+            let key_pair = test_key_pair().with_hash_algorithm(hash_alg_id);
+            let unsigned = unsigned_image(&key_pair.public_key().to_bytes());
+
+            let mut image = unsigned.clone();
+            sign_strong_name(&mut image, SigningMode::KeyPair(&key_pair)).unwrap();
+            assert_eq!(verify_strong_name(&image).unwrap(), StrongNameStatus::Valid);
+            // CRT signing agrees with the plain private exponent
+            let mut expected = unsigned.clone();
+            sign_with_exponent(&mut expected, hash_alg_id);
+            assert_eq!(image, expected);
+
+            // Patching the metadata breaks the signature until re-signed
+            let metadata_offset = 0x200 + 72;
+            image[metadata_offset + 20] ^= 0x01;
+            assert_eq!(
+                verify_strong_name(&image).unwrap(),
+                StrongNameStatus::Invalid
+            );
+            image[metadata_offset + 20] ^= 0x01;
+            let last = image.len() - 1;
+            image[last] = 0xAA;
+            sign_strong_name(&mut image, SigningMode::KeyPair(&key_pair)).unwrap();
+            assert_eq!(verify_strong_name(&image).unwrap(), StrongNameStatus::Valid);
+        }
+    }
+
+    #[test]
+    fn test_public_and_delay_sign() {
+        let key_pair = test_key_pair();
+        let mut image = unsigned_image(&key_pair.public_key().to_bytes());
+        sign_strong_name(&mut image, SigningMode::KeyPair(&key_pair)).unwrap();
+
+        sign_strong_name(&mut image, SigningMode::DelaySign).unwrap();
+        assert_eq!(
+            verify_strong_name(&image).unwrap(),
+            StrongNameStatus::DelaySigned
+        );
+        sign_strong_name(&mut image, SigningMode::PublicSign).unwrap();
+        let pe = PeImage::parse(&image).unwrap();
+        let cli = pe.cli_header().unwrap();
+        assert_ne!(cli.flags & CliHeader::FLAG_STRONG_NAME_SIGNED, 0);
+        assert!(
+            pe.directory_data(cli.strong_name_signature)
+                .unwrap()
+                .iter()
+                .all(|&b| b == 0)
+        );
+        assert_eq!(
+            verify_strong_name(&image).unwrap(),
+            StrongNameStatus::Invalid
+        );
+
+        // The key pair must match the assembly's public key
+        let mut other = unsigned_image(
+            &StrongNamePublicKey {
+                exponent: 3,
+                ..test_key(CALG_SHA1)
+            }
+            .to_bytes(),
+        );
+        assert!(sign_strong_name(&mut other, SigningMode::KeyPair(&key_pair)).is_err());
+        let mut plain = test_image(
+            &assemble(".assembly Plain {}").unwrap().metadata.write(),
+            128,
+        );
+        assert!(sign_strong_name(&mut plain, SigningMode::PublicSign).is_err());
+    }
+
+    #[test]
+    fn test_enhanced_strong_naming() {
+        let identity = test_key_pair();
+        let signature_key = signature_key_pair().with_hash_algorithm(CALG_SHA_256);
+        assert!(
+            !identity
+                .public_key()
+                .same_rsa_key(signature_key.public_key())
+        );
+        let identity_key = identity.public_key().to_bytes();
+        let attribute = |(public_key, counter_signature): (String, String)| {
+            format!(
+                ".custom instance void [mscorlib]System.Reflection.AssemblySignatureKeyAttribute::.ctor(string, string) = {{ string('{public_key}') string('{counter_signature}') }}"
+            )
+        };
+        let args = identity
+            .signature_key_attribute_args(signature_key.public_key())
+            .unwrap();
+        let unsigned = image_with_attributes(&identity_key, &attribute(args.clone()));
+        let metadata = Metadata::parse(metadata_bytes(&unsigned).unwrap()).unwrap();
+        let (key, _) = metadata.assembly_signature_key().unwrap();
+        assert_eq!(key, signature_key.public_key().to_bytes());
+
+        // The signature key signs the image; the identity key only vouches for it
+        let mut image = unsigned.clone();
+        sign_strong_name(&mut image, SigningMode::KeyPair(&signature_key)).unwrap();
+        assert_eq!(verify_strong_name(&image).unwrap(), StrongNameStatus::Valid);
+        assert_eq!(
+            verify_strong_name_with_key(&image, &identity_key).unwrap(),
+            StrongNameStatus::Valid
+        );
+        // The signature key cannot stand in for the counter-signing identity
+        assert_eq!(
+            verify_strong_name_with_key(&image, &signature_key.public_key().to_bytes()).unwrap(),
+            StrongNameStatus::Invalid
+        );
+
+        // Signing with the identity key instead is refused, and rejected
+        // when done anyway
+        let mut image = unsigned.clone();
+        assert!(matches!(
+            sign_strong_name(&mut image, SigningMode::KeyPair(&identity)),
+            Err(Error::InvalidStrongNameKey(_))
+        ));
+        sign_with_exponent(&mut image, CALG_SHA_256);
+        assert_eq!(
+            verify_strong_name(&image).unwrap(),
+            StrongNameStatus::Invalid
+        );
+
+        // A counter-signature made by the signature key itself is rejected
+        let args = signature_key
+            .signature_key_attribute_args(signature_key.public_key())
+            .unwrap();
+        let mut image = image_with_attributes(&identity_key, &attribute(args));
+        sign_strong_name(&mut image, SigningMode::KeyPair(&signature_key)).unwrap();
+        assert_eq!(
+            verify_strong_name(&image).unwrap(),
+            StrongNameStatus::Invalid
+        );
+
+        // So is a forged counter-signature
+        let (public_key, counter_signature) = identity
+            .signature_key_attribute_args(signature_key.public_key())
+            .unwrap();
+        let forged = format!("{}{}", &counter_signature[..2], "00".repeat(127));
+        let mut image = image_with_attributes(&identity_key, &attribute((public_key, forged)));
+        sign_strong_name(&mut image, SigningMode::KeyPair(&signature_key)).unwrap();
+        assert_eq!(
+            verify_strong_name(&image).unwrap(),
+            StrongNameStatus::Invalid
+        );
+    }
+}
@@ -96,7 +96,7 @@ impl TableContext {
     #[must_use]
     pub fn row_size(&self, table: TableId) -> usize {
         match table {
-            TableId::Module => 2 + self.string_index_size() * 2 + self.guid_index_size() * 3,
+            TableId::Module => 2 + self.string_index_size() + self.guid_index_size() * 3,
             TableId::TypeRef => {
                 self.coded_index_size(CodedIndexKind::ResolutionScope)
                     + self.string_index_size() * 2
@@ -181,6 +181,21 @@ impl TableContext {
             TableId::FieldRva => 4 + self.table_index_size(TableId::Field),
             TableId::EncLog => 4 + 4, // token + func_code
             TableId::EncMap => 4,     // token
+            TableId::AssemblyProcessor => 4,
+            TableId::AssemblyOs => 4 * 3,
+            TableId::AssemblyRefProcessor => 4 + self.table_index_size(TableId::AssemblyRef),
+            TableId::AssemblyRefOs => 4 * 3 + self.table_index_size(TableId::AssemblyRef),
+            TableId::File => 4 + self.string_index_size() + self.blob_index_size(),
+            TableId::ExportedType => {
+                4 + 4
+                    + self.string_index_size() * 2
+                    + self.coded_index_size(CodedIndexKind::Implementation)
+            }
+            TableId::ManifestResource => {
+                4 + 4
+                    + self.string_index_size()
+                    + self.coded_index_size(CodedIndexKind::Implementation)
+            }
             TableId::NestedClass => self.table_index_size(TableId::TypeDef) * 2,
             TableId::GenericParam => {
                 2 + 2
@@ -194,8 +209,38 @@ impl TableContext {
                 self.table_index_size(TableId::GenericParam)
                     + self.coded_index_size(CodedIndexKind::TypeDefOrRef)
             }
-            // Remaining tables return 0 (not implemented)
-            _ => 0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_sizes() {
+        let ctx = TableContext::new(0, [0; 64]);
+        assert_eq!(ctx.row_size(TableId::Module), 10);
+        assert_eq!(ctx.row_size(TableId::AssemblyProcessor), 4);
+        assert_eq!(ctx.row_size(TableId::AssemblyOs), 12);
+        assert_eq!(ctx.row_size(TableId::AssemblyRefProcessor), 6);
+        assert_eq!(ctx.row_size(TableId::AssemblyRefOs), 14);
+        assert_eq!(ctx.row_size(TableId::File), 8);
+        assert_eq!(ctx.row_size(TableId::ExportedType), 14);
+        assert_eq!(ctx.row_size(TableId::ManifestResource), 12);
+
+        // Every table has a size
+        for id in 0..=TableId::MAX {
+            if let Ok(table) = TableId::from_u8(id) {
+                assert_ne!(ctx.row_size(table), 0, "{table:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_row_sizes_wide_heaps() {
+        let ctx = TableContext::new(0x07, [0; 64]);
+        assert_eq!(ctx.row_size(TableId::Module), 2 + 4 + 4 * 3);
+        assert_eq!(ctx.row_size(TableId::File), 4 + 4 + 4);
+    }
+}
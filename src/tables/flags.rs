@@ -0,0 +1,44 @@
+//! Flag bits of TypeDef, MethodDef and Field rows.
+
+// TypeAttributes (ECMA-335 II.23.1.15)
+pub(crate) const TYPE_VISIBILITY_MASK: u32 = 0x0000_0007;
+pub(crate) const TYPE_PUBLIC: u32 = 0x0000_0001;
+pub(crate) const TYPE_NESTED_PUBLIC: u32 = 0x0000_0002;
+pub(crate) const TYPE_NESTED_FAMILY: u32 = 0x0000_0004;
+pub(crate) const TYPE_NESTED_FAM_OR_ASSEM: u32 = 0x0000_0007;
+pub(crate) const TYPE_LAYOUT_MASK: u32 = 0x0000_0018;
+pub(crate) const TYPE_INTERFACE: u32 = 0x0000_0020;
+pub(crate) const TYPE_ABSTRACT: u32 = 0x0000_0080;
+pub(crate) const TYPE_SEALED: u32 = 0x0000_0100;
+
+// Member access, shared by MethodAttributes and FieldAttributes
+// (ECMA-335 II.23.1.10, II.23.1.5)
+pub(crate) const MEMBER_ACCESS_MASK: u16 = 0x0007;
+pub(crate) const MEMBER_PRIVATE: u16 = 0x0001;
+pub(crate) const MEMBER_FAM_AND_ASSEM: u16 = 0x0002;
+pub(crate) const MEMBER_ASSEMBLY: u16 = 0x0003;
+pub(crate) const MEMBER_FAMILY: u16 = 0x0004;
+pub(crate) const MEMBER_FAM_OR_ASSEM: u16 = 0x0005;
+pub(crate) const MEMBER_PUBLIC: u16 = 0x0006;
+
+// MethodAttributes (ECMA-335 II.23.1.10)
+pub(crate) const METHOD_STATIC: u16 = 0x0010;
+pub(crate) const METHOD_FINAL: u16 = 0x0020;
+pub(crate) const METHOD_VIRTUAL: u16 = 0x0040;
+pub(crate) const METHOD_NEW_SLOT: u16 = 0x0100;
+pub(crate) const METHOD_ABSTRACT: u16 = 0x0400;
+pub(crate) const METHOD_SPECIAL_NAME: u16 = 0x0800;
+pub(crate) const METHOD_RT_SPECIAL_NAME: u16 = 0x1000;
+pub(crate) const METHOD_PINVOKE_IMPL: u16 = 0x2000;
+
+// MethodImplAttributes (ECMA-335 II.23.1.11)
+pub(crate) const METHOD_IMPL_CODE_TYPE_MASK: u16 = 0x0003;
+pub(crate) const METHOD_IMPL_INTERNAL_CALL: u16 = 0x1000;
+
+// FieldAttributes (ECMA-335 II.23.1.5)
+pub(crate) const FIELD_STATIC: u16 = 0x0010;
+pub(crate) const FIELD_INIT_ONLY: u16 = 0x0020;
+pub(crate) const FIELD_LITERAL: u16 = 0x0040;
+pub(crate) const FIELD_SPECIAL_NAME: u16 = 0x0200;
+pub(crate) const FIELD_RT_SPECIAL_NAME: u16 = 0x0400;
+pub(crate) const FIELD_HAS_DEFAULT: u16 = 0x8000;
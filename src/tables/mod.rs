@@ -2,12 +2,14 @@
 
 mod coded_index;
 mod context;
+mod flags;
 mod header;
 mod rows;
 mod table_id;
 
 pub use coded_index::{CodedIndex, CodedIndexKind};
 pub use context::TableContext;
+pub(crate) use flags::*;
 pub use header::TablesHeader;
 pub use rows::*;
 pub use table_id::TableId;
@@ -0,0 +1,675 @@
+//! Reflection type names.
+//!
+//! Custom attribute `System.Type` arguments, resource headers and
+//! configuration files name types with the grammar of `Type.GetType`:
+//! ``System.Collections.Generic.Dictionary`2[[System.String, mscorlib],[System.Int32, mscorlib]], mscorlib``.
+//! Nested types are separated with `+`, generic arguments are bracketed
+//! (and bracketed again when assembly-qualified), and `[]`, `[,]`, `[*]`, `*`
+//! and `&` suffixes denote arrays, pointers and byrefs. Special characters in
+//! names are escaped with a backslash.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::identity::AssemblyIdentity;
+use crate::metadata::{Metadata, ResolvedType};
+use crate::resolve::type_path;
+use crate::signature::{ElementType, TypeSig};
+use crate::tables::{CodedIndex, CodedIndexKind, TableId};
+
+/// Characters escaped with a backslash inside type names.
+const SPECIAL_CHARS: &[char] = &['\\', ',', '+', '&', '*', '[', ']'];
+
+/// Array, pointer or byref suffix of a type name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeNameModifier {
+    /// Single-dimensional zero-based array (`[]`).
+    SzArray,
+    /// Multi-dimensional array of the given rank (`[*]` for rank 1, `[,]`...).
+    Array(u32),
+    /// Unmanaged pointer (`*`).
+    Pointer,
+    /// Managed reference (`&`).
+    ByRef,
+}
+
+/// A parsed reflection type name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct TypeName {
+    /// Namespace of the outermost type (None if empty).
+    pub namespace: Option<String>,
+    /// Name of the outermost type, including any generic arity suffix.
+    pub name: String,
+    /// Names of nested types, outermost first.
+    pub nested: Vec<String>,
+    /// Generic type arguments.
+    pub generic_args: Vec<TypeName>,
+    /// Suffixes, applied in order to the element type.
+    pub modifiers: Vec<TypeNameModifier>,
+    /// Assembly containing the type, if qualified.
+    pub assembly: Option<AssemblyIdentity>,
+}
+
+impl TypeName {
+    /// Create a name for a top-level type.
+    #[must_use]
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self {
+            namespace: (!namespace.is_empty()).then(|| namespace.to_string()),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse a type name, optionally assembly-qualified.
+    pub fn parse(name: &str) -> Result<Self> {
+        name.parse()
+    }
+
+    /// Get the name without the assembly, as `Type.FullName` formats it.
+    #[must_use]
+    pub fn full_name(&self) -> String {
+        let mut out = String::new();
+        if let Some(namespace) = &self.namespace {
+            push_escaped(&mut out, namespace);
+            out.push('.');
+        }
+        push_escaped(&mut out, &self.name);
+        for nested in &self.nested {
+            out.push('+');
+            push_escaped(&mut out, nested);
+        }
+        if !self.generic_args.is_empty() {
+            out.push('[');
+            for (i, arg) in self.generic_args.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                if arg.assembly.is_some() {
+                    out.push('[');
+                    out.push_str(&arg.to_string());
+                    out.push(']');
+                } else {
+                    out.push_str(&arg.full_name());
+                }
+            }
+            out.push(']');
+        }
+        for modifier in &self.modifiers {
+            match modifier {
+                TypeNameModifier::SzArray => out.push_str("[]"),
+                TypeNameModifier::Array(1) => out.push_str("[*]"),
+                TypeNameModifier::Array(rank) => {
+                    out.push('[');
+                    out.extend(std::iter::repeat_n(',', rank.saturating_sub(1) as usize));
+                    out.push(']');
+                }
+                TypeNameModifier::Pointer => out.push('*'),
+                TypeNameModifier::ByRef => out.push('&'),
+            }
+        }
+        out
+    }
+
+    /// Get the (namespace, name) of the outermost type as stored in metadata.
+    #[must_use]
+    pub fn top_level(&self) -> (&str, &str) {
+        (self.namespace.as_deref().unwrap_or(""), &self.name)
+    }
+
+    /// Check whether the name has generic arguments or modifiers.
+    #[must_use]
+    pub fn is_constructed(&self) -> bool {
+        !self.generic_args.is_empty() || !self.modifiers.is_empty()
+    }
+}
+
+fn push_escaped(out: &mut String, name: &str) {
+    for c in name.chars() {
+        if SPECIAL_CHARS.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+/// Formats the assembly-qualified name if an assembly is known.
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.full_name())?;
+        if let Some(assembly) = &self.assembly {
+            write!(f, ", {assembly}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TypeName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let name = parser.type_name(Context::TopLevel)?;
+        parser.skip_whitespace();
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(name)
+    }
+}
+
+/// Where a type name appears, which decides how it may end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// The whole string; an assembly may follow.
+    TopLevel,
+    /// A `[...]` generic argument; an assembly may follow.
+    Bracketed,
+    /// An unbracketed generic argument; a comma starts the next argument.
+    Bare,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.input[self.pos..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        if self.bump() == Some(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{expected}'")))
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidTypeName(format!(
+            "{message} at offset {} in '{}'",
+            self.pos, self.input
+        ))
+    }
+
+    fn type_name(&mut self, context: Context) -> Result<TypeName> {
+        self.skip_whitespace();
+        let full = self.identifier()?;
+        let mut nested = Vec::new();
+        while self.peek() == Some('+') {
+            self.bump();
+            nested.push(self.identifier()?);
+        }
+        let (namespace, name) = match full.rfind('.') {
+            Some(dot) if dot > 0 => (Some(full[..dot].to_string()), full[dot + 1..].to_string()),
+            _ => (None, full),
+        };
+
+        let mut generic_args = Vec::new();
+        if self.peek() == Some('[') && !matches!(self.peek_second(), Some(']' | ',' | '*')) {
+            self.bump();
+            loop {
+                self.skip_whitespace();
+                let arg = if self.peek() == Some('[') {
+                    self.bump();
+                    let arg = self.type_name(Context::Bracketed)?;
+                    self.skip_whitespace();
+                    self.expect(']')?;
+                    arg
+                } else {
+                    self.type_name(Context::Bare)?
+                };
+                generic_args.push(arg);
+                self.skip_whitespace();
+                match self.bump() {
+                    Some(',') => {}
+                    Some(']') => break,
+                    _ => return Err(self.error("expected ',' or ']' in generic arguments")),
+                }
+            }
+        }
+
+        let mut modifiers = Vec::new();
+        loop {
+            let modifier = match self.peek() {
+                Some('*') => TypeNameModifier::Pointer,
+                Some('&') => TypeNameModifier::ByRef,
+                Some('[') => {
+                    self.bump();
+                    match self.peek() {
+                        Some(']') => TypeNameModifier::SzArray,
+                        Some('*') => {
+                            self.bump();
+                            TypeNameModifier::Array(1)
+                        }
+                        Some(',') => {
+                            let mut rank = 1;
+                            while self.peek() == Some(',') {
+                                self.bump();
+                                rank += 1;
+                            }
+                            TypeNameModifier::Array(rank)
+                        }
+                        _ => return Err(self.error("invalid array specifier")),
+                    }
+                }
+                _ => break,
+            };
+            if modifiers.last() == Some(&TypeNameModifier::ByRef) {
+                return Err(self.error("byref must be the last modifier"));
+            }
+            if modifier != TypeNameModifier::Pointer && modifier != TypeNameModifier::ByRef {
+                self.expect(']')?;
+            } else {
+                self.bump();
+            }
+            modifiers.push(modifier);
+        }
+
+        self.skip_whitespace();
+        let assembly = if context != Context::Bare && self.peek() == Some(',') {
+            self.bump();
+            let text = self.assembly_text(context == Context::Bracketed);
+            Some(AssemblyIdentity::parse(text.trim())?)
+        } else {
+            None
+        };
+
+        Ok(TypeName {
+            namespace,
+            name,
+            nested,
+            generic_args,
+            modifiers,
+            assembly,
+        })
+    }
+
+    /// Read a name up to the next unescaped special character.
+    fn identifier(&mut self) -> Result<String> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => {
+                    self.bump();
+                    let escaped = self
+                        .bump()
+                        .ok_or_else(|| self.error("incomplete escape sequence"))?;
+                    name.push(escaped);
+                }
+                c if SPECIAL_CHARS.contains(&c) => break,
+                c => {
+                    self.bump();
+                    name.push(c);
+                }
+            }
+        }
+        let trimmed = name.trim_end();
+        if trimmed.is_empty() {
+            return Err(self.error("expected type name"));
+        }
+        Ok(trimmed.to_string())
+    }
+
+    /// Read an assembly display name, up to the closing bracket if inside a
+    /// generic argument. Escapes are kept for [`AssemblyIdentity::parse`].
+    fn assembly_text(&mut self, bracketed: bool) -> &str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => {
+                    self.bump();
+                    self.bump();
+                }
+                ']' if bracketed => break,
+                _ => {
+                    self.bump();
+                }
+            }
+        }
+        &self.input[start..self.pos]
+    }
+}
+
+impl Metadata {
+    /// Get the reflection name of a type signature.
+    ///
+    /// Types are qualified with the assembly that defines them where it is
+    /// known, primitives with the core library. Returns `None` for generic
+    /// parameters and function pointers, which have no reflection name.
+    #[must_use]
+    pub fn type_sig_name(&self, sig: &TypeSig) -> Option<TypeName> {
+        let (element, modifier) = match sig {
+            TypeSig::Primitive(element) => {
+                let mut name = TypeName::new("System", primitive_name(*element)?);
+                name.assembly = self.corlib_identity();
+                return Some(name);
+            }
+            TypeSig::Class(coded) | TypeSig::ValueType(coded) => {
+                return self.type_def_or_ref_name(CodedIndex::decode(
+                    CodedIndexKind::TypeDefOrRef,
+                    *coded,
+                ));
+            }
+            TypeSig::GenericInst {
+                type_ref,
+                type_args,
+                ..
+            } => {
+                let mut name = self.type_def_or_ref_name(CodedIndex::decode(
+                    CodedIndexKind::TypeDefOrRef,
+                    *type_ref,
+                ))?;
+                name.generic_args = type_args
+                    .iter()
+                    .map(|arg| self.type_sig_name(arg))
+                    .collect::<Option<_>>()?;
+                return Some(name);
+            }
+            TypeSig::SzArray(element) => (element, TypeNameModifier::SzArray),
+            TypeSig::Array {
+                element_type, rank, ..
+            } => (element_type, TypeNameModifier::Array(*rank)),
+            TypeSig::Ptr(element) => (element, TypeNameModifier::Pointer),
+            TypeSig::ByRef(element) => (element, TypeNameModifier::ByRef),
+            TypeSig::Modified { inner, .. } | TypeSig::Pinned(inner) => {
+                return self.type_sig_name(inner);
+            }
+            TypeSig::Var(_) | TypeSig::MVar(_) | TypeSig::FnPtr(_) => return None,
+        };
+        let mut name = self.type_sig_name(element)?;
+        name.modifiers.push(modifier);
+        Some(name)
+    }
+
+    /// Get the reflection name of a type returned by [`Metadata::resolve_type`].
+    #[must_use]
+    pub fn resolved_type_name(&self, resolved: &ResolvedType) -> Option<TypeName> {
+        let (table, row) = match *resolved {
+            ResolvedType::TypeDef { index, .. } => (TableId::TypeDef, index),
+            ResolvedType::TypeRef { index, .. } => (TableId::TypeRef, index),
+            ResolvedType::TypeSpec { index, .. } => (TableId::TypeSpec, index),
+        };
+        self.type_def_or_ref_name(CodedIndex {
+            table: Some(table),
+            row,
+        })
+    }
+
+    /// Find the TypeDef or TypeRef row naming the type definition of a
+    /// reflection name. Generic arguments and modifiers are ignored.
+    ///
+    /// TypeDefs are searched if the name is unqualified or qualified with this
+    /// assembly; otherwise TypeRefs into the named assembly are.
+    #[must_use]
+    pub fn resolve_type_name(&self, name: &TypeName) -> Option<CodedIndex> {
+        let local = match &name.assembly {
+            None => true,
+            Some(assembly) => self
+                .assembly_identity()
+                .is_some_and(|own| own.name.eq_ignore_ascii_case(&assembly.name)),
+        };
+        if local {
+            let (namespace, top) = name.top_level();
+            let mut rid = self.find_type_def(namespace, top, None);
+            for nested in &name.nested {
+                rid = rid.and_then(|rid| self.find_type_def("", nested, Some(rid)));
+            }
+            if let Some(rid) = rid {
+                return Some(CodedIndex {
+                    table: Some(TableId::TypeDef),
+                    row: rid,
+                });
+            }
+        }
+
+        (1..=self.type_refs.len() as u32)
+            .map(|row| CodedIndex {
+                table: Some(TableId::TypeRef),
+                row,
+            })
+            .find(|&index| {
+                let Some(path) = type_path(self, index) else {
+                    return false;
+                };
+                let scope_matches = match &name.assembly {
+                    None => true,
+                    Some(assembly) => self
+                        .type_ref_assembly(index.row)
+                        .is_some_and(|scope| scope.name.eq_ignore_ascii_case(&assembly.name)),
+                };
+                scope_matches
+                    && path.len() == name.nested.len() + 1
+                    && path[0] == name.top_level()
+                    && path[1..].iter().zip(&name.nested).all(|((_, a), b)| a == b)
+            })
+    }
+
+    fn type_def_or_ref_name(&self, index: CodedIndex) -> Option<TypeName> {
+        if index.table? == TableId::TypeSpec {
+            let sig = self.type_spec_signature(index.row)?.ok()?;
+            return self.type_sig_name(&sig);
+        }
+        let path = type_path(self, index)?;
+        let (&(namespace, name), nested) = path.split_first()?;
+        let mut type_name = TypeName::new(namespace, name);
+        type_name.nested = nested.iter().map(|(_, name)| name.to_string()).collect();
+        type_name.assembly = match index.table? {
+            TableId::TypeRef => self.type_ref_assembly(index.row),
+            _ => self.assembly_identity(),
+        };
+        Some(type_name)
+    }
+
+    /// Get the assembly a TypeRef points into, following enclosing TypeRefs.
+    fn type_ref_assembly(&self, mut type_ref: u32) -> Option<AssemblyIdentity> {
+        for _ in 0..=self.type_refs.len() {
+            let scope = self.get_type_ref(type_ref)?.resolution_scope;
+            match scope.table {
+                Some(TableId::TypeRef) if !scope.is_null() => type_ref = scope.row,
+                Some(TableId::AssemblyRef) if !scope.is_null() => {
+                    return self.get_assembly_ref(scope.row).map(|r| r.identity());
+                }
+                _ => return self.assembly_identity(),
+            }
+        }
+        None
+    }
+}
+
+/// Get the `System` type name of a primitive element type.
+fn primitive_name(element: ElementType) -> Option<&'static str> {
+    Some(match element {
+        ElementType::Void => "Void",
+        ElementType::Boolean => "Boolean",
+        ElementType::Char => "Char",
+        ElementType::I1 => "SByte",
+        ElementType::U1 => "Byte",
+        ElementType::I2 => "Int16",
+        ElementType::U2 => "UInt16",
+        ElementType::I4 => "Int32",
+        ElementType::U4 => "UInt32",
+        ElementType::I8 => "Int64",
+        ElementType::U8 => "UInt64",
+        ElementType::R4 => "Single",
+        ElementType::R8 => "Double",
+        ElementType::String => "String",
+        ElementType::TypedByRef => "TypedReference",
+        ElementType::IntPtr => "IntPtr",
+        ElementType::UIntPtr => "UIntPtr",
+        ElementType::Object => "Object",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ilasm::assemble;
+    use crate::signature::FieldSig;
+
+    const DICTIONARY: &str = "System.Collections.Generic.Dictionary`2[[System.String, mscorlib],[System.Int32, mscorlib]], mscorlib";
+
+    #[test]
+    fn test_parse_generic() {
+        let name = TypeName::parse(DICTIONARY).unwrap();
+        assert_eq!(
+            name.namespace.as_deref(),
+            Some("System.Collections.Generic")
+        );
+        assert_eq!(name.name, "Dictionary`2");
+        assert_eq!(name.assembly.as_ref().unwrap().name, "mscorlib");
+        assert_eq!(name.generic_args.len(), 2);
+        assert_eq!(name.generic_args[1].top_level(), ("System", "Int32"));
+        assert_eq!(
+            name.generic_args[0].assembly.as_ref().unwrap().name,
+            "mscorlib"
+        );
+        assert_eq!(name.to_string(), DICTIONARY);
+        assert_eq!(
+            name.full_name(),
+            "System.Collections.Generic.Dictionary`2[[System.String, mscorlib],[System.Int32, mscorlib]]"
+        );
+
+        // Unqualified arguments may be bare
+        let bare = TypeName::parse("List`1[System.Int32][]").unwrap();
+        assert_eq!(bare.namespace, None);
+        assert_eq!(bare.generic_args[0].name, "Int32");
+        assert_eq!(bare.modifiers, [TypeNameModifier::SzArray]);
+        assert_eq!(bare.to_string(), "List`1[System.Int32][]");
+    }
+
+    #[test]
+    fn test_parse_modifiers_and_nesting() {
+        let name = TypeName::parse(
+            "Outer+Inner\\+Odd+Leaf[,][*]*&, Lib, Version=1.0.0.0, Culture=neutral, PublicKeyToken=null",
+        )
+        .unwrap();
+        assert_eq!(name.name, "Outer");
+        assert_eq!(name.nested, ["Inner+Odd", "Leaf"]);
+        assert_eq!(
+            name.modifiers,
+            [
+                TypeNameModifier::Array(2),
+                TypeNameModifier::Array(1),
+                TypeNameModifier::Pointer,
+                TypeNameModifier::ByRef
+            ]
+        );
+        assert_eq!(name.assembly.as_ref().unwrap().version, Some((1, 0, 0, 0)));
+        assert_eq!(
+            name.to_string(),
+            "Outer+Inner\\+Odd+Leaf[,][*]*&, Lib, Version=1.0.0.0, Culture=neutral, PublicKeyToken=null"
+        );
+
+        for bad in [
+            "",
+            "A[",
+            "A[[B]",
+            "A&*",
+            "A[x",
+            "A+",
+            "A[], Lib, Version=x",
+            "A]",
+            "A\\",
+        ] {
+            assert!(TypeName::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_names_from_metadata() {
+        let md = assemble(
+            r#"
+            .assembly extern mscorlib {
+                .publickeytoken = (B7 7A 5C 56 19 34 E0 89)
+                .ver 4:0:0:0
+            }
+            .assembly Sample { .ver 1:0:0:0 }
+            .class public Outer extends [mscorlib]System.Object {
+                .class nested public Inner extends [mscorlib]System.Object {}
+            }
+            .class public Holder extends [mscorlib]System.Object {
+                .field public class [mscorlib]System.Collections.Generic.List`1<int32[]> items
+                .field public class Outer/Inner[0...,0...] grid
+                .field public !0 open
+            }
+            "#,
+        )
+        .unwrap()
+        .metadata;
+
+        let holder = md.find_type("Holder", None).unwrap().0;
+        let fields: Vec<_> = md
+            .get_type_fields(holder)
+            .into_iter()
+            .map(|(_, field)| {
+                let blob = md.blobs.get(field.signature).unwrap();
+                FieldSig::parse_blob(blob).unwrap().field_type
+            })
+            .collect();
+
+        let corlib = "mscorlib, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089";
+        let items = md.type_sig_name(&fields[0]).unwrap();
+        assert_eq!(
+            items.to_string(),
+            format!("System.Collections.Generic.List`1[[System.Int32[], {corlib}]], {corlib}")
+        );
+        let grid = md.type_sig_name(&fields[1]).unwrap();
+        assert_eq!(
+            grid.to_string(),
+            "Outer+Inner[,], Sample, Version=1.0.0.0, Culture=neutral, PublicKeyToken=null"
+        );
+        assert!(md.type_sig_name(&fields[2]).is_none());
+
+        let base = md.get_base_type(holder).unwrap();
+        assert_eq!(
+            md.resolved_type_name(&base).unwrap().to_string(),
+            format!("System.Object, {corlib}")
+        );
+
+        // Names round-trip to rows
+        assert_eq!(
+            md.resolve_type_name(&grid).unwrap(),
+            CodedIndex {
+                table: Some(TableId::TypeDef),
+                row: md
+                    .find_type_def("", "Inner", md.find_type_def("", "Outer", None))
+                    .unwrap(),
+            }
+        );
+        let list = md.resolve_type_name(&items).unwrap();
+        assert_eq!(list.table, Some(TableId::TypeRef));
+        assert_eq!(
+            md.resolve_type_name(&TypeName::parse("System.Object, mscorlib").unwrap()),
+            md.get_type_def(holder).map(|row| row.extends)
+        );
+        assert!(
+            md.resolve_type_name(&TypeName::parse("System.Object, Other").unwrap())
+                .is_none()
+        );
+        assert!(
+            md.resolve_type_name(&TypeName::parse("Missing").unwrap())
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,1764 @@
+//! Metadata validation with structured diagnostics.
+//!
+//! [`Metadata::validate`] checks the tables against the rules of ECMA-335
+//! Partition II: heap and row indices, coded index tags, the ordering of
+//! sorted tables and member lists, flag combinations, constructor, enum and
+//! interface conventions, the nesting of types, and the signature blobs the
+//! rows reference. Each finding is a [`Diagnostic`] naming its [`Rule`], the
+//! row and column it applies to, and where possible how to fix it.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+use crate::signature::{ElementType, FieldSig, MethodSig, TypeSig};
+use crate::tables::{
+    CodedIndex, CodedIndexKind, FIELD_INIT_ONLY, FIELD_LITERAL, FIELD_RT_SPECIAL_NAME,
+    FIELD_SPECIAL_NAME, FIELD_STATIC, MEMBER_ACCESS_MASK, METHOD_ABSTRACT, METHOD_FINAL,
+    METHOD_NEW_SLOT, METHOD_RT_SPECIAL_NAME, METHOD_SPECIAL_NAME, METHOD_STATIC, METHOD_VIRTUAL,
+    TYPE_ABSTRACT, TYPE_INTERFACE, TYPE_LAYOUT_MASK, TYPE_NESTED_PUBLIC, TYPE_SEALED,
+    TYPE_VISIBILITY_MASK, TableId,
+};
+
+mod signature;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing; the metadata is valid.
+    Info,
+    /// Questionable but loadable, or valid only on some runtimes.
+    Warning,
+    /// Violates ECMA-335; the runtime may reject the module.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A validation rule, tied to the ECMA-335 clause that states it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+    /// The Module table has exactly one row.
+    ModuleRow,
+    /// String heap indices are in bounds.
+    StringIndex,
+    /// GUID heap indices are in bounds.
+    GuidIndex,
+    /// Blob heap indices are in bounds.
+    BlobIndex,
+    /// Simple table indices are in bounds.
+    RowIndex,
+    /// Coded indices use a tag valid for their column.
+    CodedIndexTag,
+    /// Coded indices point at existing rows.
+    CodedIndexRow,
+    /// Sorted tables are in key order.
+    SortOrder,
+    /// Tables that must be sorted are flagged in the `sorted` mask.
+    SortedFlag,
+    /// Member lists (field, method, param, event, property) are monotonic.
+    RunList,
+    /// No two TypeDefs share a name in the same scope.
+    DuplicateTypeDef,
+    /// TypeDef flags are a valid combination.
+    TypeFlags,
+    /// MethodDef flags are a valid combination.
+    MethodFlags,
+    /// Field flags are a valid combination.
+    FieldFlags,
+    /// Instance and type constructors follow their conventions.
+    Constructor,
+    /// Enums have the shape the runtime expects.
+    EnumShape,
+    /// Interface members are abstract virtual methods and static fields.
+    InterfaceMember,
+    /// NestedClass rows are consistent with type visibility.
+    NestedClass,
+    /// Types are not nested in themselves.
+    NestedClassCycle,
+    /// Signature blobs are well-formed and fully consumed.
+    Signature,
+    /// Signatures use the calling convention their table requires.
+    CallingConvention,
+    /// TypeDefOrRef tokens in signatures point at existing rows.
+    SignatureToken,
+    /// `!n` and `!!n` refer to declared generic parameters.
+    GenericParamIndex,
+    /// Generic instantiations supply one argument per generic parameter.
+    GenericArity,
+}
+
+impl Rule {
+    /// The ECMA-335 clause stating the rule.
+    #[must_use]
+    pub const fn clause(self) -> &'static str {
+        match self {
+            Self::ModuleRow => "II.22.30",
+            Self::StringIndex => "II.24.2.3",
+            Self::GuidIndex => "II.24.2.5",
+            Self::BlobIndex => "II.24.2.4",
+            Self::RowIndex => "II.22",
+            Self::CodedIndexTag | Self::CodedIndexRow | Self::SortedFlag => "II.24.2.6",
+            Self::SortOrder => "II.22",
+            Self::RunList => "II.22.37",
+            Self::DuplicateTypeDef | Self::TypeFlags => "II.22.37",
+            Self::MethodFlags | Self::Constructor => "II.22.26",
+            Self::FieldFlags => "II.22.15",
+            Self::EnumShape => "II.14.3",
+            Self::InterfaceMember => "II.12",
+            Self::NestedClass | Self::NestedClassCycle => "II.22.32",
+            Self::Signature => "II.23.2",
+            Self::CallingConvention => "II.23.2.1",
+            Self::SignatureToken => "II.23.2.8",
+            Self::GenericParamIndex => "II.23.2.12",
+            Self::GenericArity => "II.22.29",
+        }
+    }
+
+    /// Short kebab-case name of the rule.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ModuleRow => "module-row",
+            Self::StringIndex => "string-index",
+            Self::GuidIndex => "guid-index",
+            Self::BlobIndex => "blob-index",
+            Self::RowIndex => "row-index",
+            Self::CodedIndexTag => "coded-index-tag",
+            Self::CodedIndexRow => "coded-index-row",
+            Self::SortOrder => "sort-order",
+            Self::SortedFlag => "sorted-flag",
+            Self::RunList => "run-list",
+            Self::DuplicateTypeDef => "duplicate-type-def",
+            Self::TypeFlags => "type-flags",
+            Self::MethodFlags => "method-flags",
+            Self::FieldFlags => "field-flags",
+            Self::Constructor => "constructor",
+            Self::EnumShape => "enum-shape",
+            Self::InterfaceMember => "interface-member",
+            Self::NestedClass => "nested-class",
+            Self::NestedClassCycle => "nested-class-cycle",
+            Self::Signature => "signature",
+            Self::CallingConvention => "calling-convention",
+            Self::SignatureToken => "signature-token",
+            Self::GenericParamIndex => "generic-param-index",
+            Self::GenericArity => "generic-arity",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.clause(), self.name())
+    }
+}
+
+/// A validation finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How serious the finding is.
+    pub severity: Severity,
+    /// The rule that is violated.
+    pub rule: Rule,
+    /// Table of the offending row, if any.
+    pub table: Option<TableId>,
+    /// 1-based offending row, if any.
+    pub row: Option<u32>,
+    /// Offending column, if any.
+    pub column: Option<&'static str>,
+    /// Byte offset into the blob the column references, if the finding is
+    /// inside one.
+    pub offset: Option<usize>,
+    /// What is wrong.
+    pub message: String,
+    /// How to fix it, if known.
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic without a location.
+    #[must_use]
+    pub fn new(severity: Severity, rule: Rule, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            rule,
+            table: None,
+            row: None,
+            column: None,
+            offset: None,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    /// Set the table and 1-based row.
+    #[must_use]
+    pub fn at(mut self, table: TableId, row: u32) -> Self {
+        self.table = Some(table);
+        self.row = Some(row);
+        self
+    }
+
+    /// Set the column.
+    #[must_use]
+    pub fn column(mut self, column: &'static str) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    /// Set the byte offset into the referenced blob.
+    #[must_use]
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Set the fix hint.
+    #[must_use]
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Check if this is an error.
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}] ", self.severity, self.rule)?;
+        if let Some(table) = self.table {
+            f.write_str(table.name())?;
+            if let Some(row) = self.row {
+                write!(f, "[{row}]")?;
+            }
+            if let Some(column) = self.column {
+                write!(f, ".{column}")?;
+            }
+            if let Some(offset) = self.offset {
+                write!(f, "+{offset}")?;
+            }
+            f.write_str(": ")?;
+        }
+        f.write_str(&self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, " (hint: {hint})")?;
+        }
+        Ok(())
+    }
+}
+
+impl Metadata {
+    /// Validate the metadata against the ECMA-335 table rules.
+    ///
+    /// Returns every finding, errors and warnings alike. Metadata without
+    /// error diagnostics is valid.
+    #[must_use]
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.validate_heap_indices(&mut diagnostics);
+        self.validate_coded_indices(&mut diagnostics);
+        self.validate_sorted_tables(&mut diagnostics);
+        self.validate_run_lists(&mut diagnostics);
+        self.validate_nested_classes(&mut diagnostics);
+        self.validate_type_defs(&mut diagnostics);
+        self.validate_fields(&mut diagnostics);
+        self.validate_methods(&mut diagnostics);
+        self.validate_signatures(&mut diagnostics);
+        diagnostics
+    }
+
+    /// Validate the metadata, failing on the first error diagnostic.
+    pub fn validate_strict(&self) -> Result<()> {
+        match self.validate().into_iter().find(Diagnostic::is_error) {
+            Some(diagnostic) => Err(Error::ValidationError(diagnostic.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the sort key of each row of a table that ECMA-335 requires to be
+    /// sorted, or `None` for other tables. Coded index keys compare by their
+    /// encoded value, as the spec orders them.
+    pub(crate) fn sort_keys(&self, table: TableId) -> Option<Vec<(u32, u32)>> {
+        use CodedIndexKind as Kind;
+        let keys = match table {
+            TableId::InterfaceImpl => self
+                .interface_impls
+                .iter()
+                .map(|row| (row.class, row.interface.encode(Kind::TypeDefOrRef)))
+                .collect(),
+            TableId::Constant => self
+                .constants
+                .iter()
+                .map(|row| (row.parent.encode(Kind::HasConstant), 0))
+                .collect(),
+            TableId::CustomAttribute => self
+                .custom_attributes
+                .iter()
+                .map(|row| (row.parent.encode(Kind::HasCustomAttribute), 0))
+                .collect(),
+            TableId::FieldMarshal => self
+                .field_marshals
+                .iter()
+                .map(|row| (row.parent.encode(Kind::HasFieldMarshal), 0))
+                .collect(),
+            TableId::DeclSecurity => self
+                .decl_securities
+                .iter()
+                .map(|row| (row.parent.encode(Kind::HasDeclSecurity), 0))
+                .collect(),
+            TableId::ClassLayout => self
+                .class_layouts
+                .iter()
+                .map(|row| (row.parent, 0))
+                .collect(),
+            TableId::FieldLayout => self
+                .field_layouts
+                .iter()
+                .map(|row| (row.field, 0))
+                .collect(),
+            TableId::MethodSemantics => self
+                .method_semantics
+                .iter()
+                .map(|row| (row.association.encode(Kind::HasSemantics), 0))
+                .collect(),
+            TableId::MethodImpl => self.method_impls.iter().map(|row| (row.class, 0)).collect(),
+            TableId::ImplMap => self
+                .impl_maps
+                .iter()
+                .map(|row| (row.member_forwarded.encode(Kind::MemberForwarded), 0))
+                .collect(),
+            TableId::FieldRva => self.field_rvas.iter().map(|row| (row.field, 0)).collect(),
+            TableId::NestedClass => self
+                .nested_classes
+                .iter()
+                .map(|row| (row.nested_class, 0))
+                .collect(),
+            TableId::GenericParam => self
+                .generic_params
+                .iter()
+                .map(|row| {
+                    (
+                        row.owner.encode(Kind::TypeOrMethodDef),
+                        u32::from(row.number),
+                    )
+                })
+                .collect(),
+            TableId::GenericParamConstraint => self
+                .generic_param_constraints
+                .iter()
+                .map(|row| (row.owner, 0))
+                .collect(),
+            _ => return None,
+        };
+        Some(keys)
+    }
+
+    fn validate_heap_indices(&self, diagnostics: &mut Vec<Diagnostic>) {
+        match self.modules.len() {
+            1 => {}
+            0 => diagnostics.push(
+                Diagnostic::new(Severity::Error, Rule::ModuleRow, "Module table is empty")
+                    .hint("add the module definition row"),
+            ),
+            count => diagnostics.push(
+                Diagnostic::new(
+                    Severity::Error,
+                    Rule::ModuleRow,
+                    format!("Module table has {count} rows"),
+                )
+                .at(TableId::Module, 2),
+            ),
+        }
+
+        let mut strings = |table, row: usize, column, index: u32| {
+            if index != 0 && self.strings.get(index).is_err() {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::StringIndex,
+                        format!("invalid string index {index}"),
+                    )
+                    .at(table, row as u32 + 1)
+                    .column(column),
+                );
+            }
+        };
+        for (i, row) in self.modules.iter().enumerate() {
+            strings(TableId::Module, i, "name", row.name);
+        }
+        for (i, row) in self.type_refs.iter().enumerate() {
+            strings(TableId::TypeRef, i, "type_name", row.type_name);
+            strings(TableId::TypeRef, i, "type_namespace", row.type_namespace);
+        }
+        for (i, row) in self.type_defs.iter().enumerate() {
+            strings(TableId::TypeDef, i, "type_name", row.type_name);
+            strings(TableId::TypeDef, i, "type_namespace", row.type_namespace);
+        }
+        for (i, row) in self.fields.iter().enumerate() {
+            strings(TableId::Field, i, "name", row.name);
+        }
+        for (i, row) in self.method_defs.iter().enumerate() {
+            strings(TableId::MethodDef, i, "name", row.name);
+        }
+        for (i, row) in self.params.iter().enumerate() {
+            strings(TableId::Param, i, "name", row.name);
+        }
+        for (i, row) in self.member_refs.iter().enumerate() {
+            strings(TableId::MemberRef, i, "name", row.name);
+        }
+        for (i, row) in self.events.iter().enumerate() {
+            strings(TableId::Event, i, "name", row.name);
+        }
+        for (i, row) in self.properties.iter().enumerate() {
+            strings(TableId::Property, i, "name", row.name);
+        }
+        for (i, row) in self.module_refs.iter().enumerate() {
+            strings(TableId::ModuleRef, i, "name", row.name);
+        }
+        for (i, row) in self.impl_maps.iter().enumerate() {
+            strings(TableId::ImplMap, i, "import_name", row.import_name);
+        }
+        for (i, row) in self.assemblies.iter().enumerate() {
+            strings(TableId::Assembly, i, "name", row.name);
+            strings(TableId::Assembly, i, "culture", row.culture);
+        }
+        for (i, row) in self.assembly_refs.iter().enumerate() {
+            strings(TableId::AssemblyRef, i, "name", row.name);
+            strings(TableId::AssemblyRef, i, "culture", row.culture);
+        }
+        for (i, row) in self.files.iter().enumerate() {
+            strings(TableId::File, i, "name", row.name);
+        }
+        for (i, row) in self.exported_types.iter().enumerate() {
+            strings(TableId::ExportedType, i, "type_name", row.type_name);
+            strings(
+                TableId::ExportedType,
+                i,
+                "type_namespace",
+                row.type_namespace,
+            );
+        }
+        for (i, row) in self.manifest_resources.iter().enumerate() {
+            strings(TableId::ManifestResource, i, "name", row.name);
+        }
+        for (i, row) in self.generic_params.iter().enumerate() {
+            strings(TableId::GenericParam, i, "name", row.name);
+        }
+
+        for (i, row) in self.modules.iter().enumerate() {
+            if row.mvid != 0 && self.guids.get(row.mvid).is_err() {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::GuidIndex,
+                        format!("invalid GUID index {}", row.mvid),
+                    )
+                    .at(TableId::Module, i as u32 + 1)
+                    .column("mvid"),
+                );
+            }
+        }
+
+        let mut blobs = |table, row: usize, column, index: u32| {
+            if index != 0 && self.blobs.get(index).is_err() {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::BlobIndex,
+                        format!("invalid blob index {index}"),
+                    )
+                    .at(table, row as u32 + 1)
+                    .column(column),
+                );
+            }
+        };
+        for (i, row) in self.fields.iter().enumerate() {
+            blobs(TableId::Field, i, "signature", row.signature);
+        }
+        for (i, row) in self.method_defs.iter().enumerate() {
+            blobs(TableId::MethodDef, i, "signature", row.signature);
+        }
+        for (i, row) in self.member_refs.iter().enumerate() {
+            blobs(TableId::MemberRef, i, "signature", row.signature);
+        }
+        for (i, row) in self.constants.iter().enumerate() {
+            blobs(TableId::Constant, i, "value", row.value);
+        }
+        for (i, row) in self.custom_attributes.iter().enumerate() {
+            blobs(TableId::CustomAttribute, i, "value", row.value);
+        }
+        for (i, row) in self.field_marshals.iter().enumerate() {
+            blobs(TableId::FieldMarshal, i, "native_type", row.native_type);
+        }
+        for (i, row) in self.decl_securities.iter().enumerate() {
+            blobs(
+                TableId::DeclSecurity,
+                i,
+                "permission_set",
+                row.permission_set,
+            );
+        }
+        for (i, row) in self.stand_alone_sigs.iter().enumerate() {
+            blobs(TableId::StandAloneSig, i, "signature", row.signature);
+        }
+        for (i, row) in self.properties.iter().enumerate() {
+            blobs(TableId::Property, i, "type", row.property_type);
+        }
+        for (i, row) in self.type_specs.iter().enumerate() {
+            blobs(TableId::TypeSpec, i, "signature", row.signature);
+        }
+        for (i, row) in self.method_specs.iter().enumerate() {
+            blobs(TableId::MethodSpec, i, "instantiation", row.instantiation);
+        }
+        for (i, row) in self.assemblies.iter().enumerate() {
+            blobs(TableId::Assembly, i, "public_key", row.public_key);
+        }
+        for (i, row) in self.assembly_refs.iter().enumerate() {
+            blobs(
+                TableId::AssemblyRef,
+                i,
+                "public_key_or_token",
+                row.public_key_or_token,
+            );
+            blobs(TableId::AssemblyRef, i, "hash_value", row.hash_value);
+        }
+        for (i, row) in self.files.iter().enumerate() {
+            blobs(TableId::File, i, "hash_value", row.hash_value);
+        }
+    }
+
+    fn validate_coded_indices(&self, diagnostics: &mut Vec<Diagnostic>) {
+        use CodedIndexKind as Kind;
+        let mut check = |table, row: usize, column, index: &CodedIndex, kind: Kind| {
+            if index.is_null() {
+                return;
+            }
+            let row = row as u32 + 1;
+            match index.table {
+                Some(target) => {
+                    let max = self.table_row_count(target);
+                    if index.row > max {
+                        diagnostics.push(
+                            Diagnostic::new(
+                                Severity::Error,
+                                Rule::CodedIndexRow,
+                                format!(
+                                    "{kind:?} index points to {} row {} (max {max})",
+                                    target.name(),
+                                    index.row
+                                ),
+                            )
+                            .at(table, row)
+                            .column(column),
+                        );
+                    }
+                }
+                None => diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::CodedIndexTag,
+                        format!("invalid table tag for {kind:?}"),
+                    )
+                    .at(table, row)
+                    .column(column),
+                ),
+            }
+        };
+
+        for (i, row) in self.type_refs.iter().enumerate() {
+            let scope = &row.resolution_scope;
+            check(
+                TableId::TypeRef,
+                i,
+                "resolution_scope",
+                scope,
+                Kind::ResolutionScope,
+            );
+        }
+        for (i, row) in self.type_defs.iter().enumerate() {
+            check(
+                TableId::TypeDef,
+                i,
+                "extends",
+                &row.extends,
+                Kind::TypeDefOrRef,
+            );
+        }
+        for (i, row) in self.interface_impls.iter().enumerate() {
+            let interface = &row.interface;
+            check(
+                TableId::InterfaceImpl,
+                i,
+                "interface",
+                interface,
+                Kind::TypeDefOrRef,
+            );
+        }
+        for (i, row) in self.member_refs.iter().enumerate() {
+            check(
+                TableId::MemberRef,
+                i,
+                "class",
+                &row.class,
+                Kind::MemberRefParent,
+            );
+        }
+        for (i, row) in self.constants.iter().enumerate() {
+            check(
+                TableId::Constant,
+                i,
+                "parent",
+                &row.parent,
+                Kind::HasConstant,
+            );
+        }
+        for (i, row) in self.custom_attributes.iter().enumerate() {
+            let parent = &row.parent;
+            check(
+                TableId::CustomAttribute,
+                i,
+                "parent",
+                parent,
+                Kind::HasCustomAttribute,
+            );
+            let attr_type = &row.attr_type;
+            check(
+                TableId::CustomAttribute,
+                i,
+                "type",
+                attr_type,
+                Kind::CustomAttributeType,
+            );
+        }
+        for (i, row) in self.field_marshals.iter().enumerate() {
+            check(
+                TableId::FieldMarshal,
+                i,
+                "parent",
+                &row.parent,
+                Kind::HasFieldMarshal,
+            );
+        }
+        for (i, row) in self.decl_securities.iter().enumerate() {
+            check(
+                TableId::DeclSecurity,
+                i,
+                "parent",
+                &row.parent,
+                Kind::HasDeclSecurity,
+            );
+        }
+        for (i, row) in self.events.iter().enumerate() {
+            check(
+                TableId::Event,
+                i,
+                "event_type",
+                &row.event_type,
+                Kind::TypeDefOrRef,
+            );
+        }
+        for (i, row) in self.method_semantics.iter().enumerate() {
+            let association = &row.association;
+            check(
+                TableId::MethodSemantics,
+                i,
+                "association",
+                association,
+                Kind::HasSemantics,
+            );
+        }
+        for (i, row) in self.method_impls.iter().enumerate() {
+            let body = &row.method_body;
+            check(
+                TableId::MethodImpl,
+                i,
+                "method_body",
+                body,
+                Kind::MethodDefOrRef,
+            );
+            let declaration = &row.method_declaration;
+            check(
+                TableId::MethodImpl,
+                i,
+                "method_declaration",
+                declaration,
+                Kind::MethodDefOrRef,
+            );
+        }
+        for (i, row) in self.impl_maps.iter().enumerate() {
+            let member = &row.member_forwarded;
+            check(
+                TableId::ImplMap,
+                i,
+                "member_forwarded",
+                member,
+                Kind::MemberForwarded,
+            );
+        }
+        for (i, row) in self.exported_types.iter().enumerate() {
+            let implementation = &row.implementation;
+            check(
+                TableId::ExportedType,
+                i,
+                "implementation",
+                implementation,
+                Kind::Implementation,
+            );
+        }
+        for (i, row) in self.manifest_resources.iter().enumerate() {
+            let implementation = &row.implementation;
+            check(
+                TableId::ManifestResource,
+                i,
+                "implementation",
+                implementation,
+                Kind::Implementation,
+            );
+        }
+        for (i, row) in self.generic_params.iter().enumerate() {
+            check(
+                TableId::GenericParam,
+                i,
+                "owner",
+                &row.owner,
+                Kind::TypeOrMethodDef,
+            );
+        }
+        for (i, row) in self.method_specs.iter().enumerate() {
+            check(
+                TableId::MethodSpec,
+                i,
+                "method",
+                &row.method,
+                Kind::MethodDefOrRef,
+            );
+        }
+        for (i, row) in self.generic_param_constraints.iter().enumerate() {
+            let constraint = &row.constraint;
+            check(
+                TableId::GenericParamConstraint,
+                i,
+                "constraint",
+                constraint,
+                Kind::TypeDefOrRef,
+            );
+        }
+    }
+
+    fn validate_sorted_tables(&self, diagnostics: &mut Vec<Diagnostic>) {
+        for id in 0..TableId::COUNT as u8 {
+            let Ok(table) = TableId::from_u8(id) else {
+                continue;
+            };
+            let Some(keys) = self.sort_keys(table) else {
+                continue;
+            };
+            if keys.is_empty() {
+                continue;
+            }
+            if let Some(position) = keys.windows(2).position(|pair| pair[1] < pair[0]) {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::SortOrder,
+                        format!("{} table is not sorted by its key", table.name()),
+                    )
+                    .at(table, position as u32 + 2)
+                    .hint("sort the rows by their primary key column"),
+                );
+            }
+            if self.tables_header.sorted & (1u64 << id) == 0 {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Warning,
+                        Rule::SortedFlag,
+                        format!("{} table is not flagged as sorted", table.name()),
+                    )
+                    .hint(format!("set bit {id} of the sorted mask")),
+                );
+            }
+        }
+    }
+
+    fn validate_run_lists(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let list_len = |table: TableId, indirect: TableId| match self.table_row_count(indirect) {
+            0 => self.table_row_count(table),
+            count => count,
+        };
+        let mut check = |owner: TableId, column, target: TableId, max: u32, lists: Vec<u32>| {
+            let mut previous = 1;
+            for (i, &start) in lists.iter().enumerate() {
+                let row = i as u32 + 1;
+                if start == 0 || start > max + 1 {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Severity::Error,
+                            Rule::RowIndex,
+                            format!(
+                                "{} list starts at row {start} (max {})",
+                                target.name(),
+                                max + 1
+                            ),
+                        )
+                        .at(owner, row)
+                        .column(column),
+                    );
+                } else if start < previous {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Severity::Error,
+                            Rule::RunList,
+                            format!(
+                                "{} list starts at row {start}, before the previous row's {previous}",
+                                target.name()
+                            ),
+                        )
+                        .at(owner, row)
+                        .column(column)
+                        .hint(format!(
+                            "keep {} rows grouped by owner in owner order",
+                            target.name()
+                        )),
+                    );
+                } else {
+                    previous = start;
+                }
+            }
+        };
+
+        let fields = list_len(TableId::Field, TableId::FieldPtr);
+        let methods = list_len(TableId::MethodDef, TableId::MethodPtr);
+        let params = list_len(TableId::Param, TableId::ParamPtr);
+        let events = list_len(TableId::Event, TableId::EventPtr);
+        let properties = list_len(TableId::Property, TableId::PropertyPtr);
+        let type_defs = &self.type_defs;
+        check(
+            TableId::TypeDef,
+            "field_list",
+            TableId::Field,
+            fields,
+            type_defs.iter().map(|row| row.field_list).collect(),
+        );
+        check(
+            TableId::TypeDef,
+            "method_list",
+            TableId::MethodDef,
+            methods,
+            type_defs.iter().map(|row| row.method_list).collect(),
+        );
+        check(
+            TableId::MethodDef,
+            "param_list",
+            TableId::Param,
+            params,
+            self.method_defs.iter().map(|row| row.param_list).collect(),
+        );
+        check(
+            TableId::EventMap,
+            "event_list",
+            TableId::Event,
+            events,
+            self.event_maps.iter().map(|row| row.event_list).collect(),
+        );
+        check(
+            TableId::PropertyMap,
+            "property_list",
+            TableId::Property,
+            properties,
+            self.property_maps
+                .iter()
+                .map(|row| row.property_list)
+                .collect(),
+        );
+    }
+
+    fn validate_nested_classes(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let type_count = self.type_defs.len() as u32;
+        let mut enclosing = HashMap::new();
+        for (i, row) in self.nested_classes.iter().enumerate() {
+            let position = i as u32 + 1;
+            for (column, index) in [
+                ("nested_class", row.nested_class),
+                ("enclosing_class", row.enclosing_class),
+            ] {
+                if index == 0 || index > type_count {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Severity::Error,
+                            Rule::RowIndex,
+                            format!("invalid TypeDef index {index} (max {type_count})"),
+                        )
+                        .at(TableId::NestedClass, position)
+                        .column(column),
+                    );
+                }
+            }
+            if enclosing
+                .insert(row.nested_class, row.enclosing_class)
+                .is_some()
+            {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::NestedClass,
+                        format!(
+                            "TypeDef {} has more than one enclosing type",
+                            row.nested_class
+                        ),
+                    )
+                    .at(TableId::NestedClass, position)
+                    .column("nested_class"),
+                );
+            }
+        }
+
+        for (i, row) in self.nested_classes.iter().enumerate() {
+            let mut current = row.enclosing_class;
+            for _ in 0..=enclosing.len() {
+                if current == row.nested_class {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Severity::Error,
+                            Rule::NestedClassCycle,
+                            format!("TypeDef {} is nested in itself", row.nested_class),
+                        )
+                        .at(TableId::NestedClass, i as u32 + 1)
+                        .column("enclosing_class"),
+                    );
+                    break;
+                }
+                match enclosing.get(&current) {
+                    Some(&next) => current = next,
+                    None => break,
+                }
+            }
+        }
+
+        for (i, row) in self.type_defs.iter().enumerate() {
+            let index = i as u32 + 1;
+            let nested_visibility = row.flags & TYPE_VISIBILITY_MASK >= TYPE_NESTED_PUBLIC;
+            match (nested_visibility, enclosing.contains_key(&index)) {
+                (true, false) => diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::NestedClass,
+                        "type has nested visibility but no enclosing type",
+                    )
+                    .at(TableId::TypeDef, index)
+                    .column("flags")
+                    .hint("add a NestedClass row or use Public/NotPublic visibility"),
+                ),
+                (false, true) => diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Rule::NestedClass,
+                        "nested type has top-level visibility",
+                    )
+                    .at(TableId::TypeDef, index)
+                    .column("flags")
+                    .hint("use a Nested* visibility"),
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    fn validate_type_defs(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let enclosing: HashMap<u32, u32> = self
+            .nested_classes
+            .iter()
+            .map(|row| (row.nested_class, row.enclosing_class))
+            .collect();
+        let mut names = HashSet::new();
+        let mut error = |index: u32, rule, column, message: &str, hint: Option<&str>| {
+            let mut diagnostic = Diagnostic::new(Severity::Error, rule, message)
+                .at(TableId::TypeDef, index)
+                .column(column);
+            if let Some(hint) = hint {
+                diagnostic = diagnostic.hint(hint);
+            }
+            diagnostics.push(diagnostic);
+        };
+
+        for (i, row) in self.type_defs.iter().enumerate() {
+            let index = i as u32 + 1;
+            let flags = row.flags;
+            let name = self.strings.get(row.type_name).unwrap_or("");
+            let namespace = self.strings.get(row.type_namespace).unwrap_or("");
+
+            let outer = enclosing.get(&index).copied();
+            let namespace_key = if outer.is_some() { "" } else { namespace };
+            if !name.is_empty() && !names.insert((outer, namespace_key, name)) {
+                let full_name = if namespace_key.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{namespace}.{name}")
+                };
+                error(
+                    index,
+                    Rule::DuplicateTypeDef,
+                    "type_name",
+                    &format!("duplicate type {full_name}"),
+                    Some("rename or remove one of the types"),
+                );
+            }
+
+            if flags & TYPE_LAYOUT_MASK == TYPE_LAYOUT_MASK {
+                error(index, Rule::TypeFlags, "flags", "invalid layout", None);
+            }
+            if flags & TYPE_INTERFACE != 0 {
+                if flags & TYPE_ABSTRACT == 0 {
+                    error(
+                        index,
+                        Rule::TypeFlags,
+                        "flags",
+                        "interface is not abstract",
+                        Some("set the Abstract flag"),
+                    );
+                }
+                if flags & TYPE_SEALED != 0 {
+                    error(index, Rule::TypeFlags, "flags", "interface is sealed", None);
+                }
+                if !row.extends.is_null() {
+                    error(
+                        index,
+                        Rule::TypeFlags,
+                        "extends",
+                        "interface has a base type",
+                        Some("clear Extends; interfaces only implement other interfaces"),
+                    );
+                }
+            }
+        }
+
+        for index in 1..=self.type_defs.len() as u32 {
+            if self.is_enum(index) {
+                self.validate_enum(index, diagnostics);
+            }
+        }
+    }
+
+    /// Check if a TypeDef extends `System.Enum`.
+    fn is_enum(&self, index: u32) -> bool {
+        self.get_base_type(index)
+            .is_some_and(|base| !base.is_type_spec() && base.full_name() == "System.Enum")
+    }
+
+    fn validate_enum(&self, index: u32, diagnostics: &mut Vec<Diagnostic>) {
+        let row = &self.type_defs[(index - 1) as usize];
+        let mut push = |severity, table, row, column, message: &str| {
+            diagnostics.push(
+                Diagnostic::new(severity, Rule::EnumShape, message)
+                    .at(table, row)
+                    .column(column),
+            );
+        };
+        if row.flags & TYPE_SEALED == 0 {
+            push(
+                Severity::Error,
+                TableId::TypeDef,
+                index,
+                "flags",
+                "enum is not sealed",
+            );
+        }
+        if !self.get_type_methods(index).is_empty() {
+            push(
+                Severity::Error,
+                TableId::TypeDef,
+                index,
+                "method_list",
+                "enum has methods",
+            );
+        }
+        if self.interface_impls.iter().any(|row| row.class == index) {
+            push(
+                Severity::Error,
+                TableId::TypeDef,
+                index,
+                "flags",
+                "enum implements interfaces",
+            );
+        }
+
+        let fields = self.get_type_fields(index);
+        let instance: Vec<_> = fields
+            .iter()
+            .filter(|(_, field)| field.flags & FIELD_STATIC == 0)
+            .collect();
+        if instance.len() != 1 {
+            push(
+                Severity::Error,
+                TableId::TypeDef,
+                index,
+                "field_list",
+                &format!(
+                    "enum has {} instance fields instead of one value field",
+                    instance.len()
+                ),
+            );
+        }
+        for &&(field_index, field) in &instance {
+            let integral = FieldSig::parse_blob(self.blobs.get(field.signature).unwrap_or(&[]))
+                .is_ok_and(|sig| {
+                    matches!(
+                        sig.field_type,
+                        TypeSig::Primitive(
+                            ElementType::Boolean
+                                | ElementType::Char
+                                | ElementType::I1
+                                | ElementType::U1
+                                | ElementType::I2
+                                | ElementType::U2
+                                | ElementType::I4
+                                | ElementType::U4
+                                | ElementType::I8
+                                | ElementType::U8
+                                | ElementType::IntPtr
+                                | ElementType::UIntPtr
+                        )
+                    )
+                });
+            if !integral {
+                push(
+                    Severity::Error,
+                    TableId::Field,
+                    field_index,
+                    "signature",
+                    "enum value field is not of an integral type",
+                );
+            }
+            if self.strings.get(field.name).ok() != Some("value__") {
+                push(
+                    Severity::Warning,
+                    TableId::Field,
+                    field_index,
+                    "name",
+                    "enum value field is not named value__",
+                );
+            }
+            if field.flags & FIELD_RT_SPECIAL_NAME == 0 {
+                push(
+                    Severity::Warning,
+                    TableId::Field,
+                    field_index,
+                    "flags",
+                    "enum value field is not RTSpecialName",
+                );
+            }
+        }
+        for &(field_index, field) in &fields {
+            let static_only = field.flags & FIELD_STATIC != 0;
+            if static_only && field.flags & FIELD_LITERAL == 0 {
+                push(
+                    Severity::Error,
+                    TableId::Field,
+                    field_index,
+                    "flags",
+                    "enum member is static but not literal",
+                );
+            }
+        }
+    }
+
+    fn validate_fields(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let constant_parents: HashSet<u32> = self
+            .constants
+            .iter()
+            .filter(|row| row.parent.table == Some(TableId::Field))
+            .map(|row| row.parent.row)
+            .collect();
+        let interface_fields = self.interface_members(TableId::Field, |row| row.field_list);
+
+        for (i, row) in self.fields.iter().enumerate() {
+            let index = i as u32 + 1;
+            let flags = row.flags;
+            let mut error = |rule, message: &str, hint: Option<&str>| {
+                let mut diagnostic = Diagnostic::new(Severity::Error, rule, message)
+                    .at(TableId::Field, index)
+                    .column("flags");
+                if let Some(hint) = hint {
+                    diagnostic = diagnostic.hint(hint);
+                }
+                diagnostics.push(diagnostic);
+            };
+
+            if flags & MEMBER_ACCESS_MASK == MEMBER_ACCESS_MASK {
+                error(Rule::FieldFlags, "invalid field access", None);
+            }
+            if flags & FIELD_LITERAL != 0 {
+                if flags & FIELD_STATIC == 0 {
+                    error(
+                        Rule::FieldFlags,
+                        "literal field is not static",
+                        Some("set Static"),
+                    );
+                }
+                if flags & FIELD_INIT_ONLY != 0 {
+                    error(Rule::FieldFlags, "literal field is init-only", None);
+                }
+                if !constant_parents.contains(&index) {
+                    error(
+                        Rule::FieldFlags,
+                        "literal field has no constant value",
+                        Some("add a Constant row"),
+                    );
+                }
+            }
+            if flags & FIELD_RT_SPECIAL_NAME != 0 && flags & FIELD_SPECIAL_NAME == 0 {
+                error(
+                    Rule::FieldFlags,
+                    "RTSpecialName field is not SpecialName",
+                    Some("set SpecialName"),
+                );
+            }
+            if interface_fields.contains(&index) && flags & FIELD_STATIC == 0 {
+                error(
+                    Rule::InterfaceMember,
+                    "interface has an instance field",
+                    Some("make the field static"),
+                );
+            }
+        }
+    }
+
+    fn validate_methods(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let owners = self.member_owners(TableId::MethodDef, |row| row.method_list);
+        let mut type_initializers = HashSet::new();
+
+        for (i, row) in self.method_defs.iter().enumerate() {
+            let index = i as u32 + 1;
+            let flags = row.flags;
+            let owner_flags = owners
+                .get(i)
+                .copied()
+                .flatten()
+                .map_or(0, |owner| self.type_defs[(owner - 1) as usize].flags);
+            let in_interface = owner_flags & TYPE_INTERFACE != 0;
+            let mut push = |severity, rule, column, message: &str, hint: Option<&str>| {
+                let mut diagnostic = Diagnostic::new(severity, rule, message)
+                    .at(TableId::MethodDef, index)
+                    .column(column);
+                if let Some(hint) = hint {
+                    diagnostic = diagnostic.hint(hint);
+                }
+                diagnostics.push(diagnostic);
+            };
+
+            if flags & MEMBER_ACCESS_MASK == MEMBER_ACCESS_MASK {
+                push(
+                    Severity::Error,
+                    Rule::MethodFlags,
+                    "flags",
+                    "invalid member access",
+                    None,
+                );
+            }
+            let is_static = flags & METHOD_STATIC != 0;
+            let is_virtual = flags & METHOD_VIRTUAL != 0;
+            let is_abstract = flags & METHOD_ABSTRACT != 0;
+            // Static virtual methods are only valid in interfaces
+            if is_static
+                && flags & (METHOD_FINAL | METHOD_VIRTUAL | METHOD_NEW_SLOT) != 0
+                && !in_interface
+            {
+                push(
+                    Severity::Error,
+                    Rule::MethodFlags,
+                    "flags",
+                    "static method is virtual, final or new-slot",
+                    None,
+                );
+            }
+            if flags & (METHOD_ABSTRACT | METHOD_FINAL) != 0 && !is_virtual {
+                push(
+                    Severity::Error,
+                    Rule::MethodFlags,
+                    "flags",
+                    "abstract or final method is not virtual",
+                    Some("set Virtual"),
+                );
+            }
+            if is_abstract && row.rva != 0 {
+                push(
+                    Severity::Error,
+                    Rule::MethodFlags,
+                    "rva",
+                    "abstract method has a body",
+                    None,
+                );
+            }
+            if is_abstract && owner_flags & TYPE_ABSTRACT == 0 {
+                push(
+                    Severity::Error,
+                    Rule::MethodFlags,
+                    "flags",
+                    "abstract method in a non-abstract type",
+                    Some("make the type abstract"),
+                );
+            }
+            if flags & METHOD_RT_SPECIAL_NAME != 0 && flags & METHOD_SPECIAL_NAME == 0 {
+                push(
+                    Severity::Error,
+                    Rule::MethodFlags,
+                    "flags",
+                    "RTSpecialName method is not SpecialName",
+                    Some("set SpecialName"),
+                );
+            }
+
+            if in_interface && !is_static {
+                if !is_virtual {
+                    push(
+                        Severity::Error,
+                        Rule::InterfaceMember,
+                        "flags",
+                        "interface instance method is not virtual",
+                        Some("set Virtual"),
+                    );
+                } else if !is_abstract {
+                    push(
+                        Severity::Warning,
+                        Rule::InterfaceMember,
+                        "flags",
+                        "interface method has a default implementation",
+                        Some("requires a runtime with default interface methods"),
+                    );
+                }
+            }
+
+            let name = self.strings.get(row.name).unwrap_or("");
+            if name != ".ctor" && name != ".cctor" {
+                continue;
+            }
+            let special = METHOD_SPECIAL_NAME | METHOD_RT_SPECIAL_NAME;
+            if flags & special != special {
+                push(
+                    Severity::Error,
+                    Rule::Constructor,
+                    "flags",
+                    &format!("{name} is not SpecialName and RTSpecialName"),
+                    Some("set SpecialName and RTSpecialName"),
+                );
+            }
+            let signature = self
+                .blobs
+                .get(row.signature)
+                .ok()
+                .and_then(|blob| MethodSig::parse_blob(blob).ok());
+            let returns_void = signature
+                .as_ref()
+                .is_none_or(|sig| sig.return_type == TypeSig::Primitive(ElementType::Void));
+            if !returns_void {
+                push(
+                    Severity::Error,
+                    Rule::Constructor,
+                    "signature",
+                    &format!("{name} does not return void"),
+                    None,
+                );
+            }
+            if name == ".ctor" {
+                if is_static || is_virtual || is_abstract {
+                    push(
+                        Severity::Error,
+                        Rule::Constructor,
+                        "flags",
+                        ".ctor is static, virtual or abstract",
+                        None,
+                    );
+                }
+                if in_interface {
+                    push(
+                        Severity::Error,
+                        Rule::Constructor,
+                        "name",
+                        "interface has an instance constructor",
+                        None,
+                    );
+                }
+            } else {
+                if !is_static {
+                    push(
+                        Severity::Error,
+                        Rule::Constructor,
+                        "flags",
+                        ".cctor is not static",
+                        Some("set Static"),
+                    );
+                }
+                if signature.is_some_and(|sig| !sig.params.is_empty()) {
+                    push(
+                        Severity::Error,
+                        Rule::Constructor,
+                        "signature",
+                        ".cctor has parameters",
+                        None,
+                    );
+                }
+                if owners
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .is_some_and(|owner| !type_initializers.insert(owner))
+                {
+                    push(
+                        Severity::Error,
+                        Rule::Constructor,
+                        "name",
+                        "type has more than one .cctor",
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Map each member row to its owning TypeDef, following the run lists
+    /// through the Ptr table if there is one.
+    fn member_owners(
+        &self,
+        table: TableId,
+        list: impl Fn(&crate::tables::TypeDefRow) -> u32,
+    ) -> Vec<Option<u32>> {
+        let count = self.logical_row_count(table);
+        let mut owners = vec![None; self.table_row_count(table) as usize];
+        for (i, row) in self.type_defs.iter().enumerate() {
+            let start = list(row).max(1);
+            let end = self
+                .type_defs
+                .get(i + 1)
+                .map_or(count + 1, &list)
+                .min(count + 1);
+            for slot in start..end {
+                if let Some(member) = self.resolve_ptr(table, slot) {
+                    owners[member as usize - 1] = Some(i as u32 + 1);
+                }
+            }
+        }
+        owners
+    }
+
+    /// Get the 1-based member rows owned by interfaces.
+    fn interface_members(
+        &self,
+        table: TableId,
+        list: impl Fn(&crate::tables::TypeDefRow) -> u32,
+    ) -> HashSet<u32> {
+        self.member_owners(table, list)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, owner)| {
+                owner.is_some_and(|owner| {
+                    self.type_defs[(owner - 1) as usize].flags & TYPE_INTERFACE != 0
+                })
+            })
+            .map(|(i, _)| i as u32 + 1)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::{
+        ConstantRow, FieldPtrRow, FieldRow, InterfaceImplRow, MethodDefRow, MethodPtrRow,
+        ModuleRow, NestedClassRow, TypeDefRow, TypeRefRow,
+    };
+
+    /// Metadata with a module, `<Module>`, and TypeRefs to System.Object and
+    /// System.Enum (rows 1 and 2).
+    fn base_metadata() -> Metadata {
+        let mut md = Metadata::new();
+        let name = md.strings.add("Test.dll");
+        let mvid = md.guids.add(&[1; 16]);
+        md.modules.push(ModuleRow {
+            name,
+            mvid,
+            ..Default::default()
+        });
+        let system = md.strings.add("System");
+        for name in ["Object", "Enum"] {
+            let type_name = md.strings.add(name);
+            md.type_refs.push(TypeRefRow {
+                resolution_scope: CodedIndex::null(),
+                type_name,
+                type_namespace: system,
+            });
+        }
+        let type_name = md.strings.add("<Module>");
+        md.type_defs.push(TypeDefRow {
+            type_name,
+            field_list: 1,
+            method_list: 1,
+            ..Default::default()
+        });
+        md
+    }
+
+    fn add_type(md: &mut Metadata, name: &str, flags: u32, extends: CodedIndex) -> u32 {
+        let type_name = md.strings.add(name);
+        let type_namespace = md.strings.add("Tests");
+        md.type_defs.push(TypeDefRow {
+            flags,
+            type_name,
+            type_namespace,
+            extends,
+            field_list: md.fields.len() as u32 + 1,
+            method_list: md.method_defs.len() as u32 + 1,
+        });
+        md.type_defs.len() as u32
+    }
+
+    fn add_method(md: &mut Metadata, name: &str, flags: u16, rva: u32, signature: &[u8]) {
+        let name = md.strings.add(name);
+        let signature = md.blobs.add(signature);
+        md.method_defs.push(MethodDefRow {
+            rva,
+            impl_flags: 0,
+            flags,
+            name,
+            signature,
+            param_list: 1,
+        });
+    }
+
+    fn add_field(md: &mut Metadata, name: &str, flags: u16, signature: &[u8]) {
+        let name = md.strings.add(name);
+        let signature = md.blobs.add(signature);
+        md.fields.push(FieldRow {
+            flags,
+            name,
+            signature,
+        });
+    }
+
+    fn type_ref(row: u32) -> CodedIndex {
+        CodedIndex {
+            table: Some(TableId::TypeRef),
+            row,
+        }
+    }
+
+    fn rules(md: &Metadata) -> Vec<(Severity, Rule, Option<u32>)> {
+        md.validate()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.rule, diagnostic.row))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_metadata() {
+        let mut md = base_metadata();
+        add_type(&mut md, "Widget", 0x0010_0001, type_ref(1));
+        // .ctor: hasthis, 0 params, void
+        add_method(&mut md, ".ctor", 0x1886, 0x2050, &[0x20, 0x00, 0x01]);
+        add_method(&mut md, ".cctor", 0x1891, 0x2060, &[0x00, 0x00, 0x01]);
+        add_type(&mut md, "Color", 0x0000_0101, type_ref(2));
+        add_field(&mut md, "value__", 0x0606, &[0x06, 0x08]);
+        add_field(&mut md, "Red", 0x8056, &[0x06, 0x08]);
+        let value = md.blobs.add(&[0, 0, 0, 0]);
+        md.constants.push(ConstantRow {
+            constant_type: 0x08,
+            padding: 0,
+            parent: CodedIndex {
+                table: Some(TableId::Field),
+                row: 2,
+            },
+            value,
+        });
+
+        let diagnostics = md.validate();
+        assert!(diagnostics.is_empty(), "{diagnostics:#?}");
+        assert!(md.validate_strict().is_ok());
+    }
+
+    #[test]
+    fn test_index_rules() {
+        let mut md = Metadata::new();
+        let name = md.strings.add("Broken");
+        md.type_defs.push(TypeDefRow {
+            type_name: name,
+            type_namespace: 9999,
+            extends: CodedIndex {
+                table: None,
+                row: 3,
+            },
+            field_list: 5,
+            method_list: 1,
+            ..Default::default()
+        });
+        md.custom_attributes
+            .push(crate::tables::CustomAttributeRow {
+                parent: CodedIndex {
+                    table: Some(TableId::TypeDef),
+                    row: 7,
+                },
+                attr_type: CodedIndex::null(),
+                value: 9999,
+            });
+
+        let found = rules(&md);
+        assert!(found.contains(&(Severity::Error, Rule::ModuleRow, None)));
+        assert!(found.contains(&(Severity::Error, Rule::StringIndex, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::CodedIndexTag, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::CodedIndexRow, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::BlobIndex, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::RowIndex, Some(1))));
+
+        let message = md
+            .validate()
+            .into_iter()
+            .find(|diagnostic| diagnostic.rule == Rule::StringIndex)
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            message,
+            "error[II.24.2.3 string-index] TypeDef[1].type_namespace: invalid string index 9999"
+        );
+        assert!(matches!(
+            md.validate_strict(),
+            Err(Error::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_ordering_rules() {
+        let mut md = base_metadata();
+        let a = add_type(&mut md, "A", 0x0010_0001, type_ref(1));
+        add_field(&mut md, "x", 0x0001, &[0x06, 0x08]);
+        add_field(&mut md, "y", 0x0001, &[0x06, 0x08]);
+        let b = add_type(&mut md, "B", 0x0010_0001, type_ref(1));
+        md.type_defs[(b - 1) as usize].field_list = 2;
+        md.type_defs[(a - 1) as usize].field_list = 3;
+
+        for class in [b, a] {
+            md.interface_impls.push(InterfaceImplRow {
+                class,
+                interface: type_ref(1),
+            });
+        }
+        md.tables_header.sorted &= !(1 << TableId::InterfaceImpl as u8);
+
+        let found = rules(&md);
+        assert!(found.contains(&(Severity::Error, Rule::RunList, Some(b))));
+        assert!(found.contains(&(Severity::Error, Rule::SortOrder, Some(2))));
+        assert!(found.contains(&(Severity::Warning, Rule::SortedFlag, None)));
+    }
+
+    #[test]
+    fn test_type_rules() {
+        let mut md = base_metadata();
+        add_type(&mut md, "Dup", 0x0010_0001, type_ref(1));
+        let duplicate = add_type(&mut md, "Dup", 0x0010_0001, type_ref(1));
+        // Interface that is neither abstract nor base-less
+        let interface = add_type(&mut md, "IBad", 0x0000_00A1 & !0x80, type_ref(1));
+        add_method(&mut md, "Run", 0x0086, 0x2050, &[0x20, 0x00, 0x01]);
+
+        let outer = add_type(&mut md, "Outer", 0x0010_0001, type_ref(1));
+        let inner = add_type(&mut md, "Inner", 0x0010_0002, type_ref(1));
+        md.nested_classes.push(NestedClassRow {
+            nested_class: outer,
+            enclosing_class: inner,
+        });
+        md.nested_classes.push(NestedClassRow {
+            nested_class: inner,
+            enclosing_class: outer,
+        });
+
+        let found = rules(&md);
+        assert!(found.contains(&(Severity::Error, Rule::DuplicateTypeDef, Some(duplicate))));
+        assert!(found.contains(&(Severity::Error, Rule::TypeFlags, Some(interface))));
+        assert!(found.contains(&(Severity::Error, Rule::InterfaceMember, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::NestedClassCycle, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::NestedClassCycle, Some(2))));
+        // Outer is nested (in Inner) but has top-level visibility
+        assert!(found.contains(&(Severity::Error, Rule::NestedClass, Some(outer))));
+    }
+
+    #[test]
+    fn test_member_rules() {
+        let mut md = base_metadata();
+        let class = add_type(&mut md, "Members", 0x0010_0001, type_ref(1));
+        // Static .ctor returning int32
+        add_method(&mut md, ".ctor", 0x1896, 0x2050, &[0x00, 0x00, 0x08]);
+        // .cctor that is an instance method with a parameter
+        add_method(&mut md, ".cctor", 0x1881, 0x2060, &[0x20, 0x01, 0x01, 0x08]);
+        // Abstract method with a body in a non-abstract type
+        add_method(&mut md, "Run", 0x04C6, 0x2070, &[0x20, 0x00, 0x01]);
+        // Final method that is not virtual
+        add_method(&mut md, "Sealed", 0x0026, 0x2080, &[0x20, 0x00, 0x01]);
+        // Literal instance field without a constant
+        add_field(&mut md, "Answer", 0x0041, &[0x06, 0x08]);
+
+        let enumeration = add_type(&mut md, "Shape", 0x0000_0001, type_ref(2));
+        add_field(&mut md, "value__", 0x0606, &[0x06, 0x0E]);
+        add_field(&mut md, "Extra", 0x0001, &[0x06, 0x08]);
+
+        let found = rules(&md);
+        assert!(found.contains(&(Severity::Error, Rule::Constructor, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::Constructor, Some(2))));
+        assert!(found.contains(&(Severity::Error, Rule::MethodFlags, Some(3))));
+        assert!(found.contains(&(Severity::Error, Rule::MethodFlags, Some(4))));
+        assert!(found.contains(&(Severity::Error, Rule::FieldFlags, Some(1))));
+        assert!(found.contains(&(Severity::Error, Rule::EnumShape, Some(enumeration))));
+        // value__ is a string
+        assert!(found.contains(&(Severity::Error, Rule::EnumShape, Some(2))));
+        assert!(class < enumeration);
+
+        let hint = md
+            .validate()
+            .into_iter()
+            .find(|diagnostic| diagnostic.rule == Rule::FieldFlags)
+            .and_then(|diagnostic| diagnostic.hint);
+        assert!(hint.is_some());
+    }
+
+    #[test]
+    fn test_uncompressed_member_lists() {
+        let mut md = base_metadata();
+        let shape = add_type(&mut md, "IShape", 0x0000_00A1, CodedIndex::null());
+        let circle = add_type(&mut md, "Circle", 0x0010_0001, type_ref(1));
+        // Rows appended out of list order: Circle's members first
+        add_method(&mut md, "Area", 0x0086, 0x2050, &[0x20, 0x00, 0x0D]);
+        add_method(&mut md, "Area", 0x05C6, 0, &[0x20, 0x00, 0x0D]);
+        add_field(&mut md, "radius", 0x0001, &[0x06, 0x0D]);
+        add_field(&mut md, "Unit", 0x0016, &[0x06, 0x0D]);
+        for row in [2, 1] {
+            md.method_ptrs.push(MethodPtrRow { method: row });
+            md.field_ptrs.push(FieldPtrRow { field: row });
+        }
+        for (type_def, list) in [(shape, 1), (circle, 2)] {
+            md.type_defs[type_def as usize - 1].method_list = list;
+            md.type_defs[type_def as usize - 1].field_list = list;
+        }
+        md.tables_header.uncompressed = true;
+
+        let owned = |md: &Metadata, type_def| -> Vec<u16> {
+            md.get_type_methods(type_def)
+                .into_iter()
+                .map(|(_, row)| row.flags)
+                .collect()
+        };
+        assert_eq!(owned(&md, shape), [0x05C6]);
+        assert!(md.validate().is_empty(), "{:?}", rules(&md));
+
+        md.compress_tables();
+        assert_eq!(owned(&md, shape), [0x05C6]);
+        assert!(md.validate().is_empty(), "{:?}", rules(&md));
+    }
+}
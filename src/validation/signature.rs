@@ -0,0 +1,893 @@
+//! Signature blob validation.
+//!
+//! Decodes every signature a table row references with the grammar of its
+//! column (II.23.2), checking calling conventions, embedded TypeDefOrRef
+//! tokens, generic parameter numbers and that the blob is fully consumed.
+//! Findings carry the byte offset into the blob where decoding failed.
+
+use std::collections::HashMap;
+
+use super::{Diagnostic, Rule, Severity};
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::reader::Reader;
+use crate::signature::{CallingConvention, ElementType, MethodSig, TypeSig};
+use crate::tables::{CodedIndex, CodedIndexKind, TableId};
+
+/// A signature finding at a byte offset into the blob.
+struct SigError {
+    offset: usize,
+    severity: Severity,
+    rule: Rule,
+    message: String,
+}
+
+type Check<T> = std::result::Result<T, SigError>;
+
+/// Where a type appears, which limits the element types allowed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Position {
+    Return,
+    Param,
+    Local,
+    Other,
+}
+
+/// Which method signature grammar applies.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MethodKind {
+    /// MethodDefSig: managed conventions, no sentinel.
+    Def,
+    /// MethodRefSig: managed conventions, sentinel for varargs.
+    Ref,
+    /// StandAloneMethodSig and function pointers: unmanaged conventions too.
+    StandAlone,
+}
+
+struct SigChecker<'a> {
+    md: &'a Metadata,
+    reader: Reader<'a>,
+    /// Generic parameter count of the enclosing type, if known.
+    type_arity: Option<u32>,
+    /// Generic parameter count of the enclosing method, if known.
+    method_arity: Option<u32>,
+    /// Generic parameter count of each TypeDef and MethodDef.
+    arities: &'a HashMap<(TableId, u32), u32>,
+    warnings: Vec<SigError>,
+}
+
+impl<'a> SigChecker<'a> {
+    fn new(
+        md: &'a Metadata,
+        blob: &'a [u8],
+        arities: &'a HashMap<(TableId, u32), u32>,
+        type_arity: Option<u32>,
+        method_arity: Option<u32>,
+    ) -> Self {
+        Self {
+            md,
+            reader: Reader::new(blob),
+            type_arity,
+            method_arity,
+            arities,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn error(offset: usize, rule: Rule, message: impl Into<String>) -> SigError {
+        SigError {
+            offset,
+            severity: Severity::Error,
+            rule,
+            message: message.into(),
+        }
+    }
+
+    fn malformed(&self, offset: usize, error: &Error) -> SigError {
+        let message = match error {
+            Error::UnexpectedEof { .. } => "signature ends unexpectedly".to_string(),
+            error => error.to_string(),
+        };
+        Self::error(offset.min(self.reader.len()), Rule::Signature, message)
+    }
+
+    fn read_u8(&mut self) -> Check<u8> {
+        let offset = self.reader.position();
+        self.reader
+            .read_u8()
+            .map_err(|error| self.malformed(offset, &error))
+    }
+
+    fn peek_u8(&self) -> Option<u8> {
+        self.reader.peek_u8().ok()
+    }
+
+    fn read_uint(&mut self) -> Check<u32> {
+        let offset = self.reader.position();
+        self.reader
+            .read_compressed_uint()
+            .map_err(|error| self.malformed(offset, &error))
+    }
+
+    /// Read the number of items that follow, each at least a byte long.
+    fn read_count(&mut self) -> Check<u32> {
+        let offset = self.reader.position();
+        let value = self.read_uint()?;
+        if value as usize > self.reader.remaining() {
+            return Err(Self::error(
+                offset,
+                Rule::Signature,
+                format!(
+                    "count {value} exceeds the {} remaining bytes",
+                    self.reader.remaining()
+                ),
+            ));
+        }
+        Ok(value)
+    }
+
+    fn read_int(&mut self) -> Check<i32> {
+        let offset = self.reader.position();
+        self.reader
+            .read_compressed_int()
+            .map_err(|error| self.malformed(offset, &error))
+    }
+
+    /// Read a TypeDefOrRef token, returning its table and row.
+    fn token(&mut self) -> Check<(TableId, u32)> {
+        let offset = self.reader.position();
+        let value = self.read_uint()?;
+        let index = CodedIndex::decode(CodedIndexKind::TypeDefOrRef, value);
+        let Some(table) = index.table else {
+            return Err(Self::error(
+                offset,
+                Rule::SignatureToken,
+                format!("invalid TypeDefOrRef tag in token 0x{value:X}"),
+            ));
+        };
+        let max = self.md.table_row_count(table);
+        if index.row == 0 || index.row > max {
+            return Err(Self::error(
+                offset,
+                Rule::SignatureToken,
+                format!(
+                    "token points to {} row {} (max {max})",
+                    table.name(),
+                    index.row
+                ),
+            ));
+        }
+        Ok((table, index.row))
+    }
+
+    /// Read custom modifiers preceding a type.
+    fn custom_mods(&mut self) -> Check<()> {
+        while matches!(
+            self.peek_u8(),
+            Some(b) if b == ElementType::CModReqd as u8 || b == ElementType::CModOpt as u8
+        ) {
+            self.read_u8()?;
+            self.token()?;
+        }
+        Ok(())
+    }
+
+    fn type_sig(&mut self, position: Position) -> Check<()> {
+        self.custom_mods()?;
+        let offset = self.reader.position();
+        let element = self.read_u8()?;
+        let Some(element_type) = ElementType::from_u8(element) else {
+            return Err(Self::error(
+                offset,
+                Rule::Signature,
+                format!("invalid element type 0x{element:02X}"),
+            ));
+        };
+        match element_type {
+            ElementType::Void if position != Position::Return => Err(Self::error(
+                offset,
+                Rule::Signature,
+                "void outside a return type or pointer",
+            )),
+            ElementType::TypedByRef if position == Position::Other => Err(Self::error(
+                offset,
+                Rule::Signature,
+                "typedref outside a return, parameter or local type",
+            )),
+            ElementType::Void
+            | ElementType::TypedByRef
+            | ElementType::Boolean
+            | ElementType::Char
+            | ElementType::I1
+            | ElementType::U1
+            | ElementType::I2
+            | ElementType::U2
+            | ElementType::I4
+            | ElementType::U4
+            | ElementType::I8
+            | ElementType::U8
+            | ElementType::R4
+            | ElementType::R8
+            | ElementType::String
+            | ElementType::IntPtr
+            | ElementType::UIntPtr
+            | ElementType::Object => Ok(()),
+            ElementType::Class | ElementType::ValueType => self.token().map(drop),
+            ElementType::Ptr => {
+                self.custom_mods()?;
+                if self.peek_u8() == Some(ElementType::Void as u8) {
+                    self.read_u8().map(drop)
+                } else {
+                    self.type_sig(Position::Other)
+                }
+            }
+            ElementType::ByRef if position == Position::Other => Err(Self::error(
+                offset,
+                Rule::Signature,
+                "byref nested inside another type",
+            )),
+            ElementType::ByRef => self.type_sig(Position::Other),
+            ElementType::Pinned if position != Position::Local => Err(Self::error(
+                offset,
+                Rule::Signature,
+                "pinned outside a local variable signature",
+            )),
+            ElementType::Pinned => self.type_sig(Position::Local),
+            ElementType::SzArray => self.type_sig(Position::Other),
+            ElementType::Array => self.array_shape(),
+            ElementType::GenericInst => self.generic_inst(),
+            ElementType::Var => self.generic_param(offset, false),
+            ElementType::MVar => self.generic_param(offset, true),
+            ElementType::FnPtr => self.method_sig(MethodKind::StandAlone).map(drop),
+            _ => Err(Self::error(
+                offset,
+                Rule::Signature,
+                format!("unexpected element type {}", element_type.name()),
+            )),
+        }
+    }
+
+    fn array_shape(&mut self) -> Check<()> {
+        self.type_sig(Position::Other)?;
+        let offset = self.reader.position();
+        let rank = self.read_uint()?;
+        if rank == 0 {
+            return Err(Self::error(offset, Rule::Signature, "array rank is zero"));
+        }
+        let offset = self.reader.position();
+        let sizes = self.read_count()?;
+        for _ in 0..sizes {
+            self.read_uint()?;
+        }
+        let bounds_offset = self.reader.position();
+        let bounds = self.read_count()?;
+        for _ in 0..bounds {
+            self.read_int()?;
+        }
+        if sizes > rank || bounds > rank {
+            return Err(Self::error(
+                if sizes > rank { offset } else { bounds_offset },
+                Rule::Signature,
+                format!("array of rank {rank} has {sizes} sizes and {bounds} lower bounds"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn generic_inst(&mut self) -> Check<()> {
+        let offset = self.reader.position();
+        let kind = self.read_u8()?;
+        if kind != ElementType::Class as u8 && kind != ElementType::ValueType as u8 {
+            return Err(Self::error(
+                offset,
+                Rule::Signature,
+                format!("generic instantiation of element type 0x{kind:02X}"),
+            ));
+        }
+        let (table, row) = self.token()?;
+        let count_offset = self.reader.position();
+        let count = self.read_count()?;
+        let expected = match table {
+            TableId::TypeDef => Some(self.arities.get(&(table, row)).copied().unwrap_or(0)),
+            _ => None,
+        };
+        if count == 0 || expected.is_some_and(|expected| expected != count) {
+            return Err(Self::error(
+                count_offset,
+                Rule::GenericArity,
+                format!(
+                    "{count} type arguments for a type with {} generic parameters",
+                    expected.map_or_else(|| "at least one".to_string(), |n| n.to_string())
+                ),
+            ));
+        }
+        for _ in 0..count {
+            self.type_sig(Position::Other)?;
+        }
+        Ok(())
+    }
+
+    fn generic_param(&mut self, offset: usize, method: bool) -> Check<()> {
+        let number = self.read_uint()?;
+        let (arity, owner, prefix) = if method {
+            (self.method_arity, "method", "!!")
+        } else {
+            (self.type_arity, "type", "!")
+        };
+        match arity {
+            Some(arity) if number >= arity => Err(Self::error(
+                offset,
+                Rule::GenericParamIndex,
+                format!("{prefix}{number} used in a {owner} with {arity} generic parameters"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a method signature after its calling convention byte, returning
+    /// the declared generic parameter count.
+    fn method_sig(&mut self, kind: MethodKind) -> Check<u32> {
+        let offset = self.reader.position();
+        let cc = self.read_u8()?;
+        let convention = cc & 0x0F;
+        let allowed = match kind {
+            MethodKind::Def | MethodKind::Ref => {
+                convention == CallingConvention::DEFAULT || convention == CallingConvention::VARARG
+            }
+            MethodKind::StandAlone => convention <= CallingConvention::VARARG,
+        };
+        if !allowed || cc & 0x80 != 0 {
+            return Err(Self::error(
+                offset,
+                Rule::CallingConvention,
+                format!("calling convention 0x{cc:02X} is not a method convention"),
+            ));
+        }
+        if cc & CallingConvention::EXPLICIT_THIS != 0 && cc & CallingConvention::HAS_THIS == 0 {
+            return Err(Self::error(
+                offset,
+                Rule::CallingConvention,
+                "EXPLICITTHIS without HASTHIS",
+            ));
+        }
+        let generic_count = if cc & CallingConvention::GENERIC != 0 {
+            if kind == MethodKind::StandAlone {
+                return Err(Self::error(
+                    offset,
+                    Rule::CallingConvention,
+                    "generic stand-alone method signature",
+                ));
+            }
+            let count_offset = self.reader.position();
+            let count = self.read_count()?;
+            if count == 0 {
+                return Err(Self::error(
+                    count_offset,
+                    Rule::GenericArity,
+                    "generic method signature with no generic parameters",
+                ));
+            }
+            count
+        } else {
+            0
+        };
+        // MVARs in a MethodRefSig refer to the referenced method's parameters
+        let outer_method_arity = self.method_arity;
+        if kind != MethodKind::StandAlone && self.method_arity.is_none() {
+            self.method_arity = Some(generic_count);
+        }
+        let param_count = self.read_count()?;
+        self.type_sig(Position::Return)?;
+        let vararg = convention == CallingConvention::VARARG || convention == 0x01;
+        let mut sentinel = false;
+        for _ in 0..param_count {
+            if self.peek_u8() == Some(ElementType::Sentinel as u8) {
+                let offset = self.reader.position();
+                if kind == MethodKind::Def || !vararg || sentinel {
+                    return Err(Self::error(
+                        offset,
+                        Rule::Signature,
+                        "sentinel outside the call site of a vararg method",
+                    ));
+                }
+                self.read_u8()?;
+                sentinel = true;
+            }
+            self.type_sig(Position::Param)?;
+        }
+        self.method_arity = outer_method_arity;
+        Ok(generic_count)
+    }
+
+    fn field_sig(&mut self) -> Check<()> {
+        let offset = self.reader.position();
+        let cc = self.read_u8()?;
+        if cc != CallingConvention::FIELD {
+            return Err(Self::error(
+                offset,
+                Rule::CallingConvention,
+                format!("calling convention 0x{cc:02X} is not FIELD"),
+            ));
+        }
+        self.type_sig(Position::Param)
+    }
+
+    fn property_sig(&mut self) -> Check<()> {
+        let offset = self.reader.position();
+        let cc = self.read_u8()?;
+        if cc & !CallingConvention::HAS_THIS != CallingConvention::PROPERTY {
+            return Err(Self::error(
+                offset,
+                Rule::CallingConvention,
+                format!("calling convention 0x{cc:02X} is not PROPERTY"),
+            ));
+        }
+        let count = self.read_count()?;
+        self.type_sig(Position::Return)?;
+        for _ in 0..count {
+            self.type_sig(Position::Param)?;
+        }
+        Ok(())
+    }
+
+    fn stand_alone_sig(&mut self) -> Check<()> {
+        if self.peek_u8() != Some(CallingConvention::LOCAL_SIG) {
+            return self.method_sig(MethodKind::StandAlone).map(drop);
+        }
+        self.read_u8()?;
+        let count = self.read_count()?;
+        for _ in 0..count {
+            self.type_sig(Position::Local)?;
+        }
+        Ok(())
+    }
+
+    fn type_spec(&mut self) -> Check<()> {
+        self.custom_mods()?;
+        let offset = self.reader.position();
+        const ALLOWED: [ElementType; 7] = [
+            ElementType::Ptr,
+            ElementType::FnPtr,
+            ElementType::Array,
+            ElementType::SzArray,
+            ElementType::GenericInst,
+            ElementType::Var,
+            ElementType::MVar,
+        ];
+        if let Some(element) = self
+            .peek_u8()
+            .filter(|&element| !ALLOWED.iter().any(|allowed| *allowed as u8 == element))
+        {
+            self.warnings.push(SigError {
+                offset,
+                severity: Severity::Warning,
+                rule: Rule::Signature,
+                message: format!("TypeSpec for element type 0x{element:02X}"),
+            });
+        }
+        self.type_sig(Position::Other)
+    }
+
+    /// Check a MethodSpec instantiation, returning its argument count.
+    fn method_spec(&mut self) -> Check<u32> {
+        let offset = self.reader.position();
+        let cc = self.read_u8()?;
+        if cc != CallingConvention::GENERIC_INST {
+            return Err(Self::error(
+                offset,
+                Rule::CallingConvention,
+                format!("calling convention 0x{cc:02X} is not GENERICINST"),
+            ));
+        }
+        let count = self.read_count()?;
+        for _ in 0..count {
+            self.type_sig(Position::Other)?;
+        }
+        Ok(count)
+    }
+
+    /// Run a check and require it to consume the whole blob.
+    fn finish<T>(mut self, check: impl FnOnce(&mut Self) -> Check<T>) -> (Check<T>, Vec<SigError>) {
+        let result = check(&mut self).and_then(|value| {
+            let remaining = self.reader.remaining();
+            if remaining > 0 {
+                return Err(Self::error(
+                    self.reader.position(),
+                    Rule::Signature,
+                    format!("{remaining} bytes after the end of the signature"),
+                ));
+            }
+            Ok(value)
+        });
+        (result, self.warnings)
+    }
+}
+
+/// Split a check result into its value and the list of findings.
+fn collect<T>((result, mut findings): (Check<T>, Vec<SigError>)) -> (Option<T>, Vec<SigError>) {
+    let value = result.map_err(|error| findings.push(error)).ok();
+    (value, findings)
+}
+
+impl Metadata {
+    pub(super) fn validate_signatures(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let mut arities = HashMap::new();
+        for row in &self.generic_params {
+            if let Some(table) = row.owner.table {
+                *arities.entry((table, row.owner.row)).or_insert(0) += 1;
+            }
+        }
+        let arity = |table, row| arities.get(&(table, row)).copied().unwrap_or(0);
+        let blob = |index| self.blobs.get(index).ok();
+        let mut report = |table, row: usize, column, findings: Vec<SigError>| {
+            for finding in findings {
+                diagnostics.push(
+                    Diagnostic::new(finding.severity, finding.rule, finding.message)
+                        .at(table, row as u32 + 1)
+                        .column(column)
+                        .offset(finding.offset),
+                );
+            }
+        };
+
+        let field_owners = self.member_owners(TableId::Field, |row| row.field_list);
+        for (i, row) in self.fields.iter().enumerate() {
+            let Some(data) = blob(row.signature) else {
+                continue;
+            };
+            let type_arity = field_owners[i].map(|owner| arity(TableId::TypeDef, owner));
+            let checker = SigChecker::new(self, data, &arities, type_arity, Some(0));
+            let (_, findings) = collect(checker.finish(SigChecker::field_sig));
+            report(TableId::Field, i, "signature", findings);
+        }
+
+        let method_owners = self.member_owners(TableId::MethodDef, |row| row.method_list);
+        for (i, row) in self.method_defs.iter().enumerate() {
+            let Some(data) = blob(row.signature) else {
+                continue;
+            };
+            let index = i as u32 + 1;
+            let type_arity = method_owners[i].map(|owner| arity(TableId::TypeDef, owner));
+            let declared = arity(TableId::MethodDef, index);
+            let checker = SigChecker::new(self, data, &arities, type_arity, Some(declared));
+            let (count, mut findings) =
+                collect(checker.finish(|checker| checker.method_sig(MethodKind::Def)));
+            if let Some(count) = count.filter(|&count| count != declared) {
+                findings.push(SigChecker::error(
+                    0,
+                    Rule::GenericArity,
+                    format!("signature declares {count} generic parameters but the method has {declared}"),
+                ));
+            }
+            report(TableId::MethodDef, i, "signature", findings);
+        }
+
+        for (i, row) in self.member_refs.iter().enumerate() {
+            let Some(data) = blob(row.signature) else {
+                continue;
+            };
+            let type_arity = match row.class.table {
+                Some(TableId::TypeDef) => Some(arity(TableId::TypeDef, row.class.row)),
+                Some(TableId::ModuleRef) => Some(0),
+                Some(TableId::MethodDef) => method_owners
+                    .get((row.class.row as usize).wrapping_sub(1))
+                    .copied()
+                    .flatten()
+                    .map(|owner| arity(TableId::TypeDef, owner)),
+                Some(TableId::TypeSpec) => self.type_spec_arity(row.class.row),
+                _ => None,
+            };
+            let is_field = data.first() == Some(&CallingConvention::FIELD);
+            let method_arity = is_field.then_some(0);
+            let checker = SigChecker::new(self, data, &arities, type_arity, method_arity);
+            let (_, findings) = if is_field {
+                collect(checker.finish(SigChecker::field_sig))
+            } else {
+                collect(checker.finish(|checker| checker.method_sig(MethodKind::Ref).map(drop)))
+            };
+            report(TableId::MemberRef, i, "signature", findings);
+        }
+
+        for (i, row) in self.stand_alone_sigs.iter().enumerate() {
+            let Some(data) = blob(row.signature) else {
+                continue;
+            };
+            // Shared by any method, so generic parameters cannot be checked
+            let checker = SigChecker::new(self, data, &arities, None, None);
+            let (_, findings) = collect(checker.finish(SigChecker::stand_alone_sig));
+            report(TableId::StandAloneSig, i, "signature", findings);
+        }
+
+        for (i, row) in self.type_specs.iter().enumerate() {
+            let Some(data) = blob(row.signature) else {
+                continue;
+            };
+            let checker = SigChecker::new(self, data, &arities, None, None);
+            let (_, findings) = collect(checker.finish(SigChecker::type_spec));
+            report(TableId::TypeSpec, i, "signature", findings);
+        }
+
+        for (i, row) in self.method_specs.iter().enumerate() {
+            let Some(data) = blob(row.instantiation) else {
+                continue;
+            };
+            let checker = SigChecker::new(self, data, &arities, None, None);
+            let (count, mut findings) = collect(checker.finish(SigChecker::method_spec));
+            let expected = match row.method.table {
+                Some(TableId::MethodDef) => Some(arity(TableId::MethodDef, row.method.row)),
+                Some(TableId::MemberRef) => self
+                    .member_refs
+                    .get((row.method.row as usize).wrapping_sub(1))
+                    .and_then(|member| blob(member.signature))
+                    .and_then(|data| MethodSig::parse_blob(data).ok())
+                    .map(|sig| sig.generic_param_count),
+                _ => None,
+            };
+            if let Some((count, expected)) = count
+                .zip(expected)
+                .filter(|(count, expected)| count != expected)
+            {
+                findings.push(SigChecker::error(
+                    1,
+                    Rule::GenericArity,
+                    format!(
+                        "{count} type arguments for a method with {expected} generic parameters"
+                    ),
+                ));
+            }
+            report(TableId::MethodSpec, i, "instantiation", findings);
+        }
+
+        let property_owners = self.property_owners();
+        for (i, row) in self.properties.iter().enumerate() {
+            let Some(data) = blob(row.property_type) else {
+                continue;
+            };
+            let type_arity = property_owners[i].map(|owner| arity(TableId::TypeDef, owner));
+            let checker = SigChecker::new(self, data, &arities, type_arity, Some(0));
+            let (_, findings) = collect(checker.finish(SigChecker::property_sig));
+            report(TableId::Property, i, "type", findings);
+        }
+    }
+
+    /// Get the argument count of a TypeSpec that instantiates a generic type.
+    fn type_spec_arity(&self, row: u32) -> Option<u32> {
+        let spec = self.type_specs.get((row as usize).checked_sub(1)?)?;
+        let data = self.blobs.get(spec.signature).ok()?;
+        match TypeSig::parse(&mut Reader::new(data)).ok()? {
+            TypeSig::GenericInst { type_args, .. } => Some(type_args.len() as u32),
+            _ => None,
+        }
+    }
+
+    /// Map each Property row to its owning TypeDef through PropertyMap.
+    fn property_owners(&self) -> Vec<Option<u32>> {
+        let count = self.properties.len();
+        let mut owners = vec![None; count];
+        for (i, map) in self.property_maps.iter().enumerate() {
+            let start = map.property_list as usize;
+            let end = self
+                .property_maps
+                .get(i + 1)
+                .map_or(count + 1, |next| next.property_list as usize)
+                .min(count + 1);
+            for owner in owners
+                .iter_mut()
+                .take(end.saturating_sub(1))
+                .skip(start.saturating_sub(1))
+            {
+                *owner = Some(map.parent);
+            }
+        }
+        owners
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::{
+        FieldRow, GenericParamRow, MemberRefRow, MethodDefRow, MethodSpecRow, ModuleRow,
+        StandAloneSigRow, TypeDefRow, TypeSpecRow,
+    };
+
+    /// Metadata with `<Module>` and a generic class `Box<T>` (TypeDef 2).
+    fn metadata() -> Metadata {
+        let mut md = Metadata::new();
+        let name = md.strings.add("Test.dll");
+        md.modules.push(ModuleRow {
+            name,
+            ..Default::default()
+        });
+        for name in ["<Module>", "Box`1"] {
+            let type_name = md.strings.add(name);
+            md.type_defs.push(TypeDefRow {
+                type_name,
+                field_list: 1,
+                method_list: 1,
+                ..Default::default()
+            });
+        }
+        let name = md.strings.add("T");
+        md.generic_params.push(GenericParamRow {
+            number: 0,
+            flags: 0,
+            owner: CodedIndex {
+                table: Some(TableId::TypeDef),
+                row: 2,
+            },
+            name,
+        });
+        md
+    }
+
+    fn add_field(md: &mut Metadata, signature: &[u8]) {
+        let name = md.strings.add("f");
+        let signature = md.blobs.add(signature);
+        md.fields.push(FieldRow {
+            flags: 0x0001,
+            name,
+            signature,
+        });
+    }
+
+    fn findings(md: &Metadata) -> Vec<(Rule, TableId, u32, usize)> {
+        let mut diagnostics = Vec::new();
+        md.validate_signatures(&mut diagnostics);
+        diagnostics
+            .into_iter()
+            .map(|d| (d.rule, d.table.unwrap(), d.row.unwrap(), d.offset.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_field_signatures() {
+        let mut md = metadata();
+        // Box<T>: !0, class Box<int32>, int32[]
+        add_field(&mut md, &[0x06, 0x13, 0x00]);
+        add_field(&mut md, &[0x06, 0x15, 0x12, 0x08, 0x01, 0x08]);
+        add_field(&mut md, &[0x06, 0x1D, 0x08]);
+        assert!(findings(&md).is_empty(), "{:?}", findings(&md));
+
+        // !1, trailing byte, TypeRef token out of range, method convention,
+        // Box with two arguments, truncated
+        add_field(&mut md, &[0x06, 0x13, 0x01]);
+        add_field(&mut md, &[0x06, 0x08, 0x08]);
+        add_field(&mut md, &[0x06, 0x12, 0x05]);
+        add_field(&mut md, &[0x00, 0x00, 0x01]);
+        add_field(&mut md, &[0x06, 0x15, 0x12, 0x08, 0x02, 0x08, 0x08]);
+        add_field(&mut md, &[0x06, 0x1D]);
+        assert_eq!(
+            findings(&md),
+            [
+                (Rule::GenericParamIndex, TableId::Field, 4, 1),
+                (Rule::Signature, TableId::Field, 5, 2),
+                (Rule::SignatureToken, TableId::Field, 6, 2),
+                (Rule::CallingConvention, TableId::Field, 7, 0),
+                (Rule::GenericArity, TableId::Field, 8, 4),
+                (Rule::Signature, TableId::Field, 9, 2),
+            ]
+        );
+
+        let mut diagnostics = Vec::new();
+        md.validate_signatures(&mut diagnostics);
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "error[II.23.2 signature] Field[5].signature+2: 1 bytes after the end of the signature"
+        );
+    }
+
+    #[test]
+    fn test_array_shapes() {
+        let mut md = metadata();
+        // int32[0...9], int32[0...9, 0...4], int32[-5...994], int32[,,]
+        add_field(&mut md, &[0x06, 0x14, 0x08, 0x01, 0x01, 0x0A, 0x01, 0x00]);
+        add_field(
+            &mut md,
+            &[0x06, 0x14, 0x08, 0x02, 0x02, 0x0A, 0x05, 0x02, 0x00, 0x00],
+        );
+        add_field(
+            &mut md,
+            &[0x06, 0x14, 0x08, 0x01, 0x01, 0x83, 0xE8, 0x01, 0x77],
+        );
+        add_field(&mut md, &[0x06, 0x14, 0x08, 0x03, 0x00, 0x00]);
+        assert!(findings(&md).is_empty(), "{:?}", findings(&md));
+
+        // Rank 0, more sizes than the rank
+        add_field(&mut md, &[0x06, 0x14, 0x08, 0x00, 0x00, 0x00]);
+        add_field(&mut md, &[0x06, 0x14, 0x08, 0x01, 0x02, 0x0A, 0x0A, 0x00]);
+        assert_eq!(
+            findings(&md),
+            [
+                (Rule::Signature, TableId::Field, 5, 3),
+                (Rule::Signature, TableId::Field, 6, 4),
+            ]
+        );
+
+        // Bounded arrays from the assembler validate cleanly
+        let md = crate::ilasm::assemble(
+            ".assembly Arrays {} .class public Grid { .field public int32[0...9] row .field public int32[0...9, 0...4] grid }",
+        )
+        .unwrap()
+        .metadata;
+        md.validate_strict().unwrap();
+    }
+
+    #[test]
+    fn test_method_signatures() {
+        let mut md = metadata();
+        let name = md.strings.add("M");
+        // Generic method M<U>(!!0, !0) in Box<T>, with a GenericParam row
+        for signature in [
+            &[0x30, 0x01, 0x02, 0x01, 0x1E, 0x00, 0x13, 0x00][..],
+            // !!1 in a method with one generic parameter
+            &[0x30, 0x01, 0x01, 0x01, 0x1E, 0x01],
+            // Sentinel in a definition
+            &[0x05, 0x01, 0x01, 0x41, 0x08],
+            // void parameter
+            &[0x20, 0x01, 0x01, 0x01],
+        ] {
+            let signature = md.blobs.add(signature);
+            md.method_defs.push(MethodDefRow {
+                name,
+                signature,
+                param_list: 1,
+                ..Default::default()
+            });
+        }
+        for method in [1, 2] {
+            md.generic_params.push(GenericParamRow {
+                number: 0,
+                flags: 0,
+                owner: CodedIndex {
+                    table: Some(TableId::MethodDef),
+                    row: method,
+                },
+                name,
+            });
+        }
+        md.type_defs[1].method_list = 1;
+
+        // Vararg call site, generic method reference and a local signature
+        for signature in [
+            &[0x05, 0x02, 0x01, 0x08, 0x41, 0x0E][..],
+            &[0x30, 0x01, 0x01, 0x1E, 0x00, 0x1E, 0x00],
+        ] {
+            let signature = md.blobs.add(signature);
+            md.member_refs.push(MemberRefRow {
+                class: CodedIndex {
+                    table: Some(TableId::TypeDef),
+                    row: 2,
+                },
+                name,
+                signature,
+            });
+        }
+        let signature = md.blobs.add(&[0x07, 0x02, 0x45, 0x10, 0x08, 0x13, 0x00]);
+        md.stand_alone_sigs.push(StandAloneSigRow { signature });
+        let signature = md.blobs.add(&[0x15, 0x12, 0x08, 0x01, 0x0E]);
+        md.type_specs.push(TypeSpecRow { signature });
+        for (method, instantiation) in
+            [(1, &[0x0A, 0x01, 0x08][..]), (2, &[0x0A, 0x02, 0x08, 0x08])]
+        {
+            let instantiation = md.blobs.add(instantiation);
+            md.method_specs.push(MethodSpecRow {
+                method: CodedIndex {
+                    table: Some(TableId::MethodDef),
+                    row: method,
+                },
+                instantiation,
+            });
+        }
+
+        assert_eq!(
+            findings(&md),
+            [
+                (Rule::GenericParamIndex, TableId::MethodDef, 2, 4),
+                (Rule::Signature, TableId::MethodDef, 3, 3),
+                (Rule::Signature, TableId::MethodDef, 4, 3),
+                (Rule::GenericArity, TableId::MethodSpec, 2, 1),
+            ]
+        );
+    }
+}
@@ -112,6 +112,25 @@ impl Writer {
         }
     }
 
+    /// Write a compressed signed integer (ECMA-335 II.23.2).
+    pub fn write_compressed_int(&mut self, value: i32) {
+        // Rotate the sign bit into the LSB within the narrowest payload width
+        let rotated = |mask: u32| ((value << 1) as u32 | u32::from(value < 0)) & mask;
+        if (-0x40..0x40).contains(&value) {
+            self.write_u8(rotated(0x7F) as u8);
+        } else if (-0x2000..0x2000).contains(&value) {
+            let encoded = rotated(0x3FFF);
+            self.write_u8((0x80 | (encoded >> 8)) as u8);
+            self.write_u8(encoded as u8);
+        } else {
+            let encoded = rotated(0x1FFF_FFFF);
+            self.write_u8((0xC0 | (encoded >> 24)) as u8);
+            self.write_u8((encoded >> 16) as u8);
+            self.write_u8((encoded >> 8) as u8);
+            self.write_u8(encoded as u8);
+        }
+    }
+
     /// Reserve space and return the offset for later patching.
     pub fn reserve(&mut self, len: usize) -> usize {
         let offset = self.data.len();
@@ -191,6 +210,21 @@ mod tests {
         assert_eq!(writer.as_slice(), &[0xC0, 0x00, 0x40, 0x00]);
     }
 
+    #[test]
+    fn test_write_compressed_int() {
+        // ECMA-335 II.23.2 examples
+        let mut writer = Writer::new();
+        for value in [3, -3, 64, -64, 8192, -8192] {
+            writer.write_compressed_int(value);
+        }
+        assert_eq!(
+            writer.as_slice(),
+            &[
+                0x06, 0x7B, 0x80, 0x80, 0x01, 0xC0, 0x00, 0x40, 0x00, 0x80, 0x01
+            ]
+        );
+    }
+
     #[test]
     fn test_align() {
         let mut writer = Writer::new();